@@ -14,31 +14,47 @@ use tauri::Manager;
 use tauri_plugin_aptabase::EventTracker;
 
 mod t_ai;
+mod t_ai_model;
 mod t_ai_png;
 mod t_apple_sidecar;
+mod t_catalog_import;
 mod t_cluster;
 mod t_cmds;
 mod t_common;
 mod t_config;
 mod t_dedup;
+mod t_digikam;
 mod t_face;
 mod t_heif;
 mod t_http;
 mod t_image;
+mod t_import;
+mod t_index_errors;
+mod t_integrity;
 mod t_jpeg;
 mod t_jxl;
 mod t_lens;
 mod t_libraw;
+mod t_lrcat;
 mod t_menu;
 mod t_migration;
 mod t_motion_photo;
+mod t_ocr;
+mod t_organize;
 mod t_pasteboard;
 mod t_protocol;
+mod t_rename;
+mod t_scan_filter;
+mod t_shotwell;
 mod t_similar;
 mod t_sqlite;
 mod t_storage;
+mod t_takeout;
+mod t_template;
 mod t_utils;
 mod t_video;
+mod t_volume;
+mod t_watcher;
 
 /// The main function is the entry point for the Tauri application.
 #[tokio::main]
@@ -69,6 +85,7 @@ async fn main() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .manage(t_video::VideoManager::default())
         .manage(t_ai::AiState(std::sync::Mutex::new(t_ai::AiEngine::new())))
+        .manage(t_ai_model::ReembedState::default())
         .manage(t_face::FaceState(std::sync::Arc::new(
             std::sync::Mutex::new(t_face::FaceEngine::new()),
         )))
@@ -89,7 +106,12 @@ async fn main() {
                 phase: "indexing".to_string(),
             }),
         )))
+        .manage(t_ocr::OcrState::default())
         .manage(t_dedup::DedupState::default())
+        .manage(t_integrity::IntegrityState::default())
+        .manage(t_import::ImportState::default())
+        .manage(t_organize::OrganizeState::default())
+        .manage(t_takeout::TakeoutState::default())
         .manage(t_similar::SimilarState::default())
         .setup(|_app| {
             t_video::init_ffmpeg_path(&_app.handle());
@@ -161,8 +183,14 @@ async fn main() {
                     }
                 }
             }
+            drop(ai_engine);
+            if let Err(e) = t_ai_model::apply_library_model(app_handle) {
+                eprintln!("Failed to apply the library image search model: {}", e);
+            }
 
             t_utils::start_folder_mtime_sync(_app.handle().clone());
+            t_watcher::start_folder_watcher(_app.handle().clone());
+            t_integrity::start_scheduler(_app.handle().clone());
 
             // Open devtools in development mode
             // #[cfg(debug_assertions)] // only include this block in debug builds
@@ -240,13 +268,21 @@ async fn main() {
             t_cmds::recount_album,
             t_cmds::add_album,
             t_cmds::edit_album,
+            t_cmds::get_album_scan_filter,
+            t_cmds::set_album_scan_filter,
             t_cmds::remove_album,
             t_cmds::reorder_albums,
             t_cmds::set_album_cover,
             t_cmds::index_album,
             t_cmds::cancel_indexing,
+            t_cmds::set_folder_watcher_options,
             t_cmds::get_index_recovery_info,
             t_cmds::clear_index_recovery_info,
+            t_cmds::get_index_errors,
+            t_cmds::count_index_errors,
+            t_cmds::retry_index_errors,
+            t_cmds::ignore_index_errors,
+            t_cmds::export_index_errors,
             // folder
             t_cmds::select_folder,
             t_cmds::fetch_folder,
@@ -371,6 +407,11 @@ async fn main() {
             t_cmds::set_image_search_model,
             t_cmds::download_multilingual_image_search_model,
             t_cmds::cancel_multilingual_image_search_model_download,
+            t_cmds::list_image_search_models,
+            t_cmds::set_image_search_image_model,
+            t_cmds::start_image_search_reembed,
+            t_cmds::cancel_image_search_reembed,
+            t_cmds::get_image_search_reembed_status,
             t_cmds::generate_embedding,
             t_cmds::search_similar_images,
             // person (face recognition)
@@ -384,6 +425,19 @@ async fn main() {
             t_cmds::rename_person,
             t_cmds::delete_person,
             t_cmds::get_faces_for_file,
+            t_cmds::add_face,
+            t_cmds::update_face_box,
+            t_cmds::set_face_person,
+            t_cmds::delete_face,
+            t_cmds::search_faces,
+            // text recognition (OCR)
+            t_cmds::is_ocr_available,
+            t_cmds::start_ocr_index,
+            t_cmds::cancel_ocr_index,
+            t_cmds::get_ocr_index_status,
+            t_cmds::reset_ocr_index,
+            t_cmds::get_file_text,
+            t_cmds::recognize_file_text,
             // dedup
             t_cmds::dedup_start_scan,
             t_cmds::dedup_get_scan_status,
@@ -391,7 +445,47 @@ async fn main() {
             t_cmds::dedup_list_groups,
             t_cmds::dedup_get_overview,
             t_cmds::dedup_set_keep,
+            t_cmds::dedup_get_keep_policy,
+            t_cmds::dedup_set_keep_policy,
             t_cmds::dedup_delete_selected,
+            t_cmds::dedup_link_selected,
+            // integrity
+            t_cmds::integrity_start_check,
+            t_cmds::integrity_get_status,
+            t_cmds::integrity_cancel_check,
+            t_cmds::integrity_get_overview,
+            t_cmds::integrity_list_issues,
+            t_cmds::integrity_find_intact_copies,
+            t_cmds::integrity_accept_current,
+            t_cmds::integrity_get_schedule,
+            t_cmds::integrity_set_schedule,
+            // import
+            t_cmds::import_scan_source,
+            t_cmds::import_close_session,
+            t_cmds::import_get_preview,
+            t_cmds::import_start,
+            t_cmds::import_get_status,
+            t_cmds::import_cancel,
+            t_cmds::import_expand_template,
+            // batch rename
+            t_cmds::batch_rename_preview,
+            t_cmds::batch_rename_apply,
+            t_cmds::batch_rename_undo,
+            t_cmds::batch_rename_list_batches,
+            // organize
+            t_cmds::organize_preview,
+            t_cmds::organize_start,
+            t_cmds::organize_get_status,
+            t_cmds::organize_cancel,
+            // takeout
+            t_cmds::takeout_scan_source,
+            t_cmds::takeout_close_session,
+            t_cmds::takeout_start,
+            t_cmds::takeout_get_status,
+            t_cmds::takeout_cancel,
+            // catalog import
+            t_cmds::catalog_import_preview,
+            t_cmds::catalog_import_apply,
             t_cmds::similar_start_scan,
             t_cmds::similar_get_scan_status,
             t_cmds::similar_cancel_scan,
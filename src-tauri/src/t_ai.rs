@@ -2,6 +2,7 @@
  * AI Engine module
  * Handles ONNX Runtime sessions and model inference.
  */
+use crate::t_ai_model::{self, ModelManifest, ModelPooling};
 use crate::t_common;
 use image::DynamicImage;
 use ndarray::{Array, Array4};
@@ -31,6 +32,7 @@ pub struct AiEngine {
     vision_model: Option<Session>,
     tokenizer: Option<Tokenizer>,
     text_model_kind: ImageSearchTextModel,
+    image_model: ModelManifest,
 }
 
 const AI_INTRA_THREADS: usize = 2;
@@ -74,6 +76,7 @@ impl ImageSearchTextModel {
 pub struct ImageSearchModelStatus {
     pub active_model: i64,
     pub multilingual_available: bool,
+    pub image_model: String,
 }
 
 #[derive(Debug, Clone)]
@@ -173,6 +176,7 @@ impl AiEngine {
             vision_model: None,
             tokenizer: None,
             text_model_kind: ImageSearchTextModel::Default,
+            image_model: ModelManifest::builtin(PathBuf::new()),
         }
     }
 
@@ -183,8 +187,10 @@ impl AiEngine {
 
         println!("Loading AI Models...");
 
-        let resource_dir = Self::resource_model_dir(app)?;
-        let vision_model_path = resource_dir.join(t_common::AI_VISION_MODEL);
+        if self.image_model.builtin {
+            self.image_model.dir = Self::resource_model_dir(app)?;
+        }
+        let vision_model_path = self.image_model.vision_model_path();
         // Load Vision Model
         if self.vision_model.is_none() {
             let vision_model = Self::load_session(&vision_model_path, "vision")?;
@@ -194,6 +200,7 @@ impl AiEngine {
         if self.text_model.is_none() {
             self.set_text_model(app, ImageSearchTextModel::Default)?;
         }
+        t_ai_model::set_active_model_id(&self.image_model.id);
 
         println!("AI Models Loaded Successfully!");
         Ok(())
@@ -259,9 +266,75 @@ impl AiEngine {
         ImageSearchModelStatus {
             active_model: self.text_model_kind.as_i64(),
             multilingual_available: Self::is_multilingual_model_available(app),
+            image_model: self.image_model.id.clone(),
         }
     }
 
+    /// Id of the model that produces image embeddings.
+    pub fn image_model_id(&self) -> &str {
+        &self.image_model.id
+    }
+
+    /// List the bundled model and the custom models installed locally.
+    pub fn list_image_models(app: &AppHandle) -> Result<Vec<ModelManifest>, String> {
+        Ok(t_ai_model::list_models(&Self::resource_model_dir(app)?))
+    }
+
+    /// Switch the vision, text and tokenizer models to the given registry model.
+    /// The multilingual text model only pairs with the bundled model.
+    pub fn set_image_model(&mut self, app: &AppHandle, model_id: &str) -> Result<(), String> {
+        if self.is_loaded() && self.image_model.id == model_id {
+            return Ok(());
+        }
+
+        let manifest = t_ai_model::find_model(&Self::resource_model_dir(app)?, model_id)?;
+        let text_model_kind = if manifest.builtin
+            && self.text_model_kind == ImageSearchTextModel::Multilingual
+            && Self::is_multilingual_model_available(app)
+        {
+            ImageSearchTextModel::Multilingual
+        } else {
+            ImageSearchTextModel::Default
+        };
+        let text_paths = if manifest.builtin {
+            Self::text_model_paths(app, text_model_kind)?
+        } else {
+            TextModelPaths {
+                model: manifest.text_model_path(),
+                tokenizer: manifest.tokenizer_path(),
+            }
+        };
+
+        let vision_model = Self::load_session(&manifest.vision_model_path(), "vision")?;
+        let tokenizer = Tokenizer::from_file(&text_paths.tokenizer).map_err(|e| {
+            format!(
+                "Failed to load tokenizer from {:?}: {}",
+                text_paths.tokenizer, e
+            )
+        })?;
+        let text_model = Self::load_session(&text_paths.model, "text")?;
+
+        let previous_vision_model = self.vision_model.replace(vision_model);
+        let previous_text_model = self.text_model.replace(text_model);
+        let previous_tokenizer = self.tokenizer.replace(tokenizer);
+        let previous_image_model = std::mem::replace(&mut self.image_model, manifest);
+        let previous_model_kind = self.text_model_kind;
+        if self.image_model.builtin {
+            self.text_model_kind = text_model_kind;
+        }
+        if let Err(error) = self.ensure_embedding_dimensions_match() {
+            self.vision_model = previous_vision_model;
+            self.text_model = previous_text_model;
+            self.tokenizer = previous_tokenizer;
+            self.image_model = previous_image_model;
+            self.text_model_kind = previous_model_kind;
+            return Err(error);
+        }
+
+        t_ai_model::set_active_model_id(&self.image_model.id);
+        Ok(())
+    }
+
     pub fn set_text_model(
         &mut self,
         app: &AppHandle,
@@ -270,6 +343,11 @@ impl AiEngine {
         if self.text_model.is_some() && self.text_model_kind == model {
             return Ok(());
         }
+        // custom models bring their own text model; keep the choice for the bundled model
+        if !self.image_model.builtin {
+            self.text_model_kind = model;
+            return Ok(());
+        }
 
         let paths = Self::text_model_paths(app, model)?;
         if model == ImageSearchTextModel::Multilingual {
@@ -307,14 +385,25 @@ impl AiEngine {
         if self.vision_model.is_none() {
             return Ok(());
         }
+        let size = self.image_model.input_size as usize;
         let text_dim = self.encode_text("__lap_embedding_probe__")?.len();
-        let vision_dim = self.run_vision_model(Array::zeros((1, 3, 224, 224)))?.len();
+        let vision_dim = self
+            .run_vision_model(Array::zeros((1, 3, size, size)))?
+            .len();
         if text_dim != vision_dim {
             return Err(format!(
                 "Image search model is incompatible with the bundled vision model (text dimension {}, vision dimension {}). Please select or download a compatible model.",
                 text_dim, vision_dim
             ));
         }
+        if let Some(expected_dim) = self.image_model.embedding_dim {
+            if vision_dim != expected_dim {
+                return Err(format!(
+                    "Image search model {} produces {}-dimensional embeddings, but its manifest declares {}",
+                    self.image_model.id, vision_dim, expected_dim
+                ));
+            }
+        }
         Ok(())
     }
 
@@ -332,8 +421,12 @@ impl AiEngine {
             .encode(text, true)
             .map_err(|e| format!("Tokenization error: {}", e))?;
 
-        let input_ids = encoding.get_ids();
-        let attention_mask = encoding.get_attention_mask();
+        let mut input_ids = encoding.get_ids().to_vec();
+        let mut attention_mask = encoding.get_attention_mask().to_vec();
+        if let Some(max_length) = self.image_model.max_text_length {
+            input_ids.resize(max_length, self.image_model.pad_token_id);
+            attention_mask.resize(max_length, 0);
+        }
 
         let input_ids_array = Array::from_shape_vec(
             (1, input_ids.len()),
@@ -370,7 +463,12 @@ impl AiEngine {
         }
         .map_err(|e| format!("Inference error: {}", e))?;
 
-        let (embedding, first_token_only) = if let Some(vals) = outputs.get("pooler_output") {
+        let (embedding, sequence_output) = if let Some(name) = &self.image_model.text_output {
+            let vals = outputs
+                .get(name.as_str())
+                .ok_or_else(|| format!("Text model has no output named {}", name))?;
+            (vals, true)
+        } else if let Some(vals) = outputs.get("pooler_output") {
             (vals, false)
         } else if let Some(vals) = outputs.get("text_embeds") {
             (vals, false)
@@ -380,34 +478,67 @@ impl AiEngine {
             (&outputs[0], true)
         };
 
-        Self::extract_text_embedding(embedding, first_token_only)
+        Self::pool_embedding(
+            embedding,
+            self.image_model.pooling,
+            sequence_output,
+            Some(attention_mask.as_slice()),
+        )
     }
 
-    fn extract_text_embedding(
+    /// Reduce a model output to one embedding vector.
+    /// Sequence outputs (batch, tokens, hidden) use the manifest pooling; `Auto`
+    /// keeps pooled outputs as-is and takes the first token of sequence outputs.
+    fn pool_embedding(
         embedding: &ort::value::DynValue,
-        first_token_only: bool,
+        pooling: ModelPooling,
+        sequence_output: bool,
+        attention_mask: Option<&[u32]>,
     ) -> Result<Vec<f32>, String> {
         let (shape, embedding_data) = embedding
             .try_extract_tensor::<f32>()
             .map_err(|e| format!("Failed to extract tensor: {}", e))?;
 
-        if first_token_only && shape.len() >= 3 {
-            let hidden_size = shape
-                .last()
-                .copied()
-                .filter(|dim| *dim > 0)
-                .ok_or_else(|| format!("Invalid text embedding shape: {}", shape))?
-                as usize;
-            if embedding_data.len() < hidden_size {
-                return Err(format!(
-                    "Text embedding data is shorter than shape {}",
-                    shape
-                ));
-            }
+        if shape.len() < 3 || (pooling == ModelPooling::Auto && !sequence_output) {
+            return Ok(embedding_data.to_vec());
+        }
+
+        let hidden_size = shape
+            .last()
+            .copied()
+            .filter(|dim| *dim > 0)
+            .ok_or_else(|| format!("Invalid embedding shape: {}", shape))?
+            as usize;
+        let token_count = shape[shape.len() - 2].max(0) as usize;
+        if token_count == 0 || embedding_data.len() < hidden_size * token_count {
+            return Err(format!("Embedding data is shorter than shape {}", shape));
+        }
+
+        if pooling != ModelPooling::Mean {
             return Ok(embedding_data[..hidden_size].to_vec());
         }
 
-        Ok(embedding_data.to_vec())
+        let mut pooled = vec![0.0f32; hidden_size];
+        let mut used_tokens = 0usize;
+        for token in 0..token_count {
+            let masked_out = attention_mask
+                .and_then(|mask| mask.get(token))
+                .is_some_and(|value| *value == 0);
+            if masked_out {
+                continue;
+            }
+            let row = &embedding_data[token * hidden_size..(token + 1) * hidden_size];
+            for (sum, value) in pooled.iter_mut().zip(row) {
+                *sum += value;
+            }
+            used_tokens += 1;
+        }
+        if used_tokens > 0 {
+            for value in pooled.iter_mut() {
+                *value /= used_tokens as f32;
+            }
+        }
+        Ok(pooled)
     }
 
     pub fn encode_image(&mut self, image_path: &str) -> Result<Vec<f32>, String> {
@@ -433,29 +564,31 @@ impl AiEngine {
 
     fn run_vision_model(&mut self, image_input: Array4<f32>) -> Result<Vec<f32>, String> {
         let image_input_value = Value::from_array(image_input).map_err(|e| e.to_string())?;
+        let input_name = self.image_model.vision_input.clone();
 
         let outputs = self
             .vision_model
             .as_mut()
             .unwrap()
             .run(inputs![
-                "pixel_values" => image_input_value,
+                input_name => image_input_value,
             ])
             .map_err(|e| format!("Inference error: {}", e))?;
 
-        let embedding = if let Some(vals) = outputs.get("pooler_output") {
-            vals
+        let (embedding, sequence_output) = if let Some(name) = &self.image_model.vision_output {
+            let vals = outputs
+                .get(name.as_str())
+                .ok_or_else(|| format!("Vision model has no output named {}", name))?;
+            (vals, true)
+        } else if let Some(vals) = outputs.get("pooler_output") {
+            (vals, false)
         } else if let Some(vals) = outputs.get("image_embeds") {
-            vals
+            (vals, false)
         } else {
-            &outputs[0]
+            (&outputs[0], false)
         };
 
-        let (_, embedding_data) = embedding
-            .try_extract_tensor::<f32>()
-            .map_err(|e| format!("Failed to extract tensor: {}", e))?;
-
-        Ok(embedding_data.to_vec())
+        Self::pool_embedding(embedding, self.image_model.pooling, sequence_output, None)
     }
 
     fn preprocess_image(&self, path: &str) -> Result<Array4<f32>, String> {
@@ -464,15 +597,16 @@ impl AiEngine {
     }
 
     fn preprocess_dynamic_image(&self, img: DynamicImage) -> Result<Array4<f32>, String> {
-        // resize to 224x224
-        let img = img.resize_exact(224, 224, image::imageops::FilterType::Triangle);
+        // resize to the model input size (224x224 for the bundled CLIP)
+        let size = self.image_model.input_size;
+        let img = img.resize_exact(size, size, image::imageops::FilterType::Triangle);
         let rgb_img = img.to_rgb8();
 
         // Normalize
-        let mean = [0.48145466, 0.4578275, 0.40821073];
-        let std = [0.26862954, 0.26130258, 0.27577711];
+        let mean = self.image_model.mean;
+        let std = self.image_model.std;
 
-        let mut array = Array::zeros((1, 3, 224, 224));
+        let mut array = Array::zeros((1, 3, size as usize, size as usize));
 
         for (x, y, pixel) in rgb_img.enumerate_pixels() {
             let r = (pixel[0] as f32 / 255.0 - mean[0]) / std[0];
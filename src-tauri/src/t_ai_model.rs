@@ -0,0 +1,492 @@
+/**
+ * Image search model registry
+ * Describes image/text embedding models through a `model.json` manifest, tracks the
+ * active model of the current library and re-embeds files after a model switch.
+ */
+use crate::t_common;
+use crate::t_sqlite::{self, AFile};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use tauri::{AppHandle, Emitter, Manager};
+
+const REEMBED_BATCH_SIZE: i64 = 200;
+const LIBRARY_MODEL_SETTING: &str = "image_search_model";
+
+static ACTIVE_MODEL_ID: RwLock<String> = RwLock::new(String::new());
+
+// ----------------------------------------------------------------------------
+// Manifest
+// ----------------------------------------------------------------------------
+
+/// How a sequence output (batch, tokens, hidden) is reduced to a single embedding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ModelPooling {
+    /// Use pooled outputs when the model exports them, else the first token.
+    #[default]
+    Auto,
+    FirstToken,
+    Mean,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelManifest {
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_vision_model")]
+    pub vision_model: String,
+    #[serde(default = "default_text_model")]
+    pub text_model: String,
+    #[serde(default = "default_tokenizer")]
+    pub tokenizer: String,
+    #[serde(default = "default_input_size")]
+    pub input_size: u32,
+    #[serde(default = "default_mean")]
+    pub mean: [f32; 3],
+    #[serde(default = "default_std")]
+    pub std: [f32; 3],
+    #[serde(default)]
+    pub pooling: ModelPooling,
+    #[serde(default)]
+    pub embedding_dim: Option<usize>,
+    #[serde(default = "default_vision_input")]
+    pub vision_input: String,
+    #[serde(default)]
+    pub vision_output: Option<String>,
+    #[serde(default)]
+    pub text_output: Option<String>,
+    #[serde(default)]
+    pub max_text_length: Option<usize>, // fixed-length padding (e.g. 64 for SigLIP)
+    #[serde(default)]
+    pub pad_token_id: u32,
+    #[serde(default)]
+    pub builtin: bool,
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+fn default_vision_model() -> String {
+    t_common::AI_VISION_MODEL.to_string()
+}
+
+fn default_text_model() -> String {
+    t_common::AI_TEXT_MODEL.to_string()
+}
+
+fn default_tokenizer() -> String {
+    t_common::AI_TOKENIZER.to_string()
+}
+
+fn default_input_size() -> u32 {
+    224
+}
+
+fn default_mean() -> [f32; 3] {
+    [0.48145466, 0.4578275, 0.40821073]
+}
+
+fn default_std() -> [f32; 3] {
+    [0.26862954, 0.26130258, 0.27577711]
+}
+
+fn default_vision_input() -> String {
+    "pixel_values".to_string()
+}
+
+impl ModelManifest {
+    /// The CLIP model bundled with the app resources.
+    pub fn builtin(dir: PathBuf) -> Self {
+        Self {
+            id: t_common::AI_BUILTIN_MODEL_ID.to_string(),
+            name: "CLIP (bundled)".to_string(),
+            vision_model: default_vision_model(),
+            text_model: default_text_model(),
+            tokenizer: default_tokenizer(),
+            input_size: default_input_size(),
+            mean: default_mean(),
+            std: default_std(),
+            pooling: ModelPooling::Auto,
+            embedding_dim: None,
+            vision_input: default_vision_input(),
+            vision_output: None,
+            text_output: None,
+            max_text_length: None,
+            pad_token_id: 0,
+            builtin: true,
+            dir,
+        }
+    }
+
+    pub fn vision_model_path(&self) -> PathBuf {
+        self.dir.join(&self.vision_model)
+    }
+
+    pub fn text_model_path(&self) -> PathBuf {
+        self.dir.join(&self.text_model)
+    }
+
+    pub fn tokenizer_path(&self) -> PathBuf {
+        self.dir.join(&self.tokenizer)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !is_valid_model_id(&self.id) {
+            return Err(format!("Invalid image search model id: {}", self.id));
+        }
+        if self.id == t_common::AI_BUILTIN_MODEL_ID {
+            return Err(format!("Model id {} is reserved", self.id));
+        }
+        if self.input_size == 0 {
+            return Err(format!("Model {} has an invalid input size", self.id));
+        }
+        if self.std.iter().any(|value| *value <= 0.0) {
+            return Err(format!(
+                "Model {} has an invalid normalization std",
+                self.id
+            ));
+        }
+        if self.embedding_dim.unwrap_or(0) == 0 {
+            return Err(format!("Model {} is missing embeddingDim", self.id));
+        }
+        for path in [
+            self.vision_model_path(),
+            self.text_model_path(),
+            self.tokenizer_path(),
+        ] {
+            if !path.exists() {
+                return Err(format!("Model {} is missing {}", self.id, path.display()));
+            }
+        }
+        Ok(())
+    }
+}
+
+// model ids are inlined into SQL conditions, so keep them to a safe character set
+fn is_valid_model_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Directory scanned for custom models: `<app data>/models/custom/<model>/model.json`.
+pub fn custom_models_dir() -> Result<PathBuf, String> {
+    crate::t_config::get_app_data_dir().map(|dir| dir.join("models").join("custom"))
+}
+
+/// Load and validate the manifest in a model directory.
+pub fn load_manifest(dir: &Path) -> Result<ModelManifest, String> {
+    let manifest_path = dir.join(t_common::AI_MODEL_MANIFEST);
+    let content = std::fs::read_to_string(&manifest_path)
+        .map_err(|e| format!("Failed to read {}: {}", manifest_path.display(), e))?;
+    let mut manifest: ModelManifest = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid model manifest {}: {}", manifest_path.display(), e))?;
+    manifest.builtin = false;
+    manifest.dir = dir.to_path_buf();
+    manifest.validate()?;
+    Ok(manifest)
+}
+
+/// List the bundled model followed by every valid custom model.
+pub fn list_models(resource_dir: &Path) -> Vec<ModelManifest> {
+    let mut models = vec![ModelManifest::builtin(resource_dir.to_path_buf())];
+    let Ok(custom_dir) = custom_models_dir() else {
+        return models;
+    };
+    let Ok(entries) = std::fs::read_dir(&custom_dir) else {
+        return models;
+    };
+
+    let mut custom: Vec<ModelManifest> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.join(t_common::AI_MODEL_MANIFEST).exists())
+        .filter_map(|path| match load_manifest(&path) {
+            Ok(manifest) => Some(manifest),
+            Err(e) => {
+                eprintln!("Skipping image search model: {}", e);
+                None
+            }
+        })
+        .collect();
+    custom.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    for manifest in custom {
+        if !models.iter().any(|model| model.id == manifest.id) {
+            models.push(manifest);
+        }
+    }
+    models
+}
+
+pub fn find_model(resource_dir: &Path, model_id: &str) -> Result<ModelManifest, String> {
+    list_models(resource_dir)
+        .into_iter()
+        .find(|model| model.id == model_id)
+        .ok_or_else(|| format!("Image search model not found: {}", model_id))
+}
+
+// ----------------------------------------------------------------------------
+// Active model
+// ----------------------------------------------------------------------------
+
+/// Id of the model that produces the embeddings currently used for search.
+pub fn active_model_id() -> String {
+    let id = ACTIVE_MODEL_ID.read().unwrap();
+    if id.is_empty() {
+        t_common::AI_BUILTIN_MODEL_ID.to_string()
+    } else {
+        id.clone()
+    }
+}
+
+pub fn set_active_model_id(model_id: &str) {
+    *ACTIVE_MODEL_ID.write().unwrap() = model_id.to_string();
+}
+
+/// SQL condition matching files whose embedding was produced by the active model.
+/// Embeddings written before model ids were recorded belong to the bundled model.
+pub fn embeds_model_condition(alias: &str) -> String {
+    let prefix = if alias.is_empty() {
+        String::new()
+    } else {
+        format!("{}.", alias)
+    };
+    format!(
+        "COALESCE({prefix}embeds_model, '{}') = '{}'",
+        t_common::AI_BUILTIN_MODEL_ID,
+        active_model_id()
+    )
+}
+
+/// Get the model selected for the current library (None = bundled model).
+pub fn get_library_model_id() -> Result<Option<String>, String> {
+    t_sqlite::get_library_setting(LIBRARY_MODEL_SETTING)
+}
+
+/// Save the model selected for the current library.
+pub fn save_library_model_id(model_id: &str) -> Result<(), String> {
+    t_sqlite::save_library_setting(LIBRARY_MODEL_SETTING, model_id)
+}
+
+/// Switch the engine to the model saved for the current library.
+/// Falls back to the bundled model when the saved model is no longer installed.
+pub fn apply_library_model(app: &AppHandle) -> Result<(), String> {
+    let model_id =
+        get_library_model_id()?.unwrap_or_else(|| t_common::AI_BUILTIN_MODEL_ID.to_string());
+    let ai_state = app.state::<crate::t_ai::AiState>();
+    let mut engine = ai_state.0.lock().unwrap();
+    if engine.image_model_id() == model_id {
+        set_active_model_id(&model_id);
+        return Ok(());
+    }
+    if let Err(e) = engine.set_image_model(app, &model_id) {
+        eprintln!("Failed to load image search model {}: {}", model_id, e);
+        engine.set_image_model(app, t_common::AI_BUILTIN_MODEL_ID)?;
+    }
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+// Background re-embedding
+// ----------------------------------------------------------------------------
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReembedStatus {
+    pub state: String, // "running", "idle", "finished", "error"
+    pub model_id: String,
+    pub processed: u64,
+    pub failed: u64,
+    pub total: u64,
+    pub is_running: bool,
+}
+
+impl Default for ReembedStatus {
+    fn default() -> Self {
+        Self {
+            state: "idle".to_string(),
+            model_id: String::new(),
+            processed: 0,
+            failed: 0,
+            total: 0,
+            is_running: false,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ReembedState {
+    pub is_running: Arc<AtomicBool>,
+    pub cancel_flag: Arc<AtomicBool>,
+    pub status: Arc<Mutex<ReembedStatus>>,
+}
+
+fn stale_condition() -> String {
+    format!(
+        "file_type IN (1, 2, 3) AND (embeds IS NULL OR NOT ({}))",
+        embeds_model_condition("")
+    )
+}
+
+/// Count image files without an embedding from the active model.
+pub fn count_stale_embeddings() -> Result<u64, String> {
+    let conn = t_sqlite::open_conn()?;
+    let sql = format!("SELECT COUNT(*) FROM afiles WHERE {}", stale_condition());
+    conn.query_row(&sql, [], |row| row.get::<_, i64>(0))
+        .map(|count| count as u64)
+        .map_err(|e| e.to_string())
+}
+
+fn fetch_stale_batch(after_id: i64) -> Result<Vec<i64>, String> {
+    let conn = t_sqlite::open_conn()?;
+    let sql = format!(
+        "SELECT id FROM afiles WHERE id > ?1 AND {} ORDER BY id LIMIT ?2",
+        stale_condition()
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    stmt.query_map(params![after_id, REEMBED_BATCH_SIZE], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<i64>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Re-embed, in the background, every image whose embedding is missing or was
+/// produced by another model. Old embeddings stay in place until replaced.
+pub fn start_reembed(app_handle: AppHandle, state: &ReembedState) -> Result<(), String> {
+    if state
+        .is_running
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return Err("Image search re-embedding is already running.".into());
+    }
+    state.cancel_flag.store(false, Ordering::SeqCst);
+
+    let status_clone = state.status.clone();
+    let is_running_clone = state.is_running.clone();
+    let cancel_flag_clone = state.cancel_flag.clone();
+
+    {
+        let mut status = status_clone.lock().unwrap();
+        *status = ReembedStatus {
+            state: "running".to_string(),
+            is_running: true,
+            ..Default::default()
+        };
+    }
+
+    std::thread::spawn(move || {
+        let result = reembed_files(&app_handle, &status_clone, &cancel_flag_clone);
+
+        let mut final_status = status_clone.lock().unwrap();
+        match result {
+            Ok(_) => {
+                if cancel_flag_clone.load(Ordering::SeqCst) {
+                    final_status.state = "idle".to_string();
+                } else {
+                    final_status.state = "finished".to_string();
+                }
+            }
+            Err(e) => {
+                eprintln!("Image search re-embed error: {}", e);
+                final_status.state = "error".to_string();
+            }
+        }
+
+        is_running_clone.store(false, Ordering::SeqCst);
+        final_status.is_running = false;
+        let _ = app_handle.emit("image-search-reembed-progress", final_status.clone());
+    });
+
+    Ok(())
+}
+
+fn reembed_files(
+    app_handle: &AppHandle,
+    status_mutex: &Arc<Mutex<ReembedStatus>>,
+    cancel_flag: &Arc<AtomicBool>,
+) -> Result<(), String> {
+    let ai_state = app_handle.state::<crate::t_ai::AiState>();
+    let mut model_id = String::new();
+    let mut last_id = 0;
+    loop {
+        if cancel_flag.load(Ordering::SeqCst) {
+            break;
+        }
+        // (re)start from the beginning when the active model changes mid-run
+        let current_model_id = active_model_id();
+        if current_model_id != model_id {
+            model_id = current_model_id;
+            last_id = 0;
+            let total = count_stale_embeddings()?;
+            {
+                let mut status = status_mutex.lock().unwrap();
+                status.model_id = model_id.clone();
+                status.processed = 0;
+                status.failed = 0;
+                status.total = total;
+            }
+            let _ = app_handle.emit(
+                "image-search-reembed-progress",
+                status_mutex.lock().unwrap().clone(),
+            );
+        }
+
+        let batch = fetch_stale_batch(last_id)?;
+        let Some(&batch_last) = batch.last() else {
+            break;
+        };
+        last_id = batch_last;
+
+        for file_id in batch {
+            if cancel_flag.load(Ordering::SeqCst) {
+                break;
+            }
+            let result = AFile::generate_embedding(&ai_state, file_id);
+            let mut status = status_mutex.lock().unwrap();
+            status.processed += 1;
+            if result.is_err() {
+                status.failed += 1;
+            }
+        }
+        let _ = app_handle.emit(
+            "image-search-reembed-progress",
+            status_mutex.lock().unwrap().clone(),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_ids_are_restricted_to_safe_characters() {
+        assert!(is_valid_model_id("siglip-base-patch16_224.v2"));
+        assert!(!is_valid_model_id(""));
+        assert!(!is_valid_model_id("clip'; DROP TABLE afiles; --"));
+        assert!(!is_valid_model_id("../clip"));
+    }
+
+    #[test]
+    fn manifest_defaults_match_bundled_clip() {
+        let manifest: ModelManifest =
+            serde_json::from_str(r#"{"id":"siglip","name":"SigLIP","embeddingDim":768}"#).unwrap();
+        let builtin = ModelManifest::builtin(PathBuf::new());
+        assert_eq!(manifest.input_size, builtin.input_size);
+        assert_eq!(manifest.mean, builtin.mean);
+        assert_eq!(manifest.vision_input, "pixel_values");
+        assert_eq!(manifest.pooling, ModelPooling::Auto);
+        assert!(!manifest.builtin);
+    }
+}
@@ -425,6 +425,19 @@ pub(crate) fn resolve_group_primary_target(
     ))
 }
 
+/// Targets of a file's Live Photo/RAW companions and AAE sidecars when the file
+/// itself moves to `primary_target`.
+pub(crate) fn group_component_targets(
+    file_id: Option<i64>,
+    file_path: &str,
+    primary_target: &Path,
+) -> Result<Vec<PathBuf>, String> {
+    let sources = resolve_apple_sidecar_transfer_sources(file_id, file_path)?;
+    let plans =
+        build_apple_sidecar_transfer_plan_from_sources(file_path, primary_target, &sources)?;
+    Ok(plans.into_iter().map(|plan| plan.new_path).collect())
+}
+
 fn push_replaced_file_id(
     file_id: i64,
     source_file_ids: &HashSet<i64>,
@@ -0,0 +1,664 @@
+/**
+ * t_catalog_import.rs - Import metadata from other photo catalogs
+ *
+ * Catalog readers (Lightroom Classic, digiKam and Shotwell) turn another
+ * application's database into a list of `CatalogImage`s: an absolute file path
+ * with a rating, a pick/reject flag, tag names, collection names and named face
+ * regions. This module matches those paths to Lap files, with root remapping for
+ * libraries that moved to another drive, and writes the metadata into `atags`,
+ * `acollections`, `rating`, `culling_flag`, `persons` and `faces`. Nothing on
+ * disk is touched.
+ */
+use crate::t_face::{parse_bbox, rect_iou};
+use crate::t_sqlite::{Person, open_conn};
+use crate::t_utils;
+use crate::{t_digikam, t_lrcat, t_shotwell};
+use rusqlite::{OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+pub const KIND_LIGHTROOM: &str = "lightroom";
+pub const KIND_DIGIKAM: &str = "digikam";
+pub const KIND_SHOTWELL: &str = "shotwell";
+
+pub const TAG_PATH_SEPARATOR: &str = "/"; // nested tags become "Parent/Child", since atags is flat
+pub const COLOR_LABEL_PREFIX: &str = "Color Label: ";
+
+const MAX_UNMATCHED_PATHS: usize = 100; // unmatched paths listed in a preview or result
+const FACE_IOU_THRESHOLD: f32 = 0.5; // an imported region this close to a stored face is the same face
+
+// ----------------------------------------------------------------------------
+// Types and Structs
+// ----------------------------------------------------------------------------
+
+/// One photo as another catalog describes it
+#[derive(Debug, Clone, Default)]
+pub struct CatalogImage {
+    pub path: String, // absolute path as stored in the catalog
+    pub rating: Option<i32>,
+    pub culling_flag: Option<i32>, // 1: pick, 2: reject
+    pub tags: Vec<String>,
+    pub collections: Vec<String>,
+    pub faces: Vec<CatalogFace>,
+}
+
+/// A named face region, in pixels of the image as displayed (EXIF orientation applied)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CatalogFace {
+    pub person: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Everything a reader extracts from a catalog
+#[derive(Debug, Clone, Default)]
+pub struct CatalogData {
+    pub roots: Vec<String>, // top-level folders the catalog references
+    pub images: Vec<CatalogImage>,
+    pub skipped_collections: usize, // smart collections and the like, which have no fixed members
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RootMapping {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CatalogImportOptions {
+    pub root_map: Vec<RootMapping>,
+    pub ratings: bool,
+    pub flags: bool,
+    pub tags: bool,
+    pub collections: bool,
+    pub faces: bool,
+    pub overwrite: bool, // replace ratings and flags Lap already has, and the person of overlapping faces
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogRoot {
+    pub path: String,
+    pub mapped_path: String,
+    pub exists: bool,
+    pub image_count: usize,
+    pub matched_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogPreview {
+    pub image_count: usize,
+    pub matched_count: usize,
+    pub rated_count: usize,
+    pub flagged_count: usize,
+    pub tag_count: usize,        // distinct tags
+    pub collection_count: usize, // distinct collections
+    pub face_count: usize,
+    pub person_count: usize, // distinct person names
+    pub skipped_collections: usize,
+    pub roots: Vec<CatalogRoot>,
+    pub unmatched: Vec<String>, // first few paths without a Lap file
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogImportResult {
+    pub matched: usize,
+    pub unmatched: usize,
+    pub rated: usize,
+    pub flagged: usize,
+    pub tagged: usize,    // file-tag links added
+    pub collected: usize, // files added to collections
+    pub faces: usize,     // faces added or assigned to a person
+}
+
+/// Lap file ids by normalized path
+struct LibraryPaths {
+    exact: HashMap<String, i64>,
+    folded: HashMap<String, i64>, // lowercase, for case-insensitive volumes
+}
+
+// ----------------------------------------------------------------------------
+// Reading and matching
+// ----------------------------------------------------------------------------
+
+fn read_catalog(kind: &str, catalog_path: &str) -> Result<CatalogData, String> {
+    if !Path::new(catalog_path).is_file() {
+        return Err(format!("Catalog not found: {}", catalog_path));
+    }
+    match kind {
+        KIND_LIGHTROOM => t_lrcat::read_catalog(catalog_path),
+        KIND_DIGIKAM => t_digikam::read_catalog(catalog_path),
+        KIND_SHOTWELL => t_shotwell::read_catalog(catalog_path),
+        _ => Err(format!("Unknown catalog type: {}", kind)),
+    }
+}
+
+/// Counts, roots and unmatched paths for a catalog under a root mapping
+pub fn preview(
+    kind: &str,
+    catalog_path: &str,
+    root_map: &[RootMapping],
+) -> Result<CatalogPreview, String> {
+    let data = read_catalog(kind, catalog_path)?;
+    let library = LibraryPaths::load()?;
+
+    let mut roots: Vec<CatalogRoot> = data
+        .roots
+        .iter()
+        .map(|root| {
+            let mapped_path = remap_path(root, root_map);
+            CatalogRoot {
+                exists: Path::new(&mapped_path).is_dir(),
+                path: root.clone(),
+                mapped_path,
+                image_count: 0,
+                matched_count: 0,
+            }
+        })
+        .collect();
+    let mut preview = CatalogPreview {
+        image_count: data.images.len(),
+        matched_count: 0,
+        rated_count: 0,
+        flagged_count: 0,
+        tag_count: 0,
+        collection_count: 0,
+        face_count: 0,
+        person_count: 0,
+        skipped_collections: data.skipped_collections,
+        roots: Vec::new(),
+        unmatched: Vec::new(),
+    };
+    let mut tags: BTreeSet<&str> = BTreeSet::new();
+    let mut collections: BTreeSet<&str> = BTreeSet::new();
+    let mut persons: BTreeSet<&str> = BTreeSet::new();
+    for image in &data.images {
+        let matched = library.find(&remap_path(&image.path, root_map)).is_some();
+        let root = roots
+            .iter_mut()
+            .filter(|root| is_under(&image.path, &root.path))
+            .max_by_key(|root| root.path.len());
+        if let Some(root) = root {
+            root.image_count += 1;
+            if matched {
+                root.matched_count += 1;
+            }
+        }
+        if !matched {
+            if preview.unmatched.len() < MAX_UNMATCHED_PATHS {
+                preview.unmatched.push(image.path.clone());
+            }
+            continue;
+        }
+
+        preview.matched_count += 1;
+        if image.rating.is_some_and(|rating| rating >= 0) {
+            preview.rated_count += 1;
+        }
+        if image.culling_flag.is_some_and(|flag| flag > 0) {
+            preview.flagged_count += 1;
+        }
+        tags.extend(image.tags.iter().map(String::as_str));
+        collections.extend(image.collections.iter().map(String::as_str));
+        preview.face_count += image.faces.len();
+        persons.extend(image.faces.iter().map(|face| face.person.as_str()));
+    }
+    preview.tag_count = tags.len();
+    preview.collection_count = collections.len();
+    preview.person_count = persons.len();
+    preview.roots = roots;
+    Ok(preview)
+}
+
+/// Write a catalog's ratings, flags, tags and collections into the Lap catalog
+pub fn import(
+    kind: &str,
+    catalog_path: &str,
+    options: &CatalogImportOptions,
+) -> Result<CatalogImportResult, String> {
+    let data = read_catalog(kind, catalog_path)?;
+    let library = LibraryPaths::load()?;
+
+    let mut result = CatalogImportResult::default();
+    let mut collection_ids: HashMap<String, i64> = HashMap::new();
+    let mut touched_collections: BTreeSet<i64> = BTreeSet::new();
+    let mut tag_ids: HashMap<String, i64> = HashMap::new();
+    let mut person_ids: HashMap<String, i64> = HashMap::new();
+    let mut touched_persons: BTreeSet<i64> = BTreeSet::new();
+
+    let now = chrono::Utc::now().timestamp();
+    let mut conn = open_conn()?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for image in &data.images {
+        let Some(file_id) = library.find(&remap_path(&image.path, &options.root_map)) else {
+            result.unmatched += 1;
+            continue;
+        };
+        result.matched += 1;
+
+        // without overwrite only files Lap has not rated or flagged yet are changed;
+        // with it a rating of 0 clears Lap's rating, and only unknown ratings are left out
+        if options.ratings {
+            if let Some(rating) = image.rating.filter(|rating| *rating >= 0) {
+                result.rated += tx
+                    .execute(
+                        "UPDATE afiles SET rating = ?1
+                         WHERE id = ?2 AND (?3 OR rating = 0) AND rating IS NOT ?1",
+                        params![rating.clamp(0, 5), file_id, options.overwrite],
+                    )
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        if options.flags {
+            if let Some(flag) = image.culling_flag.filter(|flag| *flag > 0) {
+                result.flagged += tx
+                    .execute(
+                        "UPDATE afiles SET culling_flag = ?1 WHERE id = ?2 AND (?3 OR culling_flag = 0)",
+                        params![flag.clamp(0, 2), file_id, options.overwrite],
+                    )
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        if options.tags {
+            for tag in &image.tags {
+                let tag_id = match tag_ids.get(tag) {
+                    Some(tag_id) => *tag_id,
+                    None => {
+                        let tag_id = get_or_add_tag(&tx, tag)?;
+                        tag_ids.insert(tag.clone(), tag_id);
+                        tag_id
+                    }
+                };
+                let added = tx
+                    .execute(
+                        "INSERT OR IGNORE INTO afile_tags (file_id, tag_id) VALUES (?1, ?2)",
+                        params![file_id, tag_id],
+                    )
+                    .map_err(|e| e.to_string())?;
+                if added > 0 {
+                    tx.execute(
+                        "UPDATE afiles SET has_tags = 1 WHERE id = ?1",
+                        params![file_id],
+                    )
+                    .map_err(|e| e.to_string())?;
+                    result.tagged += 1;
+                }
+            }
+        }
+        if options.faces && !image.faces.is_empty() {
+            for face in &image.faces {
+                let person_id = match person_ids.get(&face.person) {
+                    Some(person_id) => *person_id,
+                    None => {
+                        let person_id = get_or_add_person(&tx, &face.person)?;
+                        person_ids.insert(face.person.clone(), person_id);
+                        person_id
+                    }
+                };
+                if add_face(&tx, file_id, face, person_id, options.overwrite)? {
+                    touched_persons.insert(person_id);
+                    result.faces += 1;
+                }
+            }
+        }
+        if options.collections {
+            for name in &image.collections {
+                let name = name.trim();
+                if name.is_empty() {
+                    continue;
+                }
+                let collection_id = match collection_ids.get(name) {
+                    Some(collection_id) => *collection_id,
+                    None => {
+                        let collection_id = get_or_add_collection(&tx, name)?;
+                        collection_ids.insert(name.to_string(), collection_id);
+                        collection_id
+                    }
+                };
+                let added = tx
+                    .execute(
+                        "INSERT OR IGNORE INTO acollections_files (collection_id, file_id, added_at)
+                         VALUES (?1, ?2, ?3)",
+                        params![collection_id, file_id, now],
+                    )
+                    .map_err(|e| e.to_string())?;
+                if added > 0 {
+                    touched_collections.insert(collection_id);
+                    result.collected += 1;
+                }
+            }
+        }
+    }
+    for collection_id in touched_collections {
+        tx.execute(
+            "UPDATE acollections SET updated_at = ?1 WHERE id = ?2",
+            params![now, collection_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    for person_id in touched_persons {
+        Person::update_thumbnail(person_id)?;
+    }
+    Ok(result)
+}
+
+fn get_or_add_tag(conn: &rusqlite::Connection, name: &str) -> Result<i64, String> {
+    let existing: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM atags WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some(tag_id) = existing {
+        return Ok(tag_id);
+    }
+    conn.execute("INSERT INTO atags (name) VALUES (?1)", params![name])
+        .map_err(|e| e.to_string())?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// The oldest collection with this name, added after the others when there is none
+fn get_or_add_collection(conn: &rusqlite::Connection, name: &str) -> Result<i64, String> {
+    let existing: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM acollections WHERE name = ?1 ORDER BY id LIMIT 1",
+            params![name],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some(collection_id) = existing {
+        return Ok(collection_id);
+    }
+    let now = chrono::Utc::now().timestamp();
+    conn.execute(
+        "INSERT INTO acollections (name, sort_order, created_at, updated_at)
+         SELECT ?1, COALESCE(MAX(sort_order), -1) + 1, ?2, ?2 FROM acollections",
+        params![name, now],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn get_or_add_person(conn: &rusqlite::Connection, name: &str) -> Result<i64, String> {
+    let existing: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM persons WHERE name = ?1 ORDER BY id LIMIT 1",
+            params![name],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some(person_id) = existing {
+        return Ok(person_id);
+    }
+    conn.execute(
+        "INSERT INTO persons (name, created_at) VALUES (?1, ?2)",
+        params![name, chrono::Utc::now().timestamp()],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Add a named face region to a file, or name the detected face it overlaps.
+/// Imported faces have no embedding, like a box the face model never saw, and
+/// count as drawn by the user. Returns whether anything changed.
+fn add_face(
+    conn: &rusqlite::Connection,
+    file_id: i64,
+    face: &CatalogFace,
+    person_id: i64,
+    overwrite: bool,
+) -> Result<bool, String> {
+    let (width, height, orientation): (Option<i64>, Option<i64>, Option<i32>) = conn
+        .query_row(
+            "SELECT width, height, e_orientation FROM afiles WHERE id = ?1",
+            params![file_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    let Some(rect) = to_stored_rect(
+        (face.x, face.y, face.width, face.height),
+        orientation.unwrap_or(1),
+        width.unwrap_or(0) as f32,
+        height.unwrap_or(0) as f32,
+    ) else {
+        return Ok(false);
+    };
+
+    let mut stmt = conn
+        .prepare("SELECT id, bbox, person_id FROM faces WHERE file_id = ?1")
+        .map_err(|e| e.to_string())?;
+    let existing = stmt
+        .query_map(params![file_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<i64>>(2)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for (face_id, bbox, current_person) in existing {
+        let Some(other) = bbox.as_deref().and_then(parse_bbox) else {
+            continue;
+        };
+        if rect_iou(rect, other) <= FACE_IOU_THRESHOLD {
+            continue;
+        }
+        if current_person == Some(person_id) || (current_person.is_some() && !overwrite) {
+            return Ok(false);
+        }
+        conn.execute(
+            "UPDATE faces SET person_id = ?1 WHERE id = ?2",
+            params![person_id, face_id],
+        )
+        .map_err(|e| e.to_string())?;
+        return Ok(true);
+    }
+
+    let bbox = serde_json::json!({
+        "x": rect.0,
+        "y": rect.1,
+        "width": rect.2,
+        "height": rect.3,
+        "confidence": 1.0,
+    })
+    .to_string();
+    conn.execute(
+        "INSERT INTO faces (file_id, bbox, person_id, created_at, is_manual) VALUES (?1, ?2, ?3, ?4, 1)",
+        params![file_id, bbox, person_id, chrono::Utc::now().timestamp()],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE afiles SET has_faces = 1 WHERE id = ?1",
+        params![file_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// Map a rectangle of the displayed image back to the stored pixels Lap keeps
+/// face boxes in; `width` and `height` are the stored dimensions. None when the
+/// orientation needs them and they are unknown (0).
+pub fn to_stored_rect(
+    rect: (f32, f32, f32, f32),
+    orientation: i32,
+    width: f32,
+    height: f32,
+) -> Option<(f32, f32, f32, f32)> {
+    let (x, y, w, h) = rect;
+    let known = width > 0.0 && height > 0.0;
+    match orientation {
+        3 | 6 | 8 if !known => None,
+        3 => Some((width - x - w, height - y - h, w, h)),
+        6 => Some((y, height - x - w, h, w)),
+        8 => Some((width - y - h, x, h, w)),
+        _ => Some(rect),
+    }
+}
+
+impl LibraryPaths {
+    fn load() -> Result<Self, String> {
+        let conn = open_conn()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT a.id, b.path, a.name FROM afiles a JOIN afolders b ON a.folder_id = b.id",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+
+        let mut paths = Self {
+            exact: HashMap::new(),
+            folded: HashMap::new(),
+        };
+        for row in rows {
+            let (id, folder_path, name) = row.map_err(|e| e.to_string())?;
+            let path = normalize_path(&t_utils::get_file_path(&folder_path, &name));
+            paths.folded.entry(path.to_lowercase()).or_insert(id);
+            paths.exact.insert(path, id);
+        }
+        Ok(paths)
+    }
+
+    fn find(&self, path: &str) -> Option<i64> {
+        let path = normalize_path(path);
+        self.exact
+            .get(&path)
+            .or_else(|| self.folded.get(&path.to_lowercase()))
+            .copied()
+    }
+}
+
+/// Forward slashes and no trailing slash, so catalogs written on Windows and
+/// macOS compare with the paths Lap stores
+pub fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        path
+    } else {
+        trimmed.to_string()
+    }
+}
+
+fn is_under(path: &str, root: &str) -> bool {
+    let path = normalize_path(path).to_lowercase();
+    let root = normalize_path(root).to_lowercase();
+    path == root || path.starts_with(&format!("{}/", root.trim_end_matches('/')))
+}
+
+/// Replace the longest matching `from` prefix of a path with its `to` folder
+pub fn remap_path(path: &str, root_map: &[RootMapping]) -> String {
+    let normalized = normalize_path(path);
+    let mapping = root_map
+        .iter()
+        .filter(|mapping| !mapping.from.trim().is_empty() && !mapping.to.trim().is_empty())
+        .filter(|mapping| is_under(&normalized, &mapping.from))
+        .max_by_key(|mapping| normalize_path(&mapping.from).len());
+    match mapping {
+        Some(mapping) => {
+            let from_len = normalize_path(&mapping.from).len();
+            let rest = normalized.get(from_len..).unwrap_or("");
+            format!(
+                "{}{}",
+                normalize_path(&mapping.to).trim_end_matches('/'),
+                rest
+            )
+        }
+        None => normalized,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(from: &str, to: &str) -> RootMapping {
+        RootMapping {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    #[test]
+    fn normalizes_paths() {
+        assert_eq!(normalize_path("C:\\Photos\\2019\\"), "C:/Photos/2019");
+        assert_eq!(normalize_path("/Volumes/Photos/"), "/Volumes/Photos");
+        assert_eq!(normalize_path("/"), "/");
+    }
+
+    #[test]
+    fn remaps_moved_roots() {
+        let root_map = [
+            mapping("/Volumes/Old Drive/Photos/", "/Volumes/New/Photos"),
+            mapping("/Volumes/Old Drive/Photos/Scans", "/Volumes/Scans"),
+        ];
+        assert_eq!(
+            remap_path("/Volumes/Old Drive/Photos/2019/IMG_1.CR2", &root_map),
+            "/Volumes/New/Photos/2019/IMG_1.CR2"
+        );
+        // the longest matching root wins
+        assert_eq!(
+            remap_path("/Volumes/Old Drive/Photos/Scans/a.tif", &root_map),
+            "/Volumes/Scans/a.tif"
+        );
+        // a folder that only shares a prefix is not a match
+        assert_eq!(
+            remap_path("/Volumes/Old Drive/Photos2/a.jpg", &root_map),
+            "/Volumes/Old Drive/Photos2/a.jpg"
+        );
+        assert_eq!(
+            remap_path(
+                "D:/Pictures/a.jpg",
+                &[mapping("D:/Pictures/", "E:\\Pictures")]
+            ),
+            "E:/Pictures/a.jpg"
+        );
+    }
+
+    #[test]
+    fn maps_face_rects_to_stored_pixels() {
+        // a 4000x3000 sensor image shown upright as 3000x4000
+        let displayed = (100.0, 200.0, 300.0, 400.0);
+        assert_eq!(
+            to_stored_rect(displayed, 1, 4000.0, 3000.0),
+            Some(displayed)
+        );
+        assert_eq!(
+            to_stored_rect(displayed, 3, 4000.0, 3000.0),
+            Some((3600.0, 2400.0, 300.0, 400.0))
+        );
+        assert_eq!(
+            to_stored_rect(displayed, 6, 4000.0, 3000.0),
+            Some((200.0, 2600.0, 400.0, 300.0))
+        );
+        assert_eq!(
+            to_stored_rect(displayed, 8, 4000.0, 3000.0),
+            Some((3400.0, 100.0, 400.0, 300.0))
+        );
+        // rotated faces need the stored dimensions
+        assert_eq!(to_stored_rect(displayed, 1, 0.0, 0.0), Some(displayed));
+        assert_eq!(to_stored_rect(displayed, 6, 0.0, 0.0), None);
+    }
+}
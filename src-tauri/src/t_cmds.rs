@@ -7,12 +7,15 @@
 use crate::t_config::{self, AppConfig, Library, LibraryInfo, LibraryState};
 use crate::t_face;
 use crate::t_image;
+use crate::t_index_errors::{self, IndexError, IndexErrorQuery, IndexErrorRetryResult};
+use crate::t_ocr;
 use crate::t_apple_sidecar::{
     apple_aae_sidecar_paths, build_apple_sidecar_rename_plan,
-    collect_original_rename_db_names, collect_replaced_file_ids_for_targets,
+    collect_original_rename_db_names,
     delete_apple_aae_sidecars, preflight_rename_plan, resolve_group_primary_target,
     rollback_copied_transfers, rollback_rename_changes, rollback_renamed_sidecars,
 };
+use crate::t_scan_filter::{ScanFilter, ScanFilterSettings};
 use crate::t_similar;
 use crate::t_sqlite::{
     ACamera, ACollection, ACollectionOrder, AFile, AFileCollection, AFolder, ALens, ALocation, ATag, ATagFileState,
@@ -21,7 +24,8 @@ use crate::t_sqlite::{
 };
 use crate::t_storage;
 use crate::t_utils;
-use crate::{t_ai, t_common, t_sqlite};
+use crate::t_watcher;
+use crate::{t_ai, t_ai_model, t_common, t_sqlite};
 
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
@@ -306,6 +310,10 @@ pub async fn switch_library(app_handle: tauri::AppHandle, id: String) -> Result<
     .map_err(|e| format!("Failed to join switch library task: {}", e))??;
 
     t_utils::restore_album_scopes(&app_handle)?;
+    if let Err(e) = t_ai_model::apply_library_model(&app_handle) {
+        eprintln!("Failed to apply the library image search model: {}", e);
+    }
+    t_watcher::start_folder_watcher(app_handle.clone());
     t_utils::start_folder_mtime_sync(app_handle);
     Ok(())
 }
@@ -341,7 +349,12 @@ pub fn get_current_library_state() -> Result<LibraryState, String> {
 /// get all albums
 #[tauri::command]
 pub fn get_all_albums() -> Result<Vec<Album>, String> {
-    Album::get_all_albums().map_err(|e| format!("Error while getting all albums: {}", e))
+    let mut albums =
+        Album::get_all_albums().map_err(|e| format!("Error while getting all albums: {}", e))?;
+    for album in &mut albums {
+        album.is_offline = Some(!t_utils::album_online(album));
+    }
+    Ok(albums)
 }
 
 /// Get the indexed folder records used by the album sidebar search.
@@ -363,7 +376,10 @@ pub fn generate_directory_thumbnails(
 /// get one album
 #[tauri::command]
 pub fn get_album(album_id: i64) -> Result<Album, String> {
-    Album::get_album_by_id(album_id).map_err(|e| format!("Error while getting one album: {}", e))
+    let mut album = Album::get_album_by_id(album_id)
+        .map_err(|e| format!("Error while getting one album: {}", e))?;
+    album.is_offline = Some(!t_utils::album_online(&album));
+    Ok(album)
 }
 
 /// recount files for an album and return updated album
@@ -396,6 +412,27 @@ pub fn edit_album(id: i64, name: &str, description: &str) -> Result<usize, Strin
         .map_err(|e| format!("Error while editing album with id {}: {}", id, e))
 }
 
+/// get an album's scan include/exclude filter
+#[tauri::command]
+pub fn get_album_scan_filter(album_id: i64) -> Result<ScanFilterSettings, String> {
+    ScanFilterSettings::load(album_id)
+        .map_err(|e| format!("Error while getting scan filter of album {}: {}", album_id, e))
+}
+
+/// save an album's scan filter and remove the rows it now excludes;
+/// returns the number of removed files (the caller rescans the album to pick up
+/// files a loosened filter lets through again)
+#[tauri::command]
+pub async fn set_album_scan_filter(
+    album_id: i64,
+    settings: ScanFilterSettings,
+) -> Result<u32, String> {
+    settings
+        .save(album_id)
+        .map_err(|e| format!("Error while saving scan filter of album {}: {}", album_id, e))?;
+    t_utils::prune_scan_filtered_files(album_id)
+}
+
 /// remove an album
 #[tauri::command]
 pub async fn remove_album(state: State<'_, IndexCancellation>, id: i64) -> Result<usize, String> {
@@ -488,6 +525,57 @@ pub fn clear_index_recovery_info() -> Result<(), String> {
     Ok(())
 }
 
+/// get the files that were skipped or failed during indexing
+#[tauri::command]
+pub fn get_index_errors(
+    query: IndexErrorQuery,
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<IndexError>, String> {
+    t_index_errors::list(&query, offset, limit)
+        .map_err(|e| format!("Error while getting index errors: {}", e))
+}
+
+/// count the files that were skipped or failed during indexing
+#[tauri::command]
+pub fn count_index_errors(query: IndexErrorQuery) -> Result<i64, String> {
+    t_index_errors::count(&query).map_err(|e| format!("Error while counting index errors: {}", e))
+}
+
+/// retry the failed files matching the query
+#[tauri::command]
+pub async fn retry_index_errors(
+    app_handle: tauri::AppHandle,
+    query: IndexErrorQuery,
+    thumbnail_size: u32,
+) -> Result<IndexErrorRetryResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        t_utils::retry_index_errors(&app_handle, &query, thumbnail_size)
+    })
+    .await
+    .map_err(|e| format!("Failed to join index error retry task: {}", e))?
+}
+
+/// hide index errors from the report and from retries (or show them again)
+#[tauri::command]
+pub fn ignore_index_errors(ids: Vec<i64>, ignored: bool) -> Result<usize, String> {
+    t_index_errors::set_ignored(&ids, ignored)
+        .map_err(|e| format!("Error while updating index errors: {}", e))
+}
+
+/// export the index errors matching the query to a CSV file
+#[tauri::command]
+pub fn export_index_errors(query: IndexErrorQuery, file_path: &str) -> Result<usize, String> {
+    t_index_errors::export_csv(&query, file_path)
+        .map_err(|e| format!("Error while exporting index errors: {}", e))
+}
+
+/// set how the folder watcher syncs changes (it holds events until this is known)
+#[tauri::command]
+pub fn set_folder_watcher_options(group_raw_jpeg_pairs: bool) {
+    t_watcher::set_group_raw_jpeg_pairs(group_raw_jpeg_pairs);
+}
+
 // folder
 
 fn find_renamed_sibling_folder(
@@ -570,10 +658,16 @@ pub fn fetch_folder(
     t_utils::FileNode::build_nodes(path, is_recursive, sort)
 }
 
-/// count all files in a folder (include all sub-folders)
+/// count all files in a folder (include all sub-folders), honouring the
+/// given filter settings (as being edited), else the album's saved scan filter
 #[tauri::command]
-pub fn count_folder(path: &str) -> (u64, u64, u64, u64, u64, u64, u64) {
-    t_utils::count_folder_files(path)
+pub fn count_folder(path: &str, album_id: Option<i64>, settings: Option<ScanFilterSettings>) -> (u64, u64, u64, u64, u64, u64, u64) {
+    let filter = match (settings, album_id) {
+        (Some(settings), _) => ScanFilter::new(path, &settings),
+        (None, Some(album_id)) => ScanFilter::for_album(album_id, path),
+        (None, None) => ScanFilter::none(path),
+    };
+    t_utils::count_folder_files(path, &filter)
 }
 
 /// create a new folder
@@ -1189,97 +1283,8 @@ pub fn move_file(
     new_folder_path: &str,
     conflict_policy: &str,
 ) -> Result<String, String> {
-    let old_file_info = AFile::get_file_info(file_id).ok().flatten();
-    let old_album_id = old_file_info.as_ref().and_then(|file| file.album_id);
-    let new_album_id = AFolder::get_by_id(new_folder_id)
-        .ok()
-        .flatten()
-        .map(|folder| folder.album_id);
     let policy = t_utils::FileConflictPolicy::from_str(conflict_policy);
-    let (primary_target, sidecar_plans) =
-        resolve_group_primary_target(Some(file_id), file_path, new_folder_path, policy)?;
-    let mut replaced_file_ids = Vec::new();
-    let mut source_file_ids = HashSet::from([file_id]);
-    for plan in &sidecar_plans {
-        if let Some(component_id) = plan.file_id {
-            source_file_ids.insert(component_id);
-        }
-    }
-    if policy == t_utils::FileConflictPolicy::Replace {
-        collect_replaced_file_ids_for_targets(
-            new_folder_id,
-            std::iter::once(&primary_target).chain(sidecar_plans.iter().map(|plan| &plan.new_path)),
-            &source_file_ids,
-            &mut replaced_file_ids,
-        );
-    }
-
-    let mut component_transfers = Vec::new();
-    for plan in &sidecar_plans {
-        let source_path = plan.old_path.to_string_lossy().into_owned();
-        match t_utils::move_file_to_path_with_policy(&source_path, &plan.new_path, policy) {
-            Ok(transfer) => {
-                component_transfers.push((plan.file_id, plan.old_path.clone(), transfer))
-            }
-            Err(error) => {
-                for (_, original_path, transfer) in component_transfers {
-                    let _ = transfer.rollback_move(&original_path);
-                }
-                return Err(error);
-            }
-        }
-    }
-
-    let transfer = match t_utils::move_file_to_path_with_policy(file_path, &primary_target, policy)
-    {
-        Ok(transfer) => transfer,
-        Err(error) => {
-            for (_, original_path, transfer) in component_transfers {
-                let _ = transfer.rollback_move(&original_path);
-            }
-            return Err(error);
-        }
-    };
-
-    let component_file_ids = component_transfers
-        .iter()
-        .filter_map(|(component_id, _, _)| *component_id)
-        .collect::<Vec<_>>();
-    if let Err(error) = AFile::update_moved_file_group(
-        file_id,
-        &component_file_ids,
-        &replaced_file_ids,
-        new_folder_id,
-    ) {
-        let rollback_error = transfer.rollback_move(Path::new(file_path)).err();
-        for (_, original_path, transfer) in component_transfers {
-            let _ = transfer.rollback_move(&original_path);
-        }
-        return Err(match rollback_error {
-            Some(rollback_error) => format!(
-                "Error while moving file group in DB: {}; rollback also failed: {}",
-                error, rollback_error
-            ),
-            None => format!("Error while moving file group in DB: {}", error),
-        });
-    }
-
-    if let (Some(old_album_id), Some(new_album_id)) = (old_album_id, new_album_id) {
-        let _ = AThumb::relocate_for_file(file_id, old_album_id, new_album_id)
-            .map_err(|e| format!("Error while relocating thumbnail cache: {}", e));
-    }
-    for (component_id, _, transfer) in component_transfers {
-        if let Some(component_id) = component_id {
-            if let (Some(old_album_id), Some(new_album_id)) = (old_album_id, new_album_id) {
-                let _ = AThumb::relocate_for_file(component_id, old_album_id, new_album_id)
-                    .map_err(|e| {
-                        format!("Error while relocating Live Photo thumbnail cache: {}", e)
-                    });
-            }
-        }
-        transfer.finalize()?;
-    }
-    transfer.finalize()
+    t_utils::move_file_group(file_id, file_path, new_folder_id, new_folder_path, policy)
 }
 
 /// move a file outside the library and remove its database record
@@ -2632,6 +2637,60 @@ pub async fn cancel_multilingual_image_search_model_download(
     t_ai::cancel_multilingual_text_model_download(app_handle).await
 }
 
+/// list the bundled and locally installed image search models
+#[tauri::command]
+pub fn list_image_search_models(
+    app_handle: AppHandle,
+) -> Result<Vec<t_ai_model::ModelManifest>, String> {
+    t_ai::AiEngine::list_image_models(&app_handle)
+}
+
+/// switch the image embedding model of the current library and re-embed in the background
+#[tauri::command]
+pub async fn set_image_search_image_model(
+    app_handle: AppHandle,
+    state: State<'_, t_ai::AiState>,
+    reembed_state: State<'_, t_ai_model::ReembedState>,
+    model_id: String,
+) -> Result<t_ai::ImageSearchModelStatus, String> {
+    let status = {
+        let mut ai_engine = state.0.lock().unwrap();
+        ai_engine.set_image_model(&app_handle, &model_id)?;
+        ai_engine.model_status(&app_handle)
+    };
+    t_ai_model::save_library_model_id(&model_id)?;
+    // a job that is already running picks up the new model by itself
+    if !reembed_state.is_running.load(std::sync::atomic::Ordering::SeqCst)
+        && t_ai_model::count_stale_embeddings()? > 0
+    {
+        t_ai_model::start_reembed(app_handle, &reembed_state)?;
+    }
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn start_image_search_reembed(
+    app_handle: AppHandle,
+    state: State<t_ai_model::ReembedState>,
+) -> Result<(), String> {
+    t_ai_model::start_reembed(app_handle, &state)
+}
+
+#[tauri::command]
+pub fn cancel_image_search_reembed(state: State<t_ai_model::ReembedState>) -> Result<(), String> {
+    state
+        .cancel_flag
+        .store(true, std::sync::atomic::Ordering::SeqCst);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_image_search_reembed_status(
+    state: State<t_ai_model::ReembedState>,
+) -> Result<t_ai_model::ReembedStatus, String> {
+    Ok(state.status.lock().unwrap().clone())
+}
+
 /// generate embedding for a file
 #[tauri::command]
 pub fn generate_embedding(state: State<t_ai::AiState>, file_id: i64) -> Result<String, String> {
@@ -2658,13 +2717,22 @@ pub fn similar_start_scan(
     params: Option<QueryParams>,
     collection_id: Option<i64>,
     file_ids: Option<Vec<i64>>,
+    full_rescan: Option<bool>,
+    time_window: Option<i64>,
+    cluster_mode: Option<String>,
 ) -> Result<(), String> {
+    let options = t_similar::ScanOptions {
+        similarity_threshold,
+        time_window,
+        cluster_mode: t_similar::ClusterMode::parse(cluster_mode.as_deref())?,
+        full_rescan: full_rescan.unwrap_or(false),
+    };
     t_similar::start_scan(
         app_handle,
         state,
         scope_key,
         source_version,
-        similarity_threshold,
+        options,
         params,
         collection_id,
         file_ids,
@@ -2777,6 +2845,58 @@ pub fn is_face_indexing(
     Ok((is_running, progress))
 }
 
+/// check if the optional OCR models are installed
+#[tauri::command]
+pub fn is_ocr_available(app_handle: AppHandle) -> bool {
+    t_ocr::OcrEngine::is_available(&app_handle)
+}
+
+/// recognize text in all unprocessed images in the background
+#[tauri::command]
+pub fn start_ocr_index(app_handle: AppHandle, state: State<t_ocr::OcrState>) -> Result<(), String> {
+    t_ocr::start_text_index(app_handle, &state, None)
+}
+
+#[tauri::command]
+pub fn cancel_ocr_index(state: State<t_ocr::OcrState>) -> Result<(), String> {
+    state
+        .cancel_flag
+        .store(true, std::sync::atomic::Ordering::SeqCst);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_ocr_index_status(
+    state: State<t_ocr::OcrState>,
+) -> Result<t_ocr::OcrIndexStatus, String> {
+    Ok(state.status.lock().unwrap().clone())
+}
+
+/// forget all recognized text so the next run processes every image again
+#[tauri::command]
+pub fn reset_ocr_index(state: State<t_ocr::OcrState>) -> Result<(), String> {
+    if state.is_running.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err("Text recognition is running.".to_string());
+    }
+    t_ocr::reset_text_index()
+}
+
+/// get the recognized text of a file
+#[tauri::command]
+pub fn get_file_text(file_id: i64) -> Result<Option<String>, String> {
+    t_ocr::get_file_text(file_id)
+}
+
+/// recognize the text of a single file now
+#[tauri::command]
+pub async fn recognize_file_text(
+    app_handle: AppHandle,
+    state: State<'_, t_ocr::OcrState>,
+    file_id: i64,
+) -> Result<String, String> {
+    t_ocr::recognize_file(&app_handle, &state, file_id)
+}
+
 /// get all persons with face counts
 #[tauri::command]
 pub fn get_persons(sort: i64) -> Result<Vec<Person>, String> {
@@ -2809,6 +2929,55 @@ pub fn get_faces_for_file(file_id: i64) -> Result<Vec<t_sqlite::Face>, String> {
         .map_err(|e| format!("Error while getting faces for file: {}", e))
 }
 
+/// add a face box drawn by the user to a file
+#[tauri::command]
+pub async fn add_face(
+    app_handle: tauri::AppHandle,
+    state: State<'_, t_face::FaceState>,
+    file_id: i64,
+    rect: t_face::FaceRect,
+    person_id: Option<i64>,
+) -> Result<i64, String> {
+    t_face::add_manual_face(&app_handle, &state, file_id, rect, person_id)
+        .map_err(|e| format!("Error while adding face: {}", e))
+}
+
+/// move or resize a face box
+#[tauri::command]
+pub async fn update_face_box(
+    app_handle: tauri::AppHandle,
+    state: State<'_, t_face::FaceState>,
+    face_id: i64,
+    rect: t_face::FaceRect,
+) -> Result<usize, String> {
+    t_face::update_face_box(&app_handle, &state, face_id, rect)
+        .map_err(|e| format!("Error while updating face: {}", e))
+}
+
+/// assign a face to a person (or unassign it)
+#[tauri::command]
+pub fn set_face_person(face_id: i64, person_id: Option<i64>) -> Result<usize, String> {
+    t_face::set_face_person(face_id, person_id)
+        .map_err(|e| format!("Error while setting face person: {}", e))
+}
+
+/// delete a face (false-positive detections are not recreated by reindexing)
+#[tauri::command]
+pub fn delete_face(face_id: i64) -> Result<usize, String> {
+    t_face::delete_face(face_id).map_err(|e| format!("Error while deleting face: {}", e))
+}
+
+/// search the library for files containing faces similar to a given face
+#[tauri::command]
+pub async fn search_faces(
+    face_id: i64,
+    threshold: Option<f32>,
+    limit: Option<usize>,
+) -> Result<Vec<t_face::FaceSearchMatch>, String> {
+    t_face::search_faces(face_id, threshold.unwrap_or(0.45), limit.unwrap_or(0))
+        .map_err(|e| format!("Error while searching faces: {}", e))
+}
+
 // ----------------------------------------------------------------------------
 // Deduplication Commands
 // ----------------------------------------------------------------------------
@@ -2820,8 +2989,19 @@ pub fn dedup_start_scan(
     params: Option<crate::t_sqlite::QueryParams>,
     collection_id: Option<i64>,
     file_ids: Option<Vec<i64>>,
+    match_kind: Option<String>,
+    max_distance: Option<u32>,
 ) -> Result<(), String> {
-    crate::t_dedup::start_scan(app_handle, state, params, collection_id, file_ids)
+    let match_kind = crate::t_dedup::MatchKind::parse(match_kind.as_deref())?;
+    crate::t_dedup::start_scan(
+        app_handle,
+        state,
+        params,
+        collection_id,
+        file_ids,
+        match_kind,
+        max_distance,
+    )
 }
 
 #[tauri::command]
@@ -2863,12 +3043,352 @@ pub fn dedup_set_keep(group_id: i64, file_id: i64) -> Result<(), String> {
     crate::t_dedup::set_keep(group_id, file_id)
 }
 
+#[tauri::command]
+pub fn dedup_get_keep_policy() -> Result<crate::t_dedup::KeepPolicy, String> {
+    Ok(crate::t_dedup::load_keep_policy())
+}
+
+/// save the keep policy of the current library and re-rank existing duplicate groups
+#[tauri::command]
+pub fn dedup_set_keep_policy(policy: crate::t_dedup::KeepPolicy) -> Result<(), String> {
+    crate::t_dedup::save_keep_policy(&policy)
+}
+
+/// replace selected duplicates with hardlinks/reflinks to the kept file
+/// mode: "auto" (default), "hardlink" or "reflink"
+#[tauri::command]
+pub fn dedup_link_selected(
+    group_ids: Option<Vec<i64>>,
+    file_ids: Option<Vec<i64>>,
+    mode: Option<String>,
+) -> Result<crate::t_dedup::DedupLinkResult, String> {
+    let mode = crate::t_dedup::LinkMode::parse(mode.as_deref())?;
+    crate::t_dedup::link_selected(group_ids, file_ids, mode)
+}
+
 #[tauri::command]
 pub fn dedup_delete_selected(
     group_ids: Option<Vec<i64>>,
     file_ids: Option<Vec<i64>>,
+    merge_metadata: Option<bool>,
 ) -> Result<crate::t_dedup::DedupDeleteResult, String> {
-    crate::t_dedup::delete_selected(group_ids, file_ids)
+    crate::t_dedup::delete_selected(group_ids, file_ids, merge_metadata.unwrap_or(false))
+}
+
+// ----------------------------------------------------------------------------
+// Integrity Commands
+// ----------------------------------------------------------------------------
+
+/// verify file hashes (and optionally decode images) in the background
+#[tauri::command]
+pub fn integrity_start_check(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, crate::t_integrity::IntegrityState>,
+    options: Option<crate::t_integrity::IntegrityOptions>,
+) -> Result<(), String> {
+    crate::t_integrity::start_check(app_handle, &state, options.unwrap_or_default(), false)
+}
+
+#[tauri::command]
+pub fn integrity_get_status(
+    state: tauri::State<'_, crate::t_integrity::IntegrityState>,
+) -> Result<crate::t_integrity::IntegrityStatus, String> {
+    let mut status = state.status.lock().unwrap().clone();
+    status.is_running = state.is_running.load(std::sync::atomic::Ordering::SeqCst);
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn integrity_cancel_check(
+    state: tauri::State<'_, crate::t_integrity::IntegrityState>,
+) -> Result<(), String> {
+    state
+        .cancel_flag
+        .store(true, std::sync::atomic::Ordering::SeqCst);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn integrity_get_overview() -> Result<crate::t_integrity::IntegrityOverview, String> {
+    crate::t_integrity::get_overview()
+}
+
+/// status: "corrupt", "decode_failed", "unreadable" or None for all problems
+#[tauri::command]
+pub fn integrity_list_issues(
+    status: Option<String>,
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<crate::t_integrity::IntegrityIssue>, String> {
+    crate::t_integrity::list_issues(status.as_deref(), offset, limit)
+}
+
+#[tauri::command]
+pub async fn integrity_find_intact_copies(
+    file_id: i64,
+) -> Result<Vec<crate::t_integrity::IntactCopy>, String> {
+    tauri::async_runtime::spawn_blocking(move || crate::t_integrity::find_intact_copies(file_id))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn integrity_accept_current(file_ids: Vec<i64>) -> Result<usize, String> {
+    crate::t_integrity::accept_current(&file_ids)
+}
+
+#[tauri::command]
+pub fn integrity_get_schedule() -> Result<crate::t_integrity::IntegritySchedule, String> {
+    Ok(crate::t_integrity::load_schedule())
+}
+
+#[tauri::command]
+pub fn integrity_set_schedule(
+    schedule: crate::t_integrity::IntegritySchedule,
+) -> Result<(), String> {
+    crate::t_integrity::save_schedule(&schedule)
+}
+
+// ----------------------------------------------------------------------------
+// Import Commands
+// ----------------------------------------------------------------------------
+
+/// scan a memory card or camera folder and open an import session
+#[tauri::command]
+pub async fn import_scan_source(
+    state: tauri::State<'_, crate::t_import::ImportState>,
+    source_path: String,
+) -> Result<crate::t_import::ImportSession, String> {
+    let session =
+        tauri::async_runtime::spawn_blocking(move || crate::t_import::scan_source(&source_path))
+            .await
+            .map_err(|e| e.to_string())??;
+    crate::t_import::open_session(&state, &session);
+    Ok(session)
+}
+
+#[tauri::command]
+pub fn import_close_session(
+    state: tauri::State<'_, crate::t_import::ImportState>,
+    session_id: String,
+) -> Result<(), String> {
+    crate::t_import::close_session(&state, &session_id);
+    Ok(())
+}
+
+/// thumbnail of a source file as base64 jpeg
+#[tauri::command]
+pub async fn import_get_preview(
+    file_path: String,
+    thumbnail_size: u32,
+) -> Result<Option<String>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::t_import::get_preview(&file_path, thumbnail_size)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// copy the selected items of a session into an album in the background
+#[tauri::command]
+pub fn import_start(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, crate::t_import::ImportState>,
+    session_id: String,
+    options: crate::t_import::ImportOptions,
+) -> Result<(), String> {
+    crate::t_import::start_import(app_handle, &state, &session_id, options)
+}
+
+#[tauri::command]
+pub fn import_get_status(
+    state: tauri::State<'_, crate::t_import::ImportState>,
+) -> Result<crate::t_import::ImportStatus, String> {
+    let mut status = state.status.lock().unwrap().clone();
+    status.is_running = state.is_running.load(std::sync::atomic::Ordering::SeqCst);
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn import_cancel(state: tauri::State<'_, crate::t_import::ImportState>) -> Result<(), String> {
+    state
+        .cancel_flag
+        .store(true, std::sync::atomic::Ordering::SeqCst);
+    Ok(())
+}
+
+/// preview the folder a destination template expands to for a given date
+#[tauri::command]
+pub fn import_expand_template(template: String, taken_date: i64, event: String) -> String {
+    crate::t_import::expand_template(&template, taken_date, &event)
+        .to_string_lossy()
+        .into_owned()
+}
+
+// ----------------------------------------------------------------------------
+// Batch Rename Commands
+// ----------------------------------------------------------------------------
+
+/// preview a template rename of the files in the given order
+#[tauri::command]
+pub fn batch_rename_preview(
+    file_ids: Vec<i64>,
+    options: crate::t_rename::RenameOptions,
+) -> Result<Vec<crate::t_rename::RenamePreviewItem>, String> {
+    crate::t_rename::preview(&file_ids, &options)
+}
+
+#[tauri::command]
+pub fn batch_rename_apply(
+    file_ids: Vec<i64>,
+    options: crate::t_rename::RenameOptions,
+) -> Result<crate::t_rename::RenameResult, String> {
+    crate::t_rename::apply(&file_ids, &options)
+}
+
+/// restore the names of a rename batch (the latest one when batch_id is None)
+#[tauri::command]
+pub fn batch_rename_undo(batch_id: Option<i64>) -> Result<usize, String> {
+    crate::t_rename::undo(batch_id)
+}
+
+#[tauri::command]
+pub fn batch_rename_list_batches(limit: i64) -> Result<Vec<crate::t_rename::RenameBatch>, String> {
+    crate::t_rename::list_batches(limit)
+}
+
+// ----------------------------------------------------------------------------
+// Organize Commands
+// ----------------------------------------------------------------------------
+
+/// dry run: target folders, counts and conflicts for a folder template
+#[tauri::command]
+pub async fn organize_preview(
+    scope: crate::t_organize::OrganizeScope,
+    options: crate::t_organize::OrganizeOptions,
+) -> Result<crate::t_organize::OrganizePlan, String> {
+    tauri::async_runtime::spawn_blocking(move || crate::t_organize::build_plan(&scope, &options))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// move the files of the plan in the background
+#[tauri::command]
+pub fn organize_start(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, crate::t_organize::OrganizeState>,
+    scope: crate::t_organize::OrganizeScope,
+    options: crate::t_organize::OrganizeOptions,
+) -> Result<(), String> {
+    crate::t_organize::start_organize(app_handle, &state, scope, options)
+}
+
+#[tauri::command]
+pub fn organize_get_status(
+    state: tauri::State<'_, crate::t_organize::OrganizeState>,
+) -> Result<crate::t_organize::OrganizeStatus, String> {
+    let mut status = state.status.lock().unwrap().clone();
+    status.is_running = state.is_running.load(std::sync::atomic::Ordering::SeqCst);
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn organize_cancel(
+    state: tauri::State<'_, crate::t_organize::OrganizeState>,
+) -> Result<(), String> {
+    state
+        .cancel_flag
+        .store(true, std::sync::atomic::Ordering::SeqCst);
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+// Takeout Commands
+// ----------------------------------------------------------------------------
+
+/// scan an extracted Google Takeout folder and open a session
+#[tauri::command]
+pub async fn takeout_scan_source(
+    state: tauri::State<'_, crate::t_takeout::TakeoutState>,
+    source_path: String,
+) -> Result<crate::t_takeout::TakeoutSession, String> {
+    let session =
+        tauri::async_runtime::spawn_blocking(move || crate::t_takeout::scan_source(&source_path))
+            .await
+            .map_err(|e| e.to_string())??;
+    crate::t_takeout::open_session(&state, &session);
+    Ok(session)
+}
+
+#[tauri::command]
+pub fn takeout_close_session(
+    state: tauri::State<'_, crate::t_takeout::TakeoutState>,
+    session_id: String,
+) -> Result<(), String> {
+    crate::t_takeout::close_session(&state, &session_id);
+    Ok(())
+}
+
+/// copy a Takeout session into an album and apply its sidecar metadata in the background
+#[tauri::command]
+pub fn takeout_start(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, crate::t_takeout::TakeoutState>,
+    session_id: String,
+    options: crate::t_takeout::TakeoutOptions,
+) -> Result<(), String> {
+    crate::t_takeout::start_import(app_handle, &state, &session_id, options)
+}
+
+#[tauri::command]
+pub fn takeout_get_status(
+    state: tauri::State<'_, crate::t_takeout::TakeoutState>,
+) -> Result<crate::t_import::ImportStatus, String> {
+    let mut status = state.status.lock().unwrap().clone();
+    status.is_running = state.is_running.load(std::sync::atomic::Ordering::SeqCst);
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn takeout_cancel(
+    state: tauri::State<'_, crate::t_takeout::TakeoutState>,
+) -> Result<(), String> {
+    state
+        .cancel_flag
+        .store(true, std::sync::atomic::Ordering::SeqCst);
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+// Catalog Import Commands
+// ----------------------------------------------------------------------------
+
+/// read another application's catalog and count what would match the library
+#[tauri::command]
+pub async fn catalog_import_preview(
+    kind: String,
+    catalog_path: String,
+    root_map: Vec<crate::t_catalog_import::RootMapping>,
+) -> Result<crate::t_catalog_import::CatalogPreview, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::t_catalog_import::preview(&kind, &catalog_path, &root_map)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// write a catalog's ratings, flags, keywords and collections into the library
+#[tauri::command]
+pub async fn catalog_import_apply(
+    kind: String,
+    catalog_path: String,
+    options: crate::t_catalog_import::CatalogImportOptions,
+) -> Result<crate::t_catalog_import::CatalogImportResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::t_catalog_import::import(&kind, &catalog_path, &options)
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 // ----------------------------------------------------------------------------
@@ -55,6 +55,10 @@ pub const VIDEOS: &[&str] = &[
 pub const AI_TEXT_MODEL: &str = "text_model.onnx";
 pub const AI_VISION_MODEL: &str = "vision_model.onnx";
 pub const AI_TOKENIZER: &str = "tokenizer.json";
+pub const AI_MODEL_MANIFEST: &str = "model.json"; // describes a custom model (see t_ai_model)
+pub const AI_BUILTIN_MODEL_ID: &str = "bundled-clip"; // model id of the bundled CLIP files
+pub const AI_VIDEO_KEYFRAMES: usize = 8; // keyframes sampled per video for AI search
+pub const AI_VIDEO_KEYFRAME_SIZE: u32 = 384; // longest side of a sampled keyframe
 
 // Face Recognition Constants
 
@@ -71,3 +75,8 @@ pub const MIN_BLUR_SCORE: f32 = 200.0; // Standard Laplacian variance threshold.
 // Clustering Constants
 pub const K_NEIGHBORS: usize = 80; // Prune edges to Top-K (K-NN)
 pub const MIN_SAMPLES: usize = 1; // Minimum samples per cluster
+
+// OCR models (optional, installed next to the face models)
+pub const OCR_DETECTION_MODEL: &str = "ocr_det.onnx"; // DB text detector
+pub const OCR_RECOGNITION_MODEL: &str = "ocr_rec.onnx"; // CTC text recognizer
+pub const OCR_DICTIONARY: &str = "ocr_keys.txt"; // recognizer characters, one per line
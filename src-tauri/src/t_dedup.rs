@@ -1,10 +1,12 @@
-use crate::t_sqlite::{AFile, QueryParams};
+use crate::t_face::{parse_bbox, rect_iou};
+use crate::t_sqlite::{AFile, AThumb, QueryParams};
 use crate::t_utils;
 use rusqlite::{Connection, OptionalExtension, params};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{self, Read};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -17,7 +19,9 @@ use tauri::Emitter;
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct DedupScanStatus {
-    pub state: String, // "running", "idle", "finished", "error"
+    pub state: String,      // "running", "idle", "finished", "error"
+    pub match_kind: String, // "exact", "dhash", "phash", "video"
+    pub phase: String,      // "sample" or "full" (exact), "hash" (perceptual)
     pub processed: u64,
     pub total: u64,
     pub groups: u64,
@@ -28,6 +32,8 @@ impl Default for DedupScanStatus {
     fn default() -> Self {
         Self {
             state: "idle".to_string(),
+            match_kind: MatchKind::Exact.as_str().to_string(),
+            phase: "sample".to_string(),
             processed: 0,
             total: 0,
             groups: 0,
@@ -43,6 +49,60 @@ pub struct DedupDeleteResult {
     pub failed_count: usize,
     pub errors: Vec<String>,
     pub trash_failed_file_ids: Vec<i64>,
+    pub merged: Option<DedupMergeSummary>, // set when metadata was merged into the keepers
+}
+
+/// What was carried over from deleted duplicates into their kept files
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DedupMergeSummary {
+    pub merged_files: usize,
+    pub tags_added: usize,
+    pub collections_added: usize,
+    pub ratings_raised: usize,
+    pub favorites_set: usize,
+    pub comments_merged: usize,
+    pub faces_assigned: usize,
+}
+
+impl DedupMergeSummary {
+    fn add(&mut self, other: &DedupMergeSummary) {
+        self.merged_files += other.merged_files;
+        self.tags_added += other.tags_added;
+        self.collections_added += other.collections_added;
+        self.ratings_raised += other.ratings_raised;
+        self.favorites_set += other.favorites_set;
+        self.comments_merged += other.comments_merged;
+        self.faces_assigned += other.faces_assigned;
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DedupLinkResult {
+    pub linked_file_ids: Vec<i64>,
+    pub reclaimed_bytes: i64,
+    pub failed_count: usize,
+    pub errors: Vec<String>,
+}
+
+/// How a duplicate is replaced when deduplicating in place
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkMode {
+    Auto,     // reflink when the filesystem supports it, otherwise hardlink
+    Hardlink, // shares one inode; edits to either path affect both
+    Reflink,  // copy-on-write clone; the files stay independent
+}
+
+impl LinkMode {
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value.unwrap_or("auto") {
+            "auto" => Ok(Self::Auto),
+            "hardlink" => Ok(Self::Hardlink),
+            "reflink" => Ok(Self::Reflink),
+            other => Err(format!("Unknown link mode: {}", other)),
+        }
+    }
 }
 
 #[derive(Default)]
@@ -57,8 +117,106 @@ struct KeepCandidate {
     id: i64,
     taken_date: i64,
     created_at: i64,
+    pixels: i64,
+    size: i64,
+    has_exif: bool,
+    has_gps: bool,
+    has_rating: bool, // rated or favorite
+    has_tags: bool,
+    has_collections: bool,
+    path: String,
+    score: f64,                 // filled in by rank_candidates
+    reasons: Vec<&'static str>, // policy criteria behind the score
+}
+
+/// Weighted criteria used to pick the file to keep in a duplicate group.
+/// Saved per library; a weight of 0 disables a criterion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct KeepPolicy {
+    pub resolution: f64,                // highest resolution in the group
+    pub file_size: f64,                 // largest file in the group
+    pub exif: f64,                      // has camera EXIF
+    pub gps: f64,                       // has GPS coordinates
+    pub rating: f64,                    // has a rating or is a favorite
+    pub tags: f64,                      // has tags
+    pub collections: f64,               // belongs to a collection
+    pub preferred_folder: f64,          // lives under one of preferred_folders
+    pub avoided_path: f64,              // penalty for matching one of avoided_paths
+    pub preferred_folders: Vec<String>, // folder paths, e.g. "/Photos/Archive"
+    pub avoided_paths: Vec<String>,     // case-insensitive path fragments, e.g. "Downloads"
+}
+
+impl Default for KeepPolicy {
+    fn default() -> Self {
+        Self {
+            resolution: 3.0,
+            file_size: 1.0,
+            exif: 1.0,
+            gps: 1.0,
+            rating: 2.0,
+            tags: 1.0,
+            collections: 1.0,
+            preferred_folder: 4.0,
+            avoided_path: 4.0,
+            preferred_folders: Vec::new(),
+            avoided_paths: vec!["Downloads".to_string(), "copy".to_string()],
+        }
+    }
 }
 
+const KEEP_POLICY_SETTING: &str = "dedup_keep_policy";
+
+// Columns read by keep_candidate_from_row (requires afiles a JOIN afolders f)
+const KEEP_CANDIDATE_COLUMNS: &str = "a.id, a.taken_date, a.created_at,
+    COALESCE(a.width, 0) * COALESCE(a.height, 0), a.size,
+    (a.e_make IS NOT NULL OR a.e_model IS NOT NULL OR a.e_date_time IS NOT NULL),
+    (a.gps_latitude IS NOT NULL AND a.gps_longitude IS NOT NULL),
+    (COALESCE(a.rating, 0) > 0 OR COALESCE(a.is_favorite, 0) = 1),
+    EXISTS (SELECT 1 FROM afile_tags at WHERE at.file_id = a.id),
+    EXISTS (SELECT 1 FROM acollections_files cf WHERE cf.file_id = a.id),
+    f.path || '/' || a.name";
+
+/// How files are matched into duplicate groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    Exact, // byte-identical (blake3)
+    DHash, // perceptual difference hash of the thumbnail
+    PHash, // perceptual DCT hash of the thumbnail
+    Video, // dHashes of frames sampled through ffmpeg, aligned in time
+}
+
+impl MatchKind {
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value.unwrap_or("exact") {
+            "exact" => Ok(Self::Exact),
+            "dhash" => Ok(Self::DHash),
+            "phash" => Ok(Self::PHash),
+            "video" => Ok(Self::Video),
+            other => Err(format!("Unknown dedup match kind: {}", other)),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Exact => "exact",
+            Self::DHash => "dhash",
+            Self::PHash => "phash",
+            Self::Video => "video",
+        }
+    }
+}
+
+const DEFAULT_HASH_DISTANCE: u32 = 6; // max Hamming distance (of 64 bits) for a perceptual match
+const MAX_HASH_DISTANCE: u32 = 16;
+const SAMPLE_HASH_CHUNK: usize = 64 * 1024; // bytes read at each of head, middle and tail
+const VIDEO_FRAME_INTERVAL: u32 = 2; // seconds between fingerprint frames
+const VIDEO_MAX_FRAMES: usize = 300; // fingerprint covers the first 10 minutes only
+const VIDEO_MAX_FRAME_DISTANCE: u32 = 3; // candidate buckets find every frame pair up to this
+const VIDEO_MIN_OVERLAP: f32 = 0.8; // share of the shorter clip's frames that must match
+const VIDEO_MAX_BUCKET: usize = 64;
+const FACE_MATCH_IOU: f32 = 0.5; // a kept face this close to a named duplicate face is the same face
+
 // ----------------------------------------------------------------------------
 // Core Logic
 // ----------------------------------------------------------------------------
@@ -69,6 +227,8 @@ pub fn start_scan(
     query_params: Option<QueryParams>,
     collection_id: Option<i64>,
     file_ids: Option<Vec<i64>>,
+    match_kind: MatchKind,
+    max_distance: Option<u32>,
 ) -> Result<(), String> {
     if file_ids.is_some() && (collection_id.is_some() || query_params.is_some()) {
         return Err("File ID scope cannot be combined with query or collection scope.".into());
@@ -91,6 +251,13 @@ pub fn start_scan(
     {
         let mut status = status_clone.lock().unwrap();
         status.state = "running".to_string();
+        status.match_kind = match_kind.as_str().to_string();
+        status.phase = if match_kind == MatchKind::Exact {
+            "sample"
+        } else {
+            "hash"
+        }
+        .to_string();
         status.processed = 0;
         status.total = 0;
         status.groups = 0;
@@ -98,14 +265,40 @@ pub fn start_scan(
     }
 
     std::thread::spawn(move || {
-        let result = scan_and_hash_files(
-            &app_handle,
-            &status_clone,
-            &cancel_flag_clone,
-            query_params,
-            collection_id,
-            file_ids,
-        );
+        let result = if match_kind == MatchKind::Exact {
+            scan_and_hash_files(
+                &app_handle,
+                &status_clone,
+                &cancel_flag_clone,
+                query_params,
+                collection_id,
+                file_ids,
+            )
+        } else {
+            let max_distance = max_distance
+                .unwrap_or(DEFAULT_HASH_DISTANCE)
+                .min(MAX_HASH_DISTANCE);
+            get_scoped_files(query_params, collection_id, file_ids).and_then(|scoped_files| {
+                if match_kind == MatchKind::Video {
+                    scan_video_fingerprints(
+                        &app_handle,
+                        &status_clone,
+                        &cancel_flag_clone,
+                        scoped_files,
+                        max_distance.min(VIDEO_MAX_FRAME_DISTANCE),
+                    )
+                } else {
+                    scan_perceptual_hashes(
+                        &app_handle,
+                        &status_clone,
+                        &cancel_flag_clone,
+                        scoped_files,
+                        match_kind,
+                        max_distance,
+                    )
+                }
+            })
+        };
 
         let mut final_status = status_clone.lock().unwrap();
         match result {
@@ -175,38 +368,107 @@ fn scan_and_hash_files(
     }
     let _ = app_handle.emit("dedup-scan-progress", status_mutex.lock().unwrap().clone());
 
-    // Step 3: Hash them
+    // Step 3: Sample-hash them (head/middle/tail), cached by mtime
     let mut processed = 0;
+    let mut cached_hashes: HashMap<i64, CachedHash> = HashMap::new();
 
     // We do batch inserts to speed up DB operations
     let tx = conn.transaction().map_err(|e| e.to_string())?;
 
-    for file in files_to_check {
+    for file in &files_to_check {
         if cancel_flag.load(Ordering::SeqCst) {
             break;
         }
 
-        // Only hash if mtime changed or hash is missing
-        let needs_hash = check_if_needs_hash(&tx, &file)?;
-
-        if needs_hash {
-            if let Some(path) = &file.file_path {
-                match compute_blake3_hash(path) {
-                    Ok(hash) => {
-                        let now = SystemTime::now()
-                            .duration_since(UNIX_EPOCH)
-                            .unwrap()
-                            .as_secs() as i64;
-                        let mtime = file.modified_at.unwrap_or(0);
-
-                        tx.execute(
-                            "INSERT OR REPLACE INTO file_hashes (file_id, hash, file_size, mtime, computed_at)
-                             VALUES (?1, ?2, ?3, ?4, ?5)",
-                            params![file.id.unwrap(), hash, file.size, mtime, now],
-                        ).map_err(|e| e.to_string())?;
+        let file_id = file.id.unwrap();
+        let mtime = file.modified_at.unwrap_or(0);
+        let cached = get_cached_hash(&tx, file_id)?.filter(|cached| cached.mtime == mtime);
+        let cached = match cached {
+            Some(cached) if cached.sample_hash.is_some() => Some(cached),
+            // Rows hashed before sample hashes existed keep their full hash if unchanged
+            unchanged => match &file.file_path {
+                Some(path) => match compute_sample_hash(path, file.size) {
+                    Ok((sample_hash, full_hash)) => {
+                        let cached = CachedHash {
+                            hash: full_hash.or(unchanged.and_then(|cached| cached.hash)),
+                            sample_hash: Some(sample_hash),
+                            mtime,
+                        };
+                        save_cached_hash(&tx, file_id, file.size, &cached)?;
+                        Some(cached)
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to hash file {}: {}", path, e);
+                        None
                     }
-                    Err(e) => eprintln!("Failed to hash file {}: {}", path, e),
+                },
+                None => None,
+            },
+        };
+        if let Some(cached) = cached {
+            cached_hashes.insert(file_id, cached);
+        }
+
+        processed += 1;
+        if processed % 10 == 0 {
+            {
+                let mut status = status_mutex.lock().unwrap();
+                status.processed = processed;
+            }
+            let _ = app_handle.emit("dedup-scan-progress", status_mutex.lock().unwrap().clone());
+        }
+    }
+
+    // Step 4: Full-hash only the files whose size and sample hash still collide
+    let mut sample_count: HashMap<(i64, &str), usize> = HashMap::new();
+    for file in &files_to_check {
+        if let Some(sample_hash) = cached_hashes
+            .get(&file.id.unwrap())
+            .and_then(|cached| cached.sample_hash.as_deref())
+        {
+            *sample_count.entry((file.size, sample_hash)).or_insert(0) += 1;
+        }
+    }
+    let needs_full_hash: Vec<&AFile> = files_to_check
+        .iter()
+        .filter(|file| {
+            cached_hashes.get(&file.id.unwrap()).is_some_and(|cached| {
+                cached.hash.is_none()
+                    && cached.sample_hash.as_deref().is_some_and(|sample_hash| {
+                        sample_count
+                            .get(&(file.size, sample_hash))
+                            .copied()
+                            .unwrap_or(0)
+                            > 1
+                    })
+            })
+        })
+        .collect();
+
+    {
+        let mut status = status_mutex.lock().unwrap();
+        status.phase = "full".to_string();
+        status.processed = 0;
+        status.total = needs_full_hash.len() as u64;
+    }
+    let _ = app_handle.emit("dedup-scan-progress", status_mutex.lock().unwrap().clone());
+    processed = 0;
+
+    for file in needs_full_hash {
+        if cancel_flag.load(Ordering::SeqCst) {
+            break;
+        }
+
+        if let Some(path) = &file.file_path {
+            match compute_blake3_hash(path) {
+                Ok(hash) => {
+                    tx.execute(
+                        "UPDATE file_hashes SET hash = ?1 WHERE file_id = ?2",
+                        params![hash, file.id.unwrap()],
+                    )
+                    .map_err(|e| e.to_string())?;
                 }
+                Err(e) => eprintln!("Failed to hash file {}: {}", path, e),
             }
         }
 
@@ -222,15 +484,17 @@ fn scan_and_hash_files(
 
     tx.commit().map_err(|e| e.to_string())?;
 
-    // Step 4: Rebuild duplicate groups
+    // Step 5: Rebuild duplicate groups
     if !cancel_flag.load(Ordering::SeqCst) {
         rebuild_duplicate_groups(&mut conn, scoped_file_ids.as_deref())?;
 
         // Count total groups
         let groups_count: i64 = conn
-            .query_row("SELECT COUNT(*) FROM duplicate_groups", [], |row| {
-                row.get(0)
-            })
+            .query_row(
+                "SELECT COUNT(*) FROM duplicate_groups WHERE match_kind = ?1",
+                params![MatchKind::Exact.as_str()],
+                |row| row.get(0),
+            )
             .unwrap_or(0);
 
         {
@@ -244,6 +508,199 @@ fn scan_and_hash_files(
     Ok(())
 }
 
+fn scan_perceptual_hashes(
+    app_handle: &tauri::AppHandle,
+    status_mutex: &Arc<Mutex<DedupScanStatus>>,
+    cancel_flag: &Arc<AtomicBool>,
+    scoped_files: Option<Vec<AFile>>,
+    match_kind: MatchKind,
+    max_distance: u32,
+) -> Result<(), String> {
+    let mut conn = get_db_conn()?;
+
+    // (file_id, mtime) of every image in scope; size doesn't matter for a perceptual match
+    let has_scope = scoped_files.is_some();
+    let files_to_check: Vec<(i64, i64)> = match scoped_files {
+        Some(files) => files
+            .into_iter()
+            .filter(|file| matches!(file.file_type, Some(1 | 3)))
+            .filter_map(|file| Some((file.id?, file.modified_at.unwrap_or(0))))
+            .collect(),
+        None => get_image_files(&conn)?,
+    };
+
+    {
+        let mut status = status_mutex.lock().unwrap();
+        status.total = files_to_check.len() as u64;
+        status.processed = 0;
+    }
+    let _ = app_handle.emit("dedup-scan-progress", status_mutex.lock().unwrap().clone());
+
+    let scope_file_ids: Option<Vec<i64>> =
+        has_scope.then(|| files_to_check.iter().map(|(file_id, _)| *file_id).collect());
+    let mut hashes: Vec<(i64, u64)> = Vec::with_capacity(files_to_check.len());
+    let mut processed = 0;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    for (file_id, mtime) in files_to_check {
+        if cancel_flag.load(Ordering::SeqCst) {
+            break;
+        }
+
+        match get_or_compute_image_hash(&tx, file_id, mtime, match_kind) {
+            Ok(Some(hash)) => hashes.push((file_id, hash)),
+            Ok(None) => {} // no thumbnail yet
+            Err(e) => eprintln!("Failed to compute image hash for file {}: {}", file_id, e),
+        }
+
+        processed += 1;
+        if processed % 10 == 0 {
+            {
+                let mut status = status_mutex.lock().unwrap();
+                status.processed = processed;
+            }
+            let _ = app_handle.emit("dedup-scan-progress", status_mutex.lock().unwrap().clone());
+        }
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+
+    if !cancel_flag.load(Ordering::SeqCst) {
+        let groups = group_by_hash_distance(&hashes, max_distance, cancel_flag);
+        if cancel_flag.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let hash_by_id: HashMap<i64, u64> = hashes.into_iter().collect();
+        let groups_count = rebuild_perceptual_groups(
+            &mut conn,
+            &groups,
+            &hash_by_id,
+            match_kind,
+            scope_file_ids.as_deref(),
+        )?;
+
+        {
+            let mut status = status_mutex.lock().unwrap();
+            status.processed = processed;
+            status.groups = groups_count as u64;
+        }
+        let _ = app_handle.emit("dedup-scan-progress", status_mutex.lock().unwrap().clone());
+    }
+
+    Ok(())
+}
+
+/// Group re-encoded or trimmed copies of videos. Only the first `VIDEO_MAX_FRAMES`
+/// frames (10 minutes) are fingerprinted, so longer clips that differ only after
+/// that point are grouped as well.
+fn scan_video_fingerprints(
+    app_handle: &tauri::AppHandle,
+    status_mutex: &Arc<Mutex<DedupScanStatus>>,
+    cancel_flag: &Arc<AtomicBool>,
+    scoped_files: Option<Vec<AFile>>,
+    max_distance: u32,
+) -> Result<(), String> {
+    let mut conn = get_db_conn()?;
+
+    // (file_id, file_path, mtime) of every video in scope
+    let has_scope = scoped_files.is_some();
+    let files_to_check: Vec<(i64, String, i64)> = match scoped_files {
+        Some(files) => files
+            .into_iter()
+            .filter(|file| file.file_type == Some(2))
+            .filter_map(|file| Some((file.id?, file.file_path?, file.modified_at.unwrap_or(0))))
+            .collect(),
+        None => get_video_files(&conn)?,
+    };
+
+    {
+        let mut status = status_mutex.lock().unwrap();
+        status.total = files_to_check.len() as u64;
+        status.processed = 0;
+    }
+    let _ = app_handle.emit("dedup-scan-progress", status_mutex.lock().unwrap().clone());
+
+    let scope_file_ids: Option<Vec<i64>> = has_scope.then(|| {
+        files_to_check
+            .iter()
+            .map(|(file_id, _, _)| *file_id)
+            .collect()
+    });
+    // Each video is decoded by ffmpeg, so save fingerprints one by one rather than
+    // holding a transaction open for the whole scan
+    let mut fingerprints: Vec<(i64, Vec<u64>)> = Vec::with_capacity(files_to_check.len());
+    let mut processed = 0;
+    for (file_id, file_path, mtime) in files_to_check {
+        if cancel_flag.load(Ordering::SeqCst) {
+            break;
+        }
+
+        match get_or_compute_video_fingerprint(&conn, file_id, &file_path, mtime) {
+            Ok(frames) if !frames.is_empty() => fingerprints.push((file_id, frames)),
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to fingerprint video {}: {}", file_path, e),
+        }
+
+        processed += 1;
+        {
+            let mut status = status_mutex.lock().unwrap();
+            status.processed = processed;
+        }
+        let _ = app_handle.emit("dedup-scan-progress", status_mutex.lock().unwrap().clone());
+    }
+
+    if !cancel_flag.load(Ordering::SeqCst) {
+        let groups = group_by_frame_overlap(&fingerprints, max_distance, cancel_flag);
+        if cancel_flag.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        // Video groups are keyed by the first informative frame of the keeper
+        let hash_by_id: HashMap<i64, u64> = fingerprints
+            .iter()
+            .map(|(file_id, frames)| {
+                let key = frames.iter().copied().find(|&h| is_informative_frame(h));
+                (*file_id, key.unwrap_or(frames[0]))
+            })
+            .collect();
+        let groups_count = rebuild_perceptual_groups(
+            &mut conn,
+            &groups,
+            &hash_by_id,
+            MatchKind::Video,
+            scope_file_ids.as_deref(),
+        )?;
+
+        {
+            let mut status = status_mutex.lock().unwrap();
+            status.processed = processed;
+            status.groups = groups_count as u64;
+        }
+        let _ = app_handle.emit("dedup-scan-progress", status_mutex.lock().unwrap().clone());
+    }
+
+    Ok(())
+}
+
+/// Resolve an optional scan scope; None means the whole library
+fn get_scoped_files(
+    query_params: Option<QueryParams>,
+    collection_id: Option<i64>,
+    file_ids: Option<Vec<i64>>,
+) -> Result<Option<Vec<AFile>>, String> {
+    Ok(if let Some(file_ids) = file_ids {
+        Some(AFile::get_files_by_ids(&file_ids)?)
+    } else if let Some(collection_id) = collection_id {
+        Some(get_files_by_collection(
+            collection_id,
+            query_params.as_ref(),
+        )?)
+    } else if let Some(params) = query_params.as_ref() {
+        Some(get_files_by_query(params)?)
+    } else {
+        None
+    })
+}
+
 fn get_db_conn() -> Result<Connection, String> {
     let path = crate::t_storage::get_current_db_path()
         .map_err(|e| format!("Failed to get db path: {}", e))?;
@@ -339,6 +796,8 @@ fn get_files_by_sizes(conn: &Connection) -> Result<Vec<AFile>, String> {
                 live_photo_video_id: None,
                 live_photo_video_path: None,
                 motion_photo_offset: None,
+                content_fingerprint: None,
+                match_offset: None,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -400,6 +859,37 @@ pub fn get_files_by_collection(
     Ok(all_files)
 }
 
+/// Get (file_id, mtime) of all images in the library
+fn get_image_files(conn: &Connection) -> Result<Vec<(i64, i64)>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, COALESCE(modified_at, 0) FROM afiles
+             WHERE file_type IN (1, 3)
+             ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Get (file_id, file_path, mtime) of all videos in the library
+fn get_video_files(conn: &Connection) -> Result<Vec<(i64, String, i64)>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT a.id, f.path || '/' || a.name, COALESCE(a.modified_at, 0)
+             FROM afiles a JOIN afolders f ON a.folder_id = f.id
+             WHERE a.file_type = 2
+             ORDER BY a.id",
+        )
+        .map_err(|e| e.to_string())?;
+    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
 fn filter_suspicious_files(files: Vec<AFile>) -> Vec<AFile> {
     let mut size_count: HashMap<i64, usize> = HashMap::new();
     for file in &files {
@@ -414,40 +904,596 @@ fn filter_suspicious_files(files: Vec<AFile>) -> Vec<AFile> {
         .collect()
 }
 
-fn check_if_needs_hash(conn: &Connection, file: &AFile) -> Result<bool, String> {
-    let mtime = file.modified_at.unwrap_or(0);
-    let id = file.id.unwrap();
+struct CachedHash {
+    hash: Option<String>, // full blake3, only computed when the sample hash collides
+    sample_hash: Option<String>, // blake3 of size + head/middle/tail samples
+    mtime: i64,
+}
+
+fn get_cached_hash(conn: &Connection, file_id: i64) -> Result<Option<CachedHash>, String> {
+    conn.query_row(
+        "SELECT hash, sample_hash, mtime FROM file_hashes WHERE file_id = ?1",
+        params![file_id],
+        |row| {
+            Ok(CachedHash {
+                hash: row.get(0)?,
+                sample_hash: row.get(1)?,
+                mtime: row.get(2)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn save_cached_hash(
+    conn: &Connection,
+    file_id: i64,
+    file_size: i64,
+    cached: &CachedHash,
+) -> Result<(), String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    conn.execute(
+        "INSERT OR REPLACE INTO file_hashes (file_id, hash, sample_hash, file_size, mtime, computed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            file_id,
+            cached.hash,
+            cached.sample_hash,
+            file_size,
+            cached.mtime,
+            now
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Hash the size and three samples (head, middle, tail) of a file.
+/// Small files are read whole, so their full hash comes for free: returns (sample_hash, full_hash).
+pub(crate) fn compute_sample_hash(
+    path: &str,
+    size: i64,
+) -> Result<(String, Option<String>), io::Error> {
+    if size <= (SAMPLE_HASH_CHUNK * 3) as i64 {
+        let hash = compute_blake3_hash(path)?;
+        return Ok((hash.clone(), Some(hash)));
+    }
+
+    let mut file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&size.to_le_bytes());
+
+    let mut buffer = vec![0; SAMPLE_HASH_CHUNK];
+    let size = size as u64;
+    let chunk = SAMPLE_HASH_CHUNK as u64;
+    for offset in [0, size / 2 - chunk / 2, size - chunk] {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut buffer)?;
+        hasher.update(&buffer);
+    }
+
+    Ok((hasher.finalize().to_hex().to_string(), None))
+}
+
+pub(crate) fn compute_blake3_hash(path: &str) -> Result<String, io::Error> {
+    let mut file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+
+    // Read in chunks
+    let mut buffer = [0; 65536];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Get the cached perceptual hash of a file, computing both hashes from its thumbnail
+/// when the file changed since they were stored. Returns None if there is no thumbnail yet.
+fn get_or_compute_image_hash(
+    conn: &Connection,
+    file_id: i64,
+    mtime: i64,
+    match_kind: MatchKind,
+) -> Result<Option<u64>, String> {
+    let cached: Option<(i64, i64, i64)> = conn
+        .query_row(
+            "SELECT dhash, phash, mtime FROM file_image_hashes WHERE file_id = ?1",
+            params![file_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    let (dhash, phash) = match cached {
+        Some((dhash, phash, stored_mtime)) if stored_mtime == mtime => (dhash as u64, phash as u64),
+        _ => {
+            let Some(thumb_bytes) = AThumb::fetch(file_id)?.and_then(|thumb| thumb.thumb_data)
+            else {
+                return Ok(None);
+            };
+            let img = image::load_from_memory(&thumb_bytes)
+                .map_err(|e| format!("Failed to load thumbnail: {}", e))?;
+            let (dhash, phash) = (compute_dhash(&img), compute_phash(&img));
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as i64;
+            conn.execute(
+                "INSERT OR REPLACE INTO file_image_hashes (file_id, dhash, phash, mtime, computed_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![file_id, dhash as i64, phash as i64, mtime, now],
+            )
+            .map_err(|e| e.to_string())?;
+            (dhash, phash)
+        }
+    };
+
+    Ok(Some(if match_kind == MatchKind::PHash {
+        phash
+    } else {
+        dhash
+    }))
+}
+
+/// Get the cached frame fingerprint of a video (one dHash per sampled frame), sampling
+/// it through ffmpeg when the file changed since it was stored
+fn get_or_compute_video_fingerprint(
+    conn: &Connection,
+    file_id: i64,
+    file_path: &str,
+    mtime: i64,
+) -> Result<Vec<u64>, String> {
+    let cached: Option<(Vec<u8>, i64, i64)> = conn
+        .query_row(
+            "SELECT frames, frame_interval, mtime FROM video_fingerprints WHERE file_id = ?1",
+            params![file_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some((blob, interval, stored_mtime)) = cached {
+        if stored_mtime == mtime && interval == VIDEO_FRAME_INTERVAL as i64 {
+            return Ok(blob
+                .chunks_exact(8)
+                .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+                .collect());
+        }
+    }
+
+    let frames: Vec<u64> = crate::t_video::get_video_gray_frames_sync(
+        file_path,
+        VIDEO_FRAME_INTERVAL,
+        VIDEO_MAX_FRAMES,
+        9,
+        8,
+    )?
+    .iter()
+    .map(|gray| dhash_from_gray(gray))
+    .collect();
+    let blob: Vec<u8> = frames.iter().flat_map(|h| h.to_le_bytes()).collect();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    conn.execute(
+        "INSERT OR REPLACE INTO video_fingerprints (file_id, frames, frame_interval, mtime, computed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![file_id, blob, VIDEO_FRAME_INTERVAL as i64, mtime, now],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(frames)
+}
+
+/// Difference hash: one bit per horizontal gradient of a 9x8 grayscale image
+fn compute_dhash(img: &image::DynamicImage) -> u64 {
+    let gray = img
+        .resize_exact(9, 8, image::imageops::FilterType::Triangle)
+        .to_luma8();
+    dhash_from_gray(gray.as_raw())
+}
+
+/// dHash of a row-major 9x8 luma buffer
+fn dhash_from_gray(gray: &[u8]) -> u64 {
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            if gray[y * 9 + x] > gray[y * 9 + x + 1] {
+                hash |= 1 << (y * 8 + x);
+            }
+        }
+    }
+    hash
+}
+
+/// Perceptual hash: signs of the 8x8 lowest DCT frequencies of a 32x32 grayscale image
+/// relative to their median (the DC term is skipped, its bit is always 0)
+fn compute_phash(img: &image::DynamicImage) -> u64 {
+    const SIZE: usize = 32;
+    const LOW: usize = 8;
+
+    let gray = img
+        .resize_exact(
+            SIZE as u32,
+            SIZE as u32,
+            image::imageops::FilterType::Triangle,
+        )
+        .to_luma8();
+
+    let mut cosines = [[0f64; SIZE]; LOW];
+    for (u, row) in cosines.iter_mut().enumerate() {
+        for (x, value) in row.iter_mut().enumerate() {
+            *value = (((2 * x + 1) * u) as f64 * std::f64::consts::PI / (2 * SIZE) as f64).cos();
+        }
+    }
+
+    let mut coefficients = [0f64; LOW * LOW];
+    for v in 0..LOW {
+        for u in 0..LOW {
+            let mut sum = 0.0;
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    sum += gray.get_pixel(x as u32, y as u32)[0] as f64
+                        * cosines[u][x]
+                        * cosines[v][y];
+                }
+            }
+            coefficients[v * LOW + u] = sum;
+        }
+    }
+
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let median = sorted[sorted.len() / 2];
+
+    let mut hash = 0u64;
+    for (i, coefficient) in coefficients.iter().enumerate().skip(1) {
+        if *coefficient > median {
+            hash |= 1 << i;
+        }
+    }
+    hash
+}
+
+/// Group files whose hashes are within `max_distance` bits of each other (single linkage).
+/// Only files that share a bucket of some hash band are compared.
+fn group_by_hash_distance(
+    hashes: &[(i64, u64)],
+    max_distance: u32,
+    cancel_flag: &AtomicBool,
+) -> Vec<Vec<i64>> {
+    let bands = hash_bands(max_distance);
+    let mut buckets: HashMap<(usize, u64), Vec<usize>> = HashMap::new();
+    for (i, (_, hash)) in hashes.iter().enumerate() {
+        for (band, &range) in bands.iter().enumerate() {
+            buckets
+                .entry((band, band_key(*hash, range)))
+                .or_default()
+                .push(i);
+        }
+    }
+
+    let mut parent: Vec<usize> = (0..hashes.len()).collect();
+    for bucket in buckets.values() {
+        if cancel_flag.load(Ordering::SeqCst) {
+            return Vec::new();
+        }
+        for (n, &i) in bucket.iter().enumerate() {
+            for &j in &bucket[n + 1..] {
+                if (hashes[i].1 ^ hashes[j].1).count_ones() <= max_distance {
+                    let (a, b) = (find_root(&mut parent, i), find_root(&mut parent, j));
+                    if a != b {
+                        parent[b] = a;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<i64>> = HashMap::new();
+    for (i, (file_id, _)) in hashes.iter().enumerate() {
+        let root = find_root(&mut parent, i);
+        groups.entry(root).or_default().push(*file_id);
+    }
+    groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+/// Split the 64 hash bits into `max_distance + 1` bands of (shift, width). Hashes within
+/// `max_distance` bits differ in at most that many bands, so they agree on at least one.
+fn hash_bands(max_distance: u32) -> Vec<(u32, u32)> {
+    let count = (max_distance + 1).min(64);
+    (0..count)
+        .map(|band| {
+            let start = band * 64 / count;
+            (start, (band + 1) * 64 / count - start)
+        })
+        .collect()
+}
+
+fn band_key(hash: u64, (shift, width): (u32, u32)) -> u64 {
+    (hash >> shift) & (u64::MAX >> (64 - width))
+}
+
+/// Group videos that share a run of matching frames at some time offset (single linkage).
+/// Candidate pairs come from frames agreeing on any of four 16-bit bands of their hash,
+/// which always holds for frames within 3 bits of each other (`VIDEO_MAX_FRAME_DISTANCE`);
+/// narrower bands would put too many unrelated clips into every bucket.
+fn group_by_frame_overlap(
+    fingerprints: &[(i64, Vec<u64>)],
+    max_distance: u32,
+    cancel_flag: &AtomicBool,
+) -> Vec<Vec<i64>> {
+    let mut buckets: HashMap<(usize, u16), Vec<usize>> = HashMap::new();
+    for (i, (_, frames)) in fingerprints.iter().enumerate() {
+        for &hash in frames.iter().filter(|&&h| is_informative_frame(h)) {
+            for band in 0..4 {
+                let bucket = buckets
+                    .entry((band, (hash >> (band * 16)) as u16))
+                    .or_default();
+                if bucket.last() != Some(&i) {
+                    bucket.push(i);
+                }
+            }
+        }
+    }
+
+    let mut candidates: HashSet<(usize, usize)> = HashSet::new();
+    for bucket in buckets.values() {
+        // Huge buckets are generic frames (titles, dark scenes) shared by unrelated clips
+        if bucket.len() > VIDEO_MAX_BUCKET {
+            continue;
+        }
+        for (n, &a) in bucket.iter().enumerate() {
+            for &b in &bucket[n + 1..] {
+                candidates.insert((a.min(b), a.max(b)));
+            }
+        }
+    }
+
+    let mut parent: Vec<usize> = (0..fingerprints.len()).collect();
+    for (a, b) in candidates {
+        if cancel_flag.load(Ordering::SeqCst) {
+            return Vec::new();
+        }
+        if frame_overlap(&fingerprints[a].1, &fingerprints[b].1, max_distance) >= VIDEO_MIN_OVERLAP
+        {
+            let (ra, rb) = (find_root(&mut parent, a), find_root(&mut parent, b));
+            if ra != rb {
+                parent[rb] = ra;
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<i64>> = HashMap::new();
+    for (i, (file_id, _)) in fingerprints.iter().enumerate() {
+        let root = find_root(&mut parent, i);
+        groups.entry(root).or_default().push(*file_id);
+    }
+    groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+/// Best share of the shorter clip's frames that match the longer clip at a single
+/// time offset, so trimmed copies still match their source
+fn frame_overlap(a: &[u64], b: &[u64], max_distance: u32) -> f32 {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if short.is_empty() {
+        return 0.0;
+    }
+    let mut best = 0;
+    for shift in -(short.len() as isize - 1)..long.len() as isize {
+        let matches = short
+            .iter()
+            .enumerate()
+            .filter(|(i, hash)| {
+                let j = *i as isize + shift;
+                j >= 0
+                    && (j as usize) < long.len()
+                    && (*hash ^ long[j as usize]).count_ones() <= max_distance
+            })
+            .count();
+        best = best.max(matches);
+    }
+    best as f32 / short.len() as f32
+}
+
+/// Flat frames (black, white, a single color) hash to all zeros or all ones
+fn is_informative_frame(hash: u64) -> bool {
+    hash != 0 && hash != u64::MAX
+}
+
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Remove the groups a rebuild replaces: those of `match_kind` that contain a scoped
+/// file, or all of them when the whole library was scanned. Groups of other kinds
+/// and of files outside the scope stay.
+fn clear_groups(
+    tx: &rusqlite::Transaction,
+    match_kind: MatchKind,
+    scope_file_ids: Option<&[i64]>,
+) -> Result<(), String> {
+    let group_ids: Vec<i64> = match scope_file_ids {
+        None => {
+            let mut stmt = tx
+                .prepare("SELECT id FROM duplicate_groups WHERE match_kind = ?1")
+                .map_err(|e| e.to_string())?;
+            stmt.query_map(params![match_kind.as_str()], |row| row.get(0))
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?
+        }
+        Some(file_ids) => {
+            let mut stmt = tx
+                .prepare(
+                    "SELECT g.id FROM duplicate_groups g
+                     JOIN duplicate_group_items i ON i.group_id = g.id
+                     WHERE g.match_kind = ?1 AND i.file_id = ?2",
+                )
+                .map_err(|e| e.to_string())?;
+            let mut group_ids = HashSet::new();
+            for file_id in file_ids {
+                let ids = stmt
+                    .query_map(params![match_kind.as_str(), file_id], |row| row.get(0))
+                    .map_err(|e| e.to_string())?
+                    .collect::<Result<Vec<i64>, _>>()
+                    .map_err(|e| e.to_string())?;
+                group_ids.extend(ids);
+            }
+            group_ids.into_iter().collect()
+        }
+    };
+
+    for group_id in group_ids {
+        delete_group(tx, group_id)?;
+    }
+    Ok(())
+}
+
+fn delete_group(tx: &rusqlite::Transaction, group_id: i64) -> Result<(), String> {
+    tx.execute(
+        "DELETE FROM duplicate_group_items WHERE group_id = ?1",
+        params![group_id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "DELETE FROM duplicate_groups WHERE id = ?1",
+        params![group_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// A group found by another scoped scan may already use the same hash and size;
+/// the new group replaces it.
+fn delete_group_by_key(tx: &rusqlite::Transaction, hash: &str, size: i64) -> Result<(), String> {
+    let group_id: Option<i64> = tx
+        .query_row(
+            "SELECT id FROM duplicate_groups WHERE hash = ?1 AND file_size = ?2",
+            params![hash, size],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    match group_id {
+        Some(group_id) => delete_group(tx, group_id),
+        None => Ok(()),
+    }
+}
+
+fn rebuild_perceptual_groups(
+    conn: &mut Connection,
+    groups: &[Vec<i64>],
+    hash_by_id: &HashMap<i64, u64>,
+    match_kind: MatchKind,
+    scope_file_ids: Option<&[i64]>,
+) -> Result<usize, String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    clear_groups(&tx, match_kind, scope_file_ids)?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    let policy = load_keep_policy();
+    let candidate_query = format!(
+        "SELECT {} FROM afiles a JOIN afolders f ON a.folder_id = f.id WHERE a.id = ?1",
+        KEEP_CANDIDATE_COLUMNS
+    );
+    let mut f_stmt = tx.prepare(&candidate_query).map_err(|e| e.to_string())?;
+
+    let mut groups_count = 0;
+    for group in groups {
+        let mut keep_candidates: Vec<KeepCandidate> = Vec::new();
+        for file_id in group {
+            let candidate = f_stmt
+                .query_row(params![file_id], keep_candidate_from_row)
+                .optional()
+                .map_err(|e| e.to_string())?;
+            keep_candidates.extend(candidate);
+        }
+        if keep_candidates.len() < 2 {
+            continue;
+        }
+        rank_candidates(&policy, &mut keep_candidates);
+
+        // Groups are keyed by the keeper: its hash and size
+        let keep = &keep_candidates[0];
+        let hash = format!(
+            "{}:{:016x}",
+            match_kind.as_str(),
+            hash_by_id.get(&keep.id).copied().unwrap_or(0)
+        );
+        let total_size: i64 = keep_candidates.iter().map(|c| c.size).sum();
 
-    let db_mtime: Option<i64> = conn
-        .query_row(
-            "SELECT mtime FROM file_hashes WHERE file_id = ?1",
-            params![id],
-            |row| row.get(0),
+        delete_group_by_key(&tx, &hash, keep.size)?;
+        tx.execute(
+            "INSERT INTO duplicate_groups (hash, file_size, file_count, total_size, match_kind, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                hash,
+                keep.size,
+                keep_candidates.len() as i64,
+                total_size,
+                match_kind.as_str(),
+                now
+            ],
         )
-        .optional()
         .map_err(|e| e.to_string())?;
+        let target_group_id = tx.last_insert_rowid();
 
-    match db_mtime {
-        Some(stored_mtime) => Ok(stored_mtime != mtime),
-        None => Ok(true),
+        insert_group_items(&tx, target_group_id, &keep_candidates)?;
+        groups_count += 1;
     }
+    drop(f_stmt);
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(groups_count)
 }
 
-fn compute_blake3_hash(path: &str) -> Result<String, io::Error> {
-    let mut file = File::open(path)?;
-    let mut hasher = blake3::Hasher::new();
+fn insert_group_items(
+    tx: &rusqlite::Transaction,
+    group_id: i64,
+    keep_candidates: &[KeepCandidate],
+) -> Result<(), String> {
+    for (i, candidate) in keep_candidates.iter().enumerate() {
+        let is_keep = if i == 0 { 1 } else { 0 };
 
-    // Read in chunks
-    let mut buffer = [0; 65536];
-    loop {
-        let n = file.read(&mut buffer)?;
-        if n == 0 {
-            break;
-        }
-        hasher.update(&buffer[..n]);
+        tx.execute(
+            "INSERT INTO duplicate_group_items (group_id, file_id, is_keep, is_selected, score, reasons)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                group_id,
+                candidate.id,
+                is_keep,
+                0,
+                candidate.score,
+                candidate.reasons.join(",")
+            ],
+        )
+        .map_err(|e| e.to_string())?;
     }
-
-    Ok(hasher.finalize().to_hex().to_string())
+    Ok(())
 }
 
 fn rebuild_duplicate_groups(
@@ -456,11 +1502,8 @@ fn rebuild_duplicate_groups(
 ) -> Result<(), String> {
     let tx = conn.transaction().map_err(|e| e.to_string())?;
 
-    // Remove existing items and groups to rebuild clean
-    tx.execute("DELETE FROM duplicate_group_items", [])
-        .map_err(|e| e.to_string())?;
-    tx.execute("DELETE FROM duplicate_groups", [])
-        .map_err(|e| e.to_string())?;
+    // Remove the exact groups of the scanned files to rebuild them clean
+    clear_groups(&tx, MatchKind::Exact, scope_file_ids)?;
 
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -492,16 +1535,22 @@ fn rebuild_duplicate_groups(
         drop(insert_stmt);
     }
 
-    // Find dups
+    let policy = load_keep_policy();
+
+    // Find dups; files the integrity check flagged as corrupt no longer match their hash
     let group_query = if scope_file_ids.is_some() {
         "SELECT fh.hash, fh.file_size, COUNT(fh.file_id) as cnt
          FROM file_hashes fh
          JOIN temp_scope_ids ts ON ts.file_id = fh.file_id
+         WHERE fh.hash IS NOT NULL
+           AND fh.file_id NOT IN (SELECT file_id FROM integrity_checks WHERE status = 'corrupt')
          GROUP BY fh.hash, fh.file_size
          HAVING cnt > 1"
     } else {
         "SELECT hash, file_size, COUNT(file_id) as cnt
          FROM file_hashes
+         WHERE hash IS NOT NULL
+           AND file_id NOT IN (SELECT file_id FROM integrity_checks WHERE status = 'corrupt')
          GROUP BY hash, file_size
          HAVING cnt > 1"
     };
@@ -524,10 +1573,11 @@ fn rebuild_duplicate_groups(
         let total_size = size * count;
 
         // Insert group
+        delete_group_by_key(&tx, &hash, size)?;
         tx.execute(
-            "INSERT INTO duplicate_groups (hash, file_size, file_count, total_size, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![hash, size, count, total_size, now],
+            "INSERT INTO duplicate_groups (hash, file_size, file_count, total_size, match_kind, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![hash, size, count, total_size, MatchKind::Exact.as_str(), now],
         )
         .map_err(|e| e.to_string())?;
 
@@ -536,66 +1586,223 @@ fn rebuild_duplicate_groups(
         let target_group_id = tx.last_insert_rowid();
 
         // Let's get the files for this group
-        let item_query = if scope_file_ids.is_some() {
-            "SELECT a.id, a.taken_date, a.created_at
-             FROM file_hashes fh
-             JOIN afiles a ON fh.file_id = a.id
-             JOIN temp_scope_ids ts ON ts.file_id = a.id
-             WHERE fh.hash = ?1 AND fh.file_size = ?2"
-        } else {
-            "SELECT a.id, a.taken_date, a.created_at
+        let item_query = format!(
+            "SELECT {}
              FROM file_hashes fh
              JOIN afiles a ON fh.file_id = a.id
-             WHERE fh.hash = ?1 AND fh.file_size = ?2"
-        };
+             JOIN afolders f ON a.folder_id = f.id
+             {}
+             WHERE fh.hash = ?1 AND fh.file_size = ?2
+               AND fh.file_id NOT IN (SELECT file_id FROM integrity_checks WHERE status = 'corrupt')",
+            KEEP_CANDIDATE_COLUMNS,
+            if scope_file_ids.is_some() {
+                "JOIN temp_scope_ids ts ON ts.file_id = a.id"
+            } else {
+                ""
+            }
+        );
 
-        let mut f_stmt = tx.prepare(item_query).map_err(|e| e.to_string())?;
+        let mut f_stmt = tx.prepare(&item_query).map_err(|e| e.to_string())?;
 
-        let mut keep_candidates: Vec<KeepCandidate> = Vec::new();
-        let iter = f_stmt
-            .query_map(params![hash, size], |row| {
-                let id: i64 = row.get(0)?;
-                let tk: i64 = row.get(1).unwrap_or(0);
-                let ca: i64 = row.get(2).unwrap_or(0);
-                Ok((id, tk, ca))
-            })
+        let mut keep_candidates = f_stmt
+            .query_map(params![hash, size], keep_candidate_from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
             .map_err(|e| e.to_string())?;
 
-        for r in iter {
-            let (id, tk, ca) = r.map_err(|e| e.to_string())?;
-            keep_candidates.push(KeepCandidate {
-                id,
-                taken_date: tk,
-                created_at: ca,
-            });
+        rank_candidates(&policy, &mut keep_candidates);
+        insert_group_items(&tx, target_group_id, &keep_candidates)?;
+    }
+    drop(stmt);
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+// Keep Policy
+// ----------------------------------------------------------------------------
+
+fn keep_candidate_from_row(row: &rusqlite::Row) -> rusqlite::Result<KeepCandidate> {
+    Ok(KeepCandidate {
+        id: row.get(0)?,
+        taken_date: row.get(1).unwrap_or(0),
+        created_at: row.get(2).unwrap_or(0),
+        pixels: row.get(3).unwrap_or(0),
+        size: row.get(4).unwrap_or(0),
+        has_exif: row.get(5).unwrap_or(false),
+        has_gps: row.get(6).unwrap_or(false),
+        has_rating: row.get(7).unwrap_or(false),
+        has_tags: row.get(8).unwrap_or(false),
+        has_collections: row.get(9).unwrap_or(false),
+        path: row.get(10).unwrap_or_default(),
+        score: 0.0,
+        reasons: Vec::new(),
+    })
+}
+
+/// Get the keep policy of the current library (defaults if none is saved)
+pub fn load_keep_policy() -> KeepPolicy {
+    match crate::t_sqlite::get_library_setting(KEEP_POLICY_SETTING) {
+        Ok(Some(value)) => serde_json::from_str(&value).unwrap_or_default(),
+        Ok(None) => KeepPolicy::default(),
+        Err(e) => {
+            eprintln!("Failed to load dedup keep policy: {}", e);
+            KeepPolicy::default()
         }
+    }
+}
 
-        keep_candidates.sort_by(compare_best_quality);
+/// Save the keep policy of the current library and re-rank existing groups with it.
+/// Groups the user has already reviewed keep their chosen file.
+pub fn save_keep_policy(policy: &KeepPolicy) -> Result<(), String> {
+    let value = serde_json::to_string(policy).map_err(|e| e.to_string())?;
+    crate::t_sqlite::save_library_setting(KEEP_POLICY_SETTING, &value)?;
 
-        let total_candidates = keep_candidates.len() as f64;
-        for (i, candidate) in keep_candidates.iter().enumerate() {
-            let is_keep = if i == 0 { 1 } else { 0 };
-            let score = total_candidates - i as f64;
+    let mut conn = get_db_conn()?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let groups: Vec<(i64, i32)> = {
+        let mut stmt = tx
+            .prepare("SELECT id, reviewed FROM duplicate_groups")
+            .map_err(|e| e.to_string())?;
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+
+    let item_query = format!(
+        "SELECT {}
+         FROM duplicate_group_items dgi
+         JOIN afiles a ON dgi.file_id = a.id
+         JOIN afolders f ON a.folder_id = f.id
+         WHERE dgi.group_id = ?1",
+        KEEP_CANDIDATE_COLUMNS
+    );
+    for (group_id, reviewed) in groups {
+        let mut keep_candidates = {
+            let mut stmt = tx.prepare(&item_query).map_err(|e| e.to_string())?;
+            stmt.query_map(params![group_id], keep_candidate_from_row)
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?
+        };
+        rank_candidates(policy, &mut keep_candidates);
 
+        for (i, candidate) in keep_candidates.iter().enumerate() {
             tx.execute(
-                "INSERT INTO duplicate_group_items (group_id, file_id, is_keep, is_selected, score)
-                 VALUES (?1, ?2, ?3, ?4, ?5)",
-                params![target_group_id, candidate.id, is_keep, 0, score],
+                "UPDATE duplicate_group_items SET score = ?1, reasons = ?2
+                 WHERE group_id = ?3 AND file_id = ?4",
+                params![
+                    candidate.score,
+                    candidate.reasons.join(","),
+                    group_id,
+                    candidate.id
+                ],
             )
             .map_err(|e| e.to_string())?;
+            if reviewed == 0 {
+                tx.execute(
+                    "UPDATE duplicate_group_items SET is_keep = ?1
+                     WHERE group_id = ?2 AND file_id = ?3",
+                    params![if i == 0 { 1 } else { 0 }, group_id, candidate.id],
+                )
+                .map_err(|e| e.to_string())?;
+            }
         }
     }
-    drop(stmt);
 
     tx.commit().map_err(|e| e.to_string())?;
     Ok(())
 }
 
-fn compare_best_quality(a: &KeepCandidate, b: &KeepCandidate) -> std::cmp::Ordering {
-    (b.taken_date > 0)
-        .cmp(&(a.taken_date > 0))
-        .then_with(|| a.created_at.cmp(&b.created_at))
-        .then_with(|| a.id.cmp(&b.id))
+/// Score every candidate with the policy and sort them best first.
+/// Ties fall back to: has taken date, oldest created, lowest id.
+fn rank_candidates(policy: &KeepPolicy, candidates: &mut [KeepCandidate]) {
+    let max_pixels = candidates.iter().map(|c| c.pixels).max().unwrap_or(0);
+    let min_pixels = candidates.iter().map(|c| c.pixels).min().unwrap_or(0);
+    let max_size = candidates.iter().map(|c| c.size).max().unwrap_or(0);
+    let min_size = candidates.iter().map(|c| c.size).min().unwrap_or(0);
+
+    for candidate in candidates.iter_mut() {
+        let mut score = 0.0;
+        let mut reasons = Vec::new();
+
+        // relative criteria only count when the group differs
+        if max_pixels > min_pixels {
+            score += policy.resolution * candidate.pixels as f64 / max_pixels as f64;
+            if candidate.pixels == max_pixels && policy.resolution > 0.0 {
+                reasons.push("resolution");
+            }
+        }
+        if max_size > min_size {
+            score += policy.file_size * candidate.size as f64 / max_size as f64;
+            if candidate.size == max_size && policy.file_size > 0.0 {
+                reasons.push("file_size");
+            }
+        }
+
+        let flags = [
+            (candidate.has_exif, policy.exif, "exif"),
+            (candidate.has_gps, policy.gps, "gps"),
+            (candidate.has_rating, policy.rating, "rating"),
+            (candidate.has_tags, policy.tags, "tags"),
+            (candidate.has_collections, policy.collections, "collections"),
+            (
+                is_in_preferred_folder(&candidate.path, &policy.preferred_folders),
+                policy.preferred_folder,
+                "preferred_folder",
+            ),
+        ];
+        for (matched, weight, reason) in flags {
+            if matched && weight != 0.0 {
+                score += weight;
+                reasons.push(reason);
+            }
+        }
+
+        if matches_avoided_path(&candidate.path, &policy.avoided_paths)
+            && policy.avoided_path != 0.0
+        {
+            score -= policy.avoided_path;
+            reasons.push("avoided_path");
+        }
+
+        candidate.score = score;
+        candidate.reasons = reasons;
+    }
+
+    candidates.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| (b.taken_date > 0).cmp(&(a.taken_date > 0)))
+            .then_with(|| a.created_at.cmp(&b.created_at))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+}
+
+fn is_in_preferred_folder(path: &str, preferred_folders: &[String]) -> bool {
+    let path = normalize_folder_path(path);
+    preferred_folders.iter().any(|folder| {
+        let folder = normalize_folder_path(folder);
+        // "/Photos/Archive" matches files in that folder and at any depth below it
+        !folder.is_empty() && path.starts_with(&format!("{}/", folder))
+    })
+}
+
+fn normalize_folder_path(path: &str) -> String {
+    path.trim()
+        .replace('\\', "/")
+        .trim_end_matches('/')
+        .to_lowercase()
+}
+
+fn matches_avoided_path(path: &str, avoided_paths: &[String]) -> bool {
+    let path = path.to_lowercase();
+    avoided_paths
+        .iter()
+        .any(|pattern| !pattern.is_empty() && path.contains(&pattern.to_lowercase()))
 }
 
 // ----------------------------------------------------------------------------
@@ -609,6 +1816,7 @@ pub struct DedupGroup {
     pub file_size: i64,
     pub file_count: i64,
     pub total_size: i64,
+    pub match_kind: String, // "exact", "dhash", "phash", "video"
     pub reviewed: i32,
     pub updated_at: i64,
     pub items: Vec<DedupGroupItem>,
@@ -621,6 +1829,7 @@ pub struct DedupGroupItem {
     pub is_keep: i32,
     pub is_selected: i32,
     pub score: f64,
+    pub reasons: Vec<String>, // keep policy criteria matched by this file
     pub file: Option<AFile>,
 }
 
@@ -691,8 +1900,9 @@ pub fn list_groups(
     let query = format!(
         "SELECT id, hash, file_size, 
                 (SELECT COUNT(*) FROM duplicate_group_items WHERE group_id = duplicate_groups.id) as cur_count,
-                ((SELECT COUNT(*) FROM duplicate_group_items WHERE group_id = duplicate_groups.id) * file_size) as cur_size,
-                reviewed, updated_at
+                (SELECT COALESCE(SUM(a.size), 0) FROM duplicate_group_items i JOIN afiles a ON a.id = i.file_id
+                 WHERE i.group_id = duplicate_groups.id) as cur_size,
+                match_kind, reviewed, updated_at
          FROM duplicate_groups
          {}
          ORDER BY {}
@@ -716,8 +1926,9 @@ pub fn list_groups(
                 file_size: row.get(2)?,
                 file_count: row.get(3)?,
                 total_size: row.get(4)?,
-                reviewed: row.get(5)?,
-                updated_at: row.get(6)?,
+                match_kind: row.get(5)?,
+                reviewed: row.get(6)?,
+                updated_at: row.get(7)?,
                 items: Vec::new(),
             })
         })
@@ -738,7 +1949,7 @@ pub fn list_groups(
 fn get_group_items(conn: &Connection, group_id: i64) -> Result<Vec<DedupGroupItem>, String> {
     let mut stmt = conn
         .prepare(
-            "SELECT group_id, file_id, is_keep, is_selected, score
+            "SELECT group_id, file_id, is_keep, is_selected, score, reasons
          FROM duplicate_group_items
          WHERE group_id = ?1
          ORDER BY is_keep DESC, score DESC",
@@ -753,6 +1964,12 @@ fn get_group_items(conn: &Connection, group_id: i64) -> Result<Vec<DedupGroupIte
                 is_keep: row.get(2)?,
                 is_selected: row.get(3)?,
                 score: row.get(4)?,
+                reasons: row
+                    .get::<_, String>(5)?
+                    .split(',')
+                    .filter(|reason| !reason.is_empty())
+                    .map(str::to_string)
+                    .collect(),
                 file: None, // Will populate shortly
             })
         })
@@ -808,16 +2025,23 @@ pub fn set_keep(group_id: i64, file_id: i64) -> Result<(), String> {
 pub fn delete_selected(
     group_ids: Option<Vec<i64>>,
     file_ids: Option<Vec<i64>>,
+    merge_metadata: bool,
 ) -> Result<DedupDeleteResult, String> {
     let mut conn = get_db_conn()?;
     let tx = conn.transaction().map_err(|e| e.to_string())?;
 
-    let mut files_to_delete: Vec<(i64, String)> = Vec::new();
+    // (file_id, file_path, keeper file_id of its group)
+    let mut files_to_delete: Vec<(i64, String, Option<i64>)> = Vec::new();
+    fn map_row(row: &rusqlite::Row) -> rusqlite::Result<(i64, String, Option<i64>)> {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    }
 
     if let Some(ids) = file_ids {
         let mut stmt = tx
             .prepare(
-                "SELECT a.id, f.path || '/' || a.name
+                "SELECT a.id, f.path || '/' || a.name,
+                 (SELECT k.file_id FROM duplicate_group_items k
+                  WHERE k.group_id = dgi.group_id AND k.is_keep = 1 LIMIT 1)
                  FROM duplicate_group_items dgi
                  JOIN afiles a ON dgi.file_id = a.id
                  JOIN afolders f ON a.folder_id = f.id
@@ -826,7 +2050,7 @@ pub fn delete_selected(
             .map_err(|e| e.to_string())?;
         for id in ids {
             let mut iter = stmt
-                .query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?)))
+                .query_map(params![id], map_row)
                 .map_err(|e| e.to_string())?;
             for row in &mut iter {
                 files_to_delete.push(row.map_err(|e| e.to_string())?);
@@ -836,7 +2060,9 @@ pub fn delete_selected(
         for gid in gids {
             let mut stmt = tx
                 .prepare(
-                    "SELECT a.id, f.path || '/' || a.name
+                    "SELECT a.id, f.path || '/' || a.name,
+                    (SELECT k.file_id FROM duplicate_group_items k
+                     WHERE k.group_id = dgi.group_id AND k.is_keep = 1 LIMIT 1)
                     FROM duplicate_group_items dgi
                     JOIN afiles a ON dgi.file_id = a.id
                     JOIN afolders f ON a.folder_id = f.id
@@ -845,7 +2071,7 @@ pub fn delete_selected(
                 .map_err(|e| e.to_string())?;
 
             let mut iter = stmt
-                .query_map(params![gid], |row| Ok((row.get(0)?, row.get(1)?)))
+                .query_map(params![gid], map_row)
                 .map_err(|e| e.to_string())?;
             for row in &mut iter {
                 files_to_delete.push(row.map_err(|e| e.to_string())?);
@@ -854,16 +2080,16 @@ pub fn delete_selected(
     } else {
         let mut stmt = tx
             .prepare(
-                "SELECT a.id, f.path || '/' || a.name
+                "SELECT a.id, f.path || '/' || a.name,
+                 (SELECT k.file_id FROM duplicate_group_items k
+                  WHERE k.group_id = dgi.group_id AND k.is_keep = 1 LIMIT 1)
                  FROM duplicate_group_items dgi
                  JOIN afiles a ON dgi.file_id = a.id
                  JOIN afolders f ON a.folder_id = f.id
                  WHERE dgi.is_keep = 0 AND dgi.is_selected = 1",
             )
             .map_err(|e| e.to_string())?;
-        let mut iter = stmt
-            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
-            .map_err(|e| e.to_string())?;
+        let mut iter = stmt.query_map([], map_row).map_err(|e| e.to_string())?;
         for row in &mut iter {
             files_to_delete.push(row.map_err(|e| e.to_string())?);
         }
@@ -874,12 +2100,50 @@ pub fn delete_selected(
     let mut failures: Vec<String> = Vec::new();
     let mut deleted_file_ids: Vec<i64> = Vec::new();
     let mut trash_failed_file_ids: Vec<i64> = Vec::new();
-    for (file_id, file_path) in files_to_delete {
+    let mut merged = merge_metadata.then(DedupMergeSummary::default);
+    for (file_id, file_path, keep_id) in files_to_delete {
+        // Carry Lap-only data over before the delete cascades it away. The merge stays
+        // uncommitted until the file is in the trash, so a failed trash leaves both untouched.
+        let merge = match (merge_metadata, keep_id) {
+            (true, Some(keep_id)) => {
+                let merged_file = conn
+                    .transaction()
+                    .map_err(|e| e.to_string())
+                    .and_then(|tx| {
+                        let file_summary = merge_into_keeper(&tx, file_id, keep_id)?;
+                        Ok((tx, file_summary))
+                    });
+                match merged_file {
+                    Ok(merged_file) => Some(merged_file),
+                    Err(e) => {
+                        failures.push(format!(
+                            "Failed to merge metadata of {} into the kept file: {}",
+                            file_path, e
+                        ));
+                        continue;
+                    }
+                }
+            }
+            _ => None,
+        };
         if let Err(e) = t_utils::trash_path(&file_path) {
             trash_failed_file_ids.push(file_id);
             failures.push(format!("Failed to move to trash: {} ({})", file_path, e));
             continue;
         }
+        if let Some((tx, file_summary)) = merge {
+            match tx.commit() {
+                Ok(_) => {
+                    if let Some(summary) = merged.as_mut() {
+                        summary.add(&file_summary);
+                    }
+                }
+                Err(e) => failures.push(format!(
+                    "Failed to merge metadata of {} into the kept file: {}",
+                    file_path, e
+                )),
+            }
+        }
         match AFile::delete(file_id) {
             Ok(0) => failures.push(format!("File not removed from DB: id={}", file_id)),
             Ok(_) => deleted_file_ids.push(file_id),
@@ -909,5 +2173,482 @@ pub fn delete_selected(
         failed_count: failures.len(),
         errors: failures,
         trash_failed_file_ids,
+        merged,
+    })
+}
+
+/// Replace selected duplicates with links to the kept file, keeping their paths and catalog rows.
+/// Content is verified with blake3 before linking and after the link is in place.
+pub fn link_selected(
+    group_ids: Option<Vec<i64>>,
+    file_ids: Option<Vec<i64>>,
+    mode: LinkMode,
+) -> Result<DedupLinkResult, String> {
+    let mut conn = get_db_conn()?;
+
+    // (group_id, file_id, file_path, keep_path, size, match_kind)
+    let base_query = "SELECT dgi.group_id, a.id, f.path || '/' || a.name,
+                kf.path || '/' || ka.name, a.size, g.match_kind
+         FROM duplicate_group_items dgi
+         JOIN duplicate_groups g ON g.id = dgi.group_id
+         JOIN afiles a ON dgi.file_id = a.id
+         JOIN afolders f ON a.folder_id = f.id
+         JOIN duplicate_group_items k ON k.group_id = dgi.group_id AND k.is_keep = 1
+         JOIN afiles ka ON k.file_id = ka.id
+         JOIN afolders kf ON ka.folder_id = kf.id
+         WHERE dgi.is_keep = 0";
+    let mut files_to_link: Vec<(i64, i64, String, String, i64, String)> = Vec::new();
+    {
+        fn map_row(
+            row: &rusqlite::Row,
+        ) -> rusqlite::Result<(i64, i64, String, String, i64, String)> {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+            ))
+        }
+        if let Some(ids) = file_ids {
+            let mut stmt = conn
+                .prepare(&format!("{} AND dgi.file_id = ?1", base_query))
+                .map_err(|e| e.to_string())?;
+            for id in ids {
+                let rows = stmt
+                    .query_map(params![id], map_row)
+                    .map_err(|e| e.to_string())?;
+                for row in rows {
+                    files_to_link.push(row.map_err(|e| e.to_string())?);
+                }
+            }
+        } else if let Some(gids) = group_ids {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "{} AND dgi.group_id = ?1 AND dgi.is_selected = 1",
+                    base_query
+                ))
+                .map_err(|e| e.to_string())?;
+            for gid in gids {
+                let rows = stmt
+                    .query_map(params![gid], map_row)
+                    .map_err(|e| e.to_string())?;
+                for row in rows {
+                    files_to_link.push(row.map_err(|e| e.to_string())?);
+                }
+            }
+        } else {
+            let mut stmt = conn
+                .prepare(&format!("{} AND dgi.is_selected = 1", base_query))
+                .map_err(|e| e.to_string())?;
+            let rows = stmt.query_map([], map_row).map_err(|e| e.to_string())?;
+            for row in rows {
+                files_to_link.push(row.map_err(|e| e.to_string())?);
+            }
+        }
+    }
+
+    let mut failures: Vec<String> = Vec::new();
+    let mut linked: Vec<(i64, i64)> = Vec::new(); // (group_id, file_id)
+    let mut reclaimed_bytes = 0;
+    for (group_id, file_id, file_path, keep_path, size, match_kind) in files_to_link {
+        if match_kind != MatchKind::Exact.as_str() {
+            failures.push(format!(
+                "Only exact duplicates can be linked: {}",
+                file_path
+            ));
+            continue;
+        }
+        match link_duplicate(&keep_path, &file_path, mode) {
+            Ok(reclaimed) => {
+                linked.push((group_id, file_id));
+                if reclaimed {
+                    reclaimed_bytes += size;
+                }
+            }
+            Err(e) => failures.push(format!("Failed to link {}: {}", file_path, e)),
+        }
+    }
+
+    // The linked files keep their rows; refresh what changed on disk so the next
+    // sync doesn't treat them as modified, and take them out of their groups.
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for (group_id, file_id) in &linked {
+        let file_path: Option<String> = tx
+            .query_row(
+                "SELECT f.path || '/' || a.name FROM afiles a
+                 JOIN afolders f ON a.folder_id = f.id WHERE a.id = ?1",
+                params![file_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        if let Some(info) = file_path.and_then(|path| t_utils::FileInfo::new(&path).ok()) {
+            tx.execute(
+                "UPDATE afiles SET modified_at = ?1, inode = ?2 WHERE id = ?3",
+                params![info.modified, info.inode as i64, file_id],
+            )
+            .map_err(|e| e.to_string())?;
+            tx.execute(
+                "UPDATE file_hashes SET mtime = ?1 WHERE file_id = ?2",
+                params![info.modified.unwrap_or(0), file_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.execute(
+            "DELETE FROM duplicate_group_items WHERE group_id = ?1 AND file_id = ?2",
+            params![group_id, file_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.execute(
+        "DELETE FROM duplicate_groups
+         WHERE (SELECT COUNT(*) FROM duplicate_group_items WHERE group_id = duplicate_groups.id) <= 1",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(DedupLinkResult {
+        linked_file_ids: linked.into_iter().map(|(_, file_id)| file_id).collect(),
+        reclaimed_bytes,
+        failed_count: failures.len(),
+        errors: failures,
+    })
+}
+
+/// Replace `file_path` with a link to `keep_path`.
+/// Returns false when the two paths were already the same file (nothing reclaimed).
+fn link_duplicate(keep_path: &str, file_path: &str, mode: LinkMode) -> Result<bool, String> {
+    if t_utils::is_same_file(keep_path, file_path) {
+        return Ok(false);
+    }
+
+    let expected = compute_blake3_hash(keep_path).map_err(|e| e.to_string())?;
+    if compute_blake3_hash(file_path).map_err(|e| e.to_string())? != expected {
+        return Err("content differs from the kept file".to_string());
+    }
+
+    // Build the link next to the duplicate, then swap it in with an atomic rename
+    let path = Path::new(file_path);
+    let file_name = path
+        .file_name()
+        .ok_or("invalid file name")?
+        .to_string_lossy();
+    let temp_path = path
+        .with_file_name(format!(".{}.lap-dedup", file_name))
+        .to_string_lossy()
+        .to_string();
+    let _ = fs::remove_file(&temp_path);
+
+    let link_result = match mode {
+        LinkMode::Hardlink => fs::hard_link(keep_path, &temp_path).map_err(|e| e.to_string()),
+        LinkMode::Reflink => t_utils::reflink_file(keep_path, &temp_path),
+        LinkMode::Auto => t_utils::reflink_file(keep_path, &temp_path)
+            .or_else(|_| fs::hard_link(keep_path, &temp_path).map_err(|e| e.to_string())),
+    };
+    link_result?;
+
+    let verify = || -> Result<(), String> {
+        if compute_blake3_hash(&temp_path).map_err(|e| e.to_string())? != expected {
+            return Err("linked file does not match the kept file".to_string());
+        }
+        // A reflink is a new file: carry over the duplicate's timestamps and permissions
+        if !t_utils::is_same_file(keep_path, &temp_path) {
+            let metadata = fs::metadata(file_path).map_err(|e| e.to_string())?;
+            if let Ok(modified) = metadata.modified() {
+                let file = fs::OpenOptions::new()
+                    .write(true)
+                    .open(&temp_path)
+                    .map_err(|e| e.to_string())?;
+                file.set_modified(modified).map_err(|e| e.to_string())?;
+            }
+            fs::set_permissions(&temp_path, metadata.permissions()).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    };
+    if let Err(e) = verify() {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    if let Err(e) = fs::rename(&temp_path, file_path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e.to_string());
+    }
+
+    if compute_blake3_hash(file_path).map_err(|e| e.to_string())? != expected {
+        return Err("file content changed after linking".to_string());
+    }
+    Ok(true)
+}
+
+/// Merge the Lap-only metadata of a duplicate into the kept file:
+/// union of tags and collections, max rating, any favorite, concatenated comments,
+/// and person assignments of overlapping face boxes. The caller commits `tx`.
+fn merge_into_keeper(
+    tx: &rusqlite::Transaction,
+    file_id: i64,
+    keep_id: i64,
+) -> Result<DedupMergeSummary, String> {
+    if file_id == keep_id {
+        return Ok(DedupMergeSummary::default());
+    }
+
+    let tags_added = tx
+        .execute(
+            "INSERT OR IGNORE INTO afile_tags (file_id, tag_id)
+             SELECT ?1, tag_id FROM afile_tags WHERE file_id = ?2",
+            params![keep_id, file_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if tags_added > 0 {
+        tx.execute(
+            "UPDATE afiles SET has_tags = 1 WHERE id = ?1",
+            params![keep_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    tx.execute(
+        "UPDATE acollections SET updated_at = ?1
+         WHERE id IN (SELECT collection_id FROM acollections_files WHERE file_id = ?2)
+           AND id NOT IN (SELECT collection_id FROM acollections_files WHERE file_id = ?3)",
+        params![now, file_id, keep_id],
+    )
+    .map_err(|e| e.to_string())?;
+    let collections_added = tx
+        .execute(
+            "INSERT OR IGNORE INTO acollections_files (collection_id, file_id, added_at)
+             SELECT collection_id, ?1, added_at FROM acollections_files WHERE file_id = ?2",
+            params![keep_id, file_id],
+        )
+        .map_err(|e| e.to_string())?;
+
+    let ratings_raised = tx
+        .execute(
+            "UPDATE afiles SET rating = (SELECT rating FROM afiles WHERE id = ?2)
+             WHERE id = ?1
+               AND COALESCE(rating, 0) < (SELECT COALESCE(rating, 0) FROM afiles WHERE id = ?2)",
+            params![keep_id, file_id],
+        )
+        .map_err(|e| e.to_string())?;
+
+    let favorites_set = tx
+        .execute(
+            "UPDATE afiles SET is_favorite = 1
+             WHERE id = ?1 AND COALESCE(is_favorite, 0) = 0
+               AND (SELECT COALESCE(is_favorite, 0) FROM afiles WHERE id = ?2) = 1",
+            params![keep_id, file_id],
+        )
+        .map_err(|e| e.to_string())?;
+
+    let (keep_comments, file_comments): (Option<String>, Option<String>) = tx
+        .query_row(
+            "SELECT (SELECT comments FROM afiles WHERE id = ?1),
+                    (SELECT comments FROM afiles WHERE id = ?2)",
+            params![keep_id, file_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    let file_comments = file_comments.unwrap_or_default();
+    let file_comments = file_comments.trim();
+    let keep_comments = keep_comments.unwrap_or_default();
+    let mut comments_merged = 0;
+    if !file_comments.is_empty() && !keep_comments.contains(file_comments) {
+        let comments = if keep_comments.trim().is_empty() {
+            file_comments.to_string()
+        } else {
+            format!("{}\n{}", keep_comments.trim_end(), file_comments)
+        };
+        tx.execute(
+            "UPDATE afiles SET comments = ?1 WHERE id = ?2",
+            params![comments, keep_id],
+        )
+        .map_err(|e| e.to_string())?;
+        comments_merged = 1;
+    }
+
+    let faces_assigned = assign_duplicate_faces(tx, file_id, keep_id)?;
+
+    Ok(DedupMergeSummary {
+        merged_files: 1,
+        tags_added,
+        collections_added,
+        ratings_raised,
+        favorites_set,
+        comments_merged,
+        faces_assigned,
     })
 }
+
+/// Name the unnamed faces of the kept file after the overlapping named faces of its
+/// duplicate. Boxes are compared relative to each file's size, so a resized copy matches.
+fn assign_duplicate_faces(
+    tx: &rusqlite::Transaction,
+    file_id: i64,
+    keep_id: i64,
+) -> Result<usize, String> {
+    let get_size = |id: i64| -> Result<Option<(f32, f32)>, String> {
+        let (width, height): (Option<i64>, Option<i64>) = tx
+            .query_row(
+                "SELECT width, height FROM afiles WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(match (width, height) {
+            (Some(width), Some(height)) if width > 0 && height > 0 => {
+                Some((width as f32, height as f32))
+            }
+            _ => None,
+        })
+    };
+    // without both sizes, compare the boxes as stored
+    let (file_size, keep_size) = match (get_size(file_id)?, get_size(keep_id)?) {
+        (Some(file_size), Some(keep_size)) => (file_size, keep_size),
+        _ => ((1.0, 1.0), (1.0, 1.0)),
+    };
+    let scale = |(x, y, width, height): (f32, f32, f32, f32), (w, h): (f32, f32)| {
+        (x / w, y / h, width / w, height / h)
+    };
+
+    let mut stmt = tx
+        .prepare("SELECT id, bbox, person_id FROM faces WHERE file_id = ?1")
+        .map_err(|e| e.to_string())?;
+    let mut get_faces = |id: i64| -> Result<Vec<(i64, String, Option<i64>)>, String> {
+        stmt.query_map(params![id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+    };
+    let named_faces: Vec<((f32, f32, f32, f32), i64)> = get_faces(file_id)?
+        .into_iter()
+        .filter_map(|(_, bbox, person_id)| Some((scale(parse_bbox(&bbox)?, file_size), person_id?)))
+        .collect();
+    if named_faces.is_empty() {
+        return Ok(0);
+    }
+    let keep_faces = get_faces(keep_id)?;
+
+    let mut faces_assigned = 0;
+    for (face_id, bbox, person_id) in keep_faces {
+        if person_id.is_some() {
+            continue;
+        }
+        let Some(rect) = parse_bbox(&bbox).map(|rect| scale(rect, keep_size)) else {
+            continue;
+        };
+        let best = named_faces
+            .iter()
+            .map(|(other, person_id)| (rect_iou(rect, *other), *person_id))
+            .filter(|(iou, _)| *iou > FACE_MATCH_IOU)
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some((_, person_id)) = best {
+            tx.execute(
+                "UPDATE faces SET person_id = ?1 WHERE id = ?2",
+                params![person_id, face_id],
+            )
+            .map_err(|e| e.to_string())?;
+            faces_assigned += 1;
+        }
+    }
+    Ok(faces_assigned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE duplicate_groups (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                hash TEXT NOT NULL,
+                file_size INTEGER NOT NULL,
+                file_count INTEGER NOT NULL,
+                total_size INTEGER NOT NULL,
+                reviewed INTEGER NOT NULL DEFAULT 0,
+                match_kind TEXT NOT NULL DEFAULT 'exact',
+                updated_at INTEGER NOT NULL
+            );
+            CREATE TABLE duplicate_group_items (
+                group_id INTEGER NOT NULL,
+                file_id INTEGER NOT NULL,
+                is_keep INTEGER NOT NULL DEFAULT 0,
+                is_selected INTEGER NOT NULL DEFAULT 0,
+                score REAL NOT NULL DEFAULT 0,
+                reasons TEXT NOT NULL DEFAULT '',
+                PRIMARY KEY (group_id, file_id)
+            );",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn add_group(conn: &Connection, hash: &str, match_kind: MatchKind, file_ids: &[i64]) {
+        conn.execute(
+            "INSERT INTO duplicate_groups (hash, file_size, file_count, total_size, match_kind, updated_at)
+             VALUES (?1, 10, ?2, ?3, ?4, 0)",
+            params![
+                hash,
+                file_ids.len() as i64,
+                10 * file_ids.len() as i64,
+                match_kind.as_str()
+            ],
+        )
+        .unwrap();
+        let group_id = conn.last_insert_rowid();
+        for file_id in file_ids {
+            conn.execute(
+                "INSERT INTO duplicate_group_items (group_id, file_id) VALUES (?1, ?2)",
+                params![group_id, file_id],
+            )
+            .unwrap();
+        }
+    }
+
+    fn group_hashes(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare("SELECT hash FROM duplicate_groups ORDER BY hash")
+            .unwrap();
+        stmt.query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<String>, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn clearing_keeps_other_kinds_and_scopes() {
+        let mut conn = test_conn();
+        add_group(&conn, "a", MatchKind::Exact, &[1, 2]);
+        add_group(&conn, "b", MatchKind::Exact, &[3, 4]);
+        add_group(&conn, "dhash:1", MatchKind::DHash, &[1, 5]);
+
+        // a scoped exact scan replaces only the exact groups of its files
+        let tx = conn.transaction().unwrap();
+        clear_groups(&tx, MatchKind::Exact, Some(&[2, 5])).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(group_hashes(&conn), vec!["b", "dhash:1"]);
+        let items: i64 = conn
+            .query_row("SELECT COUNT(*) FROM duplicate_group_items", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(items, 4);
+
+        // a library-wide perceptual scan leaves the exact groups alone
+        let tx = conn.transaction().unwrap();
+        clear_groups(&tx, MatchKind::DHash, None).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(group_hashes(&conn), vec!["b"]);
+    }
+}
@@ -0,0 +1,415 @@
+/**
+ * t_digikam.rs - digiKam database reader
+ *
+ * `digikam4.db` is an SQLite database (MySQL setups are not supported). Files
+ * live in `Images` -> `Albums` -> `AlbumRoots`; a root is identified by a volume
+ * and a path on it, so roots on removable or Windows drives usually need a
+ * remap. Tags form a tree in `Tags`; pick and color labels are internal tags
+ * under `_Digikam_Internal_Tags_`, and people are tags with a `person`
+ * property whose confirmed face regions are stored in `ImageTagProperties`.
+ * Physical albums become collections named after their relative path.
+ */
+use crate::t_catalog_import::{
+    COLOR_LABEL_PREFIX, CatalogData, CatalogFace, CatalogImage, TAG_PATH_SEPARATOR,
+};
+use rusqlite::{Connection, OpenFlags};
+use std::collections::{HashMap, HashSet};
+
+const INTERNAL_TAG_ROOT: &str = "_Digikam_Internal_Tags_";
+const PICK_LABEL_PREFIX: &str = "Pick Label ";
+const COLOR_LABEL_INTERNAL_PREFIX: &str = "Color Label ";
+
+const IMAGE_STATUS_VISIBLE: i64 = 1;
+
+/// Read the images, tags, albums and face regions of a digiKam database
+pub fn read_catalog(catalog_path: &str) -> Result<CatalogData, String> {
+    let conn = Connection::open_with_flags(
+        catalog_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("Failed to open the digiKam database: {}", e))?;
+
+    let table_count: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'
+            AND name IN ('Images', 'Albums', 'AlbumRoots', 'Tags', 'ImageTags')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(catalog_error)?;
+    if table_count < 5 {
+        return Err("Not a digiKam database.".to_string());
+    }
+
+    let mut data = CatalogData::default();
+
+    // album roots
+    let mut roots: HashMap<i64, String> = HashMap::new();
+    let mut stmt = conn
+        .prepare("SELECT id, identifier, specificPath FROM AlbumRoots")
+        .map_err(catalog_error)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })
+        .map_err(catalog_error)?;
+    for row in rows {
+        let (id, identifier, specific_path) = row.map_err(catalog_error)?;
+        let root = get_root_path(
+            identifier.as_deref().unwrap_or(""),
+            specific_path.as_deref().unwrap_or(""),
+        );
+        data.roots.push(root.clone());
+        roots.insert(id, root);
+    }
+    data.roots.sort();
+
+    // tags, with internal labels and people told apart from regular tags
+    let tags = read_tags(&conn)?;
+
+    // images with their album and rating
+    let mut image_index: HashMap<i64, usize> = HashMap::new();
+    let mut stmt = conn
+        .prepare(
+            "SELECT i.id, a.albumRoot, a.relativePath, i.name, ii.rating
+            FROM Images i
+            JOIN Albums a ON a.id = i.album
+            LEFT JOIN ImageInformation ii ON ii.imageid = i.id
+            WHERE i.status = ?1",
+        )
+        .map_err(catalog_error)?;
+    let rows = stmt
+        .query_map([IMAGE_STATUS_VISIBLE], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<i64>>(4)?,
+            ))
+        })
+        .map_err(catalog_error)?;
+    for row in rows {
+        let (id, root_id, relative_path, name, rating) = row.map_err(catalog_error)?;
+        let Some(root) = roots.get(&root_id) else {
+            continue;
+        };
+        let album = relative_path.trim_matches('/');
+        let path = if album.is_empty() {
+            format!("{}/{}", root.trim_end_matches('/'), name)
+        } else {
+            format!("{}/{}/{}", root.trim_end_matches('/'), album, name)
+        };
+        let mut image = CatalogImage {
+            path,
+            rating: rating
+                .filter(|rating| *rating >= 0)
+                .map(|rating| rating as i32),
+            ..CatalogImage::default()
+        };
+        if !album.is_empty() {
+            image.collections.push(album.to_string());
+        }
+        image_index.insert(id, data.images.len());
+        data.images.push(image);
+    }
+
+    // confirmed face regions
+    let mut face_tags: HashSet<(i64, i64)> = HashSet::new();
+    let has_regions: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'ImageTagProperties'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(catalog_error)?;
+    if has_regions > 0 {
+        let mut stmt = conn
+            .prepare(
+                "SELECT imageid, tagid, value FROM ImageTagProperties WHERE property = 'tagRegion'",
+            )
+            .map_err(catalog_error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            })
+            .map_err(catalog_error)?;
+        for row in rows {
+            let (image_id, tag_id, value) = row.map_err(catalog_error)?;
+            let Some(index) = image_index.get(&image_id) else {
+                continue;
+            };
+            let Some(person) = tags.get(&tag_id).and_then(|tag| tag.person.clone()) else {
+                continue;
+            };
+            let Some((x, y, width, height)) = value.as_deref().and_then(parse_rect) else {
+                continue;
+            };
+            data.images[*index].faces.push(CatalogFace {
+                person,
+                x,
+                y,
+                width,
+                height,
+            });
+            face_tags.insert((image_id, tag_id));
+        }
+    }
+
+    // tag links; a person tag with a face region on the same image is already a face
+    let mut stmt = conn
+        .prepare("SELECT imageid, tagid FROM ImageTags")
+        .map_err(catalog_error)?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(catalog_error)?;
+    for row in rows {
+        let (image_id, tag_id) = row.map_err(catalog_error)?;
+        let (Some(index), Some(tag)) = (image_index.get(&image_id), tags.get(&tag_id)) else {
+            continue;
+        };
+        let image = &mut data.images[*index];
+        match &tag.kind {
+            TagKind::PickLabel(flag) => {
+                if flag.is_some() {
+                    image.culling_flag = *flag;
+                }
+            }
+            TagKind::ColorLabel(Some(label)) => {
+                let name = format!("{}{}", COLOR_LABEL_PREFIX, label);
+                if !image.tags.contains(&name) {
+                    image.tags.push(name);
+                }
+            }
+            TagKind::ColorLabel(None) | TagKind::Internal => {}
+            TagKind::Regular => {
+                if tag.person.is_some() && face_tags.contains(&(image_id, tag_id)) {
+                    continue;
+                }
+                if !tag.path.is_empty() && !image.tags.contains(&tag.path) {
+                    image.tags.push(tag.path.clone());
+                }
+            }
+        }
+    }
+
+    Ok(data)
+}
+
+enum TagKind {
+    Regular,
+    PickLabel(Option<i32>), // culling flag; `None` for "None" and "Pending"
+    ColorLabel(Option<String>), // `None` for "None"
+    Internal,
+}
+
+struct Tag {
+    path: String,           // "People/Anna"
+    person: Option<String>, // set for confirmed people
+    kind: TagKind,
+}
+
+/// Read the tag tree into full paths and classify internal and person tags
+fn read_tags(conn: &Connection) -> Result<HashMap<i64, Tag>, String> {
+    let mut nodes: HashMap<i64, (i64, String)> = HashMap::new();
+    let mut stmt = conn
+        .prepare("SELECT id, pid, name FROM Tags")
+        .map_err(catalog_error)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<i64>>(1)?.unwrap_or(0),
+                row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            ))
+        })
+        .map_err(catalog_error)?;
+    for row in rows {
+        let (id, pid, name) = row.map_err(catalog_error)?;
+        nodes.insert(id, (pid, name.trim().to_string()));
+    }
+
+    // tagid -> (property, value)
+    let mut properties: HashMap<i64, Vec<(String, String)>> = HashMap::new();
+    let has_properties: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'TagProperties'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(catalog_error)?;
+    if has_properties > 0 {
+        let mut stmt = conn
+            .prepare("SELECT tagid, property, value FROM TagProperties")
+            .map_err(catalog_error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                ))
+            })
+            .map_err(catalog_error)?;
+        for row in rows {
+            let (tag_id, property, value) = row.map_err(catalog_error)?;
+            properties
+                .entry(tag_id)
+                .or_default()
+                .push((property, value));
+        }
+    }
+
+    let mut tags = HashMap::new();
+    for (id, (_, name)) in &nodes {
+        let mut names = vec![name.as_str()];
+        let mut parent = nodes.get(id).map(|(pid, _)| *pid).unwrap_or(0);
+        while parent != 0 && names.len() <= 64 {
+            let Some((pid, parent_name)) = nodes.get(&parent) else {
+                break;
+            };
+            names.push(parent_name.as_str());
+            parent = *pid;
+        }
+        names.reverse();
+        let is_internal = names.first() == Some(&INTERNAL_TAG_ROOT);
+
+        let props = properties.get(id).map(Vec::as_slice).unwrap_or(&[]);
+        let has_property = |key: &str| props.iter().any(|(property, _)| property == key);
+        // "Unknown", "Unconfirmed" and "Ignored" are placeholders, not people
+        let is_placeholder = has_property("unknownPerson")
+            || has_property("unconfirmedPerson")
+            || has_property("ignoredPerson");
+        let kind = if is_internal || has_property("internalTag") {
+            if let Some(label) = name.strip_prefix(PICK_LABEL_PREFIX) {
+                TagKind::PickLabel(match label {
+                    "Accepted" => Some(1),
+                    "Rejected" => Some(2),
+                    _ => None,
+                })
+            } else if let Some(label) = name.strip_prefix(COLOR_LABEL_INTERNAL_PREFIX) {
+                TagKind::ColorLabel(Some(label.to_string()).filter(|label| label != "None"))
+            } else {
+                TagKind::Internal
+            }
+        } else if is_placeholder {
+            TagKind::Internal
+        } else {
+            TagKind::Regular
+        };
+        let person = props
+            .iter()
+            .find(|(property, _)| property == "person")
+            .map(|(_, value)| value.trim())
+            .map(|value| {
+                if value.is_empty() {
+                    name.as_str()
+                } else {
+                    value
+                }
+            })
+            .filter(|value| !value.is_empty() && !is_placeholder)
+            .map(str::to_string);
+
+        let path = names
+            .into_iter()
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>()
+            .join(TAG_PATH_SEPARATOR);
+        tags.insert(*id, Tag { path, person, kind });
+    }
+    Ok(tags)
+}
+
+/// The folder of an album root. Roots on a known volume store the path on that
+/// volume, which is absolute when the volume is mounted at `/`; other roots keep
+/// their full path in the identifier (`volumeid:?path=%2Fhome%2Fme%2FPictures`).
+fn get_root_path(identifier: &str, specific_path: &str) -> String {
+    let query = identifier
+        .split_once('?')
+        .map(|(_, query)| query)
+        .unwrap_or("");
+    let base = query.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        match key {
+            "path" | "mountpath" => Some(
+                percent_encoding::percent_decode_str(value)
+                    .decode_utf8_lossy()
+                    .to_string(),
+            ),
+            _ => None,
+        }
+    });
+    let specific_path = specific_path.trim_end_matches('/');
+    match base {
+        Some(base) if specific_path.is_empty() => base,
+        Some(base) => format!("{}{}", base.trim_end_matches('/'), specific_path),
+        None if specific_path.is_empty() => "/".to_string(),
+        None => specific_path.to_string(),
+    }
+}
+
+/// Parse a region such as `<rect x="1201" y="752" width="402" height="402"/>`
+fn parse_rect(value: &str) -> Option<(f32, f32, f32, f32)> {
+    let get = |key: &str| -> Option<f32> {
+        let start = value.find(&format!(" {}=\"", key))? + key.len() + 3;
+        let end = start + value[start..].find('"')?;
+        value[start..end].trim().parse().ok()
+    };
+    let rect = (get("x")?, get("y")?, get("width")?, get("height")?);
+    if rect.2 <= 0.0 || rect.3 <= 0.0 {
+        return None;
+    }
+    Some(rect)
+}
+
+fn catalog_error(e: rusqlite::Error) -> String {
+    let message = e.to_string();
+    if message.contains("locked") {
+        "The database is in use. Close digiKam and try again.".to_string()
+    } else {
+        format!("Failed to read the digiKam database: {}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_face_regions() {
+        assert_eq!(
+            parse_rect(r#"<rect x="1201" y="752" width="402" height="398"/>"#),
+            Some((1201.0, 752.0, 402.0, 398.0))
+        );
+        assert_eq!(
+            parse_rect(r#"<rect width="0" height="10" x="1" y="2"/>"#),
+            None
+        );
+        assert_eq!(parse_rect("not a rect"), None);
+    }
+
+    #[test]
+    fn resolves_album_roots() {
+        assert_eq!(
+            get_root_path("volumeid:?uuid=1234-abcd", "/home/me/Pictures"),
+            "/home/me/Pictures"
+        );
+        assert_eq!(
+            get_root_path("volumeid:?path=%2Fmnt%2Fphotos%20old", "/"),
+            "/mnt/photos old"
+        );
+        assert_eq!(
+            get_root_path("networkshareid:?mountpath=%2Fmnt%2Fnas", "/Family"),
+            "/mnt/nas/Family"
+        );
+    }
+}
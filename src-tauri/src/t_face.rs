@@ -2,7 +2,7 @@
  * Face Recognition module
  * Handles face detection (RetinaFace) and embedding (MobileFaceNet) using ONNX Runtime.
  */
-use crate::{t_cluster, t_common, t_sqlite};
+use crate::{t_cluster, t_common, t_index_errors, t_sqlite};
 use image::DynamicImage;
 use ndarray::Array;
 use ort::{
@@ -61,6 +61,25 @@ pub struct FaceData {
     pub embedding: Vec<f32>,
 }
 
+/// Face rectangle drawn by the user, in original image pixels
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct FaceRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// A file matched by face search, with its best matching face
+#[derive(Debug, Serialize)]
+pub struct FaceSearchMatch {
+    pub face_id: i64,
+    pub person_id: Option<i64>,
+    pub person_name: Option<String>,
+    pub score: f32, // cosine similarity to the query face
+    pub file: t_sqlite::AFile,
+}
+
 struct Anchor {
     cx: f32,
     cy: f32,
@@ -341,7 +360,7 @@ impl FaceEngine {
     }
 
     /// Get face embedding implementation (from DynamicImage)
-    fn get_face_embedding(
+    pub fn get_face_embedding(
         &mut self,
         img: &DynamicImage,
         bbox: &FaceBox,
@@ -438,7 +457,6 @@ impl FaceEngine {
     }
 
     /// Compute cosine similarity between two embeddings
-    #[allow(dead_code)]
     pub fn compare_faces(emb1: &[f32], emb2: &[f32]) -> f32 {
         if emb1.len() != emb2.len() {
             return 0.0;
@@ -607,6 +625,247 @@ impl FaceEngine {
 #[derive(Clone)]
 pub struct FaceState(pub std::sync::Arc<Mutex<FaceEngine>>);
 
+/// Minimum side of a manual face box, in original image pixels
+const MIN_MANUAL_FACE_SIZE: f32 = 16.0;
+
+/// Overlap above which a detection is considered the same as a rejected box
+const REJECTION_IOU_THRESHOLD: f32 = 0.5;
+
+/// Read a stored face box, a JSON object with x, y, width and height
+pub(crate) fn parse_bbox(json: &str) -> Option<(f32, f32, f32, f32)> {
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+    let get = |key: &str| value.get(key).and_then(|v| v.as_f64()).map(|v| v as f32);
+    Some((get("x")?, get("y")?, get("width")?, get("height")?))
+}
+
+/// Intersection over union of two (x, y, width, height) rectangles
+pub(crate) fn rect_iou(a: (f32, f32, f32, f32), b: (f32, f32, f32, f32)) -> f32 {
+    let x1 = a.0.max(b.0);
+    let y1 = a.1.max(b.1);
+    let x2 = (a.0 + a.2).min(b.0 + b.2);
+    let y2 = (a.1 + a.3).min(b.1 + b.3);
+
+    if x2 <= x1 || y2 <= y1 {
+        return 0.0;
+    }
+
+    let inter_area = (x2 - x1) * (y2 - y1);
+    inter_area / (a.2 * a.3 + b.2 * b.3 - inter_area)
+}
+
+/// Clip a user-drawn rectangle to the image, so the crop never leaves it.
+fn clamp_face_rect(rect: &FaceRect, img_w: f32, img_h: f32) -> Result<FaceBox, String> {
+    let x = rect.x.clamp(0.0, img_w);
+    let y = rect.y.clamp(0.0, img_h);
+    let width = (rect.x + rect.width).min(img_w) - x;
+    let height = (rect.y + rect.height).min(img_h) - y;
+    if width < 1.0 || height < 1.0 {
+        return Err("Face box is outside the image".to_string());
+    }
+    Ok(FaceBox {
+        x,
+        y,
+        width,
+        height,
+        confidence: 1.0,
+        landmarks: None,
+    })
+}
+
+/// Compute the embedding of a user-drawn face rectangle.
+/// The original image is preferred so small faces keep their detail; the
+/// cached thumbnail is used (with the rectangle scaled down) when the original
+/// cannot be decoded by the `image` crate.
+fn embed_face_rect(
+    app_handle: &AppHandle,
+    face_state: &FaceState,
+    file_id: i64,
+    rect: FaceRect,
+) -> Result<(FaceBox, Vec<f32>), String> {
+    if rect.width < MIN_MANUAL_FACE_SIZE || rect.height < MIN_MANUAL_FACE_SIZE {
+        return Err("Face box is too small".to_string());
+    }
+
+    let file = t_sqlite::AFile::get_file_info(file_id)?.ok_or("File not found")?;
+    if file.file_type != Some(1) {
+        return Err("File is not an image".to_string());
+    }
+    let file_path = file.file_path.ok_or("File path not resolved")?;
+    let (orig_w, orig_h) = (
+        file.width.unwrap_or(0) as f32,
+        file.height.unwrap_or(0) as f32,
+    );
+
+    let mut engine = face_state.0.lock().unwrap();
+    if !engine.is_loaded() {
+        engine.load_models(app_handle)?;
+    }
+
+    if let Ok(img) = image::open(&file_path) {
+        let bbox = clamp_face_rect(&rect, img.width() as f32, img.height() as f32)?;
+        let embedding = engine.get_face_embedding(&img, &bbox)?;
+        return Ok((bbox, embedding));
+    }
+
+    let thumb_bytes = t_sqlite::AThumb::fetch(file_id)?
+        .and_then(|thumb| thumb.thumb_data)
+        .ok_or("Failed to load image for face embedding")?;
+    let img = image::load_from_memory(&thumb_bytes)
+        .map_err(|e| format!("Failed to load thumbnail: {}", e))?;
+    if orig_w <= 0.0 || orig_h <= 0.0 {
+        return Err("Image dimensions are unknown".to_string());
+    }
+    let bbox = clamp_face_rect(&rect, orig_w, orig_h)?;
+    let scale_x = img.width() as f32 / orig_w;
+    let scale_y = img.height() as f32 / orig_h;
+    let thumb_bbox = FaceBox {
+        x: bbox.x * scale_x,
+        y: bbox.y * scale_y,
+        width: bbox.width * scale_x,
+        height: bbox.height * scale_y,
+        confidence: bbox.confidence,
+        landmarks: None,
+    };
+    let embedding = engine.get_face_embedding(&img, &thumb_bbox)?;
+    Ok((bbox, embedding))
+}
+
+fn face_bbox_json(bbox: &FaceBox) -> String {
+    serde_json::json!({
+        "x": bbox.x,
+        "y": bbox.y,
+        "width": bbox.width,
+        "height": bbox.height,
+        "confidence": bbox.confidence,
+    })
+    .to_string()
+}
+
+/// Add a face box drawn by the user, optionally assigned to a person
+pub fn add_manual_face(
+    app_handle: &AppHandle,
+    face_state: &FaceState,
+    file_id: i64,
+    rect: FaceRect,
+    person_id: Option<i64>,
+) -> Result<i64, String> {
+    let (bbox, embedding) = embed_face_rect(app_handle, face_state, file_id, rect)?;
+    let face_id =
+        t_sqlite::Face::add_manual(file_id, &face_bbox_json(&bbox), &embedding, person_id)?;
+    if let Some(person_id) = person_id {
+        t_sqlite::Person::update_thumbnail(person_id)?;
+    }
+    Ok(face_id)
+}
+
+/// Move or resize an existing face box and recompute its embedding
+pub fn update_face_box(
+    app_handle: &AppHandle,
+    face_state: &FaceState,
+    face_id: i64,
+    rect: FaceRect,
+) -> Result<usize, String> {
+    let (file_id, person_id) = t_sqlite::Face::get_owner(face_id)?.ok_or("Face not found")?;
+    let (bbox, embedding) = embed_face_rect(app_handle, face_state, file_id, rect)?;
+    let result = t_sqlite::Face::update_box(face_id, &face_bbox_json(&bbox), &embedding)?;
+    if let Some(person_id) = person_id {
+        t_sqlite::Person::update_thumbnail(person_id)?;
+    }
+    Ok(result)
+}
+
+/// Assign a face to a person, or unassign it with `None`
+pub fn set_face_person(face_id: i64, person_id: Option<i64>) -> Result<usize, String> {
+    let (_, old_person_id) = t_sqlite::Face::get_owner(face_id)?.ok_or("Face not found")?;
+    let result = t_sqlite::Face::set_person(face_id, person_id)?;
+    for person_id in [old_person_id, person_id].into_iter().flatten() {
+        t_sqlite::Person::update_thumbnail(person_id)?;
+    }
+    Ok(result)
+}
+
+/// Delete a face; detected faces are remembered so reindexing skips them
+pub fn delete_face(face_id: i64) -> Result<usize, String> {
+    let (_, person_id) = t_sqlite::Face::get_owner(face_id)?.ok_or("Face not found")?;
+    let result = t_sqlite::Face::delete_and_reject(face_id)?;
+    if let Some(person_id) = person_id {
+        t_sqlite::Person::update_thumbnail(person_id)?;
+    }
+    Ok(result)
+}
+
+/// Search all face embeddings in the library for faces similar to `face_id`.
+/// Faces are compared regardless of their person assignment, and each file is
+/// ranked by its best matching face.
+pub fn search_faces(
+    face_id: i64,
+    threshold: f32,
+    limit: usize,
+) -> Result<Vec<FaceSearchMatch>, String> {
+    let query = t_sqlite::Face::get_embedding(face_id)?;
+    let faces = t_sqlite::Face::get_all_for_search()?;
+
+    // The query face's own file cannot contain another instance of the same person
+    let query_file_id = faces
+        .iter()
+        .find(|(id, ..)| *id == face_id)
+        .map(|(_, file_id, ..)| *file_id);
+
+    // Keep the best matching face per file: file_id -> (face_id, person_id, person_name, score)
+    let mut best_by_file: std::collections::HashMap<i64, (i64, Option<i64>, Option<String>, f32)> =
+        std::collections::HashMap::new();
+    for (id, file_id, person_id, person_name, embedding_bytes) in faces {
+        if id == face_id || Some(file_id) == query_file_id || embedding_bytes.len() % 4 != 0 {
+            continue;
+        }
+        let embedding: Vec<f32> = embedding_bytes
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+
+        let score = FaceEngine::compare_faces(&query, &embedding);
+        if score < threshold {
+            continue;
+        }
+        let is_better = best_by_file
+            .get(&file_id)
+            .map(|(_, _, _, best)| score > *best)
+            .unwrap_or(true);
+        if is_better {
+            best_by_file.insert(file_id, (id, person_id, person_name, score));
+        }
+    }
+
+    let mut ranked: Vec<_> = best_by_file.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.3.total_cmp(&a.1.3));
+    if limit > 0 {
+        ranked.truncate(limit);
+    }
+
+    // Fetch full file info in batches, then restore similarity order.
+    let file_ids = ranked
+        .iter()
+        .map(|(file_id, _)| *file_id)
+        .collect::<Vec<_>>();
+    let mut files_by_id = t_sqlite::AFile::get_files_by_ids(&file_ids)?
+        .into_iter()
+        .filter_map(|file| file.id.map(|id| (id, file)))
+        .collect::<std::collections::HashMap<_, _>>();
+
+    Ok(ranked
+        .into_iter()
+        .filter_map(|(file_id, (face_id, person_id, person_name, score))| {
+            files_by_id.remove(&file_id).map(|file| FaceSearchMatch {
+                face_id,
+                person_id,
+                person_name,
+                score,
+                file,
+            })
+        })
+        .collect())
+}
+
 pub fn run_face_indexing(
     app_handle: AppHandle,
     face_state: FaceState,
@@ -779,6 +1038,24 @@ pub fn run_face_indexing(
                         }
                     }
 
+                    // Drop detections the user has deleted before
+                    match t_sqlite::Face::get_rejections_with_conn(&db_conn, file_id) {
+                        Ok(rejected) if !rejected.is_empty() => {
+                            faces.retain(|face| {
+                                let rect =
+                                    (face.bbox.x, face.bbox.y, face.bbox.width, face.bbox.height);
+                                !rejected
+                                    .iter()
+                                    .any(|&r| rect_iou(rect, r) > REJECTION_IOU_THRESHOLD)
+                            });
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            eprintln!("Failed to load rejected faces for file {}: {}", file_id, e)
+                        }
+                    }
+
+                    t_index_errors::resolve_file(file_id, t_index_errors::STAGE_FACE);
                     let has_faces = !faces.is_empty();
                     let status = if has_faces { 1 } else { 2 };
 
@@ -813,6 +1090,7 @@ pub fn run_face_indexing(
                 }
                 Err(e) => {
                     eprintln!("Failed to process image {}: {}", file_path, e);
+                    t_index_errors::record_for_file(file_id, t_index_errors::STAGE_FACE, &e);
                 }
             }
 
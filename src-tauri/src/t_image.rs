@@ -12,6 +12,7 @@ use little_exif::exif_tag::ExifTag;
 use little_exif::filetype::FileExtension;
 use little_exif::ifd::ExifTagGroup;
 use little_exif::metadata::Metadata as LittleExifMetadata;
+use little_exif::rational::uR64;
 use once_cell::sync::Lazy;
 
 use rusqlite::Result;
@@ -116,6 +117,72 @@ fn format_little_exif_shutter_speed(value: f64) -> Option<String> {
     }
 }
 
+/// Writes a capture date (EXIF "YYYY:MM:DD HH:MM:SS"), a GPS position
+/// (latitude, longitude, optional altitude) and a description into a JPEG,
+/// keeping the rest of its metadata. Used to restore data that an export stripped.
+pub fn write_jpeg_capture_metadata(
+    file_path: &str,
+    date_time: Option<&str>,
+    gps: Option<(f64, f64, Option<f64>)>,
+    description: Option<&str>,
+) -> Result<(), String> {
+    if !is_jpeg_path(file_path) {
+        return Err("Only JPEG files are supported".to_string());
+    }
+
+    let path = Path::new(file_path);
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut metadata = LittleExifMetadata::new_from_path(path)
+            .map_err(|e| format!("little_exif read failed: {}", e))?;
+        if let Some(date_time) = date_time {
+            metadata.set_tag(ExifTag::DateTimeOriginal(date_time.to_string()));
+        }
+        if let Some(description) = description {
+            metadata.set_tag(ExifTag::ImageDescription(description.to_string()));
+        }
+        if let Some((latitude, longitude, altitude)) = gps {
+            let latitude_ref = if latitude < 0.0 { "S" } else { "N" };
+            let longitude_ref = if longitude < 0.0 { "W" } else { "E" };
+            metadata.set_tag(ExifTag::GPSLatitudeRef(latitude_ref.to_string()));
+            metadata.set_tag(ExifTag::GPSLatitude(decimal_to_dms(latitude)));
+            metadata.set_tag(ExifTag::GPSLongitudeRef(longitude_ref.to_string()));
+            metadata.set_tag(ExifTag::GPSLongitude(decimal_to_dms(longitude)));
+            if let Some(altitude) = altitude {
+                metadata.set_tag(ExifTag::GPSAltitudeRef(vec![u8::from(altitude < 0.0)]));
+                metadata.set_tag(ExifTag::GPSAltitude(vec![uR64 {
+                    nominator: (altitude.abs() * 100.0).round() as u32,
+                    denominator: 100,
+                }]));
+            }
+        }
+        metadata
+            .write_to_file(path)
+            .map_err(|e| format!("little_exif write failed: {}", e))
+    }))
+    .unwrap_or_else(|_| Err("little_exif panicked".to_string()))
+}
+
+/// Decimal degrees to EXIF degrees, minutes and seconds (1/1000 s precision)
+fn decimal_to_dms(value: f64) -> Vec<uR64> {
+    // round to thousandths of a second before splitting, so 59.9996" carries
+    // into the minutes instead of becoming 60.000"
+    let millis = (value.abs() * 3_600_000.0).round() as u64;
+    vec![
+        uR64 {
+            nominator: (millis / 3_600_000) as u32,
+            denominator: 1,
+        },
+        uR64 {
+            nominator: (millis / 60_000 % 60) as u32,
+            denominator: 1,
+        },
+        uR64 {
+            nominator: (millis % 60_000) as u32,
+            denominator: 1000,
+        },
+    ]
+}
+
 /// Quick probing of image dimensions without loading the entire file
 pub fn get_image_dimensions(file_path: &str) -> Result<(u32, u32), String> {
     if t_jxl::is_jxl_path(file_path) {
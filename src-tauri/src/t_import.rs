@@ -0,0 +1,829 @@
+/**
+ * t_import.rs - Import sessions for memory cards and camera folders
+ *
+ * A session scans a source directory (a mounted SD card, a phone's DCIM folder)
+ * and groups its media by folder and file stem, so Live Photo (HEIC/JPG + MOV)
+ * and RAW+JPEG pairs travel together. Each group is checked against the library
+ * by name+size+date and by content hash. Importing copies the selected groups into
+ * a destination folder built from a date template, verifies every copy by blake3
+ * checksum, indexes it, and optionally applies tags and a collection.
+ */
+use crate::t_sqlite::{ACollection, AFile, AFolder, ATag, Album, open_conn};
+use crate::{t_dedup, t_heif, t_image, t_template, t_utils, t_video};
+use base64::{Engine, engine::general_purpose};
+use exif::Tag;
+use rusqlite::{OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::Emitter;
+use walkdir::WalkDir;
+
+pub const DUPLICATE_HASH: &str = "hash";
+pub const DUPLICATE_NAME: &str = "name"; // same name, size and taken date
+
+pub const DEFAULT_TEMPLATE: &str = "{yyyy}/{yyyy-mm-dd} {event}";
+
+const MAX_HASH_CANDIDATES: usize = 16; // library files of the same size hashed per source file
+
+// ----------------------------------------------------------------------------
+// Types and Structs
+// ----------------------------------------------------------------------------
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportFile {
+    pub path: String,
+    pub name: String,
+    pub size: i64,
+    pub file_type: i64,
+    pub taken_date: i64,
+    pub duplicate: Option<String>, // DUPLICATE_HASH, DUPLICATE_NAME or None
+    #[serde(skip)]
+    pub hash: Option<String>, // full blake3, filled when it was needed for duplicate detection
+    #[serde(skip)]
+    pub duplicate_of: Option<i64>, // the library file it duplicates
+}
+
+/// One or more files that are imported together (a single photo, a Live Photo, RAW+JPEG)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportItem {
+    pub id: usize,
+    pub files: Vec<ImportFile>, // the primary file comes first
+    pub taken_date: i64,
+    pub is_duplicate: bool, // every file of the group is already in the library
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSession {
+    pub id: String,
+    pub source_path: String,
+    pub items: Vec<ImportItem>,
+    pub total_size: i64,
+    pub duplicate_count: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ImportOptions {
+    pub album_id: i64,
+    pub dest_template: String, // relative to the album root, see `expand_template`
+    pub event: String,
+    pub item_ids: Option<Vec<usize>>, // None = every item of the session
+    pub skip_duplicates: bool,
+    pub tag_ids: Vec<i64>,
+    pub collection_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportStatus {
+    pub state: String, // "running", "idle", "finished", "error"
+    pub processed: u64,
+    pub total: u64,
+    pub copied: u64,
+    pub skipped: u64,
+    pub failures: Vec<ImportFailure>,
+    pub undated: Vec<String>, // copies whose sidecar date could not be written into the file
+    pub is_running: bool,
+}
+
+impl Default for ImportStatus {
+    fn default() -> Self {
+        Self {
+            state: "idle".to_string(),
+            processed: 0,
+            total: 0,
+            copied: 0,
+            skipped: 0,
+            failures: Vec::new(),
+            undated: Vec::new(),
+            is_running: false,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ImportState {
+    pub sessions: Arc<Mutex<HashMap<String, ImportSession>>>,
+    pub is_running: Arc<AtomicBool>,
+    pub cancel_flag: Arc<AtomicBool>,
+    pub status: Arc<Mutex<ImportStatus>>,
+}
+
+/// Library files used to recognise duplicates
+pub(crate) struct LibraryIndex {
+    by_name: HashMap<(String, i64, i64), i64>, // (lowercase name, size, taken date) -> file id
+    by_size: HashMap<i64, Vec<(i64, String)>>, // size -> (file id, file path)
+}
+
+// ----------------------------------------------------------------------------
+// Scanning
+// ----------------------------------------------------------------------------
+
+/// Scan a source directory into a new session.
+pub fn scan_source(source_path: &str) -> Result<ImportSession, String> {
+    let source = Path::new(source_path);
+    if !source.is_dir() {
+        return Err(format!("Source folder not found: {}", source_path));
+    }
+
+    let mut files = Vec::new();
+    for entry in WalkDir::new(source)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|entry| !is_hidden(entry.file_name().to_str().unwrap_or("")))
+        .filter_map(Result::ok)
+    {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Some(path) = entry.path().to_str() else {
+            continue;
+        };
+        let Some(file_type) = t_utils::get_file_type(path) else {
+            continue;
+        };
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        files.push(ImportFile {
+            path: path.to_string(),
+            name: entry.file_name().to_string_lossy().into_owned(),
+            size: metadata.len() as i64,
+            file_type,
+            taken_date: get_taken_date(path, file_type, metadata.modified().ok()),
+            duplicate: None,
+            hash: None,
+            duplicate_of: None,
+        });
+    }
+
+    let index = load_library_index()?;
+    for file in files.iter_mut() {
+        detect_duplicate(&index, file);
+    }
+
+    let mut items = group_files(files);
+    let mut total_size = 0;
+    let mut duplicate_count = 0;
+    for (id, item) in items.iter_mut().enumerate() {
+        item.id = id;
+        total_size += item.files.iter().map(|file| file.size).sum::<i64>();
+        if item.is_duplicate {
+            duplicate_count += 1;
+        }
+    }
+
+    Ok(ImportSession {
+        id: format!("{}", now_millis()),
+        source_path: source_path.to_string(),
+        items,
+        total_size,
+        duplicate_count,
+    })
+}
+
+/// Keep a scanned session until it is imported or closed
+pub fn open_session(state: &ImportState, session: &ImportSession) {
+    state
+        .sessions
+        .lock()
+        .unwrap()
+        .insert(session.id.clone(), session.clone());
+}
+
+/// Forget a session once its dialog is closed
+pub fn close_session(state: &ImportState, session_id: &str) {
+    state.sessions.lock().unwrap().remove(session_id);
+}
+
+pub(crate) fn is_hidden(name: &str) -> bool {
+    // skip dot folders and files such as .Trashes and ._IMG_0001.JPG (AppleDouble)
+    name.starts_with('.') && name != "." && name != ".."
+}
+
+/// EXIF DateTimeOriginal for photos, the modified time otherwise;
+/// the same fallback the indexer uses for `taken_date`.
+pub(crate) fn get_taken_date(path: &str, file_type: i64, modified: Option<SystemTime>) -> i64 {
+    let modified = t_utils::systemtime_to_timestamp(modified).unwrap_or(0);
+    if file_type == 2 {
+        return modified;
+    }
+    let exif = t_image::read_exif_permissive(path);
+    AFile::get_exif_field(&exif, Tag::DateTimeOriginal)
+        .and_then(|date| t_utils::meta_date_to_timestamp(&date))
+        .unwrap_or(modified)
+}
+
+pub(crate) fn load_library_index() -> Result<LibraryIndex, String> {
+    let conn = open_conn()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT a.id, b.path, a.name, a.size, COALESCE(a.taken_date, 0)
+            FROM afiles a JOIN afolders b ON a.folder_id = b.id",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut index = LibraryIndex {
+        by_name: HashMap::new(),
+        by_size: HashMap::new(),
+    };
+    for row in rows {
+        let (id, folder_path, name, size, taken_date) = row.map_err(|e| e.to_string())?;
+        index
+            .by_name
+            .insert((name.to_lowercase(), size, taken_date), id);
+        let file_path = t_utils::get_file_path(&folder_path, &name);
+        index.by_size.entry(size).or_default().push((id, file_path));
+    }
+    Ok(index)
+}
+
+pub(crate) fn detect_duplicate(index: &LibraryIndex, file: &mut ImportFile) {
+    if let Some(file_id) =
+        index
+            .by_name
+            .get(&(file.name.to_lowercase(), file.size, file.taken_date))
+    {
+        file.duplicate = Some(DUPLICATE_NAME.to_string());
+        file.duplicate_of = Some(*file_id);
+        return;
+    }
+
+    let Some(candidates) = index.by_size.get(&file.size) else {
+        return;
+    };
+    let Ok(hash) = t_dedup::compute_blake3_hash(&file.path) else {
+        return;
+    };
+    let found = candidates
+        .iter()
+        .take(MAX_HASH_CANDIDATES)
+        .find(|(id, path)| get_library_hash(*id, path).as_deref() == Some(hash.as_str()));
+    if let Some((file_id, _)) = found {
+        file.duplicate = Some(DUPLICATE_HASH.to_string());
+        file.duplicate_of = Some(*file_id);
+    }
+    file.hash = Some(hash);
+}
+
+/// The stored hash of a library file if it is still current, otherwise a fresh one
+fn get_library_hash(file_id: i64, file_path: &str) -> Option<String> {
+    let mtime = fs::metadata(file_path)
+        .ok()
+        .and_then(|metadata| t_utils::systemtime_to_timestamp(metadata.modified().ok()))?;
+    let stored = open_conn().ok().and_then(|conn| {
+        conn.query_row(
+            "SELECT hash FROM file_hashes WHERE file_id = ?1 AND mtime = ?2 AND hash IS NOT NULL",
+            params![file_id, mtime],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .ok()
+        .flatten()
+    });
+    stored.or_else(|| t_dedup::compute_blake3_hash(file_path).ok())
+}
+
+/// Group files that share a folder and a file stem (IMG_0001.HEIC + IMG_0001.MOV,
+/// DSC_0001.NEF + DSC_0001.JPG). The primary file is the still image, with
+/// JPEG/HEIC ahead of RAW, so the group is dated like the photo the user sees.
+fn group_files(files: Vec<ImportFile>) -> Vec<ImportItem> {
+    let mut groups: BTreeMap<(String, String), Vec<ImportFile>> = BTreeMap::new();
+    for file in files {
+        let path = Path::new(&file.path);
+        let parent = path
+            .parent()
+            .map(|parent| parent.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        groups.entry((parent, stem)).or_default().push(file);
+    }
+
+    let mut items: Vec<ImportItem> = groups
+        .into_values()
+        .map(|mut files| {
+            files.sort_by_key(|file| match file.file_type {
+                1 => 0,
+                3 => 1,
+                _ => 2,
+            });
+            ImportItem {
+                id: 0,
+                taken_date: files[0].taken_date,
+                is_duplicate: files.iter().all(|file| file.duplicate.is_some()),
+                files,
+            }
+        })
+        .collect();
+    items.sort_by(|a, b| {
+        a.taken_date
+            .cmp(&b.taken_date)
+            .then_with(|| a.files[0].name.cmp(&b.files[0].name))
+    });
+    items
+}
+
+// ----------------------------------------------------------------------------
+// Preview
+// ----------------------------------------------------------------------------
+
+/// Generate a base64 JPEG thumbnail for a source file that is not in the library yet.
+pub fn get_preview(file_path: &str, thumbnail_size: u32) -> Result<Option<String>, String> {
+    let file_type = t_utils::get_file_type(file_path)
+        .ok_or_else(|| format!("Unsupported file type: {}", file_path))?;
+    let data = match file_type {
+        1 => {
+            let orientation = t_image::get_image_orientation(file_path);
+            let ext = t_utils::get_file_extension(file_path)
+                .unwrap_or_default()
+                .to_lowercase();
+            match ext.as_str() {
+                "heic" | "heif" | "hif" => {
+                    t_heif::get_heif_thumbnail(file_path, orientation, thumbnail_size)?
+                }
+                _ => t_image::get_image_thumbnail(file_path, orientation, thumbnail_size)?,
+            }
+        }
+        2 => t_video::get_video_thumbnail_sync(file_path, thumbnail_size, None, None)?,
+        3 => {
+            let orientation = t_image::get_image_orientation(file_path);
+            t_image::get_raw_thumbnail(file_path, orientation, thumbnail_size)?
+        }
+        _ => None,
+    };
+    Ok(data.map(|data| general_purpose::STANDARD.encode(data)))
+}
+
+// ----------------------------------------------------------------------------
+// Importing
+// ----------------------------------------------------------------------------
+
+pub fn start_import(
+    app_handle: tauri::AppHandle,
+    state: &ImportState,
+    session_id: &str,
+    options: ImportOptions,
+) -> Result<(), String> {
+    let session = state
+        .sessions
+        .lock()
+        .unwrap()
+        .get(session_id)
+        .cloned()
+        .ok_or_else(|| "Import session not found.".to_string())?;
+    let album = Album::get_album_by_id(options.album_id)?;
+    if !t_utils::album_online(&album) {
+        return Err("The destination album is offline.".into());
+    }
+
+    if state
+        .is_running
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return Err("An import is already running.".into());
+    }
+    state.cancel_flag.store(false, Ordering::SeqCst);
+
+    let status_clone = state.status.clone();
+    let is_running_clone = state.is_running.clone();
+    let cancel_flag_clone = state.cancel_flag.clone();
+
+    *status_clone.lock().unwrap() = ImportStatus {
+        state: "running".to_string(),
+        is_running: true,
+        ..ImportStatus::default()
+    };
+
+    std::thread::spawn(move || {
+        let result = run_import(
+            &app_handle,
+            &status_clone,
+            &cancel_flag_clone,
+            &session,
+            &album,
+            &options,
+        );
+
+        let mut final_status = status_clone.lock().unwrap();
+        match result {
+            Ok(_) => {
+                if cancel_flag_clone.load(Ordering::SeqCst) {
+                    final_status.state = "idle".to_string();
+                } else {
+                    final_status.state = "finished".to_string();
+                }
+            }
+            Err(e) => {
+                eprintln!("Import error: {}", e);
+                final_status.state = "error".to_string();
+            }
+        }
+
+        is_running_clone.store(false, Ordering::SeqCst);
+        final_status.is_running = false;
+        let _ = app_handle.emit("import-progress", final_status.clone());
+    });
+
+    Ok(())
+}
+
+fn run_import(
+    app_handle: &tauri::AppHandle,
+    status: &Arc<Mutex<ImportStatus>>,
+    cancel_flag: &Arc<AtomicBool>,
+    session: &ImportSession,
+    album: &Album,
+    options: &ImportOptions,
+) -> Result<(), String> {
+    let album_id = album.id.ok_or("Album has no id")?;
+    let selected: Option<HashSet<usize>> = options
+        .item_ids
+        .as_ref()
+        .map(|ids| ids.iter().copied().collect());
+    let items: Vec<&ImportItem> = session
+        .items
+        .iter()
+        .filter(|item| selected.as_ref().is_none_or(|ids| ids.contains(&item.id)))
+        .collect();
+    let template = if options.dest_template.trim().is_empty() {
+        DEFAULT_TEMPLATE
+    } else {
+        options.dest_template.as_str()
+    };
+
+    status.lock().unwrap().total = items.len() as u64;
+    let _ = app_handle.emit("import-progress", status.lock().unwrap().clone());
+
+    let mut imported_ids = Vec::new();
+    let mut touched_folders: HashMap<i64, HashSet<String>> = HashMap::new();
+    for item in items {
+        if cancel_flag.load(Ordering::SeqCst) {
+            break;
+        }
+
+        // duplicates are skipped file by file, so a partly known group brings only its new files
+        let files: Vec<&ImportFile> = item
+            .files
+            .iter()
+            .filter(|file| !options.skip_duplicates || file.duplicate.is_none())
+            .collect();
+        status.lock().unwrap().skipped += (item.files.len() - files.len()) as u64;
+        if !files.is_empty() {
+            // the whole group goes to the folder of its primary file
+            let relative = expand_template(template, item.taken_date, &options.event);
+            let dest_folder = Path::new(&album.path).join(&relative);
+            let outcome = fs::create_dir_all(&dest_folder)
+                .map_err(|e| format!("Failed to create folder: {}", e))
+                .and_then(|_| {
+                    let folder_path = dest_folder.to_string_lossy().into_owned();
+                    AFolder::add_to_db(album_id, &folder_path)
+                        .map(|folder| (folder.id.unwrap_or(0), folder_path))
+                });
+            match outcome {
+                Ok((folder_id, folder_path)) => {
+                    let copies = copy_group(Path::new(&folder_path), &files);
+                    for (file, copy) in files.iter().zip(copies) {
+                        let added = copy.and_then(|destination| {
+                            add_copy(app_handle, file, album_id, folder_id, destination)
+                        });
+                        match added {
+                            Ok((file_id, name)) => {
+                                imported_ids.push(file_id);
+                                touched_folders.entry(folder_id).or_default().insert(name);
+                                status.lock().unwrap().copied += 1;
+                            }
+                            Err(e) => status.lock().unwrap().failures.push(ImportFailure {
+                                path: file.path.clone(),
+                                error: e,
+                            }),
+                        }
+                    }
+                }
+                Err(e) => {
+                    let mut s = status.lock().unwrap();
+                    for file in &files {
+                        s.failures.push(ImportFailure {
+                            path: file.path.clone(),
+                            error: e.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut s = status.lock().unwrap();
+        s.processed += 1;
+        let _ = app_handle.emit("import-progress", s.clone());
+    }
+
+    // keep Live Photo and RAW+JPEG pairs linked in their new folders
+    for (folder_id, names) in &touched_folders {
+        if let Err(e) = AFile::pair_live_photos_in_folder(*folder_id, names, false) {
+            eprintln!("Failed to pair Live Photos in folder {}: {}", folder_id, e);
+        }
+        if let Err(e) = AFile::pair_raw_jpeg_in_folder(*folder_id) {
+            eprintln!("Failed to pair RAW+JPEG in folder {}: {}", folder_id, e);
+        }
+    }
+
+    for tag_id in &options.tag_ids {
+        for file_id in &imported_ids {
+            if let Err(e) = ATag::add_tag_to_file(*file_id, *tag_id) {
+                eprintln!("Failed to tag imported file {}: {}", file_id, e);
+            }
+        }
+    }
+    if let Some(collection_id) = options.collection_id {
+        if !imported_ids.is_empty() {
+            ACollection::add_files(collection_id, imported_ids.clone())?;
+        }
+    }
+
+    if !imported_ids.is_empty() {
+        Album::recount_album(album_id)?;
+    }
+    Ok(())
+}
+
+/// Add a verified copy to the library, then queue its thumbnail and embedding like
+/// a folder scan does. Returns the new file id and file name.
+fn add_copy(
+    app_handle: &tauri::AppHandle,
+    file: &ImportFile,
+    album_id: i64,
+    folder_id: i64,
+    destination: PathBuf,
+) -> Result<(i64, String), String> {
+    let dest_path = destination.to_string_lossy().into_owned();
+    match AFile::add_to_db(folder_id, &dest_path, file.file_type, now_millis()) {
+        Ok((new_file, _)) => {
+            t_utils::schedule_new_file_processing(app_handle, album_id, &new_file, &dest_path);
+            Ok((new_file.id.unwrap_or(0), new_file.name))
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&destination);
+            Err(e)
+        }
+    }
+}
+
+/// Copy the files of one group into `folder_path` under the names of `unique_group_names`.
+/// A name taken between the check and the copy moves the whole group to the next suffix.
+/// Returns the copy of every file, in order.
+fn copy_group(folder_path: &Path, files: &[&ImportFile]) -> Vec<Result<PathBuf, String>> {
+    let mut first_suffix = 0;
+    loop {
+        let (suffix, names) = unique_group_names(folder_path, files, first_suffix);
+        let mut copies = Vec::new();
+        let mut taken = false;
+        for (file, name) in files.iter().zip(names) {
+            match copy_verified(file, folder_path.join(name)) {
+                Ok(Some(destination)) => copies.push(Ok(destination)),
+                Ok(None) => {
+                    taken = true;
+                    break;
+                }
+                Err(e) => copies.push(Err(e)),
+            }
+        }
+        if !taken {
+            return copies;
+        }
+        for destination in copies.into_iter().flatten() {
+            let _ = fs::remove_file(destination);
+        }
+        first_suffix = suffix + 1;
+    }
+}
+
+/// Destination names for the files of one group. Every file keeps its own extension
+/// and gets the same `(n)` suffix, the first one from `first_suffix` free for all of
+/// them, so a Live Photo or RAW+JPEG pair still shares a stem in the destination folder.
+/// Returns the suffix (0 for none) and the names.
+fn unique_group_names(
+    folder_path: &Path,
+    files: &[&ImportFile],
+    first_suffix: usize,
+) -> (usize, Vec<String>) {
+    let parts: Vec<(String, String)> = files
+        .iter()
+        .map(|file| {
+            let name = Path::new(&file.name);
+            let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+            match name.extension() {
+                Some(ext) => (stem.into_owned(), format!(".{}", ext.to_string_lossy())),
+                None => (stem.into_owned(), String::new()),
+            }
+        })
+        .collect();
+    let mut i = first_suffix;
+    loop {
+        let names: Vec<String> = parts
+            .iter()
+            .map(|(stem, ext)| match i {
+                0 => format!("{}{}", stem, ext),
+                i => format!("{}({}){}", stem, i, ext),
+            })
+            .collect();
+        if names.iter().all(|name| !folder_path.join(name).exists()) {
+            return (i, names);
+        }
+        i += 1;
+    }
+}
+
+/// Copy one file to `destination` and verify the copy by checksum. The copy is
+/// created exclusively, so an existing file is never overwritten.
+/// Returns the path of the copy, or None if `destination` already exists.
+pub(crate) fn copy_verified(
+    file: &ImportFile,
+    destination: PathBuf,
+) -> Result<Option<PathBuf>, String> {
+    let source_hash = match &file.hash {
+        Some(hash) => hash.clone(),
+        None => t_dedup::compute_blake3_hash(&file.path).map_err(|e| e.to_string())?,
+    };
+
+    let mut dest = match fs::File::options()
+        .write(true)
+        .create_new(true)
+        .open(&destination)
+    {
+        Ok(dest) => dest,
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Ok(None),
+        Err(e) => return Err(format!("Failed to copy file: {}", e)),
+    };
+    let copied = fs::File::open(&file.path).and_then(|mut source| {
+        io::copy(&mut source, &mut dest)?;
+        // keep the camera's timestamps so the modified-time fallback dates stay right
+        if let Ok(modified) = source.metadata().and_then(|metadata| metadata.modified()) {
+            let _ = dest.set_modified(modified);
+        }
+        Ok(())
+    });
+    drop(dest);
+    if let Err(e) = copied {
+        let _ = fs::remove_file(&destination);
+        return Err(format!("Failed to copy file: {}", e));
+    }
+
+    let verified = t_dedup::compute_blake3_hash(&destination.to_string_lossy())
+        .map(|hash| hash == source_hash)
+        .unwrap_or(false);
+    if !verified {
+        let _ = fs::remove_file(&destination);
+        return Err("Checksum mismatch after copy".to_string());
+    }
+    Ok(Some(destination))
+}
+
+/// Expand a destination template such as `{yyyy}/{yyyy-mm-dd} {event}`.
+/// Tokens: the date tokens of `t_template::date_token` and {event}. Segments
+/// that end up empty are dropped, so an empty event does not leave a trailing space.
+pub fn expand_template(template: &str, taken_date: i64, event: &str) -> PathBuf {
+    t_template::expand_folder_template(template, |key, arg| match key {
+        "event" => Some(event.to_string()),
+        _ => t_template::date_token(key, arg, Some(taken_date)),
+    })
+}
+
+pub(crate) fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+
+    fn file(path: &str, file_type: i64, taken_date: i64, duplicate: bool) -> ImportFile {
+        ImportFile {
+            path: path.to_string(),
+            name: Path::new(path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned(),
+            size: 1,
+            file_type,
+            taken_date,
+            duplicate: duplicate.then(|| DUPLICATE_HASH.to_string()),
+            hash: None,
+            duplicate_of: None,
+        }
+    }
+
+    #[test]
+    fn expands_templates() {
+        let taken_date = Local
+            .with_ymd_and_hms(2024, 3, 5, 12, 0, 0)
+            .unwrap()
+            .timestamp();
+        assert_eq!(
+            expand_template(DEFAULT_TEMPLATE, taken_date, "Trip: Rome"),
+            Path::new("2024").join("2024-03-05 Trip_ Rome")
+        );
+        // an empty event drops the trailing space, empty segments disappear
+        assert_eq!(
+            expand_template("{yyyy}//{mm}/{dd} {event}", taken_date, ""),
+            Path::new("2024").join("03").join("05")
+        );
+        assert_eq!(
+            expand_template("../{event}", taken_date, ".."),
+            PathBuf::new()
+        );
+    }
+
+    #[test]
+    fn groups_files_by_folder_and_stem() {
+        let items = group_files(vec![
+            file("/card/DCIM/IMG_0002.MOV", 2, 200, false),
+            file("/card/DCIM/IMG_0002.HEIC", 1, 200, true),
+            file("/card/DCIM/DSC_0001.NEF", 3, 100, true),
+            file("/card/DCIM/DSC_0001.JPG", 1, 100, true),
+            file("/card/OTHER/IMG_0002.JPG", 1, 300, false),
+        ]);
+        let names: Vec<Vec<&str>> = items
+            .iter()
+            .map(|item| item.files.iter().map(|file| file.name.as_str()).collect())
+            .collect();
+        // still images lead their group, JPEG ahead of RAW; groups are sorted by date
+        assert_eq!(
+            names,
+            vec![
+                vec!["DSC_0001.JPG", "DSC_0001.NEF"],
+                vec!["IMG_0002.HEIC", "IMG_0002.MOV"],
+                vec!["IMG_0002.JPG"],
+            ]
+        );
+        assert!(items[0].is_duplicate);
+        assert!(!items[1].is_duplicate);
+    }
+
+    #[test]
+    fn group_names_share_a_free_suffix() {
+        let dir = std::env::temp_dir().join(format!("lap-import-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let heic = file("/card/IMG_0001.HEIC", 1, 0, false);
+        let mov = file("/card/IMG_0001.MOV", 2, 0, false);
+        let files = [&heic, &mov];
+
+        assert_eq!(
+            unique_group_names(&dir, &files, 0),
+            (
+                0,
+                vec!["IMG_0001.HEIC".to_string(), "IMG_0001.MOV".to_string()]
+            )
+        );
+        // one taken name moves both files to the same suffix
+        fs::write(dir.join("IMG_0001.MOV"), b"").unwrap();
+        fs::write(dir.join("IMG_0001(1).HEIC"), b"").unwrap();
+        assert_eq!(
+            unique_group_names(&dir, &files, 0),
+            (
+                2,
+                vec![
+                    "IMG_0001(2).HEIC".to_string(),
+                    "IMG_0001(2).MOV".to_string()
+                ]
+            )
+        );
+        assert_eq!(unique_group_names(&dir, &files, 3).0, 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
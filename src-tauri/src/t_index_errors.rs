@@ -0,0 +1,319 @@
+/**
+ * t_index_errors.rs - Per-file indexing error report
+ *
+ * Every file the indexer skips or fails on gets a row here: which stage failed
+ * (metadata, thumbnail, embedding or face), the error and when it was last seen.
+ * A later success for the same file and stage removes the row, and a complete
+ * album scan drops rows it no longer reproduced, so the report only lists files
+ * that still have problems. Rows marked ignored stay hidden from the report and
+ * are never retried.
+ */
+use crate::t_sqlite::open_conn;
+use chrono::{Local, TimeZone, Utc};
+use rusqlite::{Row, ToSql, params, params_from_iter};
+use serde::{Deserialize, Serialize};
+
+pub const STAGE_METADATA: &str = "metadata";
+pub const STAGE_THUMBNAIL: &str = "thumbnail";
+pub const STAGE_EMBEDDING: &str = "embedding";
+pub const STAGE_FACE: &str = "face";
+
+pub const KIND_FAILED: &str = "failed";
+pub const KIND_SKIPPED: &str = "skipped"; // unsupported file type
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexError {
+    pub id: i64,
+    pub album_id: i64,
+    pub file_id: Option<i64>,
+    pub file_path: String,
+    pub stage: String,
+    pub kind: String,
+    pub error: String,
+    pub file_size: i64,
+    pub first_seen_at: i64, // unix time in milliseconds
+    pub last_seen_at: i64,
+    pub occurrences: i64,
+    pub ignored: bool,
+}
+
+impl IndexError {
+    fn from_row(row: &Row) -> Result<Self, rusqlite::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            album_id: row.get(1)?,
+            file_id: row.get(2)?,
+            file_path: row.get(3)?,
+            stage: row.get(4)?,
+            kind: row.get(5)?,
+            error: row.get(6)?,
+            file_size: row.get(7)?,
+            first_seen_at: row.get(8)?,
+            last_seen_at: row.get(9)?,
+            occurrences: row.get(10)?,
+            ignored: row.get(11)?,
+        })
+    }
+}
+
+/// Outcome of retrying failed files.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexErrorRetryResult {
+    pub retried: usize,
+    pub resolved: usize, // fixed, or the file no longer exists
+    pub failed: usize,   // still failing, or the album is offline or being scanned
+    pub queued: usize,   // left to the next face indexing pass
+}
+
+/// Filter for listing, counting, exporting and retrying errors.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IndexErrorQuery {
+    pub album_id: Option<i64>,
+    pub stage: Option<String>,
+    pub kind: Option<String>,
+    pub include_ignored: bool,
+    pub ids: Option<Vec<i64>>,
+}
+
+impl IndexErrorQuery {
+    fn where_clause(&self) -> (String, Vec<Box<dyn ToSql>>) {
+        let mut conditions = Vec::new();
+        let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+        if let Some(album_id) = self.album_id {
+            conditions.push("album_id = ?".to_string());
+            values.push(Box::new(album_id));
+        }
+        if let Some(stage) = self.stage.as_ref().filter(|stage| !stage.is_empty()) {
+            conditions.push("stage = ?".to_string());
+            values.push(Box::new(stage.clone()));
+        }
+        if let Some(kind) = self.kind.as_ref().filter(|kind| !kind.is_empty()) {
+            conditions.push("kind = ?".to_string());
+            values.push(Box::new(kind.clone()));
+        }
+        if !self.include_ignored {
+            conditions.push("ignored = 0".to_string());
+        }
+        if let Some(ids) = &self.ids {
+            if ids.is_empty() {
+                conditions.push("0".to_string());
+            } else {
+                conditions.push(format!("id IN ({})", vec!["?"; ids.len()].join(",")));
+                values.extend(ids.iter().map(|id| Box::new(*id) as Box<dyn ToSql>));
+            }
+        }
+        let clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
+        (clause, values)
+    }
+}
+
+/// Record (or refresh) a problem with one file at one stage.
+pub fn record(
+    album_id: i64,
+    file_id: Option<i64>,
+    file_path: &str,
+    stage: &str,
+    kind: &str,
+    error: &str,
+    file_size: u64,
+) {
+    let result = open_conn().and_then(|conn| {
+        let now = Utc::now().timestamp_millis();
+        conn.execute(
+            "INSERT INTO index_errors
+                (album_id, file_id, file_path, stage, kind, error, file_size, first_seen_at, last_seen_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)
+             ON CONFLICT(file_path, stage) DO UPDATE SET
+                album_id = excluded.album_id,
+                file_id = COALESCE(excluded.file_id, index_errors.file_id),
+                kind = excluded.kind,
+                error = excluded.error,
+                file_size = excluded.file_size,
+                last_seen_at = excluded.last_seen_at,
+                occurrences = index_errors.occurrences + 1",
+            params![album_id, file_id, file_path, stage, kind, error, file_size as i64, now],
+        )
+        .map_err(|e| e.to_string())
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to record index error for {}: {}", file_path, e);
+    }
+}
+
+/// Record a problem for an indexed file, looking up its path and album.
+pub fn record_for_file(file_id: i64, stage: &str, error: &str) {
+    let row = open_conn().and_then(|conn| {
+        conn.query_row(
+            "SELECT b.album_id, b.path, a.name, a.size
+             FROM afiles a JOIN afolders b ON a.folder_id = b.id
+             WHERE a.id = ?1",
+            params![file_id],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            },
+        )
+        .map_err(|e| e.to_string())
+    });
+    match row {
+        Ok((album_id, folder_path, name, size)) => {
+            let file_path = std::path::Path::new(&folder_path).join(name);
+            record(
+                album_id,
+                Some(file_id),
+                &file_path.to_string_lossy(),
+                stage,
+                KIND_FAILED,
+                error,
+                size.max(0) as u64,
+            );
+        }
+        Err(e) => eprintln!("Failed to record index error for file {}: {}", file_id, e),
+    }
+}
+
+/// Forget a problem after the stage succeeded for the file.
+pub fn resolve(file_path: &str, stage: &str) {
+    if let Ok(conn) = open_conn() {
+        let _ = conn.execute(
+            "DELETE FROM index_errors WHERE file_path = ?1 AND stage = ?2",
+            params![file_path, stage],
+        );
+    }
+}
+
+pub fn resolve_file(file_id: i64, stage: &str) {
+    if let Ok(conn) = open_conn() {
+        let _ = conn.execute(
+            "DELETE FROM index_errors WHERE file_id = ?1 AND stage = ?2",
+            params![file_id, stage],
+        );
+    }
+}
+
+/// After a complete album scan, drop scan-stage rows the scan did not reproduce.
+/// Face rows come from the separate face indexing pass and are kept.
+pub fn sweep_album(album_id: i64, scan_started_at: i64) -> Result<usize, String> {
+    let conn = open_conn()?;
+    conn.execute(
+        "DELETE FROM index_errors
+         WHERE album_id = ?1 AND last_seen_at < ?2 AND stage IN (?3, ?4, ?5)",
+        params![
+            album_id,
+            scan_started_at,
+            STAGE_METADATA,
+            STAGE_THUMBNAIL,
+            STAGE_EMBEDDING
+        ],
+    )
+    .map_err(|e| e.to_string())
+}
+
+pub fn list(query: &IndexErrorQuery, offset: i64, limit: i64) -> Result<Vec<IndexError>, String> {
+    let conn = open_conn()?;
+    let (clause, mut values) = query.where_clause();
+    let sql = format!(
+        "SELECT id, album_id, file_id, file_path, stage, kind, error, file_size,
+                first_seen_at, last_seen_at, occurrences, ignored
+         FROM index_errors{} ORDER BY occurrences DESC, file_path LIMIT ? OFFSET ?",
+        clause
+    );
+    values.push(Box::new(if limit > 0 { limit } else { -1 }));
+    values.push(Box::new(offset.max(0)));
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params_from_iter(values.iter()), IndexError::from_row)
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+pub fn count(query: &IndexErrorQuery) -> Result<i64, String> {
+    let conn = open_conn()?;
+    let (clause, values) = query.where_clause();
+    conn.query_row(
+        &format!("SELECT COUNT(*) FROM index_errors{}", clause),
+        params_from_iter(values.iter()),
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Mark errors as ignored (or bring them back into the report).
+pub fn set_ignored(ids: &[i64], ignored: bool) -> Result<usize, String> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    let conn = open_conn()?;
+    let sql = format!(
+        "UPDATE index_errors SET ignored = ? WHERE id IN ({})",
+        vec!["?"; ids.len()].join(",")
+    );
+    let mut values: Vec<Box<dyn ToSql>> = vec![Box::new(ignored)];
+    values.extend(ids.iter().map(|id| Box::new(*id) as Box<dyn ToSql>));
+    conn.execute(&sql, params_from_iter(values.iter()))
+        .map_err(|e| e.to_string())
+}
+
+pub fn delete(id: i64) -> Result<usize, String> {
+    let conn = open_conn()?;
+    conn.execute("DELETE FROM index_errors WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())
+}
+
+/// Write the matching errors to a CSV file; returns the number of rows written.
+pub fn export_csv(query: &IndexErrorQuery, file_path: &str) -> Result<usize, String> {
+    let errors = list(query, 0, 0)?;
+    let mut writer = csv::Writer::from_path(file_path).map_err(|e| e.to_string())?;
+    writer
+        .write_record([
+            "album_id",
+            "file_path",
+            "stage",
+            "kind",
+            "error",
+            "file_size",
+            "first_seen",
+            "last_seen",
+            "occurrences",
+            "ignored",
+        ])
+        .map_err(|e| e.to_string())?;
+    for error in &errors {
+        writer
+            .write_record([
+                error.album_id.to_string(),
+                error.file_path.clone(),
+                error.stage.clone(),
+                error.kind.clone(),
+                error.error.clone(),
+                error.file_size.to_string(),
+                format_time(error.first_seen_at),
+                format_time(error.last_seen_at),
+                error.occurrences.to_string(),
+                error.ignored.to_string(),
+            ])
+            .map_err(|e| e.to_string())?;
+    }
+    writer.flush().map_err(|e| e.to_string())?;
+    Ok(errors.len())
+}
+
+fn format_time(timestamp_millis: i64) -> String {
+    Local
+        .timestamp_millis_opt(timestamp_millis)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
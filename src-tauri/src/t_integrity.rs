@@ -0,0 +1,833 @@
+/**
+ * t_integrity.rs - File integrity (bit-rot) verification
+ *
+ * A verification run re-hashes library files with blake3 and compares the result
+ * with the hash stored in `file_hashes`. A file whose size and mtime did not change
+ * but whose content did is flagged as corrupt; a file without a usable stored hash
+ * gets one as its baseline. Images can also be test-decoded with the same decoders
+ * used for thumbnails. Runs can cover the whole library or the least recently
+ * verified slice of it, and a per-library schedule starts them in the background.
+ */
+use crate::t_sqlite::{AThumb, Album, open_conn};
+use crate::{t_dedup, t_heif, t_image, t_jpeg, t_jxl, t_libraw, t_motion_photo, t_utils};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::panic;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{Emitter, Manager};
+
+pub const STATUS_OK: &str = "ok";
+pub const STATUS_CORRUPT: &str = "corrupt"; // content changed while size and mtime did not
+pub const STATUS_DECODE_FAILED: &str = "decode_failed";
+pub const STATUS_UNREADABLE: &str = "unreadable"; // I/O error while reading the file
+pub const STATUS_MISSING: &str = "missing"; // left to the folder sync, not reported
+
+const SCHEDULE_SETTING: &str = "integrity_schedule";
+const LAST_RUN_SETTING: &str = "integrity_last_run";
+
+const SCHEDULER_FIRST_DELAY: Duration = Duration::from_secs(120);
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(30 * 60);
+const DECODE_TEST_SIZE: u32 = 256; // small target lets libjpeg and LibRaw decode at reduced scale
+
+// ----------------------------------------------------------------------------
+// Types and Structs
+// ----------------------------------------------------------------------------
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityStatus {
+    pub state: String, // "running", "idle", "finished", "error"
+    pub processed: u64,
+    pub total: u64,
+    pub corrupt: u64,
+    pub decode_failed: u64,
+    pub unreadable: u64,
+    pub is_running: bool,
+}
+
+impl Default for IntegrityStatus {
+    fn default() -> Self {
+        Self {
+            state: "idle".to_string(),
+            processed: 0,
+            total: 0,
+            corrupt: 0,
+            decode_failed: 0,
+            unreadable: 0,
+            is_running: false,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct IntegrityState {
+    pub is_running: Arc<AtomicBool>,
+    pub cancel_flag: Arc<AtomicBool>,
+    pub status: Arc<Mutex<IntegrityStatus>>,
+}
+
+/// What a verification run covers
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IntegrityOptions {
+    pub album_id: Option<i64>, // None = the whole library
+    pub slice_size: u32,       // least recently verified files to check; 0 = all
+    pub decode: bool,          // also test-decode images
+}
+
+/// Background verification schedule, saved per library
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IntegritySchedule {
+    pub enabled: bool,
+    pub interval_days: u32,
+    pub slice_size: u32,
+    pub decode: bool,
+}
+
+impl Default for IntegritySchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_days: 7,
+            slice_size: 5000,
+            decode: false,
+        }
+    }
+}
+
+/// Summary of the last finished (or cancelled) run, saved per library
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IntegrityRunSummary {
+    pub started_at: i64, // unix time in seconds
+    pub finished_at: i64,
+    pub checked: u64,
+    pub baselined: u64, // files hashed for the first time (or after a legitimate change)
+    pub corrupt: u64,
+    pub decode_failed: u64,
+    pub unreadable: u64,
+    pub skipped: u64, // missing files and files in offline albums
+    pub cancelled: bool,
+    pub scheduled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityIssue {
+    pub file_id: i64,
+    pub album_id: i64,
+    pub file_path: String,
+    pub file_size: i64,
+    pub status: String,
+    pub error: Option<String>,
+    pub expected_hash: Option<String>, // last known good hash
+    pub actual_hash: Option<String>,
+    pub checked_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityOverview {
+    pub total_files: i64,
+    pub verified_files: i64,
+    pub corrupt: i64,
+    pub decode_failed: i64,
+    pub unreadable: i64,
+    pub last_run: Option<IntegrityRunSummary>,
+}
+
+/// Another library file whose content still matches a damaged file's last known good hash
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntactCopy {
+    pub file_id: i64,
+    pub album_id: i64,
+    pub file_path: String,
+}
+
+struct Candidate {
+    file_id: i64,
+    file_path: String,
+    file_type: i64,
+    stored_hash: Option<String>,
+    stored_size: Option<i64>,
+    stored_mtime: Option<i64>,
+    flagged_corrupt: bool, // stored_hash is then the last known good hash
+}
+
+struct Verdict {
+    status: &'static str,
+    error: Option<String>,
+    expected_hash: Option<String>,
+    actual_hash: Option<String>,
+    baselined: bool,
+}
+
+impl Verdict {
+    fn new(status: &'static str, error: Option<String>) -> Self {
+        Self {
+            status,
+            error,
+            expected_hash: None,
+            actual_hash: None,
+            baselined: false,
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Settings
+// ----------------------------------------------------------------------------
+
+pub fn load_schedule() -> IntegritySchedule {
+    match crate::t_sqlite::get_library_setting(SCHEDULE_SETTING) {
+        Ok(Some(value)) => serde_json::from_str(&value).unwrap_or_default(),
+        Ok(None) => IntegritySchedule::default(),
+        Err(e) => {
+            eprintln!("Failed to load integrity schedule: {}", e);
+            IntegritySchedule::default()
+        }
+    }
+}
+
+pub fn save_schedule(schedule: &IntegritySchedule) -> Result<(), String> {
+    let value = serde_json::to_string(schedule).map_err(|e| e.to_string())?;
+    crate::t_sqlite::save_library_setting(SCHEDULE_SETTING, &value)
+}
+
+fn load_last_run() -> Option<IntegrityRunSummary> {
+    crate::t_sqlite::get_library_setting(LAST_RUN_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_str(&value).ok())
+}
+
+fn save_last_run(conn: &Connection, summary: &IntegrityRunSummary) -> Result<(), String> {
+    let value = serde_json::to_string(summary).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO library_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![LAST_RUN_SETTING, value],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+// Core Logic
+// ----------------------------------------------------------------------------
+
+pub fn start_check(
+    app_handle: tauri::AppHandle,
+    state: &IntegrityState,
+    options: IntegrityOptions,
+    scheduled: bool,
+) -> Result<(), String> {
+    if state
+        .is_running
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return Err("An integrity check is already running.".into());
+    }
+    state.cancel_flag.store(false, Ordering::SeqCst);
+
+    let status_clone = state.status.clone();
+    let is_running_clone = state.is_running.clone();
+    let cancel_flag_clone = state.cancel_flag.clone();
+
+    *status_clone.lock().unwrap() = IntegrityStatus {
+        state: "running".to_string(),
+        is_running: true,
+        ..IntegrityStatus::default()
+    };
+
+    std::thread::spawn(move || {
+        let result = run_check(
+            &app_handle,
+            &status_clone,
+            &cancel_flag_clone,
+            &options,
+            scheduled,
+        );
+
+        let mut final_status = status_clone.lock().unwrap();
+        match result {
+            Ok(_) => {
+                if cancel_flag_clone.load(Ordering::SeqCst) {
+                    final_status.state = "idle".to_string();
+                } else {
+                    final_status.state = "finished".to_string();
+                }
+            }
+            Err(e) => {
+                eprintln!("Integrity check error: {}", e);
+                final_status.state = "error".to_string();
+            }
+        }
+
+        is_running_clone.store(false, Ordering::SeqCst);
+        final_status.is_running = false;
+        let _ = app_handle.emit("integrity-check-progress", final_status.clone());
+    });
+
+    Ok(())
+}
+
+/// Start scheduled runs for the current library when they are due.
+/// The schedule is re-read on every tick, so switching libraries needs no restart.
+pub fn start_scheduler(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        std::thread::sleep(SCHEDULER_FIRST_DELAY);
+        loop {
+            let schedule = load_schedule();
+            if schedule.enabled && is_due(&schedule) {
+                let state = app_handle.state::<IntegrityState>();
+                let options = IntegrityOptions {
+                    album_id: None,
+                    slice_size: schedule.slice_size,
+                    decode: schedule.decode,
+                };
+                // Fails quietly when a manual run is already in progress
+                let _ = start_check(app_handle.clone(), &state, options, true);
+            }
+            std::thread::sleep(SCHEDULER_INTERVAL);
+        }
+    });
+}
+
+fn is_due(schedule: &IntegritySchedule) -> bool {
+    let interval = schedule.interval_days.max(1) as i64 * 24 * 60 * 60;
+    load_last_run().is_none_or(|last_run| now_secs() - last_run.started_at >= interval)
+}
+
+fn run_check(
+    app_handle: &tauri::AppHandle,
+    status_mutex: &Arc<Mutex<IntegrityStatus>>,
+    cancel_flag: &Arc<AtomicBool>,
+    options: &IntegrityOptions,
+    scheduled: bool,
+) -> Result<(), String> {
+    // One connection for the whole run, so a library switch cannot redirect its writes
+    let conn = open_conn()?;
+    let mut summary = IntegrityRunSummary {
+        started_at: now_secs(),
+        scheduled,
+        ..IntegrityRunSummary::default()
+    };
+
+    let offline_album_ids: Vec<i64> = Album::get_all_albums()?
+        .iter()
+        .filter(|album| !t_utils::album_online(album))
+        .filter_map(|album| album.id)
+        .collect();
+    let (candidates, offline_count) = get_candidates(&conn, options, &offline_album_ids)?;
+    summary.skipped = offline_count;
+
+    {
+        let mut status = status_mutex.lock().unwrap();
+        status.total = candidates.len() as u64;
+    }
+    let _ = app_handle.emit(
+        "integrity-check-progress",
+        status_mutex.lock().unwrap().clone(),
+    );
+
+    for (index, candidate) in candidates.iter().enumerate() {
+        if cancel_flag.load(Ordering::SeqCst) {
+            summary.cancelled = true;
+            break;
+        }
+
+        // A file removed by a concurrent sync fails its writes; keep going
+        let verdict = match verify_file(&conn, candidate, options.decode)
+            .and_then(|verdict| save_verdict(&conn, candidate.file_id, &verdict).map(|_| verdict))
+        {
+            Ok(verdict) => verdict,
+            Err(e) => {
+                eprintln!("Failed to verify {}: {}", candidate.file_path, e);
+                summary.skipped += 1;
+                continue;
+            }
+        };
+
+        match verdict.status {
+            STATUS_CORRUPT => summary.corrupt += 1,
+            STATUS_DECODE_FAILED => summary.decode_failed += 1,
+            STATUS_UNREADABLE => summary.unreadable += 1,
+            STATUS_MISSING => summary.skipped += 1,
+            _ => {}
+        }
+        if verdict.status != STATUS_MISSING {
+            summary.checked += 1;
+        }
+        if verdict.baselined {
+            summary.baselined += 1;
+        }
+
+        let processed = index as u64 + 1;
+        if processed % 10 == 0 || processed == candidates.len() as u64 {
+            {
+                let mut status = status_mutex.lock().unwrap();
+                status.processed = processed;
+                status.corrupt = summary.corrupt;
+                status.decode_failed = summary.decode_failed;
+                status.unreadable = summary.unreadable;
+            }
+            let _ = app_handle.emit(
+                "integrity-check-progress",
+                status_mutex.lock().unwrap().clone(),
+            );
+        }
+    }
+
+    summary.finished_at = now_secs();
+    save_last_run(&conn, &summary)
+}
+
+/// Files to verify, least recently verified first. Files in offline albums are
+/// left out and only counted.
+fn get_candidates(
+    conn: &Connection,
+    options: &IntegrityOptions,
+    offline_album_ids: &[i64],
+) -> Result<(Vec<Candidate>, u64), String> {
+    let scope = match options.album_id {
+        Some(album_id) => format!(" AND f.album_id = {}", album_id),
+        None => String::new(),
+    };
+    let offline_ids = offline_album_ids
+        .iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let (offline, offline_count) = if offline_ids.is_empty() {
+        (String::new(), 0)
+    } else {
+        let count: i64 = conn
+            .query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM afiles a JOIN afolders f ON a.folder_id = f.id
+                     WHERE f.album_id IN ({}){}",
+                    offline_ids, scope
+                ),
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        (format!(" AND f.album_id NOT IN ({})", offline_ids), count)
+    };
+
+    let limit = if options.slice_size > 0 {
+        options.slice_size as i64
+    } else {
+        -1
+    };
+    let sql = format!(
+        "SELECT a.id, f.path, a.name, a.file_type,
+                CASE WHEN ic.status = ?2 THEN COALESCE(ic.expected_hash, fh.hash) ELSE fh.hash END,
+                fh.file_size, fh.mtime, COALESCE(ic.status = ?2, 0)
+         FROM afiles a
+         JOIN afolders f ON a.folder_id = f.id
+         LEFT JOIN file_hashes fh ON fh.file_id = a.id
+         LEFT JOIN integrity_checks ic ON ic.file_id = a.id
+         WHERE 1{}{}
+         ORDER BY COALESCE(ic.checked_at, 0), a.id
+         LIMIT ?1",
+        scope, offline
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![limit, STATUS_CORRUPT], |row| {
+            let folder_path: String = row.get(1)?;
+            let name: String = row.get(2)?;
+            Ok(Candidate {
+                file_id: row.get(0)?,
+                file_path: Path::new(&folder_path)
+                    .join(name)
+                    .to_string_lossy()
+                    .into_owned(),
+                file_type: row.get::<_, Option<i64>>(3)?.unwrap_or(0),
+                stored_hash: row.get(4)?,
+                stored_size: row.get(5)?,
+                stored_mtime: row.get(6)?,
+                flagged_corrupt: row.get(7)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    let candidates = rows
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok((candidates, offline_count.max(0) as u64))
+}
+
+fn verify_file(conn: &Connection, candidate: &Candidate, decode: bool) -> Result<Verdict, String> {
+    let path = candidate.file_path.as_str();
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(Verdict::new(STATUS_MISSING, None));
+        }
+        Err(e) => return Ok(Verdict::new(STATUS_UNREADABLE, Some(e.to_string()))),
+    };
+    let size = metadata.len() as i64;
+    let mtime = t_utils::systemtime_to_timestamp(metadata.modified().ok()).unwrap_or(0);
+
+    let actual_hash = match t_dedup::compute_blake3_hash(path) {
+        Ok(hash) => hash,
+        Err(e) => return Ok(Verdict::new(STATUS_UNREADABLE, Some(e.to_string()))),
+    };
+
+    let unchanged = candidate.stored_size == Some(size) && candidate.stored_mtime == Some(mtime);
+    let mut verdict = match candidate.stored_hash.as_deref() {
+        Some(expected_hash) if unchanged => {
+            if expected_hash != actual_hash {
+                invalidate_hash(conn, candidate.file_id)?;
+                Verdict {
+                    status: STATUS_CORRUPT,
+                    error: Some("Content changed without a change of size or modified time".into()),
+                    expected_hash: Some(expected_hash.to_string()),
+                    actual_hash: Some(actual_hash),
+                    baselined: false,
+                }
+            } else {
+                if candidate.flagged_corrupt {
+                    // the file was restored; trust its hash again
+                    save_baseline(conn, candidate.file_id, size, mtime, true, &actual_hash)?;
+                }
+                Verdict {
+                    expected_hash: Some(actual_hash.clone()),
+                    actual_hash: Some(actual_hash),
+                    ..Verdict::new(STATUS_OK, None)
+                }
+            }
+        }
+        _ => {
+            save_baseline(
+                conn,
+                candidate.file_id,
+                size,
+                mtime,
+                unchanged,
+                &actual_hash,
+            )?;
+            Verdict {
+                expected_hash: Some(actual_hash.clone()),
+                actual_hash: Some(actual_hash),
+                baselined: true,
+                ..Verdict::new(STATUS_OK, None)
+            }
+        }
+    };
+
+    if decode && verdict.status == STATUS_OK {
+        if let Err(e) = test_decode(path, candidate.file_type) {
+            verdict.status = STATUS_DECODE_FAILED;
+            verdict.error = Some(e);
+        }
+    }
+    Ok(verdict)
+}
+
+/// Store the hash of a file that had none, or whose size or mtime legitimately changed.
+/// A changed file also drops its stale sample hash so the next dedup scan recomputes it.
+fn save_baseline(
+    conn: &Connection,
+    file_id: i64,
+    size: i64,
+    mtime: i64,
+    unchanged: bool,
+    hash: &str,
+) -> Result<(), String> {
+    if unchanged {
+        conn.execute(
+            "UPDATE file_hashes SET hash = ?2, computed_at = ?3 WHERE file_id = ?1",
+            params![file_id, hash, now_secs()],
+        )
+    } else {
+        conn.execute(
+            "INSERT OR REPLACE INTO file_hashes (file_id, hash, sample_hash, file_size, mtime, computed_at)
+             VALUES (?1, ?2, NULL, ?3, ?4, ?5)",
+            params![file_id, hash, size, mtime, now_secs()],
+        )
+    }
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Stop trusting the stored hash of a corrupt file: dedup and import duplicate checks
+/// skip files without a hash. Size and mtime stay, so the next run still compares
+/// the file with its last known good hash (kept in `integrity_checks`).
+fn invalidate_hash(conn: &Connection, file_id: i64) -> Result<(), String> {
+    conn.execute(
+        "UPDATE file_hashes SET hash = NULL, sample_hash = NULL WHERE file_id = ?1",
+        params![file_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn save_verdict(conn: &Connection, file_id: i64, verdict: &Verdict) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO integrity_checks
+            (file_id, status, error, expected_hash, actual_hash, checked_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            file_id,
+            verdict.status,
+            verdict.error,
+            verdict.expected_hash,
+            verdict.actual_hash,
+            now_secs()
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Decode an image with the decoder used for its thumbnails. Videos are not decoded.
+fn test_decode(file_path: &str, file_type: i64) -> Result<(), String> {
+    if file_type != 1 && file_type != 3 {
+        return Ok(());
+    }
+
+    let result = panic::catch_unwind(|| {
+        if file_type == 3 {
+            t_libraw::verify_raw_decode(file_path)
+        } else if t_jxl::is_jxl_path(file_path) {
+            t_jxl::get_jxl_thumbnail(file_path, DECODE_TEST_SIZE).map(|_| ())
+        } else if t_image::is_heic_path(file_path) {
+            t_heif::get_heif_thumbnail(file_path, 1, DECODE_TEST_SIZE).map(|_| ())
+        } else if t_image::is_jpeg_path(file_path) {
+            test_decode_jpeg(file_path)
+        } else if t_image::is_ffmpeg_backed_image_path(file_path) {
+            Ok(()) // decoded by the FFmpeg sidecar, not tested
+        } else {
+            let reader = image::ImageReader::open(file_path)
+                .and_then(|reader| reader.with_guessed_format())
+                .map_err(|e| e.to_string())?;
+            match reader.decode() {
+                Ok(_) | Err(image::ImageError::Unsupported(_)) => Ok(()),
+                Err(e) => Err(e.to_string()),
+            }
+        }
+    });
+    result.unwrap_or_else(|_| Err("Decoder panicked".to_string()))
+}
+
+/// libjpeg only warns about missing data, so a truncated JPEG is detected by its
+/// end-of-image marker before the file is decoded.
+fn test_decode_jpeg(file_path: &str) -> Result<(), String> {
+    let data = fs::read(file_path).map_err(|e| e.to_string())?;
+    let trimmed = &data[..data.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1)];
+    if !AThumb::is_complete_jpeg(trimmed) {
+        // Some cameras append their own data after the marker; a motion photo appends a video
+        let last_marker = |marker: u8| trimmed.windows(2).rposition(|w| w == [0xFF, marker]);
+        let has_end_marker = match (last_marker(0xDA), last_marker(0xD9)) {
+            (Some(scan_start), Some(end)) => end > scan_start,
+            _ => false,
+        };
+        if !trimmed.starts_with(&[0xFF, 0xD8, 0xFF])
+            || (!has_end_marker
+                && t_motion_photo::detect_motion_photo(Path::new(file_path)).is_none())
+        {
+            return Err("JPEG data is truncated".to_string());
+        }
+    }
+    t_jpeg::decode_rgb8_scaled(file_path, DECODE_TEST_SIZE, DECODE_TEST_SIZE).map(|_| ())
+}
+
+// ----------------------------------------------------------------------------
+// Report
+// ----------------------------------------------------------------------------
+
+pub fn get_overview() -> Result<IntegrityOverview, String> {
+    let conn = open_conn()?;
+    let (total_files, verified_files, corrupt, decode_failed, unreadable) = conn
+        .query_row(
+            "SELECT
+                (SELECT COUNT(*) FROM afiles),
+                COUNT(*),
+                COALESCE(SUM(status = ?1), 0),
+                COALESCE(SUM(status = ?2), 0),
+                COALESCE(SUM(status = ?3), 0)
+             FROM integrity_checks",
+            params![STATUS_CORRUPT, STATUS_DECODE_FAILED, STATUS_UNREADABLE],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(IntegrityOverview {
+        total_files,
+        verified_files,
+        corrupt,
+        decode_failed,
+        unreadable,
+        last_run: load_last_run(),
+    })
+}
+
+/// Files with a problem, optionally limited to one status
+pub fn list_issues(
+    status: Option<&str>,
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<IntegrityIssue>, String> {
+    let conn = open_conn()?;
+    let statuses: Vec<&str> = match status.filter(|status| !status.is_empty()) {
+        Some(status) => vec![status],
+        None => vec![STATUS_CORRUPT, STATUS_DECODE_FAILED, STATUS_UNREADABLE],
+    };
+    let sql = format!(
+        "SELECT ic.file_id, f.album_id, f.path, a.name, a.size, ic.status, ic.error,
+                ic.expected_hash, ic.actual_hash, ic.checked_at
+         FROM integrity_checks ic
+         JOIN afiles a ON ic.file_id = a.id
+         JOIN afolders f ON a.folder_id = f.id
+         WHERE ic.status IN ({})
+         ORDER BY ic.checked_at DESC, ic.file_id
+         LIMIT {} OFFSET {}",
+        vec!["?"; statuses.len()].join(","),
+        if limit > 0 { limit } else { -1 },
+        offset.max(0)
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(statuses.iter()), |row| {
+            let folder_path: String = row.get(2)?;
+            let name: String = row.get(3)?;
+            Ok(IntegrityIssue {
+                file_id: row.get(0)?,
+                album_id: row.get(1)?,
+                file_path: Path::new(&folder_path)
+                    .join(name)
+                    .to_string_lossy()
+                    .into_owned(),
+                file_size: row.get(4)?,
+                status: row.get(5)?,
+                error: row.get(6)?,
+                expected_hash: row.get(7)?,
+                actual_hash: row.get(8)?,
+                checked_at: row.get(9)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Find other library files with the damaged file's last known good hash.
+/// Files of the same size that only have a sample hash (or none yet) are candidates
+/// too; each candidate is re-hashed, so only copies that are intact right now are returned.
+pub fn find_intact_copies(file_id: i64) -> Result<Vec<IntactCopy>, String> {
+    let conn = open_conn()?;
+    let expected = conn
+        .query_row(
+            "SELECT COALESCE(ic.expected_hash, fh.hash), fh.file_size
+             FROM file_hashes fh
+             LEFT JOIN integrity_checks ic ON ic.file_id = fh.file_id
+             WHERE fh.file_id = ?1",
+            params![file_id],
+            |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, i64>(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let Some((Some(hash), file_size)) = expected else {
+        return Ok(Vec::new());
+    };
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT a.id, f.album_id, f.path, a.name
+             FROM afiles a
+             JOIN afolders f ON a.folder_id = f.id
+             LEFT JOIN file_hashes fh ON fh.file_id = a.id
+             LEFT JOIN integrity_checks ic ON ic.file_id = a.id
+             WHERE (fh.hash = ?1 OR fh.hash IS NULL)
+               AND COALESCE(fh.file_size, a.size) = ?2 AND a.id != ?3
+               AND COALESCE(ic.status, ?4) != ?5",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(
+            params![hash, file_size, file_id, STATUS_OK, STATUS_CORRUPT],
+            |row| {
+                let folder_path: String = row.get(2)?;
+                let name: String = row.get(3)?;
+                Ok(IntactCopy {
+                    file_id: row.get(0)?,
+                    album_id: row.get(1)?,
+                    file_path: Path::new(&folder_path)
+                        .join(name)
+                        .to_string_lossy()
+                        .into_owned(),
+                })
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    let candidates = rows
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(candidates
+        .into_iter()
+        .filter(|copy| {
+            t_dedup::compute_blake3_hash(&copy.file_path).is_ok_and(|actual| actual == hash)
+        })
+        .collect())
+}
+
+/// Accept the current content of flagged files (e.g. an edit that kept the mtime)
+/// as their new baseline. Returns the number of files accepted.
+pub fn accept_current(file_ids: &[i64]) -> Result<usize, String> {
+    let conn = open_conn()?;
+    let mut accepted = 0;
+    for file_id in file_ids {
+        let actual_hash: Option<String> = conn
+            .query_row(
+                "SELECT actual_hash FROM integrity_checks WHERE file_id = ?1",
+                params![file_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .flatten();
+        let Some(actual_hash) = actual_hash else {
+            continue;
+        };
+        conn.execute(
+            "UPDATE file_hashes SET hash = ?2, sample_hash = NULL, computed_at = ?3 WHERE file_id = ?1",
+            params![file_id, actual_hash, now_secs()],
+        )
+        .map_err(|e| e.to_string())?;
+        accepted += conn
+            .execute(
+                "UPDATE integrity_checks
+                 SET status = ?2, error = NULL, expected_hash = actual_hash
+                 WHERE file_id = ?1",
+                params![file_id, STATUS_OK],
+            )
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(accepted)
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
@@ -470,6 +470,35 @@ pub fn get_raw_meta(file_path: &str) -> Result<RawMeta, String> {
     RawHandle::open(file_path)?.meta()
 }
 
+/// Decode the sensor data (half size) to check that a RAW file is readable.
+/// Unlike thumbnails, this never falls back to the embedded preview.
+pub fn verify_raw_decode(file_path: &str) -> Result<(), String> {
+    let raw = RawHandle::open(file_path)?;
+    let mut out = LapLibRawImage {
+        data: std::ptr::null_mut(),
+        len: 0,
+        format: 0,
+        width: 0,
+        height: 0,
+        colors: 0,
+        bits: 0,
+        flip: 0,
+    };
+
+    let ret = unsafe { lap_libraw_render_preview(raw.raw, 1, 1, &mut out) };
+    let len = out.len;
+    if !out.data.is_null() {
+        unsafe { lap_libraw_free_buffer(out.data) };
+    }
+    if ret != 0 {
+        return Err(libraw_error(ret, "Failed to decode RAW data"));
+    }
+    if len == 0 {
+        return Err("LibRaw returned no image data".to_string());
+    }
+    Ok(())
+}
+
 /// Read the EXIF Orientation tag from in-memory JPEG bytes.
 /// Returns 1 (normal) when absent or unparseable.
 fn jpeg_exif_orientation(data: &[u8]) -> i32 {
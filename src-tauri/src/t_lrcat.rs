@@ -0,0 +1,290 @@
+/**
+ * t_lrcat.rs - Lightroom Classic catalog reader
+ *
+ * A `.lrcat` is an SQLite database. Images live in `Adobe_images`, their files in
+ * `AgLibraryFile` -> `AgLibraryFolder` -> `AgLibraryRootFolder`; keywords and
+ * collections are linked through `AgLibraryKeywordImage` and
+ * `AgLibraryCollectionImage`. Virtual copies are skipped, since they share their
+ * master's file. Keywords keep their tree path, pick flags map to Lap's culling
+ * flag and color labels become tags.
+ */
+use crate::t_catalog_import::{COLOR_LABEL_PREFIX, CatalogData, CatalogImage, TAG_PATH_SEPARATOR};
+use rusqlite::{Connection, OpenFlags, params};
+use std::collections::HashMap;
+
+const COLLECTION_REGULAR: &str = "com.adobe.ag.library.collection";
+const COLLECTION_SMART: &str = "com.adobe.ag.library.smart_collection";
+
+/// Read the images, keywords and collections of a Lightroom Classic catalog
+pub fn read_catalog(catalog_path: &str) -> Result<CatalogData, String> {
+    let conn = Connection::open_with_flags(
+        catalog_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("Failed to open the Lightroom catalog: {}", e))?;
+
+    let table_count: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'
+            AND name IN ('Adobe_images', 'AgLibraryFile', 'AgLibraryFolder', 'AgLibraryRootFolder')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(catalog_error)?;
+    if table_count < 4 {
+        return Err("Not a Lightroom Classic catalog.".to_string());
+    }
+
+    let mut data = CatalogData {
+        roots: read_roots(&conn)?,
+        ..CatalogData::default()
+    };
+    let mut image_index: HashMap<i64, usize> = HashMap::new();
+
+    // images with their file path, rating, pick flag and color label
+    let mut stmt = conn
+        .prepare(
+            "SELECT i.id_local, r.absolutePath, fo.pathFromRoot, fi.baseName, fi.extension,
+                i.rating, i.pick, i.colorLabels
+            FROM Adobe_images i
+            JOIN AgLibraryFile fi ON fi.id_local = i.rootFile
+            JOIN AgLibraryFolder fo ON fo.id_local = fi.folder
+            JOIN AgLibraryRootFolder r ON r.id_local = fo.rootFolder
+            WHERE i.masterImage IS NULL",
+        )
+        .map_err(catalog_error)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<f64>>(5)?,
+                row.get::<_, Option<f64>>(6)?,
+                row.get::<_, Option<String>>(7)?,
+            ))
+        })
+        .map_err(catalog_error)?;
+    for row in rows {
+        let (id, root, folder, base_name, extension, rating, pick, color_label) =
+            row.map_err(catalog_error)?;
+        let name = match extension.as_deref().filter(|ext| !ext.is_empty()) {
+            Some(ext) => format!("{}.{}", base_name, ext),
+            None => base_name,
+        };
+        let mut image = CatalogImage {
+            path: format!("{}{}{}", root, folder.unwrap_or_default(), name),
+            rating: rating.map(|rating| rating.round() as i32),
+            culling_flag: pick.and_then(pick_to_culling_flag),
+            ..CatalogImage::default()
+        };
+        if let Some(label) = color_label.map(|label| label.trim().to_string()) {
+            if !label.is_empty() {
+                image.tags.push(format!("{}{}", COLOR_LABEL_PREFIX, label));
+            }
+        }
+        image_index.insert(id, data.images.len());
+        data.images.push(image);
+    }
+
+    // keywords, as their full path in the keyword tree ("Places/Paris")
+    let keywords = read_names(&conn, "SELECT id_local, name, parent FROM AgLibraryKeyword")?;
+    let mut stmt = conn
+        .prepare("SELECT image, tag FROM AgLibraryKeywordImage")
+        .map_err(catalog_error)?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(catalog_error)?;
+    for row in rows {
+        let (image_id, keyword_id) = row.map_err(catalog_error)?;
+        let (Some(index), Some(keyword)) = (image_index.get(&image_id), keywords.get(&keyword_id))
+        else {
+            continue;
+        };
+        if keyword.name.is_none() {
+            continue;
+        }
+        let name = get_full_name(&keywords, keyword_id, TAG_PATH_SEPARATOR);
+        let image = &mut data.images[*index];
+        if !image.tags.contains(&name) {
+            image.tags.push(name);
+        }
+    }
+
+    // regular collections, named after their collection sets ("Travel / Italy")
+    let collections = read_names(
+        &conn,
+        "SELECT id_local, name, parent, creationId FROM AgLibraryCollection",
+    )?;
+    data.skipped_collections = collections
+        .values()
+        .filter(|collection| collection.kind.as_deref() == Some(COLLECTION_SMART))
+        .count();
+    let mut stmt = conn
+        .prepare("SELECT collection, image FROM AgLibraryCollectionImage")
+        .map_err(catalog_error)?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(catalog_error)?;
+    for row in rows {
+        let (collection_id, image_id) = row.map_err(catalog_error)?;
+        let Some(index) = image_index.get(&image_id) else {
+            continue;
+        };
+        let Some(collection) = collections.get(&collection_id) else {
+            continue;
+        };
+        if collection.kind.as_deref() != Some(COLLECTION_REGULAR) {
+            continue;
+        }
+        let name = get_full_name(&collections, collection_id, " / ");
+        let image = &mut data.images[*index];
+        if !name.is_empty() && !image.collections.contains(&name) {
+            image.collections.push(name);
+        }
+    }
+
+    Ok(data)
+}
+
+/// A keyword or collection row
+struct NamedNode {
+    name: Option<String>,
+    parent: Option<i64>,
+    kind: Option<String>, // creationId of collections
+}
+
+fn read_roots(conn: &Connection) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT absolutePath FROM AgLibraryRootFolder ORDER BY absolutePath")
+        .map_err(catalog_error)?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(catalog_error)?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(catalog_error)
+}
+
+/// Read `id, name, parent[, kind]` rows into a lookup table
+fn read_names(conn: &Connection, query: &str) -> Result<HashMap<i64, NamedNode>, String> {
+    let mut stmt = conn.prepare(query).map_err(catalog_error)?;
+    let has_kind = stmt.column_count() > 3;
+    let rows = stmt
+        .query_map(params![], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                NamedNode {
+                    name: row
+                        .get::<_, Option<String>>(1)?
+                        .map(|name| name.trim().to_string())
+                        .filter(|name| !name.is_empty()),
+                    parent: row.get::<_, Option<i64>>(2)?,
+                    kind: if has_kind {
+                        row.get::<_, Option<String>>(3)?
+                    } else {
+                        None
+                    },
+                },
+            ))
+        })
+        .map_err(catalog_error)?;
+    rows.collect::<Result<HashMap<_, _>, _>>()
+        .map_err(catalog_error)
+}
+
+/// The names of a node and its named ancestors, outermost first
+fn get_full_name(nodes: &HashMap<i64, NamedNode>, id: i64, separator: &str) -> String {
+    let mut names = Vec::new();
+    let mut current = Some(id);
+    while let Some(node) = current.and_then(|id| nodes.get(&id)) {
+        if let Some(name) = &node.name {
+            names.push(name.as_str());
+        }
+        current = node.parent;
+        if names.len() > 64 {
+            break; // guard against a broken parent chain
+        }
+    }
+    names.reverse();
+    names.join(separator)
+}
+
+/// Lightroom stores picks as 1 and rejects as -1
+fn pick_to_culling_flag(pick: f64) -> Option<i32> {
+    if pick > 0.0 {
+        Some(1)
+    } else if pick < 0.0 {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+fn catalog_error(e: rusqlite::Error) -> String {
+    let message = e.to_string();
+    if message.contains("locked") {
+        "The catalog is in use. Close Lightroom Classic and try again.".to_string()
+    } else {
+        format!("Failed to read the Lightroom catalog: {}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_a_catalog() {
+        let path = std::env::temp_dir().join(format!("lap-test-{}.lrcat", uuid::Uuid::new_v4()));
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE AgLibraryRootFolder (id_local INTEGER PRIMARY KEY, absolutePath TEXT, name TEXT);
+                CREATE TABLE AgLibraryFolder (id_local INTEGER PRIMARY KEY, rootFolder INTEGER, pathFromRoot TEXT);
+                CREATE TABLE AgLibraryFile (id_local INTEGER PRIMARY KEY, folder INTEGER, baseName TEXT, extension TEXT);
+                CREATE TABLE Adobe_images (id_local INTEGER PRIMARY KEY, rootFile INTEGER, masterImage INTEGER,
+                    rating REAL, pick REAL, colorLabels TEXT);
+                CREATE TABLE AgLibraryKeyword (id_local INTEGER PRIMARY KEY, name TEXT, parent INTEGER);
+                CREATE TABLE AgLibraryKeywordImage (id_local INTEGER PRIMARY KEY, image INTEGER, tag INTEGER);
+                CREATE TABLE AgLibraryCollection (id_local INTEGER PRIMARY KEY, name TEXT, parent INTEGER, creationId TEXT);
+                CREATE TABLE AgLibraryCollectionImage (id_local INTEGER PRIMARY KEY, collection INTEGER, image INTEGER);
+
+                INSERT INTO AgLibraryRootFolder VALUES (1, '/Volumes/Photos/', 'Photos');
+                INSERT INTO AgLibraryFolder VALUES (1, 1, '2019/2019-07-04/');
+                INSERT INTO AgLibraryFile VALUES (1, 1, 'IMG_0001', 'CR2'), (2, 1, 'IMG_0002', 'JPG');
+                INSERT INTO Adobe_images VALUES (1, 1, NULL, 4, 1, 'Red'), (2, 2, NULL, NULL, -1, ''),
+                    (3, 1, 1, 1, 0, NULL);
+                INSERT INTO AgLibraryKeyword VALUES (1, NULL, NULL), (2, 'Places', 1), (3, 'Paris', 2);
+                INSERT INTO AgLibraryKeywordImage VALUES (1, 1, 3);
+                INSERT INTO AgLibraryCollection VALUES (1, 'Travel', NULL, 'com.adobe.ag.library.group'),
+                    (2, 'France', 1, 'com.adobe.ag.library.collection'),
+                    (3, 'Five stars', NULL, 'com.adobe.ag.library.smart_collection');
+                INSERT INTO AgLibraryCollectionImage VALUES (1, 2, 1), (2, 2, 2);",
+            )
+            .unwrap();
+        }
+
+        let data = read_catalog(path.to_str().unwrap()).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(data.roots, vec!["/Volumes/Photos/".to_string()]);
+        assert_eq!(data.images.len(), 2); // the virtual copy is skipped
+        assert_eq!(data.skipped_collections, 1);
+
+        let raw = &data.images[0];
+        assert_eq!(raw.path, "/Volumes/Photos/2019/2019-07-04/IMG_0001.CR2");
+        assert_eq!(raw.rating, Some(4));
+        assert_eq!(raw.culling_flag, Some(1));
+        assert_eq!(
+            raw.tags,
+            vec!["Color Label: Red".to_string(), "Places/Paris".to_string()]
+        );
+        assert_eq!(raw.collections, vec!["Travel / France".to_string()]);
+
+        let jpeg = &data.images[1];
+        assert_eq!(jpeg.rating, None);
+        assert_eq!(jpeg.culling_flag, Some(2));
+        assert!(jpeg.tags.is_empty());
+    }
+}
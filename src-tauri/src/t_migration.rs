@@ -148,6 +148,156 @@ fn get_migrations() -> Vec<Migration> {
             description: "Add motion photo offset",
             sql: "",
         },
+        Migration {
+            version: 17,
+            description: "Manual face boxes and rejected detections",
+            sql: "",
+        },
+        Migration {
+            version: 18,
+            description: "Image search model ids and library settings",
+            sql: "",
+        },
+        Migration {
+            version: 19,
+            description: "Video keyframe embeddings",
+            sql: "
+                CREATE TABLE IF NOT EXISTS video_keyframes (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    file_id INTEGER NOT NULL,
+                    offset_secs INTEGER NOT NULL,
+                    embeds BLOB NOT NULL,
+                    embeds_model TEXT NOT NULL,
+                    FOREIGN KEY (file_id) REFERENCES afiles(id) ON DELETE CASCADE
+                );
+                CREATE INDEX IF NOT EXISTS idx_video_keyframes_file ON video_keyframes(file_id);
+            ",
+        },
+        Migration {
+            version: 20,
+            description: "Recognized text (OCR) per file",
+            sql: "",
+        },
+        Migration {
+            version: 21,
+            description: "Perceptual-hash near-duplicate detection",
+            sql: "",
+        },
+        Migration {
+            version: 22,
+            description: "Keep policy reasons for duplicate group items",
+            sql: "",
+        },
+        Migration {
+            version: 23,
+            description: "Sample hashes for staged dedup hashing",
+            sql: "",
+        },
+        Migration {
+            version: 24,
+            description: "Track embeddings in the persisted similarity index",
+            sql: "
+                CREATE TABLE IF NOT EXISTS similarity_index_entries (
+                    file_id INTEGER PRIMARY KEY,
+                    vector_hash TEXT NOT NULL,
+                    FOREIGN KEY (file_id) REFERENCES afiles(id) ON DELETE CASCADE
+                );
+            ",
+        },
+        Migration {
+            version: 25,
+            description: "Cache video frame fingerprints for near-duplicate detection",
+            sql: "
+                CREATE TABLE IF NOT EXISTS video_fingerprints (
+                    file_id INTEGER PRIMARY KEY,
+                    frames BLOB NOT NULL,
+                    frame_interval INTEGER NOT NULL,
+                    mtime INTEGER NOT NULL,
+                    computed_at INTEGER NOT NULL,
+                    FOREIGN KEY (file_id) REFERENCES afiles(id) ON DELETE CASCADE
+                );
+            ",
+        },
+        Migration {
+            version: 26,
+            description: "Content fingerprints for re-attaching moved files",
+            sql: "",
+        },
+        Migration {
+            version: 27,
+            description: "Volume identity for albums on removable drives",
+            sql: "",
+        },
+        Migration {
+            version: 28,
+            description: "Per-album scan include/exclude filters",
+            sql: "
+                CREATE TABLE IF NOT EXISTS album_scan_filters (
+                    album_id INTEGER PRIMARY KEY,
+                    exclude_patterns TEXT NOT NULL DEFAULT '',
+                    include_patterns TEXT NOT NULL DEFAULT '',
+                    min_file_size INTEGER NOT NULL DEFAULT 0,
+                    min_dimension INTEGER NOT NULL DEFAULT 0,
+                    FOREIGN KEY (album_id) REFERENCES albums(id) ON DELETE CASCADE
+                );
+            ",
+        },
+        Migration {
+            version: 29,
+            description: "Per-file indexing error report",
+            sql: "
+                CREATE TABLE IF NOT EXISTS index_errors (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    album_id INTEGER NOT NULL,
+                    file_id INTEGER,
+                    file_path TEXT NOT NULL,
+                    stage TEXT NOT NULL,
+                    kind TEXT NOT NULL,
+                    error TEXT NOT NULL,
+                    file_size INTEGER NOT NULL DEFAULT 0,
+                    first_seen_at INTEGER NOT NULL,
+                    last_seen_at INTEGER NOT NULL,
+                    occurrences INTEGER NOT NULL DEFAULT 1,
+                    ignored INTEGER NOT NULL DEFAULT 0,
+                    UNIQUE (file_path, stage),
+                    FOREIGN KEY (album_id) REFERENCES albums(id) ON DELETE CASCADE
+                );
+                CREATE INDEX IF NOT EXISTS idx_index_errors_album_id ON index_errors(album_id);
+                CREATE INDEX IF NOT EXISTS idx_index_errors_file_id ON index_errors(file_id);
+            ",
+        },
+        Migration {
+            version: 30,
+            description: "File integrity verification results",
+            sql: "
+                CREATE TABLE IF NOT EXISTS integrity_checks (
+                    file_id INTEGER PRIMARY KEY,
+                    status TEXT NOT NULL,
+                    error TEXT,
+                    expected_hash TEXT,
+                    actual_hash TEXT,
+                    checked_at INTEGER NOT NULL,
+                    FOREIGN KEY (file_id) REFERENCES afiles(id) ON DELETE CASCADE
+                );
+                CREATE INDEX IF NOT EXISTS idx_integrity_checks_status ON integrity_checks(status);
+                CREATE INDEX IF NOT EXISTS idx_integrity_checks_checked_at ON integrity_checks(checked_at);
+            ",
+        },
+        Migration {
+            version: 31,
+            description: "Batch rename history for undo",
+            sql: "
+                CREATE TABLE IF NOT EXISTS rename_history (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    batch_id INTEGER NOT NULL,
+                    file_id INTEGER,
+                    old_path TEXT NOT NULL,
+                    new_path TEXT NOT NULL,
+                    created_at INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_rename_history_batch_id ON rename_history(batch_id);
+            ",
+        },
     ]
 }
 
@@ -463,6 +613,128 @@ pub fn check_and_migrate(conn: &Connection) -> Result<(), String> {
                             format!("Migration 16 failed adding motion_photo_offset: {}", e)
                         })?;
                 }
+            } else if migration.version == 17 {
+                if !table_has_column(conn, "faces", "is_manual")? {
+                    conn.execute(
+                        "ALTER TABLE faces ADD COLUMN is_manual INTEGER NOT NULL DEFAULT 0",
+                        [],
+                    )
+                    .map_err(|e| format!("Migration 17 failed adding is_manual: {}", e))?;
+                }
+                conn.execute_batch(
+                    "CREATE TABLE IF NOT EXISTS face_rejections (
+                        id INTEGER PRIMARY KEY AUTOINCREMENT,
+                        file_id INTEGER NOT NULL,
+                        bbox TEXT NOT NULL,
+                        created_at INTEGER NOT NULL,
+                        FOREIGN KEY (file_id) REFERENCES afiles(id) ON DELETE CASCADE
+                    );
+                    CREATE INDEX IF NOT EXISTS idx_face_rejections_file ON face_rejections(file_id);",
+                )
+                .map_err(|e| format!("Migration 17 failed creating face_rejections: {}", e))?;
+            } else if migration.version == 18 {
+                if !table_has_column(conn, "afiles", "embeds_model")? {
+                    conn.execute("ALTER TABLE afiles ADD COLUMN embeds_model TEXT", [])
+                        .map_err(|e| format!("Migration 18 failed adding embeds_model: {}", e))?;
+                }
+                conn.execute_batch(
+                    "CREATE TABLE IF NOT EXISTS library_settings (
+                        key TEXT PRIMARY KEY,
+                        value TEXT NOT NULL
+                    );",
+                )
+                .map_err(|e| format!("Migration 18 failed creating library_settings: {}", e))?;
+            } else if migration.version == 20 {
+                if !table_has_column(conn, "afiles", "has_text")? {
+                    conn.execute("ALTER TABLE afiles ADD COLUMN has_text INTEGER NOT NULL DEFAULT 0", [])
+                        .map_err(|e| format!("Migration 20 failed adding has_text: {}", e))?;
+                }
+                conn.execute_batch(
+                    "CREATE TABLE IF NOT EXISTS file_texts (
+                        file_id INTEGER PRIMARY KEY,
+                        text TEXT NOT NULL,
+                        updated_at INTEGER,
+                        FOREIGN KEY (file_id) REFERENCES afiles(id) ON DELETE CASCADE
+                    );",
+                )
+                .map_err(|e| format!("Migration 20 failed creating file_texts: {}", e))?;
+            } else if migration.version == 21 {
+                if !table_has_column(conn, "duplicate_groups", "match_kind")? {
+                    conn.execute(
+                        "ALTER TABLE duplicate_groups ADD COLUMN match_kind TEXT NOT NULL DEFAULT 'exact'",
+                        [],
+                    )
+                    .map_err(|e| format!("Migration 21 failed adding match_kind: {}", e))?;
+                }
+                conn.execute_batch(
+                    "CREATE TABLE IF NOT EXISTS file_image_hashes (
+                        file_id INTEGER PRIMARY KEY,
+                        dhash INTEGER NOT NULL,
+                        phash INTEGER NOT NULL,
+                        mtime INTEGER NOT NULL,
+                        computed_at INTEGER NOT NULL,
+                        FOREIGN KEY (file_id) REFERENCES afiles(id) ON DELETE CASCADE
+                    );",
+                )
+                .map_err(|e| format!("Migration 21 failed creating file_image_hashes: {}", e))?;
+            } else if migration.version == 22 {
+                if !table_has_column(conn, "duplicate_group_items", "reasons")? {
+                    conn.execute(
+                        "ALTER TABLE duplicate_group_items ADD COLUMN reasons TEXT NOT NULL DEFAULT ''",
+                        [],
+                    )
+                    .map_err(|e| format!("Migration 22 failed adding reasons: {}", e))?;
+                }
+            } else if migration.version == 23 {
+                // Rebuild file_hashes so `hash` can stay NULL until a full hash is needed
+                if !table_has_column(conn, "file_hashes", "sample_hash")? {
+                    let tx = conn.unchecked_transaction().map_err(|e| {
+                        format!("Migration 23 failed starting transaction: {}", e)
+                    })?;
+                    tx.execute_batch(
+                        "CREATE TABLE file_hashes_new (
+                            file_id INTEGER PRIMARY KEY,
+                            hash TEXT,
+                            sample_hash TEXT,
+                            file_size INTEGER NOT NULL,
+                            mtime INTEGER NOT NULL,
+                            computed_at INTEGER NOT NULL,
+                            FOREIGN KEY (file_id) REFERENCES afiles(id) ON DELETE CASCADE
+                        );
+                        INSERT INTO file_hashes_new (file_id, hash, file_size, mtime, computed_at)
+                            SELECT file_id, hash, file_size, mtime, computed_at FROM file_hashes;
+                        DROP TABLE file_hashes;
+                        ALTER TABLE file_hashes_new RENAME TO file_hashes;
+                        CREATE INDEX IF NOT EXISTS idx_file_hashes_hash_size ON file_hashes(hash, file_size);
+                        CREATE INDEX IF NOT EXISTS idx_file_hashes_mtime ON file_hashes(mtime);",
+                    )
+                    .map_err(|e| format!("Migration 23 failed rebuilding file_hashes: {}", e))?;
+                    tx.commit()
+                        .map_err(|e| format!("Migration 23 failed committing: {}", e))?;
+                }
+            } else if migration.version == 26 {
+                if !table_has_column(conn, "afiles", "content_fingerprint")? {
+                    conn.execute(
+                        "ALTER TABLE afiles ADD COLUMN content_fingerprint TEXT",
+                        [],
+                    )
+                    .map_err(|e| format!("Migration 26 failed adding content_fingerprint: {}", e))?;
+                }
+                conn.execute(
+                    "CREATE INDEX IF NOT EXISTS idx_afiles_content_fingerprint ON afiles(content_fingerprint)",
+                    [],
+                )
+                .map_err(|e| format!("Migration 26 failed adding fingerprint index: {}", e))?;
+            } else if migration.version == 27 {
+                for column in ["volume_uuid", "volume_path"] {
+                    if !table_has_column(conn, "albums", column)? {
+                        conn.execute(
+                            &format!("ALTER TABLE albums ADD COLUMN {} TEXT", column),
+                            [],
+                        )
+                        .map_err(|e| format!("Migration 27 failed adding {}: {}", column, e))?;
+                    }
+                }
             } else if !migration.sql.trim().is_empty() {
                 conn.execute_batch(migration.sql)
                     .map_err(|e| format!("Migration {} failed: {}", migration.version, e))?;
@@ -0,0 +1,735 @@
+/**
+ * OCR module
+ * Detects (DB) and recognizes (CRNN/SVTR + CTC) text in images using ONNX Runtime.
+ * Models are optional; text indexing is only offered when they are installed.
+ */
+use crate::{t_common, t_sqlite, t_utils};
+use image::{DynamicImage, GenericImageView, GrayImage, Luma};
+use ndarray::Array;
+use ort::{
+    inputs,
+    session::{Session, builder::GraphOptimizationLevel},
+    value::Value,
+};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+
+// detection
+const DET_MAX_SIDE: u32 = 960; // longest side fed to the detector (multiple of 32)
+const DET_THRESHOLD: f32 = 0.3; // pixel probability to count as text
+const DET_BOX_THRESHOLD: f32 = 0.6; // mean probability to keep a region
+const DET_UNCLIP_RATIO: f32 = 1.5; // region expansion, as in PaddleOCR
+const DET_MIN_BOX_SIZE: u32 = 3;
+const DET_MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const DET_STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+// recognition
+const REC_HEIGHT: u32 = 48;
+const REC_MAX_WIDTH: u32 = 960;
+const REC_MIN_CONFIDENCE: f32 = 0.5;
+
+/// A recognized text line, in original image pixels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrLine {
+    pub text: String,
+    pub confidence: f32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TextBox {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+pub struct OcrEngine {
+    detection_model: Option<Session>,   // DB text detector
+    recognition_model: Option<Session>, // CTC text recognizer
+    charset: Vec<String>,               // recognizer classes: blank, dictionary..., space
+}
+
+impl OcrEngine {
+    pub fn new() -> Self {
+        Self {
+            detection_model: None,
+            recognition_model: None,
+            charset: Vec::new(),
+        }
+    }
+
+    fn model_paths(app: &AppHandle) -> Result<(PathBuf, PathBuf, PathBuf), String> {
+        let resource_dir = app
+            .path()
+            .resolve("models", tauri::path::BaseDirectory::Resource)
+            .map_err(|e| format!("Failed to resolve resource path: {}", e))?;
+        Ok((
+            resource_dir.join(t_common::OCR_DETECTION_MODEL),
+            resource_dir.join(t_common::OCR_RECOGNITION_MODEL),
+            resource_dir.join(t_common::OCR_DICTIONARY),
+        ))
+    }
+
+    /// Check whether the optional OCR model files are installed
+    pub fn is_available(app: &AppHandle) -> bool {
+        Self::model_paths(app)
+            .map(|(det, rec, dict)| det.exists() && rec.exists() && dict.exists())
+            .unwrap_or(false)
+    }
+
+    pub fn load_models(&mut self, app: &AppHandle) -> Result<(), String> {
+        if self.is_loaded() {
+            return Ok(());
+        }
+
+        let (detection_model_path, recognition_model_path, dictionary_path) =
+            Self::model_paths(app)?;
+        for path in [
+            &detection_model_path,
+            &recognition_model_path,
+            &dictionary_path,
+        ] {
+            if !path.exists() {
+                return Err(format!("OCR model not found at {:?}", path));
+            }
+        }
+
+        let dictionary = std::fs::read_to_string(&dictionary_path)
+            .map_err(|e| format!("Failed to read OCR dictionary: {}", e))?;
+        let mut charset = vec![String::new()]; // CTC blank
+        charset.extend(
+            dictionary
+                .lines()
+                .map(|line| line.trim_end_matches('\r').to_string()),
+        );
+        charset.push(" ".to_string());
+
+        let detection_model = Self::load_session(&detection_model_path, "detection")?;
+        let recognition_model = Self::load_session(&recognition_model_path, "recognition")?;
+
+        self.detection_model = Some(detection_model);
+        self.recognition_model = Some(recognition_model);
+        self.charset = charset;
+        Ok(())
+    }
+
+    fn load_session(path: &Path, model_name: &str) -> Result<Session, String> {
+        Session::builder()
+            .map_err(|e| e.to_string())?
+            .with_optimization_level(GraphOptimizationLevel::Level3)
+            .map_err(|e| e.to_string())?
+            .with_intra_threads(4)
+            .map_err(|e| e.to_string())?
+            .commit_from_file(path)
+            .map_err(|e| format!("Failed to load OCR {} model: {}", model_name, e))
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.detection_model.is_some() && self.recognition_model.is_some()
+    }
+
+    /// Recognize all text lines in an image, in reading order
+    pub fn recognize(&mut self, img: &DynamicImage) -> Result<Vec<OcrLine>, String> {
+        if !self.is_loaded() {
+            return Err("OCR models not loaded".to_string());
+        }
+
+        let mut lines = Vec::new();
+        for text_box in sort_reading_order(self.detect_text(img)?) {
+            let crop = img.crop_imm(text_box.x, text_box.y, text_box.width, text_box.height);
+            // vertical text: rotate so characters run left to right
+            let crop = if crop.height() as f32 >= crop.width() as f32 * 1.5 {
+                crop.rotate270()
+            } else {
+                crop
+            };
+            let (text, confidence) = self.recognize_line(&crop)?;
+            let text = text.trim().to_string();
+            if text.is_empty() || confidence < REC_MIN_CONFIDENCE {
+                continue;
+            }
+            lines.push(OcrLine {
+                text,
+                confidence,
+                x: text_box.x,
+                y: text_box.y,
+                width: text_box.width,
+                height: text_box.height,
+            });
+        }
+        Ok(lines)
+    }
+
+    fn detect_text(&mut self, img: &DynamicImage) -> Result<Vec<TextBox>, String> {
+        let (orig_w, orig_h) = img.dimensions();
+        if orig_w == 0 || orig_h == 0 {
+            return Ok(Vec::new());
+        }
+
+        // Resize so both sides are multiples of 32 and the longest side fits DET_MAX_SIDE
+        let scale = (DET_MAX_SIDE as f32 / orig_w.max(orig_h) as f32).min(1.0);
+        let round32 = |v: f32| (((v / 32.0).round() as u32).max(1)) * 32;
+        let det_w = round32(orig_w as f32 * scale);
+        let det_h = round32(orig_h as f32 * scale);
+        let rgb_img = img
+            .resize_exact(det_w, det_h, image::imageops::FilterType::Triangle)
+            .into_rgb8();
+
+        let mut array = Array::zeros((1, 3, det_h as usize, det_w as usize));
+        for (x, y, pixel) in rgb_img.enumerate_pixels() {
+            for c in 0..3 {
+                array[[0, c, y as usize, x as usize]] =
+                    (pixel[c] as f32 / 255.0 - DET_MEAN[c]) / DET_STD[c];
+            }
+        }
+        let input_value = Value::from_array(array).map_err(|e| e.to_string())?;
+
+        let session = self.detection_model.as_mut().unwrap();
+        let input_name = session.inputs[0].name.clone();
+        let outputs = session
+            .run(inputs![input_name => input_value])
+            .map_err(|e| format!("OCR detection inference error: {}", e))?;
+        let (_, prob_data) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .map_err(|e| format!("Failed to extract OCR detection map: {}", e))?;
+        if prob_data.len() < (det_w * det_h) as usize {
+            return Err("OCR detection map is smaller than its input".to_string());
+        }
+
+        let mut prob_map = image::ImageBuffer::<Luma<f32>, Vec<f32>>::new(det_w, det_h);
+        let mut mask = GrayImage::new(det_w, det_h);
+        for y in 0..det_h {
+            for x in 0..det_w {
+                let prob = prob_data[(y * det_w + x) as usize];
+                prob_map.put_pixel(x, y, Luma([prob]));
+                if prob > DET_THRESHOLD {
+                    mask.put_pixel(x, y, Luma([1]));
+                }
+            }
+        }
+
+        let scale_x = orig_w as f32 / det_w as f32;
+        let scale_y = orig_h as f32 / det_h as f32;
+        let boxes = connected_regions(&mask, &prob_map)
+            .into_iter()
+            .filter_map(|(min_x, min_y, max_x, max_y)| {
+                let w = (max_x - min_x + 1) as f32;
+                let h = (max_y - min_y + 1) as f32;
+                // expand by area * ratio / perimeter, like PaddleOCR's unclip
+                let d = w * h * DET_UNCLIP_RATIO / (2.0 * (w + h));
+                let x0 = ((min_x as f32 - d) * scale_x).max(0.0);
+                let y0 = ((min_y as f32 - d) * scale_y).max(0.0);
+                let x1 = ((max_x as f32 + 1.0 + d) * scale_x).min(orig_w as f32);
+                let y1 = ((max_y as f32 + 1.0 + d) * scale_y).min(orig_h as f32);
+                let text_box = TextBox {
+                    x: x0 as u32,
+                    y: y0 as u32,
+                    width: (x1 - x0) as u32,
+                    height: (y1 - y0) as u32,
+                };
+                (text_box.width >= DET_MIN_BOX_SIZE && text_box.height >= DET_MIN_BOX_SIZE)
+                    .then_some(text_box)
+            })
+            .collect();
+        Ok(boxes)
+    }
+
+    fn recognize_line(&mut self, crop: &DynamicImage) -> Result<(String, f32), String> {
+        let ratio = crop.width() as f32 / crop.height().max(1) as f32;
+        let rec_w = ((REC_HEIGHT as f32 * ratio).ceil() as u32).clamp(REC_HEIGHT, REC_MAX_WIDTH);
+        let rgb_img = crop
+            .resize_exact(rec_w, REC_HEIGHT, image::imageops::FilterType::Triangle)
+            .into_rgb8();
+
+        // Normalize: (pixel / 255 - 0.5) / 0.5
+        let mut array = Array::zeros((1, 3, REC_HEIGHT as usize, rec_w as usize));
+        for (x, y, pixel) in rgb_img.enumerate_pixels() {
+            for c in 0..3 {
+                array[[0, c, y as usize, x as usize]] = (pixel[c] as f32 / 255.0 - 0.5) / 0.5;
+            }
+        }
+        let input_value = Value::from_array(array).map_err(|e| e.to_string())?;
+
+        let session = self.recognition_model.as_mut().unwrap();
+        let input_name = session.inputs[0].name.clone();
+        let outputs = session
+            .run(inputs![input_name => input_value])
+            .map_err(|e| format!("OCR recognition inference error: {}", e))?;
+        let (shape, probs) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .map_err(|e| format!("Failed to extract OCR recognition output: {}", e))?;
+        let num_classes = shape
+            .last()
+            .copied()
+            .filter(|dim| *dim > 0)
+            .ok_or_else(|| format!("Invalid OCR recognition shape: {}", shape))?
+            as usize;
+
+        Ok(ctc_greedy_decode(probs, num_classes, &self.charset))
+    }
+}
+
+/// Decode per-step class probabilities: take the best class per step, drop blanks and repeats.
+/// Returns the text and the mean probability of the kept characters.
+fn ctc_greedy_decode(probs: &[f32], num_classes: usize, charset: &[String]) -> (String, f32) {
+    let mut text = String::new();
+    let mut confidence_sum = 0.0;
+    let mut kept = 0;
+    let mut previous = 0;
+    for step in probs.chunks_exact(num_classes) {
+        let (best, best_prob) = step
+            .iter()
+            .copied()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .unwrap_or((0, 0.0));
+        if best != 0 && best != previous {
+            if let Some(ch) = charset.get(best) {
+                text.push_str(ch);
+                confidence_sum += best_prob;
+                kept += 1;
+            }
+        }
+        previous = best;
+    }
+    let confidence = if kept > 0 {
+        confidence_sum / kept as f32
+    } else {
+        0.0
+    };
+    (text, confidence)
+}
+
+/// Find 4-connected regions of the mask whose mean probability passes DET_BOX_THRESHOLD.
+/// Returns inclusive bounds (min_x, min_y, max_x, max_y).
+fn connected_regions(
+    mask: &GrayImage,
+    prob_map: &image::ImageBuffer<Luma<f32>, Vec<f32>>,
+) -> Vec<(u32, u32, u32, u32)> {
+    let (w, h) = mask.dimensions();
+    let mut visited = vec![false; (w * h) as usize];
+    let mut regions = Vec::new();
+    let mut stack = Vec::new();
+
+    for start_y in 0..h {
+        for start_x in 0..w {
+            let start = (start_y * w + start_x) as usize;
+            if visited[start] || mask.get_pixel(start_x, start_y)[0] == 0 {
+                continue;
+            }
+            visited[start] = true;
+            stack.push((start_x, start_y));
+            let (mut min_x, mut min_y, mut max_x, mut max_y) = (start_x, start_y, start_x, start_y);
+            let mut prob_sum = 0.0;
+            let mut count = 0u32;
+
+            while let Some((x, y)) = stack.pop() {
+                prob_sum += prob_map.get_pixel(x, y)[0];
+                count += 1;
+                min_x = min_x.min(x);
+                min_y = min_y.min(y);
+                max_x = max_x.max(x);
+                max_y = max_y.max(y);
+
+                let neighbors = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ];
+                for (nx, ny) in neighbors {
+                    if nx >= w || ny >= h {
+                        continue;
+                    }
+                    let idx = (ny * w + nx) as usize;
+                    if !visited[idx] && mask.get_pixel(nx, ny)[0] != 0 {
+                        visited[idx] = true;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+
+            if prob_sum / count as f32 >= DET_BOX_THRESHOLD {
+                regions.push((min_x, min_y, max_x, max_y));
+            }
+        }
+    }
+    regions
+}
+
+/// Sort boxes top to bottom, and left to right within a line.
+fn sort_reading_order(mut boxes: Vec<TextBox>) -> Vec<TextBox> {
+    boxes.sort_by_key(|b| (b.y, b.x));
+    let mut lines: Vec<Vec<TextBox>> = Vec::new();
+    for text_box in boxes {
+        let center_y = text_box.y + text_box.height / 2;
+        match lines.last_mut() {
+            Some(line)
+                if line
+                    .first()
+                    .is_some_and(|first| center_y < first.y + first.height) =>
+            {
+                line.push(text_box)
+            }
+            _ => lines.push(vec![text_box]),
+        }
+    }
+    lines
+        .into_iter()
+        .flat_map(|mut line| {
+            line.sort_by_key(|b| b.x);
+            line
+        })
+        .collect()
+}
+
+/// Join recognized lines into searchable text (one line per detected row)
+fn lines_to_text(lines: &[OcrLine]) -> String {
+    let mut text = String::new();
+    let mut last_bottom: Option<u32> = None;
+    for line in lines {
+        if let Some(bottom) = last_bottom {
+            text.push(if line.y + line.height / 2 < bottom {
+                ' '
+            } else {
+                '\n'
+            });
+        }
+        text.push_str(&line.text);
+        last_bottom = Some(line.y + line.height);
+    }
+    text
+}
+
+// ----------------------------------------------------------------------------
+// Text indexing
+// ----------------------------------------------------------------------------
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OcrIndexStatus {
+    pub state: String, // "running", "idle", "finished", "error"
+    pub processed: u64,
+    pub total: u64,
+    pub with_text: u64,
+    pub is_running: bool,
+}
+
+impl Default for OcrIndexStatus {
+    fn default() -> Self {
+        Self {
+            state: "idle".to_string(),
+            processed: 0,
+            total: 0,
+            with_text: 0,
+            is_running: false,
+        }
+    }
+}
+
+pub struct OcrState {
+    pub engine: Arc<Mutex<OcrEngine>>,
+    pub is_running: Arc<AtomicBool>,
+    pub cancel_flag: Arc<AtomicBool>,
+    pub status: Arc<Mutex<OcrIndexStatus>>,
+}
+
+impl Default for OcrState {
+    fn default() -> Self {
+        Self {
+            engine: Arc::new(Mutex::new(OcrEngine::new())),
+            is_running: Arc::new(AtomicBool::new(false)),
+            cancel_flag: Arc::new(AtomicBool::new(false)),
+            status: Arc::new(Mutex::new(OcrIndexStatus::default())),
+        }
+    }
+}
+
+/// Get image files that haven't been processed for text yet, optionally within one album.
+/// Failed files are left out; they are tried again once the file changes or the index is reset.
+/// Returns: Vec<(id, file_path)>
+fn get_unprocessed_files(
+    conn: &Connection,
+    album_id: Option<i64>,
+) -> Result<Vec<(i64, String)>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT a.id, f.path, a.name
+             FROM afiles a
+             JOIN afolders f ON a.folder_id = f.id
+             WHERE a.file_type IN (1, 3) AND COALESCE(a.has_text, 0) = 0
+               AND (?1 IS NULL OR f.album_id = ?1)
+             ORDER BY a.id",
+        )
+        .map_err(|e| e.to_string())?;
+    stmt.query_map(params![album_id], |row| {
+        let folder: String = row.get(1)?;
+        let name: String = row.get(2)?;
+        Ok((row.get(0)?, t_utils::get_file_path(&folder, &name)))
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())
+}
+
+/// Store the recognized text of a file (has_text: 1 = has text, 2 = no text, 3 = failed)
+fn save_file_text(conn: &Connection, file_id: i64, text: &str) -> Result<(), String> {
+    if text.is_empty() {
+        conn.execute(
+            "DELETE FROM file_texts WHERE file_id = ?1",
+            params![file_id],
+        )
+        .map_err(|e| e.to_string())?;
+    } else {
+        conn.execute(
+            "INSERT INTO file_texts (file_id, text, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(file_id) DO UPDATE SET text = excluded.text, updated_at = excluded.updated_at",
+            params![file_id, text, chrono::Utc::now().timestamp()],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    conn.execute(
+        "UPDATE afiles SET has_text = ?1 WHERE id = ?2",
+        params![if text.is_empty() { 2 } else { 1 }, file_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Mark a file whose image could not be read, so later runs skip it
+fn mark_text_failed(conn: &Connection, file_id: i64) -> Result<(), String> {
+    conn.execute(
+        "UPDATE afiles SET has_text = 3 WHERE id = ?1",
+        params![file_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Get the recognized text of a file
+pub fn get_file_text(file_id: i64) -> Result<Option<String>, String> {
+    let conn = t_sqlite::open_conn()?;
+    conn.query_row(
+        "SELECT text FROM file_texts WHERE file_id = ?1",
+        params![file_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Count the images of an album that still wait for text recognition
+pub fn count_unprocessed_files(album_id: i64) -> Result<i64, String> {
+    let conn = t_sqlite::open_conn()?;
+    conn.query_row(
+        "SELECT COUNT(*)
+         FROM afiles a
+         JOIN afolders f ON a.folder_id = f.id
+         WHERE a.file_type IN (1, 3) AND COALESCE(a.has_text, 0) = 0 AND f.album_id = ?1",
+        params![album_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Forget recognized text (and failures) so the next run processes every image again
+pub fn reset_text_index() -> Result<(), String> {
+    let conn = t_sqlite::open_conn()?;
+    conn.execute_batch("DELETE FROM file_texts; UPDATE afiles SET has_text = 0;")
+        .map_err(|e| e.to_string())
+}
+
+// load the original image; fall back to the thumbnail for formats `image` cannot decode
+fn load_ocr_image(file_id: i64, file_path: &str) -> Result<DynamicImage, String> {
+    if let Ok(img) = image::open(file_path) {
+        return Ok(img);
+    }
+    let thumb_bytes = t_sqlite::AThumb::fetch(file_id)?
+        .and_then(|thumb| thumb.thumb_data)
+        .ok_or_else(|| format!("Failed to open image: {}", file_path))?;
+    image::load_from_memory(&thumb_bytes).map_err(|e| format!("Failed to load thumbnail: {}", e))
+}
+
+/// Recognize text in every unprocessed image (of one album, if given), in the background
+pub fn start_text_index(
+    app_handle: AppHandle,
+    state: &OcrState,
+    album_id: Option<i64>,
+) -> Result<(), String> {
+    if !OcrEngine::is_available(&app_handle) {
+        return Err("OCR models are not installed".into());
+    }
+    if state
+        .is_running
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return Err("Text recognition is already running.".into());
+    }
+    state.cancel_flag.store(false, Ordering::SeqCst);
+
+    let engine = state.engine.clone();
+    let status_clone = state.status.clone();
+    let is_running_clone = state.is_running.clone();
+    let cancel_flag_clone = state.cancel_flag.clone();
+
+    {
+        let mut status = status_clone.lock().unwrap();
+        *status = OcrIndexStatus {
+            state: "running".to_string(),
+            is_running: true,
+            ..Default::default()
+        };
+    }
+
+    std::thread::spawn(move || {
+        let result = index_text(
+            &app_handle,
+            &engine,
+            &status_clone,
+            &cancel_flag_clone,
+            album_id,
+        );
+
+        let mut final_status = status_clone.lock().unwrap();
+        match result {
+            Ok(_) => {
+                if cancel_flag_clone.load(Ordering::SeqCst) {
+                    final_status.state = "idle".to_string();
+                } else {
+                    final_status.state = "finished".to_string();
+                }
+            }
+            Err(e) => {
+                eprintln!("Text recognition error: {}", e);
+                final_status.state = "error".to_string();
+            }
+        }
+
+        is_running_clone.store(false, Ordering::SeqCst);
+        final_status.is_running = false;
+        let _ = app_handle.emit("ocr-index-progress", final_status.clone());
+    });
+
+    Ok(())
+}
+
+fn index_text(
+    app_handle: &AppHandle,
+    engine: &Arc<Mutex<OcrEngine>>,
+    status_mutex: &Arc<Mutex<OcrIndexStatus>>,
+    cancel_flag: &Arc<AtomicBool>,
+    album_id: Option<i64>,
+) -> Result<(), String> {
+    engine.lock().unwrap().load_models(app_handle)?;
+
+    let conn = t_sqlite::open_conn()?;
+    let files = get_unprocessed_files(&conn, album_id)?;
+    status_mutex.lock().unwrap().total = files.len() as u64;
+    let _ = app_handle.emit("ocr-index-progress", status_mutex.lock().unwrap().clone());
+
+    for (index, (file_id, file_path)) in files.into_iter().enumerate() {
+        if cancel_flag.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let text = match load_ocr_image(file_id, &file_path) {
+            Ok(img) => match engine.lock().unwrap().recognize(&img) {
+                Ok(lines) => Some(lines_to_text(&lines)),
+                Err(e) => {
+                    eprintln!("Failed to recognize text in {}: {}", file_path, e);
+                    None
+                }
+            },
+            Err(e) => {
+                eprintln!("Failed to load {} for text recognition: {}", file_path, e);
+                None
+            }
+        };
+        let stored = match &text {
+            Some(text) => save_file_text(&conn, file_id, text),
+            None => mark_text_failed(&conn, file_id),
+        };
+        if let Err(e) = stored {
+            eprintln!("Failed to store text for file {}: {}", file_id, e);
+        }
+
+        {
+            let mut status = status_mutex.lock().unwrap();
+            status.processed += 1;
+            if text.is_some_and(|text| !text.is_empty()) {
+                status.with_text += 1;
+            }
+        }
+        if (index + 1) % 10 == 0 {
+            let _ = app_handle.emit("ocr-index-progress", status_mutex.lock().unwrap().clone());
+        }
+    }
+    Ok(())
+}
+
+/// Recognize the text of a single image now and store it
+pub fn recognize_file(
+    app_handle: &AppHandle,
+    state: &OcrState,
+    file_id: i64,
+) -> Result<String, String> {
+    let file = t_sqlite::AFile::get_file_info(file_id)?.ok_or("File not found")?;
+    if !matches!(file.file_type, Some(1 | 3)) {
+        return Err("File is not an image".to_string());
+    }
+    let file_path = file.file_path.ok_or("File path not resolved")?;
+    let img = load_ocr_image(file_id, &file_path)?;
+
+    let lines = {
+        let mut engine = state.engine.lock().unwrap();
+        engine.load_models(app_handle)?;
+        engine.recognize(&img)?
+    };
+    let text = lines_to_text(&lines);
+    let conn = t_sqlite::open_conn()?;
+    save_file_text(&conn, file_id, &text)?;
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ctc_decode_drops_blanks_and_repeats() {
+        let charset: Vec<String> = ["", "a", "b", " "].iter().map(|s| s.to_string()).collect();
+        // steps: a, a, blank, a, b, space, b
+        let best = [1, 1, 0, 1, 2, 3, 2];
+        let mut probs = Vec::new();
+        for class in best {
+            let mut step = vec![0.0; 4];
+            step[class] = 0.9;
+            probs.extend(step);
+        }
+        let (text, confidence) = ctc_greedy_decode(&probs, 4, &charset);
+        assert_eq!(text, "aab b");
+        assert!((confidence - 0.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn reading_order_groups_rows() {
+        let b = |x, y| TextBox {
+            x,
+            y,
+            width: 40,
+            height: 20,
+        };
+        let sorted = sort_reading_order(vec![b(100, 52), b(0, 50), b(50, 0), b(0, 2)]);
+        let order: Vec<(u32, u32)> = sorted.iter().map(|b| (b.x, b.y)).collect();
+        assert_eq!(order, vec![(0, 2), (50, 0), (0, 50), (100, 52)]);
+    }
+}
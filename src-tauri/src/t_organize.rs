@@ -0,0 +1,435 @@
+/**
+ * t_organize.rs - Reorganize files into a date/location folder structure
+ *
+ * A template such as `{yyyy}/{mm} - {geo_admin1}` is expanded for every file of
+ * an album, a folder of it, a query or an explicit selection. The dry run reports
+ * how many files move, which folders are created and which targets conflict.
+ * Execution recomputes the plan and moves each file with `move_file`, so catalog
+ * rows, thumbnails, AAE sidecars, Live Photo MOVs and RAW+JPEG companions follow.
+ */
+use crate::t_sqlite::{AFile, AFolder, Album, QueryParams, open_conn};
+use crate::{t_apple_sidecar, t_template, t_utils};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::Emitter;
+
+pub const CONFLICT_EXISTS: &str = "exists"; // a different file already has the target path
+pub const CONFLICT_DUPLICATE: &str = "duplicate"; // several files of the plan share a target path
+
+pub const POLICY_SKIP: &str = "skip"; // leave conflicting files where they are
+
+// ----------------------------------------------------------------------------
+// Types and Structs
+// ----------------------------------------------------------------------------
+
+/// Which files are organized. The target root is always the album root.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OrganizeScope {
+    pub album_id: i64,
+    pub folder_path: Option<String>, // only files under this folder (recursive)
+    pub params: Option<QueryParams>, // only files matching this query
+    pub file_ids: Option<Vec<i64>>,  // only these files
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OrganizeOptions {
+    pub template: String,
+    pub conflict_policy: String, // "keep_both", "replace" or POLICY_SKIP
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizePlanItem {
+    pub file_id: i64,
+    pub file_path: String,
+    pub target_folder: String, // absolute path
+    pub conflict: Option<String>,
+    #[serde(skip)]
+    pub companion_targets: Vec<PathBuf>, // where its companions and sidecars land
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizeFolder {
+    pub relative_path: String,
+    pub count: usize,
+    pub is_new: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizePlan {
+    pub total: usize,
+    pub unchanged: usize,             // already in their target folder
+    pub other_album: usize,           // outside the album, not moved
+    pub items: Vec<OrganizePlanItem>, // files that move
+    pub folders: Vec<OrganizeFolder>, // target folders with their incoming file counts
+    pub conflicts: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizeStatus {
+    pub state: String, // "running", "idle", "finished", "error"
+    pub processed: u64,
+    pub total: u64,
+    pub moved: u64,
+    pub skipped: u64,
+    pub failed: u64,
+    pub is_running: bool,
+}
+
+impl Default for OrganizeStatus {
+    fn default() -> Self {
+        Self {
+            state: "idle".to_string(),
+            processed: 0,
+            total: 0,
+            moved: 0,
+            skipped: 0,
+            failed: 0,
+            is_running: false,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct OrganizeState {
+    pub is_running: Arc<AtomicBool>,
+    pub cancel_flag: Arc<AtomicBool>,
+    pub status: Arc<Mutex<OrganizeStatus>>,
+}
+
+// ----------------------------------------------------------------------------
+// Planning
+// ----------------------------------------------------------------------------
+
+/// Compute the dry-run plan
+pub fn build_plan(
+    scope: &OrganizeScope,
+    options: &OrganizeOptions,
+) -> Result<OrganizePlan, String> {
+    if options.template.trim().is_empty() {
+        return Err("The folder template is empty.".into());
+    }
+    let album = Album::get_album_by_id(scope.album_id)?;
+    if !t_utils::album_online(&album) {
+        return Err("The album is offline.".into());
+    }
+
+    // Live Photo MOVs and RAW+JPEG companions move with their primary file,
+    // so a scope that matched only the companion organizes its primary
+    let primary_ids = get_companion_primary_ids()?;
+    let mut seen_ids = HashSet::new();
+    let file_ids: Vec<i64> = get_scope_file_ids(scope)?
+        .into_iter()
+        .map(|file_id| primary_ids.get(&file_id).copied().unwrap_or(file_id))
+        .filter(|file_id| seen_ids.insert(*file_id))
+        .collect();
+    let files = AFile::get_files_by_ids(&file_ids)?;
+
+    let mut plan = OrganizePlan {
+        total: 0,
+        unchanged: 0,
+        other_album: 0,
+        items: Vec::new(),
+        folders: Vec::new(),
+        conflicts: 0,
+    };
+    let mut folder_counts: BTreeMap<String, usize> = BTreeMap::new();
+    for file in &files {
+        let Some(file_id) = file.id else {
+            continue;
+        };
+        plan.total += 1;
+        if file.album_id != Some(scope.album_id) {
+            plan.other_album += 1;
+            continue;
+        }
+        let Some(file_path) = file.file_path.clone() else {
+            continue;
+        };
+
+        let relative = expand_template(&options.template, file);
+        let target_folder = Path::new(&album.path).join(&relative);
+        let current_folder = Path::new(&file_path).parent().unwrap_or(Path::new(""));
+        if path_key(current_folder) == path_key(&target_folder) {
+            plan.unchanged += 1;
+            continue;
+        }
+
+        let name = Path::new(&file_path).file_name().unwrap_or_default();
+        let companion_targets = t_apple_sidecar::group_component_targets(
+            Some(file_id),
+            &file_path,
+            &target_folder.join(name),
+        )?;
+        *folder_counts
+            .entry(relative.to_string_lossy().into_owned())
+            .or_default() += 1;
+        plan.items.push(OrganizePlanItem {
+            file_id,
+            file_path,
+            target_folder: target_folder.to_string_lossy().into_owned(),
+            conflict: None,
+            companion_targets,
+        });
+    }
+
+    mark_conflicts(&mut plan.items);
+    plan.conflicts = plan
+        .items
+        .iter()
+        .filter(|item| item.conflict.is_some())
+        .count();
+    plan.folders = folder_counts
+        .into_iter()
+        .map(|(relative_path, count)| OrganizeFolder {
+            is_new: !Path::new(&album.path).join(&relative_path).is_dir(),
+            relative_path,
+            count,
+        })
+        .collect();
+    Ok(plan)
+}
+
+fn get_scope_file_ids(scope: &OrganizeScope) -> Result<Vec<i64>, String> {
+    if let Some(file_ids) = &scope.file_ids {
+        return Ok(file_ids.clone());
+    }
+    if let Some(params) = &scope.params {
+        return AFile::get_query_file_ids(params);
+    }
+
+    let conn = open_conn()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT a.id, b.path FROM afiles a JOIN afolders b ON a.folder_id = b.id
+            WHERE b.album_id = ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![scope.album_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| e.to_string())?;
+    let folder = scope.folder_path.as_deref().map(Path::new);
+    let mut file_ids = Vec::new();
+    for row in rows {
+        let (file_id, folder_path) = row.map_err(|e| e.to_string())?;
+        if folder.is_none_or(|folder| Path::new(&folder_path).starts_with(folder)) {
+            file_ids.push(file_id);
+        }
+    }
+    Ok(file_ids)
+}
+
+/// Companion file id -> the primary file it belongs to
+fn get_companion_primary_ids() -> Result<HashMap<i64, i64>, String> {
+    let conn = open_conn()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT live_photo_video_id, MIN(id) FROM afiles
+            WHERE live_photo_video_id IS NOT NULL AND live_photo_video_id > 0
+            GROUP BY live_photo_video_id",
+        )
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(ids)
+}
+
+/// Flag targets that already exist or are claimed by several files of the plan,
+/// counting the targets of the companions that move along
+fn mark_conflicts(items: &mut [OrganizePlanItem]) {
+    let targets_of = |item: &OrganizePlanItem| {
+        let name = Path::new(&item.file_path).file_name().unwrap_or_default();
+        let mut targets = vec![Path::new(&item.target_folder).join(name)];
+        targets.extend(item.companion_targets.iter().cloned());
+        targets
+    };
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for item in items.iter() {
+        for target in targets_of(item) {
+            *counts.entry(path_key(&target)).or_default() += 1;
+        }
+    }
+    for item in items.iter_mut() {
+        let targets = targets_of(item);
+        if targets
+            .iter()
+            .any(|target| counts.get(&path_key(target)).copied().unwrap_or(0) > 1)
+        {
+            item.conflict = Some(CONFLICT_DUPLICATE.to_string());
+        } else if targets.iter().any(|target| target.exists()) {
+            item.conflict = Some(CONFLICT_EXISTS.to_string());
+        }
+    }
+}
+
+/// Expand a folder template for one file: the date tokens of
+/// `t_template::date_token`, {geo}, {geo_admin1}, {geo_admin2}, {geo_cc}, {make}
+/// and {camera}. Segments are trimmed of dangling separators, so
+/// `{mm} - {geo_admin1}` becomes `07` for a file without location, and empty
+/// segments are dropped.
+pub fn expand_template(template: &str, file: &AFile) -> std::path::PathBuf {
+    let text = |value: &Option<String>| Some(value.clone().unwrap_or_default());
+    t_template::expand_folder_template(template, |key, arg| match key {
+        "geo_admin1" => text(&file.geo_admin1),
+        "geo_admin2" => text(&file.geo_admin2),
+        "geo_cc" => text(&file.geo_cc),
+        "geo" => text(&file.geo_name),
+        "make" => text(&file.e_make),
+        "camera" => text(&file.e_model),
+        _ => t_template::date_token(key, arg, file.taken_date),
+    })
+}
+
+/// Key for comparing folder paths, case-insensitive where the file system usually is
+fn path_key(path: &Path) -> String {
+    let key = path.to_string_lossy();
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    let key = key.to_lowercase();
+    key.trim_end_matches(['/', '\\']).to_string()
+}
+
+// ----------------------------------------------------------------------------
+// Execution
+// ----------------------------------------------------------------------------
+
+pub fn start_organize(
+    app_handle: tauri::AppHandle,
+    state: &OrganizeState,
+    scope: OrganizeScope,
+    options: OrganizeOptions,
+) -> Result<(), String> {
+    if state
+        .is_running
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return Err("An organize job is already running.".into());
+    }
+    state.cancel_flag.store(false, Ordering::SeqCst);
+
+    let status_clone = state.status.clone();
+    let is_running_clone = state.is_running.clone();
+    let cancel_flag_clone = state.cancel_flag.clone();
+
+    *status_clone.lock().unwrap() = OrganizeStatus {
+        state: "running".to_string(),
+        is_running: true,
+        ..OrganizeStatus::default()
+    };
+
+    std::thread::spawn(move || {
+        let result = run_organize(
+            &app_handle,
+            &status_clone,
+            &cancel_flag_clone,
+            &scope,
+            &options,
+        );
+
+        let mut final_status = status_clone.lock().unwrap();
+        match result {
+            Ok(_) => {
+                if cancel_flag_clone.load(Ordering::SeqCst) {
+                    final_status.state = "idle".to_string();
+                } else {
+                    final_status.state = "finished".to_string();
+                }
+            }
+            Err(e) => {
+                eprintln!("Organize error: {}", e);
+                final_status.state = "error".to_string();
+            }
+        }
+
+        is_running_clone.store(false, Ordering::SeqCst);
+        final_status.is_running = false;
+        let _ = app_handle.emit("organize-progress", final_status.clone());
+    });
+
+    Ok(())
+}
+
+fn run_organize(
+    app_handle: &tauri::AppHandle,
+    status: &Arc<Mutex<OrganizeStatus>>,
+    cancel_flag: &Arc<AtomicBool>,
+    scope: &OrganizeScope,
+    options: &OrganizeOptions,
+) -> Result<(), String> {
+    let plan = build_plan(scope, options)?;
+    let skip_conflicts = options.conflict_policy == POLICY_SKIP;
+
+    status.lock().unwrap().total = plan.items.len() as u64;
+    let _ = app_handle.emit("organize-progress", status.lock().unwrap().clone());
+
+    let mut folder_ids: HashMap<String, i64> = HashMap::new();
+    for item in &plan.items {
+        if cancel_flag.load(Ordering::SeqCst) {
+            break;
+        }
+
+        if skip_conflicts && item.conflict.is_some() {
+            status.lock().unwrap().skipped += 1;
+        } else {
+            // files of the same run never replace each other
+            let conflict_policy = if item.conflict.as_deref() == Some(CONFLICT_DUPLICATE) {
+                "keep_both"
+            } else {
+                options.conflict_policy.as_str()
+            };
+            match move_item(item, scope.album_id, conflict_policy, &mut folder_ids) {
+                Ok(_) => status.lock().unwrap().moved += 1,
+                Err(e) => {
+                    eprintln!("Failed to organize '{}': {}", item.file_path, e);
+                    status.lock().unwrap().failed += 1;
+                }
+            }
+        }
+
+        let mut s = status.lock().unwrap();
+        s.processed += 1;
+        let _ = app_handle.emit("organize-progress", s.clone());
+    }
+    Ok(())
+}
+
+fn move_item(
+    item: &OrganizePlanItem,
+    album_id: i64,
+    conflict_policy: &str,
+    folder_ids: &mut HashMap<String, i64>,
+) -> Result<String, String> {
+    let folder_id = match folder_ids.get(&item.target_folder) {
+        Some(folder_id) => *folder_id,
+        None => {
+            fs::create_dir_all(&item.target_folder)
+                .map_err(|e| format!("Failed to create folder: {}", e))?;
+            let folder = AFolder::add_to_db(album_id, &item.target_folder)?;
+            let folder_id = folder.id.ok_or("Folder has no id")?;
+            folder_ids.insert(item.target_folder.clone(), folder_id);
+            folder_id
+        }
+    };
+    t_utils::move_file_group(
+        item.file_id,
+        &item.file_path,
+        folder_id,
+        &item.target_folder,
+        t_utils::FileConflictPolicy::from_str(conflict_policy),
+    )
+}
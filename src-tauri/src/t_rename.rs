@@ -0,0 +1,575 @@
+/**
+ * t_rename.rs - Template-based batch rename
+ *
+ * A template such as `{yyyy}{mm}{dd}_{seq:4} {camera}` is expanded for every
+ * selected file. The plan is previewed with conflicts before anything is touched,
+ * and Live Photo MOVs, RAW+JPEG companions and AAE sidecars follow their primary
+ * file through the same planning used by single renames. Files are renamed in two
+ * phases (to a temporary name, then to the final name) so swaps and chains within
+ * one batch work, and every batch is recorded so it can be undone.
+ */
+use crate::t_apple_sidecar::build_apple_sidecar_rename_plan;
+use crate::t_sqlite::{AFile, open_conn};
+use crate::{t_template, t_utils};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const CONFLICT_EXISTS: &str = "exists"; // a file outside the batch already has the name
+pub const CONFLICT_DUPLICATE: &str = "duplicate"; // two files of the batch get the same name
+pub const CONFLICT_INVALID: &str = "invalid"; // the template expands to an empty name
+pub const CONFLICT_MISSING: &str = "missing"; // the file is gone from disk
+
+const DEFAULT_SEQ_DIGITS: usize = 3;
+const MAX_SEQ_DIGITS: usize = 9;
+
+// ----------------------------------------------------------------------------
+// Types and Structs
+// ----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RenameOptions {
+    pub template: String,
+    pub seq_start: i64,
+    pub lowercase_ext: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameCompanion {
+    pub old_name: String,
+    pub new_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenamePreviewItem {
+    pub file_id: i64,
+    pub folder_path: String,
+    pub old_name: String,
+    pub new_name: String,
+    pub companions: Vec<RenameCompanion>, // Live Photo MOV, RAW+JPEG companion, AAE sidecars
+    pub conflict: Option<String>,         // one of the CONFLICT_* values
+    pub unchanged: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameResult {
+    pub batch_id: Option<i64>, // None when nothing changed
+    pub renamed: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameBatch {
+    pub batch_id: i64,
+    pub created_at: i64,
+    pub count: i64,
+}
+
+/// One path change of a batch
+struct RenameMove {
+    file_id: Option<i64>,
+    old_path: PathBuf,
+    new_path: PathBuf,
+}
+
+/// Values a template can reference for one file
+struct TokenValues {
+    name: String,
+    taken_date: Option<i64>,
+    make: String,
+    camera: String,
+    lens: String,
+    persons: String,
+    geo: String,
+    rating: i32,
+}
+
+// ----------------------------------------------------------------------------
+// Planning
+// ----------------------------------------------------------------------------
+
+/// Build the rename plan for the files in the given order.
+/// Companions of selected files are renamed with their primary file, so
+/// selecting both sides of a pair does not rename the companion twice.
+pub fn preview(
+    file_ids: &[i64],
+    options: &RenameOptions,
+) -> Result<Vec<RenamePreviewItem>, String> {
+    if options.template.trim().is_empty() {
+        return Err("The rename template is empty.".into());
+    }
+
+    let mut files = Vec::new();
+    for file_id in file_ids {
+        if let Some(file) = AFile::get_file_info(*file_id)? {
+            files.push(file);
+        }
+    }
+    let companion_ids: HashSet<i64> = files
+        .iter()
+        .filter_map(|file| file.live_photo_video_id.filter(|id| *id > 0))
+        .collect();
+    files.retain(|file| !file.id.is_some_and(|id| companion_ids.contains(&id)));
+
+    let conn = open_conn()?;
+    let mut items = Vec::new();
+    let mut seq = options.seq_start;
+    for file in &files {
+        let file_id = file.id.unwrap_or(0);
+        let file_path = file.file_path.clone().unwrap_or_default();
+        let path = Path::new(&file_path);
+        let folder_path = path
+            .parent()
+            .map(|parent| parent.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let values = TokenValues {
+            name: path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            taken_date: file.taken_date,
+            make: file.e_make.clone().unwrap_or_default(),
+            camera: file.e_model.clone().unwrap_or_default(),
+            lens: file.e_lens_model.clone().unwrap_or_default(),
+            persons: get_person_names(&conn, file_id).join("-"),
+            geo: file.geo_name.clone().unwrap_or_default(),
+            rating: file.rating.unwrap_or(0),
+        };
+        let stem = t_template::sanitize_name(&expand_template(&options.template, &values, seq));
+        seq += 1;
+
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().into_owned())
+            .map(|ext| {
+                if options.lowercase_ext {
+                    ext.to_lowercase()
+                } else {
+                    ext
+                }
+            });
+        let new_name = match &ext {
+            Some(ext) if !stem.is_empty() => format!("{}.{}", stem, ext),
+            _ => stem.clone(),
+        };
+
+        let mut item = RenamePreviewItem {
+            file_id,
+            folder_path,
+            old_name: file.name.clone(),
+            new_name: new_name.clone(),
+            companions: Vec::new(),
+            conflict: None,
+            unchanged: new_name == file.name,
+        };
+        if stem.is_empty() {
+            item.conflict = Some(CONFLICT_INVALID.to_string());
+        } else if !path.exists() {
+            item.conflict = Some(CONFLICT_MISSING.to_string());
+        } else if !item.unchanged {
+            for plan in build_apple_sidecar_rename_plan(file_id, &file_path, &new_name)? {
+                item.companions.push(RenameCompanion {
+                    old_name: file_name_of(&plan.old_path),
+                    new_name: plan.new_name,
+                });
+            }
+        }
+        items.push(item);
+    }
+
+    mark_conflicts(&mut items);
+    Ok(items)
+}
+
+/// Flag targets that collide with each other or with files outside the batch.
+fn mark_conflicts(items: &mut [RenamePreviewItem]) {
+    // every path that is renamed away by the batch is free for another item
+    let mut sources = HashSet::new();
+    for item in items.iter().filter(|item| item.conflict.is_none()) {
+        sources.insert(path_key(&item.folder_path, &item.old_name));
+        for companion in &item.companions {
+            sources.insert(path_key(&item.folder_path, &companion.old_name));
+        }
+    }
+
+    let mut targets: HashMap<String, usize> = HashMap::new();
+    for item in items.iter().filter(|item| item.conflict.is_none()) {
+        for name in std::iter::once(&item.new_name)
+            .chain(item.companions.iter().map(|companion| &companion.new_name))
+        {
+            *targets
+                .entry(path_key(&item.folder_path, name))
+                .or_default() += 1;
+        }
+    }
+
+    for item in items
+        .iter_mut()
+        .filter(|item| item.conflict.is_none() && !item.unchanged)
+    {
+        let names: Vec<&String> = std::iter::once(&item.new_name)
+            .chain(item.companions.iter().map(|companion| &companion.new_name))
+            .collect();
+        for name in names {
+            let key = path_key(&item.folder_path, name);
+            if targets.get(&key).copied().unwrap_or(0) > 1 {
+                item.conflict = Some(CONFLICT_DUPLICATE.to_string());
+                break;
+            }
+            if !sources.contains(&key) && Path::new(&item.folder_path).join(name).exists() {
+                item.conflict = Some(CONFLICT_EXISTS.to_string());
+                break;
+            }
+        }
+    }
+}
+
+/// Expand the tokens of a template for one file: {name}, the date tokens of
+/// `t_template::date_token`, {make}, {camera}, {lens}, {person}, {geo}, {rating},
+/// {seq} and {seq:DIGITS}. Unknown tokens are kept as typed.
+fn expand_template(template: &str, values: &TokenValues, seq: i64) -> String {
+    t_template::expand_tokens(template, |key, arg| match key {
+        "name" => Some(values.name.clone()),
+        "make" => Some(values.make.clone()),
+        "camera" => Some(values.camera.clone()),
+        "lens" => Some(values.lens.clone()),
+        "person" => Some(values.persons.clone()),
+        "geo" => Some(values.geo.clone()),
+        "rating" => Some(values.rating.to_string()),
+        "seq" => {
+            let digits = arg
+                .and_then(|arg| arg.parse::<usize>().ok())
+                .unwrap_or(DEFAULT_SEQ_DIGITS)
+                .min(MAX_SEQ_DIGITS);
+            Some(format!("{:0width$}", seq, width = digits))
+        }
+        _ => t_template::date_token(key, arg, values.taken_date),
+    })
+}
+
+fn get_person_names(conn: &rusqlite::Connection, file_id: i64) -> Vec<String> {
+    let Ok(mut stmt) = conn.prepare(
+        "SELECT DISTINCT p.name FROM faces f JOIN persons p ON f.person_id = p.id
+        WHERE f.file_id = ?1 AND p.name IS NOT NULL AND p.name != ''
+        ORDER BY p.name",
+    ) else {
+        return Vec::new();
+    };
+    stmt.query_map(params![file_id], |row| row.get::<_, String>(0))
+        .map(|rows| rows.filter_map(Result::ok).collect())
+        .unwrap_or_default()
+}
+
+fn path_key(folder_path: &str, name: &str) -> String {
+    // case-insensitive file systems (macOS, Windows) treat IMG.JPG and img.jpg as one file
+    Path::new(folder_path)
+        .join(name)
+        .to_string_lossy()
+        .to_lowercase()
+}
+
+fn file_name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// ----------------------------------------------------------------------------
+// Applying and undoing
+// ----------------------------------------------------------------------------
+
+/// Rename the files as previewed. The plan is rebuilt here and refused if it
+/// still has conflicts.
+pub fn apply(file_ids: &[i64], options: &RenameOptions) -> Result<RenameResult, String> {
+    let items = preview(file_ids, options)?;
+    if items.iter().any(|item| item.conflict.is_some()) {
+        return Err("Resolve the name conflicts before renaming.".into());
+    }
+
+    let mut moves = Vec::new();
+    for item in items.iter().filter(|item| !item.unchanged) {
+        let old_path = Path::new(&item.folder_path).join(&item.old_name);
+        let old_path_str = old_path.to_string_lossy().into_owned();
+        for plan in build_apple_sidecar_rename_plan(item.file_id, &old_path_str, &item.new_name)? {
+            moves.push(RenameMove {
+                file_id: plan.file_id,
+                old_path: plan.old_path,
+                new_path: plan.new_path,
+            });
+        }
+        moves.push(RenameMove {
+            file_id: Some(item.file_id),
+            new_path: Path::new(&item.folder_path).join(&item.new_name),
+            old_path,
+        });
+    }
+    if moves.is_empty() {
+        return Ok(RenameResult {
+            batch_id: None,
+            renamed: 0,
+        });
+    }
+
+    execute_moves(&moves)?;
+    let batch_id = save_history(&moves)?;
+    Ok(RenameResult {
+        batch_id: Some(batch_id),
+        renamed: items.iter().filter(|item| !item.unchanged).count(),
+    })
+}
+
+/// Restore the names of a batch (the latest one when no id is given)
+pub fn undo(batch_id: Option<i64>) -> Result<usize, String> {
+    let conn = open_conn()?;
+    let batch_id = match batch_id {
+        Some(batch_id) => batch_id,
+        None => conn
+            .query_row("SELECT MAX(batch_id) FROM rename_history", [], |row| {
+                row.get::<_, Option<i64>>(0)
+            })
+            .map_err(|e| e.to_string())?
+            .ok_or("There is no rename to undo.")?,
+    };
+
+    let mut stmt = conn
+        .prepare("SELECT file_id, old_path, new_path FROM rename_history WHERE batch_id = ?1")
+        .map_err(|e| e.to_string())?;
+    let moves = stmt
+        .query_map(params![batch_id], |row| {
+            Ok(RenameMove {
+                file_id: row.get(0)?,
+                old_path: PathBuf::from(row.get::<_, String>(2)?),
+                new_path: PathBuf::from(row.get::<_, String>(1)?),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    if moves.is_empty() {
+        return Err("The rename batch was not found.".into());
+    }
+
+    let sources: HashSet<String> = moves
+        .iter()
+        .map(|item| item.old_path.to_string_lossy().to_lowercase())
+        .collect();
+    for item in &moves {
+        if !item.old_path.exists() {
+            return Err(format!(
+                "File was moved or deleted since the rename: {}",
+                item.old_path.display()
+            ));
+        }
+        if item.new_path.exists()
+            && !sources.contains(&item.new_path.to_string_lossy().to_lowercase())
+        {
+            return Err(format!(
+                "The original name is taken: {}",
+                item.new_path.display()
+            ));
+        }
+    }
+
+    execute_moves(&moves)?;
+    conn.execute(
+        "DELETE FROM rename_history WHERE batch_id = ?1",
+        params![batch_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(moves.iter().filter(|item| item.file_id.is_some()).count())
+}
+
+/// Recent batches that can still be undone, newest first
+pub fn list_batches(limit: i64) -> Result<Vec<RenameBatch>, String> {
+    let conn = open_conn()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT batch_id, MAX(created_at), COUNT(file_id) FROM rename_history
+            GROUP BY batch_id ORDER BY batch_id DESC LIMIT ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let batches = stmt
+        .query_map(params![limit], |row| {
+            Ok(RenameBatch {
+                batch_id: row.get(0)?,
+                created_at: row.get(1)?,
+                count: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(batches)
+}
+
+/// Rename every path to a temporary name first, then to its target, so names
+/// freed by one file can be taken by another. Any failure rolls back the files
+/// already renamed; the database is updated only after all files succeeded.
+fn execute_moves(moves: &[RenameMove]) -> Result<(), String> {
+    let stamp = now_secs();
+    let mut staged: Vec<(usize, PathBuf)> = Vec::new();
+    for (index, item) in moves.iter().enumerate() {
+        let temp_path = item
+            .old_path
+            .with_file_name(format!(".rename-{}-{}.tmp", stamp, index));
+        if let Err(e) = fs::rename(&item.old_path, &temp_path) {
+            rollback_staged(moves, &staged, &[]);
+            return Err(format!(
+                "Failed to rename '{}': {}",
+                item.old_path.display(),
+                e
+            ));
+        }
+        staged.push((index, temp_path));
+    }
+
+    let mut finished: Vec<usize> = Vec::new();
+    for (index, temp_path) in &staged {
+        let item = &moves[*index];
+        if let Err(e) = fs::rename(temp_path, &item.new_path) {
+            rollback_staged(moves, &staged, &finished);
+            return Err(format!(
+                "Failed to rename '{}': {}",
+                item.old_path.display(),
+                e
+            ));
+        }
+        finished.push(*index);
+    }
+
+    let db_updates = moves
+        .iter()
+        .filter_map(|item| {
+            let name = file_name_of(&item.new_path);
+            item.file_id.map(|file_id| {
+                let name_pinyin = Some(t_utils::natural_sort_key(&name.to_lowercase()));
+                (file_id, name, name_pinyin)
+            })
+        })
+        .collect::<Vec<_>>();
+    if let Err(e) = AFile::batch_update_names(&db_updates) {
+        // put the files back so disk and database stay in step
+        let reversed = moves
+            .iter()
+            .map(|item| RenameMove {
+                file_id: None,
+                old_path: item.new_path.clone(),
+                new_path: item.old_path.clone(),
+            })
+            .collect::<Vec<_>>();
+        if let Err(rollback_error) = execute_moves(&reversed) {
+            eprintln!("Failed to roll back batch rename: {}", rollback_error);
+        }
+        return Err(format!("Error while renaming files in DB: {}", e));
+    }
+    Ok(())
+}
+
+fn rollback_staged(moves: &[RenameMove], staged: &[(usize, PathBuf)], finished: &[usize]) {
+    for (index, temp_path) in staged.iter().rev() {
+        let item = &moves[*index];
+        let current = if finished.contains(index) {
+            &item.new_path
+        } else {
+            temp_path
+        };
+        if let Err(e) = fs::rename(current, &item.old_path) {
+            eprintln!(
+                "Failed to roll back rename '{}' to '{}': {}",
+                current.display(),
+                item.old_path.display(),
+                e
+            );
+        }
+    }
+}
+
+fn save_history(moves: &[RenameMove]) -> Result<i64, String> {
+    let mut conn = open_conn()?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let batch_id: i64 = tx
+        .query_row(
+            "SELECT COALESCE(MAX(batch_id), 0) + 1 FROM rename_history",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let created_at = now_secs();
+    for item in moves {
+        tx.execute(
+            "INSERT INTO rename_history (batch_id, file_id, old_path, new_path, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                batch_id,
+                item.file_id,
+                item.old_path.to_string_lossy(),
+                item.new_path.to_string_lossy(),
+                created_at
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(batch_id)
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+
+    fn values() -> TokenValues {
+        TokenValues {
+            name: "IMG_0001".to_string(),
+            taken_date: Local
+                .with_ymd_and_hms(2024, 7, 5, 9, 8, 7)
+                .single()
+                .map(|date| date.timestamp()),
+            make: "Canon".to_string(),
+            camera: "EOS R5".to_string(),
+            lens: String::new(),
+            persons: "Ann-Bob".to_string(),
+            geo: "Kyoto".to_string(),
+            rating: 4,
+        }
+    }
+
+    #[test]
+    fn expands_date_sequence_and_metadata_tokens() {
+        let result = expand_template("{yyyy}{mm}{dd}_{hh}{mi}{ss}_{seq:4} {camera}", &values(), 7);
+        assert_eq!(result, "20240705_090807_0007 EOS R5");
+        assert_eq!(
+            expand_template("{name}-{seq}", &values(), 12),
+            "IMG_0001-012"
+        );
+        assert_eq!(
+            expand_template("{date:%y-%m} {person} {geo} {rating}", &values(), 1),
+            "24-07 Ann-Bob Kyoto 4"
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_tokens_and_rejects_bad_date_formats() {
+        assert_eq!(
+            expand_template("{foo}_{name}", &values(), 1),
+            "{foo}_IMG_0001"
+        );
+        assert_eq!(expand_template("{date:%Q}", &values(), 1), "{date:%Q}");
+        assert_eq!(expand_template("{name", &values(), 1), "{name");
+    }
+}
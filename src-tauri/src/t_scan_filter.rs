@@ -0,0 +1,532 @@
+/**
+ * t_scan_filter.rs - Per-album scan filters
+ *
+ * Albums can skip parts of their folder tree with gitignore-style patterns
+ * (`@eaDir/`, `*_edited/`, `/Exports/`, `!keep.jpg`), limit indexing to files
+ * matching include patterns, and ignore files below a minimum size or pixel
+ * dimension. Indexing, folder counts and the mtime sync all consult the same filter.
+ *
+ * Patterns are matched case-insensitively against the path relative to the album
+ * root, with `/` as separator. A pattern without a slash matches a name at any
+ * depth, a trailing slash matches directories only, and the last matching line
+ * wins, so `!` can re-include something an earlier line excluded.
+ */
+use crate::t_image;
+use crate::t_sqlite::open_conn;
+use crate::t_utils;
+use rusqlite::{OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Filter settings as stored for an album and edited in the album dialog.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanFilterSettings {
+    pub exclude_patterns: String, // one gitignore-style pattern per line
+    pub include_patterns: String, // when set, only matching files are indexed
+    pub min_file_size: u64,       // bytes, 0 = no limit
+    pub min_dimension: u32,       // pixels of the longer side, 0 = no limit
+}
+
+impl ScanFilterSettings {
+    pub fn load(album_id: i64) -> Result<Self, String> {
+        let conn = open_conn()?;
+        let settings = conn
+            .query_row(
+                "SELECT exclude_patterns, include_patterns, min_file_size, min_dimension
+                 FROM album_scan_filters WHERE album_id = ?1",
+                params![album_id],
+                |row| {
+                    Ok(Self {
+                        exclude_patterns: row.get(0)?,
+                        include_patterns: row.get(1)?,
+                        min_file_size: row.get::<_, i64>(2)?.max(0) as u64,
+                        min_dimension: row.get::<_, i64>(3)?.max(0) as u32,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        Ok(settings.unwrap_or_default())
+    }
+
+    pub fn save(&self, album_id: i64) -> Result<(), String> {
+        let conn = open_conn()?;
+        conn.execute(
+            "INSERT INTO album_scan_filters (album_id, exclude_patterns, include_patterns, min_file_size, min_dimension)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(album_id) DO UPDATE SET
+                exclude_patterns = excluded.exclude_patterns,
+                include_patterns = excluded.include_patterns,
+                min_file_size = excluded.min_file_size,
+                min_dimension = excluded.min_dimension",
+            params![
+                album_id,
+                self.exclude_patterns,
+                self.include_patterns,
+                self.min_file_size as i64,
+                self.min_dimension as i64,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+struct Pattern {
+    glob: Vec<char>,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool, // matched against the whole relative path instead of a name
+}
+
+impl Pattern {
+    fn parse_lines(text: &str) -> Vec<Self> {
+        text.lines()
+            .filter_map(|line| {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    return None;
+                }
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let (dir_only, line) = match line.strip_suffix('/') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let anchored = line.contains('/');
+                let line = line.trim_start_matches('/');
+                if line.is_empty() {
+                    return None;
+                }
+                Some(Self {
+                    glob: line.to_lowercase().chars().collect(),
+                    negated,
+                    dir_only,
+                    anchored,
+                })
+            })
+            .collect()
+    }
+
+    fn matches(&self, relative_path: &[char], is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            return glob_match(&self.glob, relative_path);
+        }
+        let name_start = relative_path
+            .iter()
+            .rposition(|c| *c == '/')
+            .map_or(0, |slash| slash + 1);
+        glob_match(&self.glob, &relative_path[name_start..])
+    }
+}
+
+/// The last matching pattern decides; None when no pattern matches.
+fn last_match(patterns: &[Pattern], relative_path: &[char], is_dir: bool) -> Option<bool> {
+    patterns
+        .iter()
+        .rev()
+        .find(|pattern| pattern.matches(relative_path, is_dir))
+        .map(|pattern| !pattern.negated)
+}
+
+/// Glob match where `*` and `?` stay within one path segment and `**` spans segments.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+            // `a/**/b` also matches `a/b`
+            if rest.first() == Some(&'/') && glob_match(&rest[1..], text) {
+                return true;
+            }
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        Some('*') => {
+            let rest = &pattern[1..];
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => match text.first() {
+            Some(c) if *c != '/' => glob_match(&pattern[1..], &text[1..]),
+            _ => false,
+        },
+        Some('[') => match (class_match(pattern, text.first()), text.first()) {
+            (Some((true, len)), Some(_)) => glob_match(&pattern[len..], &text[1..]),
+            (Some(_), _) => false,
+            // no closing bracket: a literal '['
+            (None, Some('[')) => glob_match(&pattern[1..], &text[1..]),
+            (None, _) => false,
+        },
+        Some(c) => text.first() == Some(c) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+/// Match a `[...]` class at the start of `pattern` against `c`.
+/// Returns (matched, length of the class) or None if the class is not closed.
+fn class_match(pattern: &[char], c: Option<&char>) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+    let start = i;
+    let mut matched = false;
+    while let Some(&p) = pattern.get(i) {
+        if p == ']' && i > start {
+            let matched = c.is_some_and(|c| *c != '/') && matched != negated;
+            return Some((matched, i + 1));
+        }
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|end| *end != ']') {
+            let end = pattern[i + 2];
+            matched |= c.is_some_and(|c| (p..=end).contains(c));
+            i += 3;
+        } else {
+            matched |= c == Some(&p);
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Stored (size, width, height) of the indexed images in one folder, by file name.
+type StoredDimensions = HashMap<String, (u64, u32, u32)>;
+
+/// An album's scan filter, ready to test paths below its root.
+pub struct ScanFilter {
+    root: PathBuf,
+    excludes: Vec<Pattern>,
+    includes: Vec<Pattern>,
+    min_file_size: u64,
+    min_dimension: u32,
+    stored_dimensions: Mutex<HashMap<PathBuf, StoredDimensions>>, // loaded per folder on first use
+}
+
+impl ScanFilter {
+    pub fn new(root: &str, settings: &ScanFilterSettings) -> Self {
+        Self {
+            root: PathBuf::from(root),
+            excludes: Pattern::parse_lines(&settings.exclude_patterns),
+            includes: Pattern::parse_lines(&settings.include_patterns),
+            min_file_size: settings.min_file_size,
+            min_dimension: settings.min_dimension,
+            stored_dimensions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A filter that lets everything through.
+    pub fn none(root: &str) -> Self {
+        Self::new(root, &ScanFilterSettings::default())
+    }
+
+    /// The album's filter; an unreadable setting behaves like no filter.
+    pub fn for_album(album_id: i64, root: &str) -> Self {
+        match ScanFilterSettings::load(album_id) {
+            Ok(settings) => Self::new(root, &settings),
+            Err(error) => {
+                eprintln!(
+                    "Failed to load scan filter for album {}: {}",
+                    album_id, error
+                );
+                Self::none(root)
+            }
+        }
+    }
+
+    fn relative_path(&self, path: &Path) -> Option<Vec<char>> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        let mut chars = Vec::new();
+        for component in relative.components() {
+            if !chars.is_empty() {
+                chars.push('/');
+            }
+            chars.extend(
+                component
+                    .as_os_str()
+                    .to_string_lossy()
+                    .to_lowercase()
+                    .chars(),
+            );
+        }
+        Some(chars)
+    }
+
+    /// Exclude patterns also apply to everything below an excluded directory.
+    fn excluded_by_patterns(&self, relative_path: &[char], is_dir: bool) -> bool {
+        if self.excludes.is_empty() {
+            return false;
+        }
+        let mut end = 0;
+        while let Some(offset) = relative_path[end..].iter().position(|c| *c == '/') {
+            end += offset;
+            if last_match(&self.excludes, &relative_path[..end], true) == Some(true) {
+                return true;
+            }
+            end += 1;
+        }
+        last_match(&self.excludes, relative_path, is_dir) == Some(true)
+    }
+
+    /// Whether a directory below the root (and everything in it) is skipped.
+    pub fn excludes_dir(&self, path: &Path) -> bool {
+        match self.relative_path(path) {
+            Some(relative_path) if !relative_path.is_empty() => {
+                self.excluded_by_patterns(&relative_path, true)
+            }
+            _ => false,
+        }
+    }
+
+    fn excludes_file_path(&self, path: &Path) -> bool {
+        let Some(relative_path) = self.relative_path(path) else {
+            return false;
+        };
+        if self.excluded_by_patterns(&relative_path, false) {
+            return true;
+        }
+        !self.includes.is_empty() && last_match(&self.includes, &relative_path, false) != Some(true)
+    }
+
+    fn excludes_dimensions(&self, width: u32, height: u32) -> bool {
+        self.min_dimension > 0 && width.max(height) < self.min_dimension
+    }
+
+    /// Dimensions stored for an indexed image whose size has not changed since.
+    fn stored_dimensions(&self, path: &Path, size: u64) -> Option<(u32, u32)> {
+        let folder = path.parent()?;
+        let name = path.file_name()?.to_str()?;
+        let mut cache = self.stored_dimensions.lock().ok()?;
+        let files = cache
+            .entry(folder.to_path_buf())
+            .or_insert_with(|| load_stored_dimensions(&folder.to_string_lossy()));
+        files
+            .get(name)
+            .filter(|(stored_size, _, _)| *stored_size == size)
+            .map(|(_, width, height)| (*width, *height))
+    }
+
+    /// Whether a file is skipped by pattern, size or, for images, pixel dimensions.
+    /// When a minimum is set, dimensions come from the index and are read from
+    /// the file only for new or changed images.
+    pub fn excludes_file(&self, path: &Path, size: u64) -> bool {
+        if self.excludes_file_path(path) || size < self.min_file_size {
+            return true;
+        }
+        if self.min_dimension == 0 {
+            return false;
+        }
+        let path_str = path.to_string_lossy();
+        if !matches!(t_utils::get_file_type(&path_str), Some(1 | 3)) {
+            return false;
+        }
+        self.stored_dimensions(path, size)
+            .or_else(|| t_image::get_image_dimensions(&path_str).ok())
+            .is_some_and(|(width, height)| self.excludes_dimensions(width, height))
+    }
+
+    /// Same as `excludes_file` for an indexed file, using its stored size and dimensions.
+    pub fn excludes_indexed_file(
+        &self,
+        path: &Path,
+        size: u64,
+        file_type: i64,
+        width: Option<u32>,
+        height: Option<u32>,
+    ) -> bool {
+        if self.excludes_file_path(path) || size < self.min_file_size {
+            return true;
+        }
+        match (file_type, width, height) {
+            (1 | 3, Some(width), Some(height)) if width > 0 && height > 0 => {
+                self.excludes_dimensions(width, height)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Load the stored dimensions of a folder's indexed images; an unindexed folder has none.
+fn load_stored_dimensions(folder_path: &str) -> StoredDimensions {
+    let load = || -> Result<StoredDimensions, String> {
+        let conn = open_conn()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT a.name, a.size, a.width, a.height
+                 FROM afiles a
+                 JOIN afolders b ON a.folder_id = b.id
+                 WHERE b.path = ?1 AND a.file_type IN (1, 3) AND a.width > 0 AND a.height > 0",
+            )
+            .map_err(|e| e.to_string())?;
+        let files = stmt
+            .query_map(params![folder_path], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    (
+                        row.get::<_, i64>(1)?.max(0) as u64,
+                        row.get::<_, u32>(2)?,
+                        row.get::<_, u32>(3)?,
+                    ),
+                ))
+            })
+            .map_err(|e| e.to_string())?
+            .filter_map(Result::ok)
+            .collect();
+        Ok(files)
+    };
+    load().unwrap_or_else(|error| {
+        eprintln!(
+            "Failed to load stored dimensions for {}: {}",
+            folder_path, error
+        );
+        StoredDimensions::new()
+    })
+}
+
+/// Indexed rows of an album that its current filter excludes.
+#[derive(Default)]
+pub struct ExcludedRows {
+    pub folder_paths: Vec<String>, // topmost excluded folders
+    pub file_ids: Vec<i64>,        // every excluded file, including those in excluded folders
+}
+
+impl ExcludedRows {
+    pub fn is_empty(&self) -> bool {
+        self.folder_paths.is_empty() && self.file_ids.is_empty()
+    }
+}
+
+impl ScanFilter {
+    /// Find the album's indexed folders and files this filter would not index now.
+    pub fn excluded_rows(&self, album_id: i64) -> Result<ExcludedRows, String> {
+        let conn = open_conn()?;
+        let mut excluded = ExcludedRows::default();
+
+        let mut stmt = conn
+            .prepare("SELECT path FROM afolders WHERE album_id = ?1 ORDER BY length(path)")
+            .map_err(|e| e.to_string())?;
+        let folder_paths = stmt
+            .query_map(params![album_id], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?
+            .filter_map(Result::ok);
+        for folder_path in folder_paths {
+            let path = Path::new(&folder_path);
+            let below_excluded = excluded
+                .folder_paths
+                .iter()
+                .any(|excluded_path| path.starts_with(excluded_path));
+            if !below_excluded && self.excludes_dir(path) {
+                excluded.folder_paths.push(folder_path);
+            }
+        }
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT a.id, b.path, a.name, a.size, a.file_type, a.width, a.height
+                 FROM afiles a
+                 JOIN afolders b ON a.folder_id = b.id
+                 WHERE b.album_id = ?1",
+            )
+            .map_err(|e| e.to_string())?;
+        let files = stmt
+            .query_map(params![album_id], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, Option<i64>>(4)?,
+                    row.get::<_, Option<u32>>(5)?,
+                    row.get::<_, Option<u32>>(6)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?
+            .filter_map(Result::ok);
+        for (file_id, folder_path, name, size, file_type, width, height) in files {
+            let in_excluded_folder = excluded
+                .folder_paths
+                .iter()
+                .any(|excluded_path| Path::new(&folder_path).starts_with(excluded_path));
+            let path = Path::new(&folder_path).join(&name);
+            if in_excluded_folder
+                || self.excludes_indexed_file(
+                    &path,
+                    size.max(0) as u64,
+                    file_type.unwrap_or(0),
+                    width,
+                    height,
+                )
+            {
+                excluded.file_ids.push(file_id);
+            }
+        }
+
+        Ok(excluded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(excludes: &str, includes: &str) -> ScanFilter {
+        ScanFilter::new(
+            "/photos",
+            &ScanFilterSettings {
+                exclude_patterns: excludes.to_string(),
+                include_patterns: includes.to_string(),
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn unanchored_patterns_match_names_at_any_depth() {
+        let filter = filter("@eaDir/\n*_edited/\n*.tmp", "");
+        assert!(filter.excludes_dir(Path::new("/photos/2023/@eadir")));
+        assert!(filter.excludes_dir(Path::new("/photos/trip_edited")));
+        assert!(filter.excludes_file_path(Path::new("/photos/a/b/upload.TMP")));
+        assert!(filter.excludes_file_path(Path::new("/photos/trip_edited/a.jpg")));
+        assert!(!filter.excludes_file_path(Path::new("/photos/trip/a.jpg")));
+    }
+
+    #[test]
+    fn anchored_patterns_and_double_star() {
+        let filter = filter("/Exports/**/*.jpg\nlr/previews/", "");
+        assert!(filter.excludes_file_path(Path::new("/photos/exports/a.jpg")));
+        assert!(filter.excludes_file_path(Path::new("/photos/exports/x/y/a.jpg")));
+        assert!(!filter.excludes_file_path(Path::new("/photos/2023/exports/a.jpg")));
+        assert!(filter.excludes_dir(Path::new("/photos/lr/previews")));
+        assert!(!filter.excludes_dir(Path::new("/photos/previews")));
+    }
+
+    #[test]
+    fn later_negation_re_includes() {
+        let filter = filter("*.png\n!cover.png", "");
+        assert!(filter.excludes_file_path(Path::new("/photos/a.png")));
+        assert!(!filter.excludes_file_path(Path::new("/photos/cover.png")));
+    }
+
+    #[test]
+    fn include_patterns_limit_files() {
+        let filter = filter("", "*.[jh][pe]?[gc]\n*.dng");
+        assert!(!filter.excludes_file_path(Path::new("/photos/a.jpeg")));
+        assert!(!filter.excludes_file_path(Path::new("/photos/a.heic")));
+        assert!(!filter.excludes_file_path(Path::new("/photos/a.DNG")));
+        assert!(filter.excludes_file_path(Path::new("/photos/a.mov")));
+        assert!(!filter.excludes_dir(Path::new("/photos/any")));
+    }
+}
@@ -0,0 +1,278 @@
+/**
+ * t_shotwell.rs - Shotwell database reader
+ *
+ * `photo.db` is an SQLite database that keeps absolute file paths in
+ * `PhotoTable` and `VideoTable`. Tags list their members as text in
+ * `TagTable.photo_id_list` ("thumb000000000000002a,video-0000000000000003,"),
+ * and hierarchical tag names start with a slash ("/Places/Paris"). Events
+ * become collections; the flagged state maps to a pick and the rejected
+ * rating (-1) to a reject.
+ */
+use crate::t_catalog_import::{CatalogData, CatalogImage, TAG_PATH_SEPARATOR};
+use chrono::TimeZone;
+use rusqlite::{Connection, OpenFlags};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+const FLAG_TRASH: i64 = 0x04;
+const FLAG_FLAGGED: i64 = 0x10;
+const RATING_REJECTED: i64 = -1;
+
+const PHOTO_ID_PREFIX: &str = "thumb";
+const VIDEO_ID_PREFIX: &str = "video-";
+
+/// A photo or video id, which live in separate tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum SourceId {
+    Photo(i64),
+    Video(i64),
+}
+
+/// Read the photos, videos, tags and events of a Shotwell database
+pub fn read_catalog(catalog_path: &str) -> Result<CatalogData, String> {
+    let conn = Connection::open_with_flags(
+        catalog_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("Failed to open the Shotwell database: {}", e))?;
+
+    let table_count: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'
+            AND name IN ('PhotoTable', 'TagTable', 'EventTable')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(catalog_error)?;
+    if table_count < 3 {
+        return Err("Not a Shotwell database.".to_string());
+    }
+    let has_videos: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'VideoTable'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(catalog_error)?;
+
+    let mut data = CatalogData::default();
+    let mut image_index: HashMap<SourceId, usize> = HashMap::new();
+    let mut image_events: Vec<(usize, i64)> = Vec::new();
+    let mut event_dates: HashMap<i64, i64> = HashMap::new(); // earliest exposure time
+
+    let mut tables: Vec<(&str, fn(i64) -> SourceId)> = vec![("PhotoTable", SourceId::Photo)];
+    if has_videos > 0 {
+        tables.push(("VideoTable", SourceId::Video));
+    }
+    for (table, source_id) in tables {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT id, filename, rating, flags, event_id, exposure_time FROM {}",
+                table
+            ))
+            .map_err(catalog_error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<i64>>(2)?.unwrap_or(0),
+                    row.get::<_, Option<i64>>(3)?.unwrap_or(0),
+                    row.get::<_, Option<i64>>(4)?.unwrap_or(-1),
+                    row.get::<_, Option<i64>>(5)?.unwrap_or(0),
+                ))
+            })
+            .map_err(catalog_error)?;
+        for row in rows {
+            let (id, path, rating, flags, event_id, exposure_time) = row.map_err(catalog_error)?;
+            if flags & FLAG_TRASH != 0 {
+                continue;
+            }
+            let culling_flag = if rating == RATING_REJECTED {
+                Some(2)
+            } else if flags & FLAG_FLAGGED != 0 {
+                Some(1)
+            } else {
+                None
+            };
+            let index = data.images.len();
+            data.images.push(CatalogImage {
+                path,
+                rating: Some(rating as i32).filter(|rating| *rating >= 0),
+                culling_flag,
+                ..CatalogImage::default()
+            });
+            image_index.insert(source_id(id), index);
+            if event_id >= 0 {
+                image_events.push((index, event_id));
+                if exposure_time > 0 {
+                    let date = event_dates.entry(event_id).or_insert(exposure_time);
+                    *date = (*date).min(exposure_time);
+                }
+            }
+        }
+    }
+    data.roots = get_roots(data.images.iter().map(|image| image.path.as_str()));
+
+    // tags
+    let mut stmt = conn
+        .prepare("SELECT name, photo_id_list FROM TagTable")
+        .map_err(catalog_error)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            ))
+        })
+        .map_err(catalog_error)?;
+    for row in rows {
+        let (name, id_list) = row.map_err(catalog_error)?;
+        let name = get_tag_path(&name);
+        if name.is_empty() {
+            continue;
+        }
+        for source_id in parse_id_list(&id_list) {
+            if let Some(index) = image_index.get(&source_id) {
+                let image = &mut data.images[*index];
+                if !image.tags.contains(&name) {
+                    image.tags.push(name.clone());
+                }
+            }
+        }
+    }
+
+    // events; Shotwell shows an unnamed event by the date of its first photo
+    let mut events: HashMap<i64, String> = HashMap::new();
+    let mut stmt = conn
+        .prepare("SELECT id, name FROM EventTable")
+        .map_err(catalog_error)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+        })
+        .map_err(catalog_error)?;
+    for row in rows {
+        let (id, name) = row.map_err(catalog_error)?;
+        let name = name
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .or_else(|| {
+                let timestamp = *event_dates.get(&id)?;
+                let date = chrono::Local.timestamp_opt(timestamp, 0).single()?;
+                Some(date.format("%Y-%m-%d").to_string())
+            });
+        if let Some(name) = name {
+            events.insert(id, name);
+        }
+    }
+    for (index, event_id) in image_events {
+        if let Some(name) = events.get(&event_id) {
+            data.images[index].collections.push(name.clone());
+        }
+    }
+
+    Ok(data)
+}
+
+/// "/Places/Paris" is a nested tag, "Paris" a top-level one
+fn get_tag_path(name: &str) -> String {
+    name.split('/')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(TAG_PATH_SEPARATOR)
+}
+
+/// Parse "thumb000000000000002a,video-0000000000000003," into source ids
+fn parse_id_list(list: &str) -> Vec<SourceId> {
+    list.split(',')
+        .map(str::trim)
+        .filter_map(|item| {
+            if let Some(hex) = item.strip_prefix(PHOTO_ID_PREFIX) {
+                i64::from_str_radix(hex, 16).ok().map(SourceId::Photo)
+            } else if let Some(hex) = item.strip_prefix(VIDEO_ID_PREFIX) {
+                i64::from_str_radix(hex, 16).ok().map(SourceId::Video)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Shotwell has no root folders, so the common folder of the files on each
+/// volume (judged by the first three path components) stands in for one.
+fn get_roots<'a>(paths: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for path in paths {
+        let path = path.replace('\\', "/");
+        let Some((folder, _)) = path.rsplit_once('/') else {
+            continue;
+        };
+        let parts: Vec<&str> = folder.split('/').collect();
+        let key = parts[..parts.len().min(4)].join("/");
+        let common = groups.entry(key).or_default();
+        if common.is_empty() {
+            common.extend(parts.iter().map(|part| part.to_string()));
+        } else {
+            let shared = common
+                .iter()
+                .zip(parts.iter())
+                .take_while(|(a, b)| a.as_str() == **b)
+                .count();
+            common.truncate(shared);
+        }
+    }
+    groups
+        .into_values()
+        .map(|parts| {
+            let root = parts.join("/");
+            if root.is_empty() {
+                "/".to_string()
+            } else {
+                root
+            }
+        })
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+fn catalog_error(e: rusqlite::Error) -> String {
+    let message = e.to_string();
+    if message.contains("locked") {
+        "The database is in use. Close Shotwell and try again.".to_string()
+    } else {
+        format!("Failed to read the Shotwell database: {}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tag_members() {
+        assert_eq!(
+            parse_id_list("thumb000000000000002a,video-0000000000000003,,bogus"),
+            vec![SourceId::Photo(42), SourceId::Video(3)]
+        );
+        assert_eq!(get_tag_path("/Places/Paris"), "Places/Paris");
+        assert_eq!(get_tag_path("Holiday"), "Holiday");
+    }
+
+    #[test]
+    fn finds_common_roots() {
+        let paths = [
+            "/home/me/Pictures/2019/07/a.jpg",
+            "/home/me/Pictures/2020/01/b.jpg",
+            "/media/me/Backup/Old/c.jpg",
+        ];
+        assert_eq!(
+            get_roots(paths.into_iter()),
+            vec![
+                "/home/me/Pictures".to_string(),
+                "/media/me/Backup/Old".to_string()
+            ]
+        );
+    }
+}
@@ -1,9 +1,12 @@
+use crate::t_ai_model;
 use crate::t_dedup;
-use crate::t_sqlite::{AFile, QueryParams};
+use crate::t_sqlite::{self, AFile, QueryParams};
+use crate::t_storage;
 use hnsw_rs::prelude::*;
 use rusqlite::{params, params_from_iter, Connection};
-use serde::Serialize;
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -14,6 +17,41 @@ const MAX_GROUP_SIZE: usize = 64;
 const TOP_K: usize = MAX_GROUP_SIZE - 1;
 const SEARCH_EF: usize = 200;
 const SQL_BATCH_SIZE: usize = 900;
+// Rebuild the persisted index once this share of its points belongs to removed or changed files.
+const MAX_STALE_RATIO: f32 = 0.25;
+const INDEX_META_KEY: &str = "similar_index_meta";
+const MAX_PROPAGATION_ROUNDS: usize = 20;
+
+/// How matching pairs are combined into groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClusterMode {
+    Complete,  // every pair in a group must match
+    Community, // label propagation over the match graph, pruned to tight sets
+}
+
+impl ClusterMode {
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value.unwrap_or("complete") {
+            "complete" => Ok(Self::Complete),
+            "community" => Ok(Self::Community),
+            other => Err(format!("Unknown similar clustering mode: {}", other)),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Complete => "complete",
+            Self::Community => "community",
+        }
+    }
+}
+
+pub struct ScanOptions {
+    pub similarity_threshold: f32,
+    pub time_window: Option<i64>, // max seconds between the taken dates of any two members
+    pub cluster_mode: ClusterMode,
+    pub full_rescan: bool,
+}
 
 #[derive(Clone, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -24,6 +62,7 @@ pub struct SimilarScanStatus {
     pub current: u64,
     pub total: u64,
     pub groups: u64,
+    pub incremental: bool,
     pub is_scanning: bool,
 }
 
@@ -36,6 +75,7 @@ impl Default for SimilarScanStatus {
             current: 0,
             total: 0,
             groups: 0,
+            incremental: false,
             is_scanning: false,
         }
     }
@@ -50,8 +90,22 @@ pub struct SimilarState {
 
 struct VectorFile {
     id: i64,
-    taken_date: i64,
+    taken_date: Option<i64>, // None for undated files, which the time window never excludes
     vector: Vec<f32>,
+    hash: String, // blake3 of the embedding, detects changed vectors between scans
+}
+
+/// Persisted HNSW index state, stored as a library setting.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SimilarIndexMeta {
+    scope_key: String,
+    basename: String,
+    dimension: usize,
+    threshold: f32,
+    time_window: Option<i64>,
+    cluster_mode: String,
+    point_count: usize, // points in the graph, including superseded ones
 }
 
 fn get_db_conn() -> Result<Connection, String> {
@@ -93,7 +147,8 @@ fn load_vectors(conn: &Connection, files: Vec<AFile>) -> Result<Vec<VectorFile>,
             .collect::<Vec<_>>()
             .join(",");
         let sql = format!(
-            "SELECT id, embeds FROM afiles WHERE id IN ({placeholders}) AND embeds IS NOT NULL"
+            "SELECT id, embeds FROM afiles WHERE id IN ({placeholders}) AND embeds IS NOT NULL AND {}",
+            t_ai_model::embeds_model_condition("")
         );
         let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
         let rows = stmt
@@ -110,8 +165,9 @@ fn load_vectors(conn: &Connection, files: Vec<AFile>) -> Result<Vec<VectorFile>,
             if !vector.is_empty() {
                 vectors.push(VectorFile {
                     id,
-                    taken_date: *dates.get(&id).unwrap_or(&0),
+                    taken_date: dates.get(&id).copied().flatten(),
                     vector,
+                    hash: blake3::hash(&blob).to_hex().to_string(),
                 });
             }
         }
@@ -152,11 +208,11 @@ fn filter_dominant_dimension(vectors: Vec<VectorFile>) -> Vec<VectorFile> {
         .unwrap_or_default()
 }
 
-fn eligible_dates(files: Vec<AFile>) -> HashMap<i64, i64> {
+fn eligible_dates(files: Vec<AFile>) -> HashMap<i64, Option<i64>> {
     files
         .into_iter()
         .filter(|file| matches!(file.file_type, Some(1 | 3)))
-        .filter_map(|file| file.id.map(|id| (id, file.taken_date.unwrap_or(0))))
+        .filter_map(|file| file.id.map(|id| (id, file.taken_date)))
         .collect()
 }
 
@@ -169,7 +225,8 @@ fn count_vectors(conn: &Connection, files: Vec<AFile>) -> Result<u64, String> {
             .collect::<Vec<_>>()
             .join(",");
         let sql = format!(
-            "SELECT length(embeds) FROM afiles WHERE id IN ({placeholders}) AND embeds IS NOT NULL"
+            "SELECT length(embeds) FROM afiles WHERE id IN ({placeholders}) AND embeds IS NOT NULL AND {}",
+            t_ai_model::embeds_model_condition("")
         );
         let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
         let lengths = stmt
@@ -204,7 +261,7 @@ pub fn start_scan(
     state: tauri::State<'_, SimilarState>,
     scope_key: String,
     source_version: i64,
-    similarity_threshold: f32,
+    mut options: ScanOptions,
     params: Option<QueryParams>,
     collection_id: Option<i64>,
     file_ids: Option<Vec<i64>>,
@@ -224,8 +281,11 @@ pub fn start_scan(
         current: 0,
         total: 0,
         groups: 0,
+        incremental: false,
         is_scanning: true,
     };
+    options.similarity_threshold = options.similarity_threshold.clamp(0.0, 1.0);
+    options.time_window = options.time_window.filter(|&window| window > 0);
     let status = state.status.clone();
     let running = state.is_scanning.clone();
     let cancel = state.cancel_flag.clone();
@@ -236,7 +296,7 @@ pub fn start_scan(
             &cancel,
             &scope_key,
             source_version,
-            similarity_threshold.clamp(0.0, 1.0),
+            &options,
             params,
             collection_id,
             file_ids,
@@ -283,16 +343,54 @@ fn scan(
     cancel: &Arc<AtomicBool>,
     scope_key: &str,
     source_version: i64,
-    similarity_threshold: f32,
+    options: &ScanOptions,
     params: Option<QueryParams>,
     collection_id: Option<i64>,
     file_ids: Option<Vec<i64>>,
 ) -> Result<(), String> {
     let mut conn = get_db_conn()?;
     let vectors = load_vectors(&conn, resolve_scope(params, collection_id, file_ids)?)?;
+    let by_id: HashMap<i64, usize> = vectors
+        .iter()
+        .enumerate()
+        .map(|(i, file)| (file.id, i))
+        .collect();
+    let dimension = vectors.first().map(|file| file.vector.len()).unwrap_or(0);
+    let dir = index_dir()?;
+
+    // Reuse the persisted index only when it was built for the same scope and settings
+    // and has not accumulated too many superseded points.
+    let scan_id = find_scan(&conn, scope_key)?;
+    let indexed = load_index_entries(&conn)?;
+    let changed: Vec<usize> = vectors
+        .iter()
+        .enumerate()
+        .filter(|(_, file)| indexed.get(&file.id) != Some(&file.hash))
+        .map(|(i, _)| i)
+        .collect();
+    let previous_basename = load_index_meta().map(|meta| meta.basename);
+    let meta = load_index_meta().filter(|meta| {
+        let point_count = meta.point_count + changed.len();
+        let stale = point_count.saturating_sub(vectors.len());
+        meta.scope_key == base_scope(scope_key)
+            && meta.dimension == dimension
+            && (meta.threshold - options.similarity_threshold).abs() < 1e-6
+            && meta.time_window == options.time_window
+            && meta.cluster_mode == options.cluster_mode.as_str()
+            && index_files_exist(&dir, &meta.basename)
+            && (stale as f32) <= point_count as f32 * MAX_STALE_RATIO
+    });
+    let incremental =
+        !options.full_rescan && scan_id.is_some() && meta.is_some() && !vectors.is_empty();
+    let queries: Vec<usize> = if incremental {
+        changed
+    } else {
+        (0..vectors.len()).collect()
+    };
     {
         let mut s = status.lock().unwrap();
-        s.total = vectors.len() as u64;
+        s.incremental = incremental;
+        s.total = queries.len() as u64;
         s.current = 0;
     }
     let _ = app.emit("similar-scan-progress", status.lock().unwrap().clone());
@@ -300,41 +398,254 @@ fn scan(
         return Ok(());
     }
 
+    let basename = meta
+        .as_ref()
+        .map(|meta| meta.basename.clone())
+        .unwrap_or_else(index_basename);
+    let mut reloader = HnswIo::new(&dir, &basename);
+    let hns: Hnsw<f32, DistCosine> = if incremental {
+        reloader
+            .load_hnsw::<f32, DistCosine>()
+            .map_err(|e| format!("Failed to load similar-photo index: {}", e))?
+    } else {
+        Hnsw::new(16, vectors.len().max(1), 16, 200, DistCosine {})
+    };
+    // Points are keyed by file id so the graph stays valid across scans.
+    let data = queries
+        .iter()
+        .map(|&i| (&vectors[i].vector, vectors[i].id as usize))
+        .collect::<Vec<_>>();
+    hns.parallel_insert(&data);
+
     let mut pair_scores: HashMap<(usize, usize), f32> = HashMap::new();
     if vectors.len() > 1 {
-        let hns = Hnsw::<f32, DistCosine>::new(16, vectors.len(), 16, 200, DistCosine {});
-        let data = vectors
-            .iter()
-            .enumerate()
-            .map(|(i, f)| (&f.vector, i))
-            .collect::<Vec<_>>();
-        hns.parallel_insert(&data);
-        for (i, file) in vectors.iter().enumerate() {
+        for (n, &i) in queries.iter().enumerate() {
             if cancel.load(Ordering::SeqCst) {
                 return Ok(());
             }
+            let file = &vectors[i];
             for neighbor in hns.search(&file.vector, TOP_K + 1, SEARCH_EF) {
-                let j = neighbor.d_id;
+                // Skip points of removed files and superseded vectors of changed files.
+                let Some(&j) = by_id.get(&(neighbor.d_id as i64)) else {
+                    continue;
+                };
                 if j == i || vectors[j].vector.len() != file.vector.len() {
                     continue;
                 }
                 let score = cosine(&file.vector, &vectors[j].vector);
-                if score >= similarity_threshold {
+                if score >= options.similarity_threshold
+                    && within_window(file, &vectors[j], options.time_window)
+                {
                     pair_scores.insert((i.min(j), i.max(j)), score);
                 }
             }
-            if i % 100 == 0 {
+            if n % 100 == 0 {
                 let mut s = status.lock().unwrap();
                 s.phase = "finding_matches".into();
-                s.current = i as u64;
+                s.current = n as u64;
                 let _ = app.emit("similar-scan-progress", s.clone());
             }
         }
     }
 
-    // Merge only fully connected sets: a weak A-B-C chain cannot create one group.
-    let mut clusters: Vec<Vec<usize>> = (0..vectors.len()).map(|i| vec![i]).collect();
-    let mut cluster_for: Vec<usize> = (0..vectors.len()).collect();
+    // Existing groups are seeded back in, so keep decisions survive the rescan.
+    let mut seeds: Vec<Vec<usize>> = Vec::new();
+    let mut member_group: HashMap<usize, i64> = HashMap::new();
+    let mut keeps: HashMap<i64, i64> = HashMap::new();
+    let mut existing_ids: Vec<i64> = Vec::new();
+    if let Some(scan_id) = scan_id.filter(|_| incremental) {
+        let requeried: HashSet<usize> = queries.iter().copied().collect();
+        for (group_id, members, keep) in load_existing_groups(&conn, scan_id)? {
+            existing_ids.push(group_id);
+            if let Some(keep) = keep {
+                keeps.insert(group_id, keep);
+            }
+            let members: Vec<usize> = members
+                .iter()
+                .filter_map(|id| by_id.get(id).copied())
+                .filter(|i| !requeried.contains(i))
+                .collect();
+            for (n, &a) in members.iter().enumerate() {
+                member_group.insert(a, group_id);
+                for &b in &members[n + 1..] {
+                    let score = cosine(&vectors[a].vector, &vectors[b].vector);
+                    pair_scores.insert((a.min(b), a.max(b)), score);
+                }
+            }
+            seeds.push(members);
+        }
+    }
+
+    let clusters = match options.cluster_mode {
+        ClusterMode::Complete => complete_linkage(vectors.len(), &seeds, &pair_scores, options),
+        ClusterMode::Community => {
+            community_clusters(vectors.len(), &seeds, &pair_scores, &vectors, options)
+        }
+    };
+    if cancel.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    // Persist the graph before the groups, so a failed dump leaves the previous scan intact.
+    let basename = if vectors.is_empty() {
+        None
+    } else {
+        Some(
+            hns.file_dump(&dir, &basename)
+                .map_err(|e| format!("Failed to save similar-photo index: {}", e))?,
+        )
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    // Each cluster continues the existing group most of its members came from.
+    let mut continued = HashSet::new();
+    let mut groups: Vec<(Vec<usize>, Option<i64>)> = Vec::new();
+    for group in clusters.into_iter().filter(|group| group.len() > 1) {
+        let mut counts: HashMap<i64, usize> = HashMap::new();
+        for member in &group {
+            if let Some(&group_id) = member_group.get(member) {
+                *counts.entry(group_id).or_insert(0) += 1;
+            }
+        }
+        let origin = counts
+            .into_iter()
+            .filter(|(group_id, _)| !continued.contains(group_id))
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+            .map(|(group_id, _)| group_id);
+        continued.extend(origin);
+        groups.push((group, origin));
+    }
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let scan_id = match scan_id.filter(|_| incremental) {
+        Some(scan_id) => {
+            tx.execute(
+                "UPDATE similarity_scans SET scope_key=?2,source_version=?3,status='finished',file_count=?4,group_count=?5,completed_at=?6 WHERE id=?1",
+                params![scan_id, scope_key, source_version, vectors.len() as i64, groups.len() as i64, now],
+            )
+            .map_err(|e| e.to_string())?;
+            scan_id
+        }
+        None => {
+            tx.execute("DELETE FROM similarity_scans", [])
+                .map_err(|e| e.to_string())?;
+            tx.execute(
+                "INSERT INTO similarity_scans(scope_key,source_version,status,file_count,group_count,created_at,completed_at) VALUES(?1,?2,'finished',?3,?4,?5,?5)",
+                params![scope_key, source_version, vectors.len() as i64, groups.len() as i64, now],
+            )
+            .map_err(|e| e.to_string())?;
+            tx.last_insert_rowid()
+        }
+    };
+    for (group, existing) in &groups {
+        let keep = existing.and_then(|id| keeps.get(&id).copied());
+        write_group(&tx, scan_id, *existing, keep, group, &vectors, &pair_scores)?;
+    }
+    for group_id in existing_ids {
+        if !continued.contains(&group_id) {
+            tx.execute(
+                "DELETE FROM similarity_groups WHERE id=?1",
+                params![group_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let saved_basename = basename.clone();
+    if let Some(basename) = basename {
+        if !incremental {
+            tx.execute("DELETE FROM similarity_index_entries", [])
+                .map_err(|e| e.to_string())?;
+        }
+        for &i in &queries {
+            tx.execute(
+                "INSERT INTO similarity_index_entries(file_id,vector_hash) VALUES(?1,?2)
+                 ON CONFLICT(file_id) DO UPDATE SET vector_hash=excluded.vector_hash",
+                params![vectors[i].id, vectors[i].hash],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        for id in indexed.keys().filter(|id| !by_id.contains_key(id)) {
+            tx.execute(
+                "DELETE FROM similarity_index_entries WHERE file_id=?1",
+                params![id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        let meta = SimilarIndexMeta {
+            scope_key: base_scope(scope_key).to_string(),
+            basename,
+            dimension,
+            threshold: options.similarity_threshold,
+            time_window: options.time_window,
+            cluster_mode: options.cluster_mode.as_str().to_string(),
+            point_count: meta
+                .filter(|_| incremental)
+                .map(|meta| meta.point_count)
+                .unwrap_or(0)
+                + queries.len(),
+        };
+        tx.execute(
+            "INSERT INTO library_settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![
+                INDEX_META_KEY,
+                serde_json::to_string(&meta).map_err(|e| e.to_string())?
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    // hnsw_rs dumps under a new name when the files exist, so drop the previous pair
+    // once the new one is recorded.
+    drop(hns);
+    drop(reloader);
+    if let Some(previous) = previous_basename.filter(|previous| {
+        saved_basename
+            .as_ref()
+            .is_some_and(|saved| saved != previous)
+    }) {
+        remove_index_files(&dir, &previous);
+    }
+
+    let mut s = status.lock().unwrap();
+    s.phase = "building_sets".into();
+    s.current = s.total;
+    s.groups = groups.len() as u64;
+    Ok(())
+}
+
+/// An undated file has no time to compare, so it matches within any window.
+fn within_window(a: &VectorFile, b: &VectorFile, time_window: Option<i64>) -> bool {
+    match (time_window, a.taken_date, b.taken_date) {
+        (Some(window), Some(a), Some(b)) => (a - b).abs() <= window,
+        _ => true,
+    }
+}
+
+/// Greedy complete linkage: merge only fully connected sets, so a weak A-B-C chain
+/// cannot create one group.
+fn complete_linkage(
+    count: usize,
+    seeds: &[Vec<usize>],
+    pair_scores: &HashMap<(usize, usize), f32>,
+    options: &ScanOptions,
+) -> Vec<Vec<usize>> {
+    let mut clusters: Vec<Vec<usize>> = (0..count).map(|i| vec![i]).collect();
+    let mut cluster_for: Vec<usize> = (0..count).collect();
+    for seed in seeds {
+        let Some(&target) = seed.first() else {
+            continue;
+        };
+        for &member in &seed[1..] {
+            clusters[member].clear();
+            cluster_for[member] = target;
+            clusters[target].push(member);
+        }
+    }
     let mut edges: Vec<_> = pair_scores
         .iter()
         .map(|(&(a, b), &score)| (a, b, score))
@@ -355,7 +666,7 @@ fn scan(
                     .get(&(x.min(y), x.max(y)))
                     .copied()
                     .unwrap_or(0.0)
-                    >= similarity_threshold
+                    >= options.similarity_threshold
             })
         }) {
             let (target, source) = if clusters[ai].len() >= clusters[bi].len() {
@@ -370,83 +681,334 @@ fn scan(
             }
         }
     }
-    if cancel.load(Ordering::SeqCst) {
-        return Ok(());
+    clusters
+}
+
+/// Weighted label propagation over the match graph: each file adopts the label with the
+/// highest total similarity among its matches. Communities are then pruned to tight sets.
+fn community_clusters(
+    count: usize,
+    seeds: &[Vec<usize>],
+    pair_scores: &HashMap<(usize, usize), f32>,
+    vectors: &[VectorFile],
+    options: &ScanOptions,
+) -> Vec<Vec<usize>> {
+    let mut neighbors: Vec<Vec<(usize, f32)>> = vec![Vec::new(); count];
+    for (&(a, b), &score) in pair_scores {
+        neighbors[a].push((b, score));
+        neighbors[b].push((a, score));
     }
+    let mut labels: Vec<usize> = (0..count).collect();
+    for seed in seeds {
+        if let Some(&first) = seed.first() {
+            for &member in seed {
+                labels[member] = first;
+            }
+        }
+    }
+    for _ in 0..MAX_PROPAGATION_ROUNDS {
+        let mut changed = false;
+        for i in 0..count {
+            let mut weights: HashMap<usize, f32> = HashMap::new();
+            for &(j, score) in &neighbors[i] {
+                *weights.entry(labels[j]).or_insert(0.0) += score;
+            }
+            // Ties go to the smaller label so the result does not depend on hash order.
+            let best = weights
+                .into_iter()
+                .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)))
+                .map(|(label, _)| label);
+            if let Some(best) = best.filter(|&best| best != labels[i]) {
+                labels[i] = best;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    let mut communities: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (i, label) in labels.into_iter().enumerate() {
+        communities.entry(label).or_default().push(i);
+    }
+    communities
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| tighten_community(members, &neighbors, vectors, options))
+        .collect()
+}
 
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
-    let groups: Vec<_> = clusters
-        .into_iter()
-        .filter(|group| group.len() > 1)
-        .collect();
-    let tx = conn.transaction().map_err(|e| e.to_string())?;
-    tx.execute("DELETE FROM similarity_scans", [])
-        .map_err(|e| e.to_string())?;
-    tx.execute(
-        "INSERT INTO similarity_scans(scope_key,source_version,status,file_count,group_count,created_at,completed_at) VALUES(?1,?2,'finished',?3,?4,?5,?5)",
-        params![scope_key, source_version, vectors.len() as i64, groups.len() as i64, now],
-    )
-    .map_err(|e| e.to_string())?;
-    let scan_id = tx.last_insert_rowid();
-    for group in &groups {
-        let member_scores: Vec<(usize, f32)> = group
+/// Drop members until the community fits the time window, every member's mean
+/// similarity to the rest passes the threshold, and the group fits the size cap.
+/// Undated members are left out of the time window check, as in `within_window`.
+fn tighten_community(
+    mut members: Vec<usize>,
+    neighbors: &[Vec<(usize, f32)>],
+    vectors: &[VectorFile],
+    options: &ScanOptions,
+) -> Vec<usize> {
+    if let Some(window) = options.time_window {
+        let undated: Vec<usize> = members
             .iter()
-            .map(|&i| {
-                let sum: f32 = group
-                    .iter()
-                    .filter(|&&j| j != i)
-                    .map(|&j| {
-                        pair_scores
-                            .get(&(i.min(j), i.max(j)))
-                            .copied()
-                            .unwrap_or(0.0)
-                    })
-                    .sum();
-                (i, sum / (group.len() - 1) as f32)
-            })
+            .copied()
+            .filter(|&i| vectors[i].taken_date.is_none())
             .collect();
-        let &(representative, _) = member_scores
+        let mut dated: Vec<(usize, i64)> = members
             .iter()
-            .max_by(|a, b| a.1.total_cmp(&b.1))
-            .unwrap();
-        let latest_taken_date = group
-            .iter()
-            .map(|&i| vectors[i].taken_date)
-            .max()
-            .unwrap_or(0);
-        let min_score = member_scores
-            .iter()
-            .map(|(_, score)| *score)
-            .fold(1.0f32, f32::min);
-        let max_score = member_scores
+            .filter_map(|&i| Some((i, vectors[i].taken_date?)))
+            .collect();
+        dated.sort_by_key(|&(_, date)| date);
+        while dated.len() > 1 {
+            let first = dated[0].1;
+            let last = dated[dated.len() - 1].1;
+            if last - first <= window {
+                break;
+            }
+            // Trim whichever end lies farther from the median shot.
+            let median = dated[dated.len() / 2].1;
+            if median - first >= last - median {
+                dated.remove(0);
+            } else {
+                dated.pop();
+            }
+        }
+        members = dated.into_iter().map(|(i, _)| i).chain(undated).collect();
+    }
+    let mut inside: HashSet<usize> = members.iter().copied().collect();
+    let mut sums: HashMap<usize, f32> = members
+        .iter()
+        .map(|&i| {
+            let sum = neighbors[i]
+                .iter()
+                .filter(|(j, _)| inside.contains(j))
+                .map(|(_, score)| *score)
+                .sum::<f32>();
+            (i, sum)
+        })
+        .collect();
+    while members.len() > 1 {
+        let peers = (members.len() - 1) as f32;
+        let (n, weakest) = members
             .iter()
-            .map(|(_, score)| *score)
-            .fold(0.0f32, f32::max);
-        tx.execute(
-            "INSERT INTO similarity_groups(scan_id,representative_file_id,file_count,latest_taken_date,min_score,max_score) VALUES(?1,?2,?3,?4,?5,?6)",
-            params![scan_id, vectors[representative].id, group.len() as i64, latest_taken_date, min_score, max_score],
-        )
-        .map_err(|e| e.to_string())?;
-        let group_id = tx.last_insert_rowid();
-        for (index, score) in member_scores {
+            .copied()
+            .enumerate()
+            .min_by(|a, b| sums[&a.1].total_cmp(&sums[&b.1]))
+            .unwrap();
+        if sums[&weakest] / peers >= options.similarity_threshold && members.len() <= MAX_GROUP_SIZE
+        {
+            break;
+        }
+        members.swap_remove(n);
+        inside.remove(&weakest);
+        for &(j, score) in &neighbors[weakest] {
+            if let Some(sum) = sums.get_mut(&j).filter(|_| inside.contains(&j)) {
+                *sum -= score;
+            }
+        }
+    }
+    members
+}
+
+/// Insert a new group, or rewrite an existing one in place while keeping its keep choice.
+fn write_group(
+    tx: &rusqlite::Transaction,
+    scan_id: i64,
+    existing: Option<i64>,
+    keep: Option<i64>,
+    group: &[usize],
+    vectors: &[VectorFile],
+    pair_scores: &HashMap<(usize, usize), f32>,
+) -> Result<(), String> {
+    let member_scores: Vec<(usize, f32)> = group
+        .iter()
+        .map(|&i| {
+            let sum: f32 = group
+                .iter()
+                .filter(|&&j| j != i)
+                .map(|&j| {
+                    pair_scores
+                        .get(&(i.min(j), i.max(j)))
+                        .copied()
+                        .unwrap_or(0.0)
+                })
+                .sum();
+            (i, sum / (group.len() - 1) as f32)
+        })
+        .collect();
+    let &(representative, _) = member_scores
+        .iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap();
+    let keep_id = keep
+        .filter(|&id| group.iter().any(|&i| vectors[i].id == id))
+        .unwrap_or(vectors[representative].id);
+    let latest_taken_date = group
+        .iter()
+        .filter_map(|&i| vectors[i].taken_date)
+        .max()
+        .unwrap_or(0);
+    let min_score = member_scores
+        .iter()
+        .map(|(_, score)| *score)
+        .fold(1.0f32, f32::min);
+    let max_score = member_scores
+        .iter()
+        .map(|(_, score)| *score)
+        .fold(0.0f32, f32::max);
+    let group_id = match existing {
+        Some(group_id) => {
+            tx.execute(
+                "UPDATE similarity_groups SET representative_file_id=?2,file_count=?3,latest_taken_date=?4,min_score=?5,max_score=?6 WHERE id=?1",
+                params![group_id, keep_id, group.len() as i64, latest_taken_date, min_score, max_score],
+            )
+            .map_err(|e| e.to_string())?;
             tx.execute(
-                "INSERT INTO similarity_group_items(group_id,file_id,score,is_keep) VALUES(?1,?2,?3,?4)",
-                params![group_id, vectors[index].id, score, i64::from(index == representative)],
+                "DELETE FROM similarity_group_items WHERE group_id=?1",
+                params![group_id],
             )
             .map_err(|e| e.to_string())?;
+            group_id
         }
+        None => {
+            tx.execute(
+                "INSERT INTO similarity_groups(scan_id,representative_file_id,file_count,latest_taken_date,min_score,max_score) VALUES(?1,?2,?3,?4,?5,?6)",
+                params![scan_id, keep_id, group.len() as i64, latest_taken_date, min_score, max_score],
+            )
+            .map_err(|e| e.to_string())?;
+            tx.last_insert_rowid()
+        }
+    };
+    for (index, score) in member_scores {
+        tx.execute(
+            "INSERT INTO similarity_group_items(group_id,file_id,score,is_keep) VALUES(?1,?2,?3,?4)",
+            params![group_id, vectors[index].id, score, i64::from(vectors[index].id == keep_id)],
+        )
+        .map_err(|e| e.to_string())?;
     }
-    tx.commit().map_err(|e| e.to_string())?;
-    let mut s = status.lock().unwrap();
-    s.phase = "building_sets".into();
-    s.current = s.total;
-    s.groups = groups.len() as u64;
     Ok(())
 }
 
+/// Scope keys end with a per-view counter; strip it so a reopened view continues its scan.
+fn base_scope(scope_key: &str) -> &str {
+    scope_key
+        .rsplit_once("|similar-view:")
+        .map_or(scope_key, |(base, _)| base)
+}
+
+fn find_scan(conn: &Connection, scope_key: &str) -> Result<Option<i64>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, scope_key FROM similarity_scans")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let (id, key) = row.map_err(|e| e.to_string())?;
+        if base_scope(&key) == base_scope(scope_key) {
+            return Ok(Some(id));
+        }
+    }
+    Ok(None)
+}
+
+/// A stored group: (group id, member file ids, kept file id).
+type ExistingGroup = (i64, Vec<i64>, Option<i64>);
+
+/// Load the groups of a scan.
+fn load_existing_groups(conn: &Connection, scan_id: i64) -> Result<Vec<ExistingGroup>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT g.id, i.file_id, i.is_keep
+             FROM similarity_groups g
+             JOIN similarity_group_items i ON i.group_id = g.id
+             WHERE g.scan_id = ?1
+             ORDER BY g.id, i.is_keep DESC, i.score DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![scan_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    let mut groups: Vec<ExistingGroup> = Vec::new();
+    for row in rows {
+        let (group_id, file_id, is_keep) = row.map_err(|e| e.to_string())?;
+        if groups.last().is_none_or(|(id, _, _)| *id != group_id) {
+            groups.push((group_id, Vec::new(), None));
+        }
+        let group = groups.last_mut().unwrap();
+        group.1.push(file_id);
+        if is_keep != 0 {
+            group.2 = Some(file_id);
+        }
+    }
+    Ok(groups)
+}
+
+fn load_index_entries(conn: &Connection) -> Result<HashMap<i64, String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT file_id, vector_hash FROM similarity_index_entries")
+        .map_err(|e| e.to_string())?;
+    stmt.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<HashMap<_, _>, _>>()
+    .map_err(|e| e.to_string())
+}
+
+fn load_index_meta() -> Option<SimilarIndexMeta> {
+    t_sqlite::get_library_setting(INDEX_META_KEY)
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_str(&value).ok())
+}
+
+/// The index lives next to the library database, one file pair per library.
+fn index_dir() -> Result<PathBuf, String> {
+    let db_path = PathBuf::from(t_storage::get_current_db_path()?);
+    let dir = db_path
+        .parent()
+        .ok_or("Invalid database path")?
+        .join("similar_index");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+fn index_basename() -> String {
+    t_storage::get_current_db_path()
+        .ok()
+        .and_then(|path| {
+            Path::new(&path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "library".into())
+}
+
+fn index_files_exist(dir: &Path, basename: &str) -> bool {
+    dir.join(format!("{basename}.hnsw.graph")).exists()
+        && dir.join(format!("{basename}.hnsw.data")).exists()
+}
+
+fn remove_index_files(dir: &Path, basename: &str) {
+    for ext in ["hnsw.graph", "hnsw.data"] {
+        let path = dir.join(format!("{basename}.{ext}"));
+        let error = std::fs::remove_file(&path)
+            .err()
+            .filter(|e| e.kind() != std::io::ErrorKind::NotFound);
+        if let Some(e) = error {
+            eprintln!("Failed to remove old index file {}: {}", path.display(), e);
+        }
+    }
+}
+
 pub fn list_groups(scope_key: &str, limit: i64, offset: i64) -> Result<serde_json::Value, String> {
     let conn = get_db_conn()?;
     let total = conn
@@ -5,15 +5,18 @@
  * date:    2024-08-08
  */
 use crate::t_ai;
+use crate::t_ai_model;
 use crate::t_ai_png;
 use crate::t_config;
 use crate::t_common;
+use crate::t_dedup;
 use crate::t_image;
 use crate::t_lens;
 use crate::t_libraw;
 use crate::t_storage;
 use crate::t_utils;
 use crate::t_video;
+use crate::t_volume;
 use base64::{Engine, engine::general_purpose};
 use chrono::{Datelike, TimeZone};
 use exif::{In, Tag, Value};
@@ -115,6 +118,9 @@ pub struct Album {
     pub merged_count: Option<u64>,  // companions merged into logical items
     pub merged_size: Option<u64>,   // total size of merged companions
     pub last_scan_time: Option<i64>,   // last scan time
+    pub volume_uuid: Option<String>,   // uuid of the volume holding the album
+    pub volume_path: Option<String>,   // album path relative to the volume's mount point
+    pub is_offline: Option<bool>,      // volume not mounted (not stored in db)
 }
 
 #[derive(Debug, Deserialize)]
@@ -128,6 +134,7 @@ impl Album {
     /// create a new album
     fn new(path: &str) -> Result<Self, String> {
         let file_info = t_utils::FileInfo::new(path)?;
+        let volume = t_volume::locate(path);
         Ok(Self {
             id: None,
             name: file_info.file_name,
@@ -146,6 +153,9 @@ impl Album {
             merged_count: Some(0),
             merged_size: Some(0),
             last_scan_time: Some(0),
+            volume_uuid: volume.as_ref().map(|volume| volume.uuid.clone()),
+            volume_path: volume.map(|volume| volume.relative_path),
+            is_offline: Some(false),
         })
     }
 
@@ -169,6 +179,9 @@ impl Album {
             merged_count: row.get(14)?,
             merged_size: row.get(15)?,
             last_scan_time: row.get(16)?,
+            volume_uuid: row.get(17)?,
+            volume_path: row.get(18)?,
+            is_offline: None,
         })
     }
 
@@ -176,7 +189,7 @@ impl Album {
     fn fetch(path: &str) -> Result<Option<Self>, String> {
         let conn = open_conn()?;
         let result = conn.query_row(
-            "SELECT id, name, path, created_at, modified_at, display_order_id, cover_file_id, description, indexed, total, skipped_count, skipped_size, failed_count, failed_size, merged_count, merged_size, last_scan_time
+            "SELECT id, name, path, created_at, modified_at, display_order_id, cover_file_id, description, indexed, total, skipped_count, skipped_size, failed_count, failed_size, merged_count, merged_size, last_scan_time, volume_uuid, volume_path
             FROM albums WHERE path = ?1",
             params![path],
             Self::from_row
@@ -199,8 +212,8 @@ impl Album {
 
         // Insert the new album into the db
         let result = conn.execute(
-            "INSERT INTO albums (name, path, created_at, modified_at, display_order_id, cover_file_id, description, indexed, total, skipped_count, skipped_size, failed_count, failed_size, merged_count, merged_size, last_scan_time)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            "INSERT INTO albums (name, path, created_at, modified_at, display_order_id, cover_file_id, description, indexed, total, skipped_count, skipped_size, failed_count, failed_size, merged_count, merged_size, last_scan_time, volume_uuid, volume_path)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
             params![
                 self.name,
                 self.path,
@@ -218,6 +231,8 @@ impl Album {
                 self.merged_count,
                 self.merged_size,
                 self.last_scan_time,
+                self.volume_uuid,
+                self.volume_path,
             ],
         ).map_err(|e| e.to_string())?;
         Ok(result)
@@ -292,7 +307,7 @@ impl Album {
         let conn = open_conn()?;
 
         let query =
-            "SELECT id, name, path, created_at, modified_at, display_order_id, cover_file_id, description, indexed, total, skipped_count, skipped_size, failed_count, failed_size, merged_count, merged_size, last_scan_time
+            "SELECT id, name, path, created_at, modified_at, display_order_id, cover_file_id, description, indexed, total, skipped_count, skipped_size, failed_count, failed_size, merged_count, merged_size, last_scan_time, volume_uuid, volume_path
             FROM albums
             ORDER BY display_order_id ASC";
 
@@ -318,7 +333,7 @@ impl Album {
     pub fn get_album_by_id(id: i64) -> Result<Self, String> {
         let conn = open_conn()?;
         let result = conn.query_row(
-            "SELECT id, name, path, created_at, modified_at, display_order_id, cover_file_id, description, indexed, total, skipped_count, skipped_size, failed_count, failed_size, merged_count, merged_size, last_scan_time
+            "SELECT id, name, path, created_at, modified_at, display_order_id, cover_file_id, description, indexed, total, skipped_count, skipped_size, failed_count, failed_size, merged_count, merged_size, last_scan_time, volume_uuid, volume_path
             FROM albums WHERE id = ?1",
             params![id],
             Self::from_row
@@ -340,6 +355,16 @@ impl Album {
         Ok(result)
     }
 
+    /// remember the volume an album lives on
+    pub fn update_volume(id: i64, volume_uuid: &str, volume_path: &str) -> Result<usize, String> {
+        let conn = open_conn()?;
+        conn.execute(
+            "UPDATE albums SET volume_uuid = ?1, volume_path = ?2 WHERE id = ?3",
+            params![volume_uuid, volume_path, id],
+        )
+        .map_err(|e| e.to_string())
+    }
+
     /// update last scan time
     pub fn update_last_scan_time(album_id: i64, scan_time: i64) -> Result<usize, String> {
         Self::update_column(album_id, "last_scan_time", &scan_time)
@@ -1305,6 +1330,8 @@ impl FolderScanState {
     }
 }
 
+const CONTENT_FINGERPRINT_FAILED: &str = "-"; // the file could not be read when fingerprinted
+
 /// Define the album file struct
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AFile {
@@ -1378,6 +1405,9 @@ pub struct AFile {
     pub live_photo_video_id: Option<i64>,   // paired Live Photo MOV file id
     pub live_photo_video_path: Option<String>, // paired Live Photo MOV path
     pub motion_photo_offset: Option<i64>,   // byte offset of embedded MP4 (Android Motion Photo)
+    #[serde(skip)]
+    pub content_fingerprint: Option<String>, // moved-file detection ("-" if unreadable)
+    pub match_offset: Option<i64>,          // matching video keyframe offset in seconds (AI search)
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1492,6 +1522,24 @@ impl ACollection {
         Self::get(id)
     }
 
+    /// The oldest collection with this name, created when there is none
+    pub fn get_or_create(name: &str) -> Result<Self, String> {
+        let name = name.trim();
+        let conn = open_conn()?;
+        let existing: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM acollections WHERE name = ?1 ORDER BY id LIMIT 1",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        match existing {
+            Some(id) => Self::get(id),
+            None => Self::create(name),
+        }
+    }
+
     pub fn get(id: i64) -> Result<Self, String> {
         let conn = open_conn()?;
         conn.query_row(
@@ -2358,6 +2406,8 @@ impl AFile {
             live_photo_video_id: None,
             live_photo_video_path: None,
             motion_photo_offset,
+            content_fingerprint: None,
+            match_offset: None,
         };
 
         Ok(file)
@@ -2845,7 +2895,8 @@ impl AFile {
                     THEN lpf.path || '/' || lpv.name
                     ELSE NULL
                 END AS live_photo_video_path,
-                a.motion_photo_offset
+                a.motion_photo_offset,
+                a.content_fingerprint
             FROM afiles a
             LEFT JOIN afolders b ON a.folder_id = b.id
             LEFT JOIN albums c ON b.album_id = c.id
@@ -2923,6 +2974,8 @@ impl AFile {
             live_photo_video_id: row.get(53)?,
             live_photo_video_path: row.get(54)?,
             motion_photo_offset: row.get(55)?,
+            content_fingerprint: row.get(56)?,
+            match_offset: None,
         })
     }
 
@@ -3290,6 +3343,9 @@ impl AFile {
                     if let Some(mut updated_file) =
                         Self::update_file_info(file_id, file_path, last_scan_time)?
                     {
+                        if modified {
+                            Self::refresh_content_fingerprint(file_id, file_path, &updated_file);
+                        }
                         // If modified, delete old thumbnail and remove embeds data
                         if modified || missing_thumb {
                             let _ = AThumb::delete(file_id);
@@ -3297,7 +3353,15 @@ impl AFile {
                             if modified {
                                 let conn = open_conn()?;
                                 let _ = conn.execute(
-                                    "UPDATE afiles SET embeds = NULL WHERE id = ?1",
+                                    "UPDATE afiles SET embeds = NULL, embeds_model = NULL, has_text = 0 WHERE id = ?1",
+                                    params![file_id],
+                                );
+                                let _ = conn.execute(
+                                    "DELETE FROM video_keyframes WHERE file_id = ?1",
+                                    params![file_id],
+                                );
+                                let _ = conn.execute(
+                                    "DELETE FROM file_texts WHERE file_id = ?1",
                                     params![file_id],
                                 );
                                 updated_file.has_embedding = Some(false);
@@ -3316,6 +3380,10 @@ impl AFile {
                 // for the mark-and-sweep deletion logic.
                 if let Some(file_id) = file.id {
                     let _ = Self::update_column(file_id, "last_scan_time", &last_scan_time);
+                    // Files indexed before fingerprints existed get one on their next scan.
+                    if file.content_fingerprint.is_none() {
+                        Self::refresh_content_fingerprint(file_id, file_path, &file);
+                    }
                     if file
                         .comments
                         .as_deref()
@@ -3334,6 +3402,29 @@ impl AFile {
         // insert the new file into the database
         let mut new_file_struct = Self::new(folder_id, file_path, file_type)?;
         new_file_struct.last_scan_time = Some(last_scan_time);
+
+        // A file moved outside the app shows up as new here while its old row
+        // is still waiting to be reconciled. Move that row instead, so tags,
+        // ratings and comments survive; status 3 reports the re-attachment.
+        let fingerprint = Self::compute_content_fingerprint(
+            file_path,
+            new_file_struct.size,
+            new_file_struct.e_date_time.as_deref(),
+        );
+        if let Some(fingerprint) = fingerprint.as_deref() {
+            if let Some(moved_id) = Self::find_moved_file(fingerprint, file_path)? {
+                if let Some(file) = Self::reattach_moved_file(
+                    moved_id,
+                    folder_id,
+                    file_path,
+                    fingerprint,
+                    last_scan_time,
+                )? {
+                    return Ok((file, 3));
+                }
+            }
+        }
+
         let inserted = new_file_struct.insert()?;
 
         // A concurrent folder sync or album scan may have inserted the same
@@ -3344,10 +3435,111 @@ impl AFile {
             return Self::add_to_db(folder_id, file_path, file_type, last_scan_time);
         }
 
-        let new_file = Self::fetch(folder_id, file_path)?;
-        new_file
-            .map(|f| (f, 1))
-            .ok_or_else(|| format!("Inserted file missing from DB: {}", file_path))
+        let new_file = Self::fetch(folder_id, file_path)?
+            .ok_or_else(|| format!("Inserted file missing from DB: {}", file_path))?;
+        if let Some(file_id) = new_file.id {
+            let fingerprint = fingerprint.as_deref().unwrap_or(CONTENT_FINGERPRINT_FAILED);
+            let _ = Self::update_column(file_id, "content_fingerprint", &fingerprint);
+        }
+        Ok((new_file, 1))
+    }
+
+    /// Identify a file's content independently of its path: a sampled hash of
+    /// the head, middle and tail, the size, and the EXIF capture time.
+    fn compute_content_fingerprint(
+        file_path: &str,
+        size: i64,
+        e_date_time: Option<&str>,
+    ) -> Option<String> {
+        let (sample_hash, _) = t_dedup::compute_sample_hash(file_path, size).ok()?;
+        Some(format!(
+            "{}:{}:{}",
+            sample_hash,
+            size,
+            e_date_time.unwrap_or("")
+        ))
+    }
+
+    /// Store a file's fingerprint, or the failure marker so an unreadable file
+    /// is not hashed again on every scan (a later modification retries it).
+    fn refresh_content_fingerprint(file_id: i64, file_path: &str, file: &Self) {
+        let fingerprint =
+            Self::compute_content_fingerprint(file_path, file.size, file.e_date_time.as_deref())
+                .unwrap_or_else(|| CONTENT_FINGERPRINT_FAILED.to_string());
+        let _ = Self::update_column(file_id, "content_fingerprint", &fingerprint);
+    }
+
+    /// Find the row of a file with the same fingerprint that is no longer at
+    /// its recorded path. Ambiguous matches are left alone.
+    fn find_moved_file(fingerprint: &str, file_path: &str) -> Result<Option<i64>, String> {
+        let conn = open_conn()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT a.id, b.path, a.name, b.album_id
+                 FROM afiles a
+                 JOIN afolders b ON a.folder_id = b.id
+                 WHERE a.content_fingerprint = ?1",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![fingerprint], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+
+        let mut candidates = Vec::new();
+        let mut album_accessibility: HashMap<i64, bool> = HashMap::new();
+        for row in rows {
+            let (id, folder_path, name, album_id) = row.map_err(|e| e.to_string())?;
+            let old_path = Path::new(&folder_path).join(&name);
+            if old_path == Path::new(file_path) {
+                continue;
+            }
+            // Files on an offline album only look missing.
+            let album_online = *album_accessibility.entry(album_id).or_insert_with(|| {
+                Album::get_album_by_id(album_id).is_ok_and(|album| t_utils::album_online(&album))
+            });
+            if !album_online || old_path.exists() {
+                continue;
+            }
+            candidates.push(id);
+        }
+        Ok(if candidates.len() == 1 {
+            candidates.pop()
+        } else {
+            None
+        })
+    }
+
+    /// Point an existing row at a file's new location, keeping its id and user metadata.
+    fn reattach_moved_file(
+        file_id: i64,
+        folder_id: i64,
+        file_path: &str,
+        fingerprint: &str,
+        last_scan_time: i64,
+    ) -> Result<Option<Self>, String> {
+        let conn = open_conn()?;
+        // The companion stayed behind, so the old pairing no longer applies;
+        // the caller re-pairs the destination folder.
+        conn.execute(
+            "UPDATE afiles SET media_subtype = NULL, live_photo_video_id = NULL
+             WHERE (id = ?1 OR live_photo_video_id = ?1)
+               AND media_subtype IN ('live_photo', 'raw_jpeg_pair')",
+            params![file_id],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE afiles SET folder_id = ?1, content_fingerprint = ?2 WHERE id = ?3",
+            params![folder_id, fingerprint, file_id],
+        )
+        .map_err(|e| e.to_string())?;
+        Self::update_file_info(file_id, file_path, last_scan_time)
     }
 
     /// get a file info from db by file_id
@@ -3508,6 +3700,53 @@ impl AFile {
             .map_err(|e| e.to_string())
     }
 
+    /// Apply capture metadata recovered from an external source (such as export
+    /// sidecars). Only the given values are written; a GPS position also refreshes
+    /// the reverse-geocoded place names.
+    pub fn update_capture_metadata(
+        file_id: i64,
+        taken_date: Option<i64>,
+        gps: Option<(f64, f64, Option<f64>)>,
+        comments: Option<&str>,
+        is_favorite: Option<bool>,
+    ) -> Result<usize, String> {
+        let conn = open_conn()?;
+        let mut changed = 0;
+        if let Some(taken_date) = taken_date {
+            changed += Self::update_column_with_conn(&conn, file_id, "taken_date", &taken_date)?;
+        }
+        if let Some((latitude, longitude, altitude)) = gps {
+            let (geo_name, geo_admin1, geo_admin2, geo_cc) =
+                match t_utils::GEOCODER.search((latitude, longitude)) {
+                    Some(result) => (
+                        Some(result.record.name.clone()),
+                        Some(result.record.admin1.clone()),
+                        Some(result.record.admin2.clone()),
+                        Some(result.record.cc.clone()),
+                    ),
+                    None => (None, None, None, None),
+                };
+            changed += conn
+                .execute(
+                    "UPDATE afiles SET gps_latitude = ?1, gps_longitude = ?2, gps_altitude = ?3,
+                    geo_name = ?4, geo_admin1 = ?5, geo_admin2 = ?6, geo_cc = ?7
+                    WHERE id = ?8",
+                    params![
+                        latitude, longitude, altitude, geo_name, geo_admin1, geo_admin2, geo_cc,
+                        file_id
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        if let Some(comments) = comments {
+            changed += Self::update_column_with_conn(&conn, file_id, "comments", &comments)?;
+        }
+        if let Some(is_favorite) = is_favorite {
+            changed += Self::update_column_with_conn(&conn, file_id, "is_favorite", &is_favorite)?;
+        }
+        Ok(changed)
+    }
+
     pub fn batch_update_names(updates: &[(i64, String, Option<String>)]) -> Result<usize, String> {
         if updates.is_empty() {
             return Ok(0);
@@ -4164,9 +4403,14 @@ impl AFile {
         let mut sql_params: Vec<Box<dyn ToSql>> = Vec::new();
 
         if !params.search_file_name.is_empty() {
-            conditions.push("(a.name LIKE ? COLLATE NOCASE OR a.comments LIKE ? COLLATE NOCASE)".to_string());
+            conditions.push(
+                "(a.name LIKE ? COLLATE NOCASE OR a.comments LIKE ? COLLATE NOCASE \
+                 OR EXISTS (SELECT 1 FROM file_texts ft WHERE ft.file_id = a.id AND ft.text LIKE ? COLLATE NOCASE))"
+                    .to_string(),
+            );
             let pattern = format!("%{}%", params.search_file_name);
             sql_params.push(Box::new(pattern.clone()));
+            sql_params.push(Box::new(pattern.clone()));
             sql_params.push(Box::new(pattern));
         }
 
@@ -5134,6 +5378,16 @@ impl AFile {
                     "(a.gps_latitude IS NULL OR a.gps_longitude IS NULL)".to_string()
                 })
             }
+            "has_text" => {
+                let desired = Self::smart_rule_bool(value).unwrap_or(true);
+                let is_positive = matches!(operator, "is" | "eq");
+                let value = if is_positive { desired } else { !desired };
+                Ok(if value {
+                    "a.has_text = 1".to_string()
+                } else {
+                    "COALESCE(a.has_text, 0) != 1".to_string()
+                })
+            }
             "orientation" => {
                 if operator != "is" && operator != "eq" {
                     return Err(format!("Unsupported orientation operator: {}", operator));
@@ -5830,10 +6084,10 @@ impl AFile {
         let file_opt = Self::get_file_info(file_id).map_err(|e| e.to_string())?;
         let file = file_opt.ok_or("File not found")?;
 
-        // 2. Check if it's an image
-        // file_type: 1 is image, 3 is HEIC
-        if file.file_type != Some(1) && file.file_type != Some(3) {
-            return Err("File is not an image".to_string());
+        // 2. Check if it's an image or a video
+        // file_type: 1 is image, 2 is video, 3 is HEIC
+        if !matches!(file.file_type, Some(1 | 2 | 3)) {
+            return Err("File is not an image or video".to_string());
         }
 
         let file_path = file.file_path.ok_or("File path not resolved")?;
@@ -5845,8 +6099,18 @@ impl AFile {
             }
         }
 
+        if file.file_type == Some(2) {
+            return Self::generate_video_embedding(
+                state,
+                file_id,
+                &file_path,
+                file.duration.map(|d| d.max(0) as u64),
+            );
+        }
+
         // 4. Generate embedding
         let mut engine = state.0.lock().unwrap();
+        let model_id = engine.image_model_id().to_string();
 
         // Optimized: Use thumbnail if available (much faster than loading original)
         // Fallback to original file if thumbnail is missing or fails to process
@@ -5890,14 +6154,109 @@ impl AFile {
         }?;
 
         // 5. Save to DB
-        let _ =
-            Self::update_embedding(file_id, embedding).map_err(|e| format!("DB Error: {}", e))?;
+        let _ = Self::update_embedding(file_id, embedding, &model_id)
+            .map_err(|e| format!("DB Error: {}", e))?;
 
         Ok("Embedding generated and saved".to_string())
     }
 
-    /// Update embedding for a file
-    pub fn update_embedding(file_id: i64, embedding: Vec<f32>) -> Result<usize, String> {
+    /// generate keyframe embeddings for a video
+    /// Each sampled keyframe is stored with its offset; the file embedding is their mean.
+    fn generate_video_embedding(
+        state: &State<t_ai::AiState>,
+        file_id: i64,
+        file_path: &str,
+        duration: Option<u64>,
+    ) -> Result<String, String> {
+        // extract frames before taking the engine lock, ffmpeg is the slow part
+        let frames = t_video::get_video_keyframes_sync(
+            file_path,
+            t_common::AI_VIDEO_KEYFRAME_SIZE,
+            duration,
+            t_common::AI_VIDEO_KEYFRAMES,
+        )?;
+
+        let mut engine = state.0.lock().unwrap();
+        let model_id = engine.image_model_id().to_string();
+        let mut keyframes = Vec::with_capacity(frames.len());
+        for (offset, bytes) in frames {
+            match panic::catch_unwind(AssertUnwindSafe(|| engine.encode_image_from_bytes(&bytes))) {
+                Ok(Ok(embedding)) => keyframes.push((offset, embedding)),
+                Ok(Err(e)) => eprintln!(
+                    "Failed to embed keyframe at {}s of {}: {}",
+                    offset, file_path, e
+                ),
+                Err(_) => eprintln!(
+                    "Embedding panic while encoding keyframe at {}s of {}",
+                    offset, file_path
+                ),
+            }
+        }
+        drop(engine);
+
+        if keyframes.is_empty() {
+            return Err(format!("No keyframes could be embedded for {}", file_path));
+        }
+        Self::update_video_embedding(file_id, &keyframes, &model_id)
+            .map_err(|e| format!("DB Error: {}", e))?;
+
+        Ok("Video keyframe embeddings generated and saved".to_string())
+    }
+
+    /// Replace the keyframe embeddings of a video and store their mean as the file embedding
+    pub fn update_video_embedding(
+        file_id: i64,
+        keyframes: &[(u64, Vec<f32>)],
+        model_id: &str,
+    ) -> Result<(), String> {
+        let dim = keyframes.first().map(|(_, v)| v.len()).unwrap_or(0);
+        let mut mean = vec![0.0f32; dim];
+        for (_, embedding) in keyframes.iter().filter(|(_, v)| v.len() == dim) {
+            for (sum, value) in mean.iter_mut().zip(embedding) {
+                *sum += value;
+            }
+        }
+        for value in mean.iter_mut() {
+            *value /= keyframes.len() as f32;
+        }
+
+        let to_blob = |embedding: &[f32]| {
+            let mut bytes = Vec::with_capacity(embedding.len() * 4);
+            for val in embedding {
+                bytes.extend_from_slice(&val.to_le_bytes());
+            }
+            bytes
+        };
+
+        let mut conn = open_conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute(
+            "DELETE FROM video_keyframes WHERE file_id = ?1",
+            params![file_id],
+        )
+        .map_err(|e| e.to_string())?;
+        for (offset, embedding) in keyframes {
+            tx.execute(
+                "INSERT INTO video_keyframes (file_id, offset_secs, embeds, embeds_model)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![file_id, *offset as i64, to_blob(embedding), model_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.execute(
+            "UPDATE afiles SET embeds = ?1, embeds_model = ?2 WHERE id = ?3",
+            params![to_blob(&mean), model_id, file_id],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Update embedding for a file, recording the model that produced it
+    pub fn update_embedding(
+        file_id: i64,
+        embedding: Vec<f32>,
+        model_id: &str,
+    ) -> Result<usize, String> {
         // Convert Vec<f32> to Vec<u8>
         let mut bytes = Vec::with_capacity(embedding.len() * 4);
         for val in embedding {
@@ -5907,18 +6266,23 @@ impl AFile {
         let conn = open_conn()?;
         let result = conn
             .execute(
-                "UPDATE afiles SET embeds = ?1 WHERE id = ?2",
-                params![bytes, file_id],
+                "UPDATE afiles SET embeds = ?1, embeds_model = ?2 WHERE id = ?3",
+                params![bytes, model_id, file_id],
             )
             .map_err(|e| e.to_string())?;
         Ok(result)
     }
 
+    /// Get the embedding of a file; embeddings from another model count as missing
     pub fn get_embedding_by_id(file_id: i64) -> Result<Vec<f32>, String> {
         let conn = open_conn()?;
+        let sql = format!(
+            "SELECT embeds FROM afiles WHERE id = ?1 AND embeds IS NOT NULL AND {}",
+            t_ai_model::embeds_model_condition("")
+        );
         let embeds_blob: Vec<u8> = conn
             .query_row(
-                "SELECT embeds FROM afiles WHERE id = ?1 AND embeds IS NOT NULL",
+                &sql,
                 params![file_id],
                 |row| row.get(0),
             )
@@ -5947,19 +6311,23 @@ impl AFile {
         // 2. Perform Vector Search
         let conn = open_conn()?;
 
+        let mut scope_condition = Self::search_exclusion_condition("b");
+        if let Some(ft_condition) = Self::build_file_type_condition(params.file_type) {
+            scope_condition.push_str(" AND ");
+            scope_condition.push_str(&ft_condition);
+        }
+
+        // videos are scored by their best keyframe below
         let mut query = "SELECT a.id, a.embeds
             FROM afiles a
             LEFT JOIN afolders b ON a.folder_id = b.id
-            WHERE a.embeds IS NOT NULL"
+            WHERE a.embeds IS NOT NULL AND a.file_type <> 2"
             .to_string();
 
         query.push_str(" AND ");
-        query.push_str(&Self::search_exclusion_condition("b"));
-
-        if let Some(ft_condition) = Self::build_file_type_condition(params.file_type) {
-            query.push_str(" AND ");
-            query.push_str(&ft_condition);
-        }
+        query.push_str(&t_ai_model::embeds_model_condition("a"));
+        query.push_str(" AND ");
+        query.push_str(&scope_condition);
 
         let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
 
@@ -5995,6 +6363,43 @@ impl AFile {
             }
         }
 
+        // Score videos by their best matching keyframe; a similar-image search
+        // stays with photos
+        let mut best_keyframes: HashMap<i64, (f32, i64)> = HashMap::new();
+        if !params.search_text.is_empty() {
+            let keyframe_query = format!(
+                "SELECT k.file_id, k.offset_secs, k.embeds
+                FROM video_keyframes k
+                JOIN afiles a ON a.id = k.file_id
+                LEFT JOIN afolders b ON a.folder_id = b.id
+                WHERE {} AND {}",
+                t_ai_model::embeds_model_condition("k"),
+                scope_condition
+            );
+            let mut keyframe_stmt = conn.prepare(&keyframe_query).map_err(|e| e.to_string())?;
+            let keyframe_rows = keyframe_stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, Vec<u8>>(2)?,
+                    ))
+                })
+                .map_err(|e| e.to_string())?;
+            for row in keyframe_rows {
+                let (id, offset, embeds_blob) = row.map_err(|e| e.to_string())?;
+                let score = Self::cosine_similarity_blob(&embedding, query_norm, &embeds_blob);
+                if score > threshold
+                    && best_keyframes
+                        .get(&id)
+                        .is_none_or(|(best_score, _)| score > *best_score)
+                {
+                    best_keyframes.insert(id, (score, offset));
+                }
+            }
+        }
+        scores.extend(best_keyframes.iter().map(|(id, (score, _))| (*id, *score)));
+
         // Sort by score descending
         scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
 
@@ -6021,6 +6426,13 @@ impl AFile {
         let results = result_ids
             .into_iter()
             .filter_map(|id| files_by_id.remove(&id))
+            .map(|mut file| {
+                file.match_offset = file
+                    .id
+                    .and_then(|id| best_keyframes.get(&id))
+                    .map(|(_, offset)| *offset);
+                file
+            })
             .collect::<Vec<_>>();
 
         println!("Returning {} files", results.len());
@@ -6118,7 +6530,7 @@ impl AThumb {
         data.starts_with(&[0x89, 0x50, 0x4E, 0x47])
     }
 
-    fn is_complete_jpeg(data: &[u8]) -> bool {
+    pub(crate) fn is_complete_jpeg(data: &[u8]) -> bool {
         data.starts_with(&[0xFF, 0xD8, 0xFF]) && data.ends_with(&[0xFF, 0xD9])
     }
 
@@ -7741,7 +8153,6 @@ impl Person {
     }
 
     /// Update thumbnail for a specific person
-    #[allow(dead_code)]
     pub fn update_thumbnail(person_id: i64) -> Result<(), String> {
         let conn = open_conn()?;
 
@@ -7855,6 +8266,7 @@ pub struct Face {
     pub person_id: Option<i64>,
     pub person_name: Option<String>,
     pub created_at: i64,
+    pub is_manual: bool, // drawn by the user instead of detected
 }
 
 impl Face {
@@ -7882,6 +8294,144 @@ impl Face {
         Ok(conn.last_insert_rowid())
     }
 
+    /// Add a face box drawn by the user and mark the file as having faces
+    pub fn add_manual(
+        file_id: i64,
+        bbox: &str,
+        embedding: &[f32],
+        person_id: Option<i64>,
+    ) -> Result<i64, String> {
+        let conn = open_conn()?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        let embedding_bytes: Vec<u8> = embedding.iter().flat_map(|f| f.to_le_bytes()).collect();
+
+        let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT INTO faces (file_id, bbox, embedding, person_id, created_at, is_manual)
+             VALUES (?1, ?2, ?3, ?4, ?5, 1)",
+            params![file_id, bbox, embedding_bytes, person_id, now],
+        )
+        .map_err(|e| e.to_string())?;
+        let face_id = tx.last_insert_rowid();
+        tx.execute(
+            "UPDATE afiles SET has_faces = 1 WHERE id = ?1",
+            params![file_id],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+
+        Ok(face_id)
+    }
+
+    /// Replace the bounding box and embedding of an existing face
+    pub fn update_box(face_id: i64, bbox: &str, embedding: &[f32]) -> Result<usize, String> {
+        let conn = open_conn()?;
+        let embedding_bytes: Vec<u8> = embedding.iter().flat_map(|f| f.to_le_bytes()).collect();
+        conn.execute(
+            "UPDATE faces SET bbox = ?1, embedding = ?2, is_manual = 1 WHERE id = ?3",
+            params![bbox, embedding_bytes, face_id],
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    /// Get (file_id, person_id) of a face
+    pub fn get_owner(face_id: i64) -> Result<Option<(i64, Option<i64>)>, String> {
+        let conn = open_conn()?;
+        conn.query_row(
+            "SELECT file_id, person_id FROM faces WHERE id = ?1",
+            params![face_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())
+    }
+
+    /// Set or clear the person of a face
+    pub fn set_person(face_id: i64, person_id: Option<i64>) -> Result<usize, String> {
+        let conn = open_conn()?;
+        conn.execute(
+            "UPDATE faces SET person_id = ?1 WHERE id = ?2",
+            params![person_id, face_id],
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    /// Delete a face and remember its box as rejected, so that reindexing the
+    /// file does not detect the same face again.
+    pub fn delete_and_reject(face_id: i64) -> Result<usize, String> {
+        let conn = open_conn()?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+        let face: Option<(i64, Option<String>, i64)> = tx
+            .query_row(
+                "SELECT file_id, bbox, is_manual FROM faces WHERE id = ?1",
+                params![face_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        let Some((file_id, bbox, is_manual)) = face else {
+            return Ok(0);
+        };
+
+        // Only detections can come back on reindex; manual boxes are just removed.
+        if is_manual == 0 {
+            if let Some(bbox) = bbox {
+                tx.execute(
+                    "INSERT INTO face_rejections (file_id, bbox, created_at) VALUES (?1, ?2, ?3)",
+                    params![file_id, bbox, now],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.execute(
+            "UPDATE persons SET cover_face_id = NULL WHERE cover_face_id = ?1",
+            params![face_id],
+        )
+        .map_err(|e| e.to_string())?;
+        let result = tx
+            .execute("DELETE FROM faces WHERE id = ?1", params![face_id])
+            .map_err(|e| e.to_string())?;
+
+        // Keep the file marked as scanned so indexing does not pick it up again.
+        tx.execute(
+            "UPDATE afiles SET has_faces = CASE
+                WHEN EXISTS (SELECT 1 FROM faces WHERE file_id = ?1) THEN 1 ELSE 2
+             END
+             WHERE id = ?1",
+            params![file_id],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+
+        Ok(result)
+    }
+
+    /// Get the rejected face boxes of a file as (x, y, width, height)
+    pub fn get_rejections_with_conn(
+        conn: &Connection,
+        file_id: i64,
+    ) -> Result<Vec<(f32, f32, f32, f32)>, String> {
+        let mut stmt = conn
+            .prepare("SELECT bbox FROM face_rejections WHERE file_id = ?1")
+            .map_err(|e| e.to_string())?;
+        let boxes = stmt
+            .query_map(params![file_id], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?
+            .filter_map(|r| r.ok())
+            .filter_map(|json| serde_json::from_str::<FaceBBox>(&json).ok())
+            .map(|b| (b.x, b.y, b.width, b.height))
+            .collect();
+        Ok(boxes)
+    }
+
     /// Check if a file already has faces detected
     /// Check if a file has faces
     #[allow(dead_code)]
@@ -7936,7 +8486,7 @@ impl Face {
         let conn = open_conn()?;
         let mut stmt = conn
             .prepare(
-                "SELECT f.id, f.file_id, f.bbox, f.embedding, f.person_id, f.created_at, p.name 
+                "SELECT f.id, f.file_id, f.bbox, f.embedding, f.person_id, f.created_at, p.name, f.is_manual
                  FROM faces f
                  LEFT JOIN persons p ON f.person_id = p.id
                  WHERE f.file_id = ?1",
@@ -7953,6 +8503,7 @@ impl Face {
                     person_id: row.get(4)?,
                     created_at: row.get(5)?,
                     person_name: row.get(6)?,
+                    is_manual: row.get::<_, Option<i64>>(7)?.unwrap_or(0) != 0,
                 })
             })
             .map_err(|e| e.to_string())?
@@ -7984,6 +8535,58 @@ impl Face {
         Ok(faces)
     }
 
+    /// Get the embedding of a single face as f32 values
+    pub fn get_embedding(face_id: i64) -> Result<Vec<f32>, String> {
+        let conn = open_conn()?;
+        let embedding_bytes: Vec<u8> = conn
+            .query_row(
+                "SELECT embedding FROM faces WHERE id = ?1 AND embedding IS NOT NULL",
+                params![face_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Face embedding not found".to_string())?;
+
+        Ok(embedding_bytes
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect())
+    }
+
+    /// Get face data for similarity search, skipping folders excluded from search
+    /// Returns: Vec<(face_id, file_id, person_id, person_name, embedding_bytes)>
+    pub fn get_all_for_search()
+    -> Result<Vec<(i64, i64, Option<i64>, Option<String>, Vec<u8>)>, String> {
+        let conn = open_conn()?;
+        let query = format!(
+            "SELECT f.id, f.file_id, f.person_id, p.name, f.embedding
+             FROM faces f
+             JOIN afiles a ON f.file_id = a.id
+             JOIN afolders b ON a.folder_id = b.id
+             LEFT JOIN persons p ON f.person_id = p.id
+             WHERE f.embedding IS NOT NULL AND {}",
+            AFile::search_exclusion_condition("b")
+        );
+        let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
+
+        let faces = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        Ok(faces)
+    }
+
     /// Reset all face assignments and delete all persons (for re-clustering)
     pub fn reset_all_assignments() -> Result<(), String> {
         let conn = open_conn()?;
@@ -8483,6 +9086,30 @@ pub(crate) fn open_conn() -> Result<PooledConn, String> {
     Ok(PooledConn(Some(create_conn()?)))
 }
 
+/// Get a setting stored in the current library's database
+pub fn get_library_setting(key: &str) -> Result<Option<String>, String> {
+    let conn = open_conn()?;
+    conn.query_row(
+        "SELECT value FROM library_settings WHERE key = ?1",
+        params![key],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Save a setting in the current library's database
+pub fn save_library_setting(key: &str, value: &str) -> Result<(), String> {
+    let conn = open_conn()?;
+    conn.execute(
+        "INSERT INTO library_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![key, value],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
 /// create all tables if not exists
 pub fn create_db() -> Result<(), String> {
     match create_db_internal() {
@@ -8521,7 +9148,9 @@ fn create_db_internal() -> Result<(), String> {
             failed_size INTEGER NOT NULL DEFAULT 0,
             merged_count INTEGER NOT NULL DEFAULT 0,
             merged_size INTEGER NOT NULL DEFAULT 0,
-            last_scan_time INTEGER DEFAULT 0
+            last_scan_time INTEGER DEFAULT 0,
+            volume_uuid TEXT,
+            volume_path TEXT
         )",
         [],
     )
@@ -8629,6 +9258,7 @@ fn create_db_internal() -> Result<(), String> {
             media_subtype TEXT,
             live_photo_video_id INTEGER,
             motion_photo_offset INTEGER,
+            content_fingerprint TEXT,
             FOREIGN KEY (folder_id) REFERENCES afolders(id) ON DELETE CASCADE
         )",
         [],
@@ -8898,7 +9528,8 @@ fn create_db_internal() -> Result<(), String> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS file_hashes (
             file_id INTEGER PRIMARY KEY,
-            hash TEXT NOT NULL,
+            hash TEXT,
+            sample_hash TEXT,
             file_size INTEGER NOT NULL,
             mtime INTEGER NOT NULL,
             computed_at INTEGER NOT NULL,
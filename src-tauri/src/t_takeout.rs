@@ -0,0 +1,871 @@
+/**
+ * t_takeout.rs - Google Photos Takeout import
+ *
+ * Takeout exports strip EXIF dates and GPS from many files and keep them in JSON
+ * sidecars (`IMG_0001.JPG.json`, `IMG_0001.JPG.supplemental-metadata.json`) whose
+ * names are cut to 46 characters and carry duplicate `(1)` suffixes in odd places.
+ * A scan pairs every media file with its sidecar, merges the copies that Takeout
+ * puts in both the year folders and the album folders, and checks the library for
+ * duplicates. Importing copies the media like a card import, applies the sidecar's
+ * taken time, location, description and favorite to the catalog (and optionally
+ * to the files), and turns the Takeout albums into collections.
+ */
+use crate::t_import::{self, ImportFailure, ImportFile, ImportStatus};
+use crate::t_sqlite::{ACollection, AFile, AFolder, Album};
+use crate::{t_image, t_utils};
+use chrono::{Local, TimeZone};
+use exif::Tag;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::Emitter;
+use walkdir::WalkDir;
+
+const SUPPLEMENTAL_SUFFIX: &str = ".supplemental-metadata";
+
+// edited copies share the sidecar of their original
+const EDITED_SUFFIXES: &[&str] = &[
+    "-edited",
+    "-bearbeitet",
+    "-modifié",
+    "-editado",
+    "-modificato",
+    "-bewerkt",
+];
+
+const MAX_UNMATCHED_NAMES: usize = 200; // unmatched files listed in the scan result
+
+// ----------------------------------------------------------------------------
+// Types and Structs
+// ----------------------------------------------------------------------------
+
+/// Metadata read from a Takeout JSON sidecar
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TakeoutMeta {
+    pub title: Option<String>, // original file name
+    pub taken_date: Option<i64>,
+    pub gps: Option<(f64, f64, Option<f64>)>, // latitude, longitude, altitude
+    pub description: Option<String>,
+    pub favorited: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct TakeoutMedia {
+    pub file: ImportFile,
+    pub meta: Option<TakeoutMeta>,
+    pub albums: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TakeoutAlbum {
+    pub name: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TakeoutSession {
+    pub id: String,
+    pub source_path: String,
+    pub media_count: usize,
+    pub total_size: i64,
+    pub matched_count: usize,   // media with a sidecar
+    pub unmatched: Vec<String>, // relative paths of media without a sidecar (first few)
+    pub unmatched_count: usize,
+    pub copy_count: usize, // album copies merged into their year-folder original
+    pub duplicate_count: usize, // already in the library
+    pub albums: Vec<TakeoutAlbum>,
+    #[serde(skip)]
+    pub media: Vec<TakeoutMedia>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TakeoutOptions {
+    pub album_id: i64,
+    pub dest_template: String, // relative to the album root, see `t_import::expand_template`
+    pub skip_duplicates: bool,
+    pub write_files: bool, // also write dates, GPS and descriptions into the copied files
+    pub create_collections: bool,
+}
+
+#[derive(Default)]
+pub struct TakeoutState {
+    pub sessions: Arc<Mutex<HashMap<String, TakeoutSession>>>,
+    pub is_running: Arc<AtomicBool>,
+    pub cancel_flag: Arc<AtomicBool>,
+    pub status: Arc<Mutex<ImportStatus>>,
+}
+
+/// A sidecar as it is matched against media names
+#[derive(Debug, Clone)]
+struct Sidecar {
+    key: String, // lowercase file name without `.json`, `(n)` and the supplemental suffix
+    dup: u32,    // the `(n)` duplicate index, 0 for none
+    meta: TakeoutMeta,
+}
+
+#[derive(Default)]
+struct TakeoutDir {
+    album_title: Option<String>, // from the album's metadata.json, empty when it has none
+    media: Vec<ImportFile>,
+    sidecars: Vec<Sidecar>,
+}
+
+// ----------------------------------------------------------------------------
+// Scanning
+// ----------------------------------------------------------------------------
+
+/// Scan an extracted Takeout folder into a new session.
+pub fn scan_source(source_path: &str) -> Result<TakeoutSession, String> {
+    let source = Path::new(source_path);
+    if !source.is_dir() {
+        return Err(format!("Takeout folder not found: {}", source_path));
+    }
+
+    let mut dirs: BTreeMap<PathBuf, TakeoutDir> = BTreeMap::new();
+    for entry in WalkDir::new(source)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|entry| !t_import::is_hidden(entry.file_name().to_str().unwrap_or("")))
+        .filter_map(Result::ok)
+    {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let dir = dirs
+            .entry(path.parent().unwrap_or(source).to_path_buf())
+            .or_default();
+
+        if name.to_lowercase().ends_with(".json") {
+            let Some(value) = fs::read_to_string(path)
+                .ok()
+                .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok())
+            else {
+                continue;
+            };
+            if value.get("photoTakenTime").is_some() {
+                if let Some((key, dup)) = parse_sidecar_name(&name) {
+                    dir.sidecars.push(Sidecar {
+                        key,
+                        dup,
+                        meta: parse_meta(&value),
+                    });
+                }
+            } else if let Some(title) = value.get("title").and_then(|title| title.as_str()) {
+                // album metadata (metadata.json, localized in some exports)
+                dir.album_title = Some(title.trim().to_string());
+            }
+            continue;
+        }
+
+        let Some(path_str) = path.to_str() else {
+            continue;
+        };
+        let Some(file_type) = t_utils::get_file_type(path_str) else {
+            continue;
+        };
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        dir.media.push(ImportFile {
+            path: path_str.to_string(),
+            name,
+            size: metadata.len() as i64,
+            file_type,
+            taken_date: t_utils::systemtime_to_timestamp(metadata.modified().ok()).unwrap_or(0),
+            duplicate: None,
+            hash: None,
+            duplicate_of: None,
+        });
+    }
+
+    let mut media: Vec<TakeoutMedia> = Vec::new();
+    let mut merged: HashMap<(String, i64), Vec<usize>> = HashMap::new();
+    let mut matched_count = 0;
+    let mut unmatched = Vec::new();
+    let mut unmatched_count = 0;
+    let mut copy_count = 0;
+    for (dir_path, dir) in dirs {
+        let album = get_album_name(&dir_path, &dir);
+        for mut file in dir.media {
+            let meta = match_sidecar(&file.name, &dir.sidecars)
+                .map(|index| dir.sidecars[index].meta.clone());
+            file.taken_date = match meta.as_ref().and_then(|meta| meta.taken_date) {
+                Some(taken_date) => taken_date,
+                None => {
+                    let modified = UNIX_EPOCH + Duration::from_secs(file.taken_date.max(0) as u64);
+                    t_import::get_taken_date(&file.path, file.file_type, Some(modified))
+                }
+            };
+
+            // the same photo appears in its year folder and in every album folder;
+            // copies agree on the taken time unless one of them lost its sidecar
+            let key = (file.name.to_lowercase(), file.size);
+            let copy_of = merged.get(&key).and_then(|indexes| {
+                indexes.iter().copied().find(|index| {
+                    let existing = &media[*index];
+                    existing.file.taken_date == file.taken_date
+                        || existing.meta.is_none()
+                        || meta.is_none()
+                })
+            });
+            if let Some(index) = copy_of {
+                let existing = &mut media[index];
+                if let Some(album) = &album {
+                    if !existing.albums.contains(album) {
+                        existing.albums.push(album.clone());
+                    }
+                }
+                if existing.meta.is_none() && meta.is_some() {
+                    existing.file.taken_date = file.taken_date;
+                    existing.meta = meta;
+                }
+                copy_count += 1;
+                continue;
+            }
+            merged.entry(key).or_default().push(media.len());
+            media.push(TakeoutMedia {
+                file,
+                meta,
+                albums: album.iter().cloned().collect(),
+            });
+        }
+    }
+
+    let index = t_import::load_library_index()?;
+    let mut total_size = 0;
+    let mut duplicate_count = 0;
+    let mut album_counts: BTreeMap<String, usize> = BTreeMap::new();
+    for item in media.iter_mut() {
+        t_import::detect_duplicate(&index, &mut item.file);
+        total_size += item.file.size;
+        if item.file.duplicate.is_some() {
+            duplicate_count += 1;
+        }
+        if item.meta.is_some() {
+            matched_count += 1;
+        } else {
+            unmatched_count += 1;
+            if unmatched.len() < MAX_UNMATCHED_NAMES {
+                let relative = Path::new(&item.file.path)
+                    .strip_prefix(source)
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .unwrap_or_else(|_| item.file.name.clone());
+                unmatched.push(relative);
+            }
+        }
+        for album in &item.albums {
+            *album_counts.entry(album.clone()).or_default() += 1;
+        }
+    }
+    media.sort_by(|a, b| {
+        a.file
+            .taken_date
+            .cmp(&b.file.taken_date)
+            .then_with(|| a.file.name.cmp(&b.file.name))
+    });
+
+    Ok(TakeoutSession {
+        id: format!("{}", t_import::now_millis()),
+        source_path: source_path.to_string(),
+        media_count: media.len(),
+        total_size,
+        matched_count,
+        unmatched,
+        unmatched_count,
+        copy_count,
+        duplicate_count,
+        albums: album_counts
+            .into_iter()
+            .map(|(name, count)| TakeoutAlbum { name, count })
+            .collect(),
+        media,
+    })
+}
+
+/// Keep a scanned session until it is imported or closed
+pub fn open_session(state: &TakeoutState, session: &TakeoutSession) {
+    state
+        .sessions
+        .lock()
+        .unwrap()
+        .insert(session.id.clone(), session.clone());
+}
+
+/// Forget a session once its dialog is closed
+pub fn close_session(state: &TakeoutState, session_id: &str) {
+    state.sessions.lock().unwrap().remove(session_id);
+}
+
+/// The Takeout album a folder stands for. Only album folders carry album
+/// metadata; year folders ("Photos from 2019", localized in other languages)
+/// and the source root are no albums.
+fn get_album_name(dir_path: &Path, dir: &TakeoutDir) -> Option<String> {
+    let title = dir.album_title.as_ref()?;
+    if !title.is_empty() {
+        return Some(title.clone());
+    }
+    Some(dir_path.file_name()?.to_string_lossy().into_owned())
+}
+
+// ----------------------------------------------------------------------------
+// Sidecars
+// ----------------------------------------------------------------------------
+
+/// Read the fields Lap uses from a sidecar. A 0/0 position means "unknown" in
+/// Takeout; `geoDataExif` is the fallback for `geoData`.
+fn parse_meta(value: &serde_json::Value) -> TakeoutMeta {
+    let text = |value: &serde_json::Value| -> Option<String> {
+        value
+            .as_str()
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+    let taken_date = value
+        .get("photoTakenTime")
+        .and_then(|time| time.get("timestamp"))
+        .and_then(|timestamp| {
+            timestamp
+                .as_i64()
+                .or_else(|| timestamp.as_str().and_then(|text| text.parse().ok()))
+        })
+        .filter(|timestamp| *timestamp > 0);
+    let gps = ["geoData", "geoDataExif"].iter().find_map(|key| {
+        let geo = value.get(*key)?;
+        let latitude = geo.get("latitude")?.as_f64()?;
+        let longitude = geo.get("longitude")?.as_f64()?;
+        if latitude == 0.0 && longitude == 0.0 {
+            return None;
+        }
+        let altitude = geo
+            .get("altitude")
+            .and_then(|altitude| altitude.as_f64())
+            .filter(|altitude| *altitude != 0.0);
+        Some((latitude, longitude, altitude))
+    });
+
+    TakeoutMeta {
+        title: value.get("title").and_then(text),
+        taken_date,
+        gps,
+        description: value.get("description").and_then(text),
+        favorited: value
+            .get("favorited")
+            .and_then(|favorited| favorited.as_bool())
+            .unwrap_or(false),
+    }
+}
+
+/// Split a sidecar file name into its match key and duplicate index:
+/// `IMG_0001.JPG.supplemental-metadata(1).json` -> (`img_0001.jpg`, 1).
+/// The supplemental suffix may itself be truncated (`.supplemental-meta`, `.sup`).
+fn parse_sidecar_name(file_name: &str) -> Option<(String, u32)> {
+    let lower = file_name.to_lowercase();
+    let stem = lower.strip_suffix(".json")?;
+    let (stem, dup) = split_dup_suffix(stem);
+    let stem = match stem.rfind('.') {
+        Some(pos) if pos + 1 < stem.len() && SUPPLEMENTAL_SUFFIX.starts_with(&stem[pos..]) => {
+            &stem[..pos]
+        }
+        _ => stem,
+    };
+    (!stem.is_empty()).then(|| (stem.to_string(), dup))
+}
+
+/// `name(2)` -> (`name`, 2); names without a numeric suffix come back unchanged
+fn split_dup_suffix(name: &str) -> (&str, u32) {
+    if let Some(inner) = name.strip_suffix(')') {
+        if let Some(open) = inner.rfind('(') {
+            let digits = &inner[open + 1..];
+            if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+                if let Ok(dup) = digits.parse() {
+                    return (&name[..open], dup);
+                }
+            }
+        }
+    }
+    (name, 0)
+}
+
+fn split_ext(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(pos) if pos > 0 => (&name[..pos], &name[pos..]),
+        _ => (name, ""),
+    }
+}
+
+/// Find the sidecar of a media file among the sidecars of its folder. Tries the
+/// name as it is, then with its `(n)` moved to the sidecar side
+/// (`IMG(1).JPG` -> `IMG.JPG(1).json`), then without an "edited" suffix.
+fn match_sidecar(media_name: &str, sidecars: &[Sidecar]) -> Option<usize> {
+    let lower = media_name.to_lowercase();
+    let (stem, ext) = split_ext(&lower);
+    let (bare_stem, dup) = split_dup_suffix(stem);
+
+    let mut candidates = vec![(lower.clone(), 0)];
+    if dup > 0 {
+        candidates.push((format!("{}{}", bare_stem, ext), dup));
+    }
+    for suffix in EDITED_SUFFIXES {
+        if let Some(original) = bare_stem.strip_suffix(suffix) {
+            candidates.push((format!("{}{}", original, ext), dup));
+        }
+    }
+
+    candidates
+        .iter()
+        .find_map(|(base, dup)| best_sidecar(base, *dup, sidecars))
+}
+
+/// Score the sidecars with the given duplicate index against a media name:
+/// exact name or title, then a truncated name (a prefix of the full sidecar name
+/// that still covers the media stem), then a sidecar of another file with the
+/// same stem (the video half of a Live Photo shares the photo's sidecar).
+fn best_sidecar(base: &str, dup: u32, sidecars: &[Sidecar]) -> Option<usize> {
+    let full = format!("{}{}", base, SUPPLEMENTAL_SUFFIX);
+    let (stem, _) = split_ext(base);
+
+    let mut best: Option<(u8, usize, usize)> = None; // (score, key length, index)
+    for (index, sidecar) in sidecars.iter().enumerate() {
+        if sidecar.dup != dup {
+            continue;
+        }
+        let title_matches = sidecar
+            .meta
+            .title
+            .as_deref()
+            .is_some_and(|title| title.to_lowercase() == base);
+        let score = if sidecar.key == base || (dup == 0 && title_matches) {
+            3
+        } else if sidecar.key.len() >= stem.len() && full.starts_with(&sidecar.key) {
+            2
+        } else if split_ext(&sidecar.key).0 == stem {
+            1
+        } else {
+            continue;
+        };
+        let candidate = (score, sidecar.key.len(), index);
+        if best.is_none_or(|(best_score, best_len, _)| {
+            (score, sidecar.key.len()) > (best_score, best_len)
+        }) {
+            best = Some(candidate);
+        }
+    }
+    best.map(|(_, _, index)| index)
+}
+
+// ----------------------------------------------------------------------------
+// Importing
+// ----------------------------------------------------------------------------
+
+pub fn start_import(
+    app_handle: tauri::AppHandle,
+    state: &TakeoutState,
+    session_id: &str,
+    options: TakeoutOptions,
+) -> Result<(), String> {
+    let session = state
+        .sessions
+        .lock()
+        .unwrap()
+        .get(session_id)
+        .cloned()
+        .ok_or_else(|| "Takeout session not found.".to_string())?;
+    let album = Album::get_album_by_id(options.album_id)?;
+    if !t_utils::album_online(&album) {
+        return Err("The destination album is offline.".into());
+    }
+
+    if state
+        .is_running
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return Err("A Takeout import is already running.".into());
+    }
+    state.cancel_flag.store(false, Ordering::SeqCst);
+
+    let status_clone = state.status.clone();
+    let is_running_clone = state.is_running.clone();
+    let cancel_flag_clone = state.cancel_flag.clone();
+
+    *status_clone.lock().unwrap() = ImportStatus {
+        state: "running".to_string(),
+        is_running: true,
+        ..ImportStatus::default()
+    };
+
+    std::thread::spawn(move || {
+        let result = run_import(
+            &app_handle,
+            &status_clone,
+            &cancel_flag_clone,
+            &session,
+            &album,
+            &options,
+        );
+
+        let mut final_status = status_clone.lock().unwrap();
+        match result {
+            Ok(_) => {
+                if cancel_flag_clone.load(Ordering::SeqCst) {
+                    final_status.state = "idle".to_string();
+                } else {
+                    final_status.state = "finished".to_string();
+                }
+            }
+            Err(e) => {
+                eprintln!("Takeout import error: {}", e);
+                final_status.state = "error".to_string();
+            }
+        }
+
+        is_running_clone.store(false, Ordering::SeqCst);
+        final_status.is_running = false;
+        let _ = app_handle.emit("takeout-import-progress", final_status.clone());
+    });
+
+    Ok(())
+}
+
+fn run_import(
+    app_handle: &tauri::AppHandle,
+    status: &Arc<Mutex<ImportStatus>>,
+    cancel_flag: &Arc<AtomicBool>,
+    session: &TakeoutSession,
+    album: &Album,
+    options: &TakeoutOptions,
+) -> Result<(), String> {
+    let album_id = album.id.ok_or("Album has no id")?;
+    let template = if options.dest_template.trim().is_empty() {
+        t_import::DEFAULT_TEMPLATE
+    } else {
+        options.dest_template.as_str()
+    };
+
+    status.lock().unwrap().total = session.media.len() as u64;
+    let _ = app_handle.emit("takeout-import-progress", status.lock().unwrap().clone());
+
+    let mut imported_count = 0;
+    let mut folders: HashMap<PathBuf, i64> = HashMap::new();
+    let mut touched_folders: HashMap<i64, HashSet<String>> = HashMap::new();
+    let mut album_files: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+    for media in &session.media {
+        if cancel_flag.load(Ordering::SeqCst) {
+            break;
+        }
+
+        if options.skip_duplicates && media.file.duplicate.is_some() {
+            // the library copy still joins the Takeout albums of the skipped file
+            if let Some(file_id) = media.file.duplicate_of {
+                for album_name in &media.albums {
+                    album_files
+                        .entry(album_name.clone())
+                        .or_default()
+                        .push(file_id);
+                }
+            }
+            status.lock().unwrap().skipped += 1;
+        } else {
+            let relative = t_import::expand_template(template, media.file.taken_date, "");
+            let dest_folder = Path::new(&album.path).join(&relative);
+            match import_media(
+                media,
+                album_id,
+                &dest_folder,
+                options.write_files,
+                &mut folders,
+            ) {
+                Ok((folder_id, file_id, name, undated)) => {
+                    imported_count += 1;
+                    if undated {
+                        status.lock().unwrap().undated.push(media.file.path.clone());
+                    }
+                    touched_folders.entry(folder_id).or_default().insert(name);
+                    for album_name in &media.albums {
+                        album_files
+                            .entry(album_name.clone())
+                            .or_default()
+                            .push(file_id);
+                    }
+                    status.lock().unwrap().copied += 1;
+                }
+                Err(e) => status.lock().unwrap().failures.push(ImportFailure {
+                    path: media.file.path.clone(),
+                    error: e,
+                }),
+            }
+        }
+
+        let mut s = status.lock().unwrap();
+        s.processed += 1;
+        let _ = app_handle.emit("takeout-import-progress", s.clone());
+    }
+
+    // Takeout keeps Live Photo halves side by side in the same folder
+    for (folder_id, names) in &touched_folders {
+        if let Err(e) = AFile::pair_live_photos_in_folder(*folder_id, names, false) {
+            eprintln!("Failed to pair Live Photos in folder {}: {}", folder_id, e);
+        }
+        if let Err(e) = AFile::pair_raw_jpeg_in_folder(*folder_id) {
+            eprintln!("Failed to pair RAW+JPEG in folder {}: {}", folder_id, e);
+        }
+    }
+
+    if options.create_collections {
+        for (name, file_ids) in album_files {
+            let collection = ACollection::get_or_create(&name)?;
+            ACollection::add_files(collection.id, file_ids)?;
+        }
+    }
+
+    if imported_count > 0 {
+        Album::recount_album(album_id)?;
+    }
+    Ok(())
+}
+
+/// Copy one media file, restore its sidecar metadata and add it to the library.
+/// Returns the folder id, the new file id, the file name and whether the sidecar
+/// date was meant for the file but could not be written (only JPEGs are written).
+fn import_media(
+    media: &TakeoutMedia,
+    album_id: i64,
+    dest_folder: &Path,
+    write_files: bool,
+    folders: &mut HashMap<PathBuf, i64>,
+) -> Result<(i64, i64, String, bool), String> {
+    let folder_id = match folders.get(dest_folder) {
+        Some(folder_id) => *folder_id,
+        None => {
+            fs::create_dir_all(dest_folder)
+                .map_err(|e| format!("Failed to create folder: {}", e))?;
+            let folder = AFolder::add_to_db(album_id, &dest_folder.to_string_lossy())?;
+            let folder_id = folder.id.ok_or("Folder has no id")?;
+            folders.insert(dest_folder.to_path_buf(), folder_id);
+            folder_id
+        }
+    };
+
+    // a file that appears between picking the name and copying takes the next name
+    let destination = loop {
+        let destination = t_utils::get_unique_path(dest_folder.join(&media.file.name));
+        if let Some(destination) = t_import::copy_verified(&media.file, destination)? {
+            break destination;
+        }
+    };
+    let dest_path = destination.to_string_lossy().into_owned();
+    if write_files {
+        if let Some(meta) = &media.meta {
+            write_file_metadata(&dest_path, meta);
+        }
+    }
+
+    let (new_file, _) = match AFile::add_to_db(
+        folder_id,
+        &dest_path,
+        media.file.file_type,
+        t_import::now_millis(),
+    ) {
+        Ok(result) => result,
+        Err(e) => {
+            let _ = fs::remove_file(&destination);
+            return Err(e);
+        }
+    };
+    let file_id = new_file.id.unwrap_or(0);
+
+    let mut undated = false;
+    if let Some(meta) = &media.meta {
+        // the file's own metadata wins; the sidecar fills what the export stripped
+        let has_date = new_file
+            .e_date_time
+            .as_deref()
+            .is_some_and(|date| !date.is_empty());
+        undated = write_files
+            && !has_date
+            && meta.taken_date.is_some()
+            && !t_image::is_jpeg_path(&dest_path);
+        let has_comments = new_file
+            .comments
+            .as_deref()
+            .is_some_and(|comments| !comments.is_empty());
+        AFile::update_capture_metadata(
+            file_id,
+            meta.taken_date.filter(|_| !has_date),
+            meta.gps.filter(|_| new_file.gps_latitude.is_none()),
+            meta.description.as_deref().filter(|_| !has_comments),
+            meta.favorited.then_some(true),
+        )?;
+    }
+    Ok((folder_id, file_id, new_file.name, undated))
+}
+
+/// Write the sidecar's date, position and description into a copied JPEG where
+/// the file lacks them, and date every copied file by its taken time.
+fn write_file_metadata(file_path: &str, meta: &TakeoutMeta) {
+    if t_image::is_jpeg_path(file_path) {
+        let exif = t_image::read_exif_permissive(file_path);
+        let date_time = meta
+            .taken_date
+            .filter(|_| AFile::get_exif_field(&exif, Tag::DateTimeOriginal).is_none())
+            .and_then(|taken_date| Local.timestamp_opt(taken_date, 0).single())
+            .map(|date| date.format("%Y:%m:%d %H:%M:%S").to_string());
+        let gps = meta
+            .gps
+            .filter(|_| AFile::get_exif_field(&exif, Tag::GPSLatitude).is_none());
+        let description = meta
+            .description
+            .as_deref()
+            .filter(|_| AFile::get_exif_field(&exif, Tag::ImageDescription).is_none());
+        if date_time.is_some() || gps.is_some() || description.is_some() {
+            if let Err(e) = t_image::write_jpeg_capture_metadata(
+                file_path,
+                date_time.as_deref(),
+                gps,
+                description,
+            ) {
+                eprintln!("Failed to write Takeout metadata to '{}': {}", file_path, e);
+            }
+        }
+    }
+
+    if let Some(taken_date) = meta.taken_date {
+        let modified = UNIX_EPOCH + Duration::from_secs(taken_date.max(0) as u64);
+        let _ = fs::File::options()
+            .write(true)
+            .open(file_path)
+            .and_then(|file| file.set_modified(modified));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sidecar(file_name: &str) -> Sidecar {
+        let (key, dup) = parse_sidecar_name(file_name).unwrap();
+        Sidecar {
+            key,
+            dup,
+            meta: TakeoutMeta::default(),
+        }
+    }
+
+    fn matched<'a>(media_name: &str, sidecars: &'a [Sidecar]) -> Option<&'a str> {
+        match_sidecar(media_name, sidecars).map(|index| sidecars[index].key.as_str())
+    }
+
+    #[test]
+    fn parses_sidecar_names() {
+        assert_eq!(
+            parse_sidecar_name("IMG_0001.JPG.json"),
+            Some(("img_0001.jpg".to_string(), 0))
+        );
+        assert_eq!(
+            parse_sidecar_name("IMG_0001.JPG.supplemental-metadata(1).json"),
+            Some(("img_0001.jpg".to_string(), 1))
+        );
+        assert_eq!(
+            parse_sidecar_name("IMG_20200101_123456789.jpg.supplemental-metad.json"),
+            Some(("img_20200101_123456789.jpg".to_string(), 0))
+        );
+        assert_eq!(parse_sidecar_name("IMG_0001.JPG"), None);
+    }
+
+    #[test]
+    fn matches_plain_and_supplemental_sidecars() {
+        let sidecars = [
+            sidecar("IMG_0001.JPG.json"),
+            sidecar("IMG_0002.JPG.supplemental-metadata.json"),
+        ];
+        assert_eq!(matched("IMG_0001.JPG", &sidecars), Some("img_0001.jpg"));
+        assert_eq!(matched("IMG_0002.JPG", &sidecars), Some("img_0002.jpg"));
+        assert_eq!(matched("IMG_0003.JPG", &sidecars), None);
+    }
+
+    #[test]
+    fn matches_truncated_sidecars() {
+        // 46 characters of "<name>.supplemental-metadata" survive
+        let sidecars = [
+            sidecar("Screenshot_20190512-201530_Long_Application.j.json"),
+            sidecar("PXL_20230704_183012345.MP.jpg.supplemental-m.json"),
+        ];
+        assert_eq!(
+            matched("Screenshot_20190512-201530_Long_Application.jpg", &sidecars),
+            Some("screenshot_20190512-201530_long_application.j")
+        );
+        assert_eq!(
+            matched("PXL_20230704_183012345.MP.jpg", &sidecars),
+            Some("pxl_20230704_183012345.mp.jpg")
+        );
+        // a shorter name that is a prefix of the sidecar must not match
+        assert_eq!(matched("Screenshot_20190512.jpg", &sidecars), None);
+    }
+
+    #[test]
+    fn matches_duplicate_suffixes() {
+        let sidecars = [
+            sidecar("IMG_0001.JPG.json"),
+            sidecar("IMG_0001.JPG(1).json"),
+        ];
+        assert_eq!(match_sidecar("IMG_0001.JPG", &sidecars), Some(0));
+        assert_eq!(match_sidecar("IMG_0001(1).JPG", &sidecars), Some(1));
+        assert_eq!(match_sidecar("IMG_0001(2).JPG", &sidecars), None);
+    }
+
+    #[test]
+    fn matches_edited_and_live_photo_companions() {
+        let sidecars = [sidecar("IMG_0001.HEIC.json")];
+        assert_eq!(match_sidecar("IMG_0001-edited.HEIC", &sidecars), Some(0));
+        assert_eq!(match_sidecar("IMG_0001.MP4", &sidecars), Some(0));
+        assert_eq!(match_sidecar("IMG_0002.MP4", &sidecars), None);
+    }
+
+    #[test]
+    fn reads_sidecar_metadata() {
+        let value: serde_json::Value = serde_json::from_str(
+            r#"{
+                "title": "IMG_0001.JPG",
+                "description": " Beach ",
+                "photoTakenTime": { "timestamp": "1562244000", "formatted": "" },
+                "geoData": { "latitude": 0.0, "longitude": 0.0, "altitude": 0.0 },
+                "geoDataExif": { "latitude": 43.7, "longitude": 7.26, "altitude": 12.5 },
+                "favorited": true
+            }"#,
+        )
+        .unwrap();
+        let meta = parse_meta(&value);
+        assert_eq!(meta.title.as_deref(), Some("IMG_0001.JPG"));
+        assert_eq!(meta.taken_date, Some(1562244000));
+        assert_eq!(meta.gps, Some((43.7, 7.26, Some(12.5))));
+        assert_eq!(meta.description.as_deref(), Some("Beach"));
+        assert!(meta.favorited);
+    }
+
+    #[test]
+    fn names_only_folders_with_album_metadata() {
+        let album = |title: Option<&str>| TakeoutDir {
+            album_title: title.map(|title| title.to_string()),
+            ..Default::default()
+        };
+        let path = Path::new("/takeout/Google Photos/Fotos von 2019");
+        assert_eq!(get_album_name(path, &album(None)), None);
+        assert_eq!(
+            get_album_name(path, &album(Some("Trip 2019"))).as_deref(),
+            Some("Trip 2019")
+        );
+        assert_eq!(
+            get_album_name(Path::new("/takeout/Summer"), &album(Some(""))).as_deref(),
+            Some("Summer")
+        );
+    }
+}
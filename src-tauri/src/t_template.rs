@@ -0,0 +1,167 @@
+/**
+ * t_template.rs - Name templates shared by rename, import and organize
+ *
+ * A template is plain text with `{key}` or `{key:ARG}` tokens. Every feature
+ * supplies the values of its own tokens; the date tokens are the same everywhere.
+ * Expanded names are cleaned of the characters file systems reject.
+ */
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Local, TimeZone};
+use std::path::PathBuf;
+
+/// Expand the `{key}` and `{key:ARG}` tokens of a template. `value` returns the
+/// text of a token, or None to keep the token as typed.
+pub fn expand_tokens(
+    template: &str,
+    value: impl Fn(&str, Option<&str>) -> Option<String>,
+) -> String {
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let token = &rest[start + 1..start + len];
+        let (key, arg) = match token.split_once(':') {
+            Some((key, arg)) => (key, Some(arg)),
+            None => (token, None),
+        };
+        match value(key, arg) {
+            Some(value) => result.push_str(&value),
+            None => result.push_str(&rest[start..start + len + 1]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// The date tokens of every template: {yyyy}, {yy}, {mm}, {dd}, {hh}, {mi}, {ss},
+/// {yyyy-mm-dd} and {date:FORMAT} (strftime, `%Y-%m-%d` by default). They expand
+/// to nothing for an undated file. None for other tokens and invalid formats.
+pub fn date_token(key: &str, arg: Option<&str>, taken_date: Option<i64>) -> Option<String> {
+    let format = match key {
+        "yyyy" => "%Y",
+        "yy" => "%y",
+        "mm" => "%m",
+        "dd" => "%d",
+        "hh" => "%H",
+        "mi" => "%M",
+        "ss" => "%S",
+        "yyyy-mm-dd" => "%Y-%m-%d",
+        "date" => {
+            let format = arg.unwrap_or("%Y-%m-%d");
+            // chrono panics while rendering an invalid strftime string
+            if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                return None;
+            }
+            format
+        }
+        _ => return None,
+    };
+    let date = taken_date.and_then(|taken_date| Local.timestamp_opt(taken_date, 0).single());
+    Some(
+        date.map(|date| date.format(format).to_string())
+            .unwrap_or_default(),
+    )
+}
+
+/// Expand a folder template into a relative path. Token values are cleaned before
+/// they are inserted, so a value never adds a level. Each segment is trimmed of
+/// separators left dangling by empty values (`{mm} - {geo}` becomes `07` without a
+/// location), and empty, `.` and `..` segments are dropped.
+pub fn expand_folder_template(
+    template: &str,
+    value: impl Fn(&str, Option<&str>) -> Option<String>,
+) -> PathBuf {
+    let mut path = PathBuf::new();
+    for segment in template.split(['/', '\\']) {
+        let expanded = expand_tokens(segment, |key, arg| {
+            value(key, arg).map(|value| sanitize_segment(&value))
+        });
+        let expanded = sanitize_segment(&expanded);
+        let expanded = expanded.trim_matches(|c: char| c == '-' || c == '_' || c.is_whitespace());
+        if !expanded.is_empty() && expanded != "." && expanded != ".." {
+            path.push(expanded);
+        }
+    }
+    path
+}
+
+/// Replace the characters file systems reject and collapse runs of whitespace
+pub fn sanitize_segment(segment: &str) -> String {
+    let cleaned: String = segment
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    cleaned.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A cleaned file name, without leading or trailing dots
+pub fn sanitize_name(name: &str) -> String {
+    sanitize_segment(name).trim_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn taken_date() -> Option<i64> {
+        Local
+            .with_ymd_and_hms(2024, 7, 5, 9, 8, 7)
+            .single()
+            .map(|date| date.timestamp())
+    }
+
+    #[test]
+    fn expands_tokens_and_keeps_unknown_ones() {
+        let value = |key: &str, arg: Option<&str>| match key {
+            "name" => Some("IMG_0001".to_string()),
+            _ => date_token(key, arg, taken_date()),
+        };
+        assert_eq!(
+            expand_tokens("{yyyy-mm-dd} {hh}{mi}{ss} {name}", value),
+            "2024-07-05 090807 IMG_0001"
+        );
+        assert_eq!(expand_tokens("{date:%y%m} {lens}", value), "2407 {lens}");
+        assert_eq!(expand_tokens("{date:%Q} {name", value), "{date:%Q} {name");
+        assert_eq!(date_token("yyyy", None, None), Some(String::new()));
+    }
+
+    #[test]
+    fn expands_folder_templates() {
+        let value = |key: &str, arg: Option<&str>| match key {
+            "geo" => Some(String::new()),
+            "event" => Some("Rome/Naples: day 1".to_string()),
+            _ => date_token(key, arg, taken_date()),
+        };
+        // values never add a level, dangling separators and empty segments go away
+        assert_eq!(
+            expand_folder_template("{yyyy}//{mm} - {geo}/{event}", value),
+            Path::new("2024").join("07").join("Rome_Naples_ day 1")
+        );
+        assert_eq!(
+            expand_folder_template("{yyyy}\\{lens}", value),
+            Path::new("2024").join("{lens}")
+        );
+        assert_eq!(
+            expand_folder_template("../{geo}/_ - _/.", value),
+            PathBuf::new()
+        );
+    }
+
+    #[test]
+    fn sanitizes_names() {
+        assert_eq!(sanitize_name(" a/b:c  d. "), "a_b_c d");
+        assert_eq!(sanitize_name("{lens}"), "{lens}");
+        assert_eq!(sanitize_name("  "), "");
+        assert_eq!(sanitize_segment("..."), "...");
+    }
+}
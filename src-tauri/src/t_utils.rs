@@ -4,8 +4,13 @@
  * author:  julyx10
  * date:    2024-08-08
  */
+use crate::t_apple_sidecar;
 use crate::t_common;
+use crate::t_index_errors;
+use crate::t_ocr;
+use crate::t_scan_filter::ScanFilter;
 use crate::t_sqlite::{AFile, AFolder, AThumb, Album, FolderScanState, FolderSubfolderState};
+use crate::t_volume;
 use chrono::{DateTime, Local, TimeZone, Utc};
 use once_cell::sync::Lazy;
 use pinyin::ToPinyin;
@@ -142,6 +147,74 @@ pub fn delete_folder_permanently(path: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Check whether two paths point to the same file on disk (e.g. hardlinks)
+#[cfg(unix)]
+pub fn is_same_file(a: &str, b: &str) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(windows)]
+pub fn is_same_file(a: &str, b: &str) -> bool {
+    match (file_id(Path::new(a)), file_id(Path::new(b))) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Create `dst` as a copy-on-write clone of `src` (btrfs/XFS FICLONE).
+/// Fails when the filesystem doesn't support reflinks or the paths are on different volumes.
+#[cfg(target_os = "linux")]
+pub fn reflink_file(src: &str, dst: &str) -> Result<(), String> {
+    use std::os::unix::io::AsRawFd;
+    const FICLONE: libc::c_ulong = 0x4004_9409;
+
+    let src_file = fs::File::open(src).map_err(|e| e.to_string())?;
+    let dst_file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dst)
+        .map_err(|e| e.to_string())?;
+    let ret = unsafe { libc::ioctl(dst_file.as_raw_fd(), FICLONE as _, src_file.as_raw_fd()) };
+    if ret != 0 {
+        let error = std::io::Error::last_os_error();
+        drop(dst_file);
+        let _ = fs::remove_file(dst);
+        return Err(format!("Reflink failed: {}", error));
+    }
+    Ok(())
+}
+
+/// Create `dst` as a copy-on-write clone of `src` (APFS clonefile).
+#[cfg(target_os = "macos")]
+pub fn reflink_file(src: &str, dst: &str) -> Result<(), String> {
+    use libc::{c_char, c_int};
+    use std::ffi::CString;
+
+    unsafe extern "C" {
+        fn clonefile(src: *const c_char, dst: *const c_char, flags: u32) -> c_int;
+    }
+
+    let src_c = CString::new(src).map_err(|e| e.to_string())?;
+    let dst_c = CString::new(dst).map_err(|e| e.to_string())?;
+    let ret = unsafe { clonefile(src_c.as_ptr(), dst_c.as_ptr(), 0) };
+    if ret != 0 {
+        return Err(format!(
+            "Reflink failed: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn reflink_file(_src: &str, _dst: &str) -> Result<(), String> {
+    Err("Reflinks are not supported on this platform".to_string())
+}
+
 // reverse geocoder
 #[derive(serde::Deserialize)]
 pub struct GeoRecord {
@@ -649,6 +722,85 @@ pub fn restore_album_scopes(app_handle: &tauri::AppHandle) -> Result<(), String>
     Ok(())
 }
 
+/// Whether an album can be read from the volume it was indexed on. An empty
+/// mount directory or another drive mounted at the album path counts as offline.
+pub fn album_online(album: &Album) -> bool {
+    if !directory_accessible(&album.path) {
+        return false;
+    }
+    match album.volume_uuid.as_deref() {
+        Some(uuid) => t_volume::locate(&album.path).is_none_or(|volume| volume.uuid == uuid),
+        None => true,
+    }
+}
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumReconnection {
+    pub album_id: i64,
+    pub old_path: String,
+    pub new_path: String,
+}
+
+/// Record the volume of albums indexed before volumes were tracked, and move
+/// offline albums to wherever their volume is mounted now. Emits
+/// `album-reconnected` for moved albums and returns them.
+pub fn reconnect_offline_albums(app_handle: &tauri::AppHandle) -> Vec<AlbumReconnection> {
+    let mut reconnections = Vec::new();
+    let Ok(albums) = Album::get_all_albums() else {
+        return reconnections;
+    };
+    let album_paths: HashSet<String> = albums.iter().map(|album| album.path.clone()).collect();
+    for album in albums {
+        let Some(album_id) = album.id else {
+            continue;
+        };
+        if album_scan_active(album_id) || album_removal_pending(album_id) {
+            continue;
+        }
+        let (Some(uuid), Some(volume_path)) = (&album.volume_uuid, &album.volume_path) else {
+            if directory_accessible(&album.path) {
+                if let Some(volume) = t_volume::locate(&album.path) {
+                    let _ = Album::update_volume(album_id, &volume.uuid, &volume.relative_path);
+                }
+            }
+            continue;
+        };
+        if album_online(&album) {
+            continue;
+        }
+        let Some(new_path) = t_volume::resolve(uuid, volume_path) else {
+            continue;
+        };
+        if album_paths.contains(&new_path) || !directory_accessible(&new_path) {
+            continue;
+        }
+        let album_sync_lock = album_sync_lock(album_id);
+        let Ok(_album_sync_guard) = album_sync_lock.lock() else {
+            continue;
+        };
+        if let Err(error) = Album::rename_root_folder(&album.path, &new_path) {
+            eprintln!(
+                "Failed to reconnect album {} at {}: {}",
+                album_id, new_path, error
+            );
+            continue;
+        }
+        let _ = authorize_directory_scope(app_handle, &new_path);
+        reconnections.push(AlbumReconnection {
+            album_id,
+            old_path: album.path,
+            new_path,
+        });
+    }
+
+    if !reconnections.is_empty() {
+        let _ = app_handle.emit("album-reconnected", &reconnections);
+        let _ = app_handle.emit("library-total-refreshed", ());
+    }
+    reconnections
+}
+
 fn normalize_external_app_name(name: &str) -> String {
     let known_suffixes = [
         ".appimage",
@@ -857,7 +1009,7 @@ pub fn rename_folder(folder_path: &str, new_folder_name: &str) -> Option<String>
 
 /// Checks if a path exists, and if so, returns a new unique path
 /// by appending a number like (1), (2), etc.
-fn get_unique_path(path: PathBuf) -> PathBuf {
+pub(crate) fn get_unique_path(path: PathBuf) -> PathBuf {
     if !path.exists() {
         return path;
     }
@@ -1216,6 +1368,111 @@ pub fn move_file_to_path_with_policy(
     Ok(TransferResult::new(&destination, backup))
 }
 
+/// Move a file and its sidecar components to another folder, update their rows
+/// and relocate their thumbnail cache. The moves are rolled back if the database
+/// update fails.
+pub fn move_file_group(
+    file_id: i64,
+    file_path: &str,
+    new_folder_id: i64,
+    new_folder_path: &str,
+    policy: FileConflictPolicy,
+) -> Result<String, String> {
+    let old_file_info = AFile::get_file_info(file_id).ok().flatten();
+    let old_album_id = old_file_info.as_ref().and_then(|file| file.album_id);
+    let new_album_id = AFolder::get_by_id(new_folder_id)
+        .ok()
+        .flatten()
+        .map(|folder| folder.album_id);
+    let (primary_target, sidecar_plans) = t_apple_sidecar::resolve_group_primary_target(
+        Some(file_id),
+        file_path,
+        new_folder_path,
+        policy,
+    )?;
+    let mut replaced_file_ids = Vec::new();
+    let mut source_file_ids = HashSet::from([file_id]);
+    for plan in &sidecar_plans {
+        if let Some(component_id) = plan.file_id {
+            source_file_ids.insert(component_id);
+        }
+    }
+    if policy == FileConflictPolicy::Replace {
+        t_apple_sidecar::collect_replaced_file_ids_for_targets(
+            new_folder_id,
+            std::iter::once(&primary_target).chain(sidecar_plans.iter().map(|plan| &plan.new_path)),
+            &source_file_ids,
+            &mut replaced_file_ids,
+        );
+    }
+
+    let mut component_transfers = Vec::new();
+    for plan in &sidecar_plans {
+        let source_path = plan.old_path.to_string_lossy().into_owned();
+        match move_file_to_path_with_policy(&source_path, &plan.new_path, policy) {
+            Ok(transfer) => {
+                component_transfers.push((plan.file_id, plan.old_path.clone(), transfer))
+            }
+            Err(error) => {
+                for (_, original_path, transfer) in component_transfers {
+                    let _ = transfer.rollback_move(&original_path);
+                }
+                return Err(error);
+            }
+        }
+    }
+
+    let transfer = match move_file_to_path_with_policy(file_path, &primary_target, policy) {
+        Ok(transfer) => transfer,
+        Err(error) => {
+            for (_, original_path, transfer) in component_transfers {
+                let _ = transfer.rollback_move(&original_path);
+            }
+            return Err(error);
+        }
+    };
+
+    let component_file_ids = component_transfers
+        .iter()
+        .filter_map(|(component_id, _, _)| *component_id)
+        .collect::<Vec<_>>();
+    if let Err(error) = AFile::update_moved_file_group(
+        file_id,
+        &component_file_ids,
+        &replaced_file_ids,
+        new_folder_id,
+    ) {
+        let rollback_error = transfer.rollback_move(Path::new(file_path)).err();
+        for (_, original_path, transfer) in component_transfers {
+            let _ = transfer.rollback_move(&original_path);
+        }
+        return Err(match rollback_error {
+            Some(rollback_error) => format!(
+                "Error while moving file group in DB: {}; rollback also failed: {}",
+                error, rollback_error
+            ),
+            None => format!("Error while moving file group in DB: {}", error),
+        });
+    }
+
+    if let (Some(old_album_id), Some(new_album_id)) = (old_album_id, new_album_id) {
+        let _ = AThumb::relocate_for_file(file_id, old_album_id, new_album_id)
+            .map_err(|e| format!("Error while relocating thumbnail cache: {}", e));
+    }
+    for (component_id, _, transfer) in component_transfers {
+        if let Some(component_id) = component_id {
+            if let (Some(old_album_id), Some(new_album_id)) = (old_album_id, new_album_id) {
+                let _ = AThumb::relocate_for_file(component_id, old_album_id, new_album_id)
+                    .map_err(|e| {
+                        format!("Error while relocating Live Photo thumbnail cache: {}", e)
+                    });
+            }
+        }
+        transfer.finalize()?;
+    }
+    transfer.finalize()
+}
+
 pub fn copy_file_to_path_with_policy(
     file_path: &str,
     destination: &Path,
@@ -1563,6 +1820,7 @@ pub fn get_folder_files(
             }
         }
     } else {
+        let scan_filter = album_scan_filter(folder.album_id);
         let mut file_list = Vec::new();
         for entry in WalkDir::new(folder_path)
             .min_depth(1)
@@ -1577,6 +1835,10 @@ pub fn get_folder_files(
                 Some(p) => p,
                 None => continue,
             };
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            if scan_filter.excludes_file(path, size) {
+                continue;
+            }
 
             if let Some(ftype) = get_file_type(file_path_str) {
                 let now = Utc::now().timestamp_millis();
@@ -1664,9 +1926,17 @@ struct SyncedFileTask {
 struct FolderSyncOutcome {
     new_file_count: u32,
     updated_file_count: u32,
-    deleted_file_count: u32,
     rename_count: u32,
     tasks: Vec<SyncedFileTask>,
+    missing_files: Vec<MissingFile>,
+}
+
+/// A file row whose file was not found by a folder sync. Deletion waits until
+/// the rest of the sync pass had a chance to re-attach it at a new location.
+pub struct MissingFile {
+    file_id: i64,
+    folder_id: i64,
+    name: String,
 }
 
 const FOLDER_SYNC_THUMBNAIL_SIZE: u32 = 512;
@@ -1786,6 +2056,7 @@ pub fn start_folder_mtime_sync(app_handle: tauri::AppHandle) {
         .wrapping_add(1);
 
     tauri::async_runtime::spawn(async move {
+        reconnect_offline_albums(&app_handle);
         match sync_dirty_folders_by_mtime(generation) {
             Ok((result, tasks)) => {
                 if !sync_generation_valid(generation) {
@@ -1819,11 +2090,11 @@ fn sync_dirty_folders_by_mtime(
     let mut new_folder_count = 0u32;
     let mut new_file_count = 0u32;
     let mut updated_file_count = 0u32;
-    let mut deleted_file_count = 0u32;
     let mut rename_count = 0u32;
     let mut deleted_folder_count = 0u32;
     let mut folder_path_migrations = Vec::new();
     let mut tasks = Vec::new();
+    let mut missing_files = Vec::new();
     let mut queue = Vec::new();
     let mut album_accessibility = HashMap::new();
     let pending_live_photo_folders = FolderScanState::folders_needing_version(
@@ -1842,7 +2113,7 @@ fn sync_dirty_folders_by_mtime(
             .entry(folder.album_id)
             .or_insert_with(|| {
                 Album::get_album_by_id(folder.album_id)
-                    .map(|album| directory_accessible(&album.path))
+                    .map(|album| album_online(&album))
                     .unwrap_or(false)
             });
         if !root_accessible {
@@ -1916,9 +2187,9 @@ fn sync_dirty_folders_by_mtime(
         )?;
         new_file_count += outcome.new_file_count;
         updated_file_count += outcome.updated_file_count;
-        deleted_file_count += outcome.deleted_file_count;
         rename_count += outcome.rename_count;
         tasks.extend(outcome.tasks);
+        missing_files.extend(outcome.missing_files);
 
         let modified_at = latest_mtime.or_else(|| {
             FileInfo::new(&folder.path)
@@ -1934,6 +2205,11 @@ fn sync_dirty_folders_by_mtime(
         }]);
     }
 
+    if !sync_generation_valid(generation) {
+        return Ok((FolderMtimeSyncResult::default(), Vec::new()));
+    }
+    let deleted_file_count = delete_missing_files(missing_files);
+
     Ok((
         FolderMtimeSyncResult {
             dirty_folder_count,
@@ -1956,12 +2232,21 @@ struct ChildFolderScan {
     folder_path_migrations: Vec<FolderPathMigration>,
 }
 
+/// The scan filter of an album, or no filter if the album cannot be read.
+fn album_scan_filter(album_id: i64) -> ScanFilter {
+    match Album::get_album_by_id(album_id) {
+        Ok(album) => ScanFilter::for_album(album_id, &album.path),
+        Err(_) => ScanFilter::none(""),
+    }
+}
+
 fn scan_new_child_folders(
     album_id: i64,
     folder_path: &str,
     reconcile_removed_children: bool,
 ) -> Result<ChildFolderScan, String> {
     let entries = fs::read_dir(folder_path).map_err(|e| e.to_string())?;
+    let scan_filter = album_scan_filter(album_id);
     let mut has_subfolders = false;
     let mut seen_paths = HashSet::new();
 
@@ -1973,7 +2258,7 @@ fn scan_new_child_folders(
         let Ok(file_type) = entry.file_type() else {
             continue;
         };
-        if !file_type.is_dir() {
+        if !file_type.is_dir() || scan_filter.excludes_dir(&entry.path()) {
             continue;
         }
         has_subfolders = true;
@@ -2071,6 +2356,7 @@ fn sync_folder_direct_files(
         }
         map
     };
+    let scan_filter = album_scan_filter(album_id);
 
     for entry in WalkDir::new(folder_path)
         .min_depth(1)
@@ -2087,6 +2373,11 @@ fn sync_folder_direct_files(
             Some(p) => p,
             None => continue,
         };
+        // Excluded files stay unseen and are handed back as missing.
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        if scan_filter.excludes_file(path, size) {
+            continue;
+        }
         let file_name = get_file_name(file_path_str);
         seen_names.insert(file_name.clone());
 
@@ -2153,6 +2444,12 @@ fn sync_folder_direct_files(
                             if is_live_photo_candidate_name(&file_name) {
                                 live_photo_affected_names.insert(file_name.clone());
                             }
+                        } else if status == 3 {
+                            // Re-attached a row moved here from elsewhere in the library.
+                            rename_count += 1;
+                            if is_live_photo_candidate_name(&file_name) {
+                                live_photo_affected_names.insert(file_name.clone());
+                            }
                         }
                         if should_process_synced_file(&file, ftype) {
                             if let Some(file_id) = file.id {
@@ -2177,48 +2474,42 @@ fn sync_folder_direct_files(
         }
     }
 
-    // Delete DB records that are truly gone (name not on disk AND file_id not seen).
+    // Collect DB records that are truly gone (name not on disk AND file_id not seen).
     // Uses the stored inode so rename detection works even when the old path
-    // no longer exists on disk.
+    // no longer exists on disk. The caller deletes them once the whole pass has
+    // run, since a later folder may turn out to hold the moved file.
     if is_cancelled() {
         return Ok(FolderSyncOutcome::default());
     }
     let album = Album::get_album_by_id(album_id).map_err(|e| e.to_string())?;
-    if !directory_accessible(&album.path) || !directory_accessible(folder_path) {
+    if !album_online(&album) || !directory_accessible(folder_path) {
         return Err(format!(
             "Folder became inaccessible during sync: {}",
             folder_path
         ));
     }
-    let deleted_count: u32 = {
-        let mut count = 0u32;
-        if let Ok(files) = AFile::get_files_by_folder_id(folder_id) {
-            for file in files {
-                if seen_names.contains(&file.name) {
-                    continue;
-                }
-                let still_exists = file
-                    .inode
-                    .map(|ino| ino > 0 && seen_inodes.contains(&(ino as u64)))
-                    .unwrap_or(false);
-                if still_exists {
-                    continue;
-                }
-                if let Some(id) = file.id {
-                    if is_cancelled() {
-                        return Ok(FolderSyncOutcome::default());
-                    }
-                    if AFile::delete(id).is_ok() {
-                        count += 1;
-                        if is_live_photo_candidate_name(&file.name) {
-                            live_photo_affected_names.insert(file.name.clone());
-                        }
-                    }
-                }
+    let mut missing_files = Vec::new();
+    if let Ok(files) = AFile::get_files_by_folder_id(folder_id) {
+        for file in files {
+            if seen_names.contains(&file.name) {
+                continue;
+            }
+            let still_exists = file
+                .inode
+                .map(|ino| ino > 0 && seen_inodes.contains(&(ino as u64)))
+                .unwrap_or(false);
+            if still_exists {
+                continue;
+            }
+            if let Some(id) = file.id {
+                missing_files.push(MissingFile {
+                    file_id: id,
+                    folder_id,
+                    name: file.name,
+                });
             }
         }
-        count
-    };
+    }
 
     if full_live_photo_reindex {
         let files = AFile::get_files_by_folder_id(folder_id).map_err(|error| {
@@ -2268,12 +2559,79 @@ fn sync_folder_direct_files(
     Ok(FolderSyncOutcome {
         new_file_count: new_count,
         updated_file_count: updated_count,
-        deleted_file_count: deleted_count,
         rename_count,
         tasks,
+        missing_files,
     })
 }
 
+/// Delete the rows of files that went missing during a sync pass, skipping any
+/// that were re-attached to a moved file in another folder meanwhile.
+/// Returns the number of deleted rows.
+pub fn delete_missing_files(missing_files: Vec<MissingFile>) -> u32 {
+    let mut deleted_count = 0u32;
+    let mut live_photo_affected_names: HashMap<i64, HashSet<String>> = HashMap::new();
+    for missing in missing_files {
+        let still_missing = matches!(
+            AFile::get_file_info(missing.file_id),
+            Ok(Some(file)) if file.folder_id == missing.folder_id && file.name == missing.name
+        );
+        if !still_missing {
+            continue;
+        }
+        if AFile::delete(missing.file_id).is_ok() {
+            deleted_count += 1;
+            if is_live_photo_candidate_name(&missing.name) {
+                live_photo_affected_names
+                    .entry(missing.folder_id)
+                    .or_default()
+                    .insert(missing.name);
+            }
+        }
+    }
+
+    for (folder_id, names) in live_photo_affected_names {
+        if let Err(error) = AFile::pair_live_photos_in_folder(folder_id, &names, false) {
+            eprintln!(
+                "Failed to pair Live Photos in folder {}: {}",
+                folder_id, error
+            );
+        }
+    }
+    deleted_count
+}
+
+/// Remove the indexed folders and files an album's scan filter now excludes.
+/// Returns the number of deleted file rows.
+pub fn prune_scan_filtered_files(album_id: i64) -> Result<u32, String> {
+    let _album_scan_guard = AlbumScanGuard::acquire(album_id)?;
+    let album_sync_lock = album_sync_lock(album_id);
+    let _album_sync_guard = album_sync_lock
+        .lock()
+        .map_err(|_| format!("Album {} sync lock is poisoned", album_id))?;
+
+    let album = Album::get_album_by_id(album_id).map_err(|e| e.to_string())?;
+    let excluded = ScanFilter::for_album(album_id, &album.path).excluded_rows(album_id)?;
+    if excluded.is_empty() {
+        return Ok(0);
+    }
+
+    // delete_folder() leaves thumbnails behind, so drop the files first.
+    let deleted_count = AFile::batch_delete(&excluded.file_ids)? as u32;
+    for folder_path in &excluded.folder_paths {
+        AFolder::delete_folder(folder_path)?;
+    }
+
+    if let Err(error) = pair_live_photos_after_album_index(album_id) {
+        eprintln!(
+            "Failed to pair Live Photos after pruning album {}: {}",
+            album_id, error
+        );
+    }
+    Album::recount_album(album_id)?;
+    Ok(deleted_count)
+}
+
 fn pair_live_photos_after_album_index(album_id: i64) -> Result<(), String> {
     for folder in AFolder::get_all()?.into_iter().filter(|folder| folder.album_id == album_id) {
         let folder_id = folder
@@ -2349,6 +2707,52 @@ pub fn sync_single_folder(
     folder_path: &str,
     group_raw_jpeg_pairs: bool,
     reconcile_missing: bool,
+) -> Result<FolderMtimeSyncResult, String> {
+    sync_folder(
+        app_handle,
+        album_id,
+        folder_id,
+        folder_path,
+        group_raw_jpeg_pairs,
+        reconcile_missing,
+        None,
+    )
+}
+
+/// Same as `sync_single_folder`, but collects missing files instead of
+/// deleting them, so a batch of folders can re-attach files moved between them.
+/// Missing child folders are kept as well: a moved folder is re-attached by inode
+/// when its new location is synced, as in the mtime sync.
+pub fn sync_single_folder_deferred(
+    app_handle: &tauri::AppHandle,
+    album_id: i64,
+    folder_id: i64,
+    folder_path: &str,
+    group_raw_jpeg_pairs: bool,
+    reconcile_missing: bool,
+    missing_files: &mut Vec<MissingFile>,
+) -> Result<FolderMtimeSyncResult, String> {
+    sync_folder(
+        app_handle,
+        album_id,
+        folder_id,
+        folder_path,
+        group_raw_jpeg_pairs,
+        reconcile_missing,
+        Some(missing_files),
+    )
+}
+
+/// Deletions are immediate without `deferred_missing`; with it, missing files are
+/// collected there and missing child folders are left alone.
+fn sync_folder(
+    app_handle: &tauri::AppHandle,
+    album_id: i64,
+    folder_id: i64,
+    folder_path: &str,
+    group_raw_jpeg_pairs: bool,
+    reconcile_missing: bool,
+    deferred_missing: Option<&mut Vec<MissingFile>>,
 ) -> Result<FolderMtimeSyncResult, String> {
     // A complete album scan owns folder and file reconciliation for this
     // album. Skip foreground refreshes until it finishes to avoid concurrent
@@ -2365,7 +2769,7 @@ pub fn sync_single_folder(
     }
 
     let album = Album::get_album_by_id(album_id).map_err(|e| e.to_string())?;
-    if !directory_accessible(&album.path) {
+    if !album_online(&album) {
         return Err(format!("Album folder is not accessible: {}", album.path));
     }
 
@@ -2411,7 +2815,11 @@ pub fn sync_single_folder(
     // Reconcile direct children even when the mtime is unchanged. A manual
     // refresh must remove a deleted child from the database, and some
     // filesystems expose directory mtimes at a coarser resolution.
-    let child_scan = scan_new_child_folders(album_id, folder_path, reconcile_missing)?;
+    let child_scan = scan_new_child_folders(
+        album_id,
+        folder_path,
+        reconcile_missing && deferred_missing.is_none(),
+    )?;
     let new_folder_count = child_scan.folders.len() as u32;
 
     let needs_live_photo_reindex = FolderScanState::needs_version(
@@ -2466,6 +2874,13 @@ pub fn sync_single_folder(
     for task in outcome.tasks {
         schedule_synced_file_processing(app_handle.clone(), task);
     }
+    let deleted_file_count = match deferred_missing {
+        Some(missing_files) => {
+            missing_files.extend(outcome.missing_files);
+            0
+        }
+        None => delete_missing_files(outcome.missing_files),
+    };
 
     let mtime = info.modified;
     let _ = FolderSubfolderState::update_after_scan(album_id, &[FolderSubfolderState {
@@ -2481,7 +2896,7 @@ pub fn sync_single_folder(
         new_folder_count,
         new_file_count: outcome.new_file_count,
         updated_file_count: outcome.updated_file_count,
-        deleted_file_count: outcome.deleted_file_count,
+        deleted_file_count,
         rename_count: outcome.rename_count,
         deleted_folder_count: child_scan.deleted_folder_count,
         folder_path_migrations: child_scan.folder_path_migrations,
@@ -2492,7 +2907,28 @@ fn should_process_synced_file(file: &AFile, file_type: i64) -> bool {
     if !file.has_thumbnail.unwrap_or(false) {
         return true;
     }
-    matches!(file_type, 1 | 3) && !file.has_embedding.unwrap_or(false)
+    matches!(file_type, 1 | 2 | 3) && !file.has_embedding.unwrap_or(false)
+}
+
+/// Queue the thumbnail and embedding of a file added outside a folder scan (an import),
+/// the same way the folder sync does for the files it finds.
+pub(crate) fn schedule_new_file_processing(
+    app_handle: &tauri::AppHandle,
+    album_id: i64,
+    file: &AFile,
+    file_path: &str,
+) {
+    let file_type = file.file_type.unwrap_or(0);
+    if !should_process_synced_file(file, file_type) {
+        return;
+    }
+    schedule_synced_file_processing(app_handle.clone(), SyncedFileTask {
+        file_id: file.id.unwrap_or(0),
+        file_path: file_path.to_string(),
+        file_type,
+        orientation: file.e_orientation.unwrap_or(1) as i32,
+        album_id,
+    });
 }
 
 fn schedule_synced_file_processing(app_handle: tauri::AppHandle, task: SyncedFileTask) {
@@ -2539,7 +2975,7 @@ fn schedule_synced_file_processing(app_handle: tauri::AppHandle, task: SyncedFil
             }),
         );
 
-        if !matches!(task.file_type, 1 | 3) {
+        if !matches!(task.file_type, 1 | 2 | 3) {
             return;
         }
 
@@ -2564,8 +3000,9 @@ fn schedule_synced_file_processing(app_handle: tauri::AppHandle, task: SyncedFil
     });
 }
 
-/// Get folder, media, and scan candidate totals (including all sub-folders).
-pub fn count_folder_files(path: &str) -> (u64, u64, u64, u64, u64, u64, u64) {
+/// Get folder, media, and scan candidate totals (including all sub-folders),
+/// leaving out whatever the album's scan filter skips.
+pub fn count_folder_files(path: &str, filter: &ScanFilter) -> (u64, u64, u64, u64, u64, u64, u64) {
     let mut folder_count = 0;
     let mut image_file_count = 0;
     let mut total_image_size = 0;
@@ -2577,7 +3014,9 @@ pub fn count_folder_files(path: &str) -> (u64, u64, u64, u64, u64, u64, u64) {
     // Use WalkDir to iterate over directory entries
     for entry in WalkDir::new(path)
         .into_iter()
-        .filter_entry(|e| !is_hidden(e))
+        .filter_entry(|e| {
+            !is_hidden(e) && !(e.file_type().is_dir() && filter.excludes_dir(e.path()))
+        })
         .filter_map(Result::ok)
     {
         let entry_type = entry.file_type();
@@ -2586,6 +3025,9 @@ pub fn count_folder_files(path: &str) -> (u64, u64, u64, u64, u64, u64, u64) {
             folder_count += 1;
         } else if entry_type.is_file() {
             let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            if filter.excludes_file(entry.path(), size) {
+                continue;
+            }
             if !is_ignored_scan_sidecar(entry.path()) {
                 scan_file_count += 1;
                 total_scan_size += size;
@@ -3210,7 +3652,7 @@ fn index_single_file(
     ftype: i64,
     thumbnail_size: u32,
     last_scan_time: i64,
-) -> Option<FileIndexOutcome> {
+) -> Result<FileIndexOutcome, String> {
     let result = panic::catch_unwind(AssertUnwindSafe(|| {
         let parent_path = Path::new(path_str)
             .parent()
@@ -3218,75 +3660,106 @@ fn index_single_file(
             .to_string_lossy()
             .to_string();
 
-        if let Ok(folder) = crate::t_sqlite::AFolder::add_to_db(album_id, &parent_path) {
-            if let Some(folder_id) = folder.id {
-                if let Ok((file, _)) =
-                    crate::t_sqlite::AFile::add_to_db(folder_id, path_str, ftype, last_scan_time)
-                {
-                    if let Some(file_id) = file.id {
-                        let has_thumbnail = file.has_thumbnail.unwrap_or(false);
-                        let has_embedding = file.has_embedding.unwrap_or(false);
-                        let processed_immediately = has_thumbnail;
-                        let search_ready_immediately = match ftype {
-                            1 | 3 => has_thumbnail && has_embedding,
-                            _ => false,
-                        };
-                        let fully_indexed = match ftype {
-                            1 | 3 => search_ready_immediately,
-                            2 => processed_immediately,
-                            _ => false,
-                        };
-
-                        let task = if fully_indexed {
-                            None
-                        } else {
-                            Some(ThumbnailTask {
-                                file_id,
-                                file_path: path_str.to_string(),
-                                file_type: ftype,
-                                orientation: file.e_orientation.unwrap_or(1) as i32,
-                                thumbnail_size,
-                                file_size: file.size.max(0) as u64,
-                                duration: file.duration.map(|d| d as u64),
-                                is_heavy: should_use_heavy_lane(
-                                    ftype,
-                                    path_str,
-                                    file.size.max(0) as u64,
-                                    file.width.unwrap_or(0),
-                                    file.height.unwrap_or(0),
-                                ),
-                                processed_already_ready: has_thumbnail,
-                            })
-                        };
-
-                        return Some(FileIndexOutcome {
-                            task,
-                            processed_immediately,
-                            search_ready_immediately,
-                        });
-                    } else {
-                        eprintln!(
-                            "Indexed file has no id, skipping follow-up tasks: {}",
-                            path_str
-                        );
-                    }
-                }
-            } else {
-                eprintln!("Indexed folder has no id, skipping file: {}", parent_path);
-            }
-        }
-        None
+        let folder = crate::t_sqlite::AFolder::add_to_db(album_id, &parent_path)?;
+        let folder_id = folder
+            .id
+            .ok_or_else(|| format!("Indexed folder has no id: {}", parent_path))?;
+        let (file, _) =
+            crate::t_sqlite::AFile::add_to_db(folder_id, path_str, ftype, last_scan_time)?;
+        let file_id = file
+            .id
+            .ok_or_else(|| format!("Indexed file has no id: {}", path_str))?;
+
+        let has_thumbnail = file.has_thumbnail.unwrap_or(false);
+        let has_embedding = file.has_embedding.unwrap_or(false);
+        let processed_immediately = has_thumbnail;
+        let search_ready_immediately = match ftype {
+            1 | 2 | 3 => has_thumbnail && has_embedding,
+            _ => false,
+        };
+        let fully_indexed = match ftype {
+            1 | 2 | 3 => search_ready_immediately,
+            _ => false,
+        };
+
+        let task = if fully_indexed {
+            None
+        } else {
+            Some(ThumbnailTask {
+                file_id,
+                file_path: path_str.to_string(),
+                file_type: ftype,
+                orientation: file.e_orientation.unwrap_or(1) as i32,
+                thumbnail_size,
+                file_size: file.size.max(0) as u64,
+                duration: file.duration.map(|d| d as u64),
+                is_heavy: should_use_heavy_lane(
+                    ftype,
+                    path_str,
+                    file.size.max(0) as u64,
+                    file.width.unwrap_or(0),
+                    file.height.unwrap_or(0),
+                ),
+                processed_already_ready: has_thumbnail,
+            })
+        };
+
+        Ok(FileIndexOutcome {
+            task,
+            processed_immediately,
+            search_ready_immediately,
+        })
     }));
 
     match result {
-        Ok(task) => task,
+        Ok(outcome) => outcome,
         Err(_) => {
             eprintln!("Panic while indexing file, skipping: {}", path_str);
-            None
+            Err("Panic while reading the file".to_string())
         }
     }
 }
 
+/// Create a file's thumbnail and keep its thumbnail entry in the index error
+/// report up to date. Returns whether a thumbnail is available, or the recorded error.
+fn create_thumbnail_recording_errors(
+    file_id: i64,
+    file_path: &str,
+    file_type: i64,
+    orientation: i32,
+    thumbnail_size: u32,
+    force_regenerate: bool,
+    duration: Option<u64>,
+) -> Result<bool, String> {
+    let thumb_error = match crate::t_sqlite::AThumb::get_or_create_thumb(
+        file_id,
+        file_path,
+        file_type,
+        orientation,
+        thumbnail_size,
+        force_regenerate,
+        duration,
+        None,
+    ) {
+        Ok(Some(thumb)) if thumb.error_code == 0 => {
+            t_index_errors::resolve_file(file_id, t_index_errors::STAGE_THUMBNAIL);
+            return Ok(true);
+        }
+        // error code 2: the original is shown instead, which is not an error
+        Ok(Some(thumb)) if thumb.error_code == 2 => {
+            t_index_errors::resolve_file(file_id, t_index_errors::STAGE_THUMBNAIL);
+            return Ok(false);
+        }
+        Ok(_) => "The file could not be decoded".to_string(),
+        Err(e) => {
+            eprintln!("Failed to generate thumb for {}: {}", file_path, e);
+            e
+        }
+    };
+    t_index_errors::record_for_file(file_id, t_index_errors::STAGE_THUMBNAIL, &thumb_error);
+    Err(thumb_error)
+}
+
 async fn process_thumbnail_task(
     app_handle: tauri::AppHandle,
     task: ThumbnailTask,
@@ -3306,7 +3779,7 @@ async fn process_thumbnail_task(
 
     let task_for_thumb = task.clone();
     let thumb_ok = tauri::async_runtime::spawn_blocking(move || {
-        match crate::t_sqlite::AThumb::get_or_create_thumb(
+        create_thumbnail_recording_errors(
             task_for_thumb.file_id,
             &task_for_thumb.file_path,
             task_for_thumb.file_type,
@@ -3314,19 +3787,8 @@ async fn process_thumbnail_task(
             task_for_thumb.thumbnail_size,
             false,
             task_for_thumb.duration,
-            None,
-        ) {
-            Ok(Some(thumb)) if thumb.error_code == 0 => true,
-            Ok(Some(_)) => false,
-            Ok(None) => false,
-            Err(e) => {
-                eprintln!(
-                    "Failed to generate thumb for {}: {}",
-                    task_for_thumb.file_path, e
-                );
-                false
-            }
-        }
+        )
+        .unwrap_or(false)
     })
     .await
     .map_err(|e| format!("Thumbnail task failed: {}", e))?;
@@ -3354,7 +3816,7 @@ async fn process_thumbnail_task(
         });
     }
 
-    if !matches!(task.file_type, 1 | 3) {
+    if !matches!(task.file_type, 1 | 2 | 3) {
         return Ok(true);
     }
 
@@ -3370,9 +3832,16 @@ async fn process_thumbnail_task(
     let embedding_ok = tauri::async_runtime::spawn_blocking(move || {
         let ai_state: State<crate::t_ai::AiState> = app_handle_for_embedding.state();
         match crate::t_sqlite::AFile::generate_embedding(&ai_state, file_id) {
-            Ok(_) => true,
+            Ok(_) => {
+                t_index_errors::resolve_file(file_id, t_index_errors::STAGE_EMBEDDING);
+                true
+            }
             Err(e) => {
                 eprintln!("Failed to generate embedding for {}: {}", file_path, e);
+                // Missing models are not a problem with this file.
+                if ai_state.0.lock().unwrap().is_loaded() {
+                    t_index_errors::record_for_file(file_id, t_index_errors::STAGE_EMBEDDING, &e);
+                }
                 false
             }
         }
@@ -3394,6 +3863,143 @@ async fn process_thumbnail_task(
     }
 }
 
+/// Retry the failed files in the index error report. Rows of files that no
+/// longer exist are dropped; face rows are left to the next face indexing pass,
+/// which picks up every file it could not process.
+pub fn retry_index_errors(
+    app_handle: &tauri::AppHandle,
+    query: &t_index_errors::IndexErrorQuery,
+    thumbnail_size: u32,
+) -> Result<t_index_errors::IndexErrorRetryResult, String> {
+    let query = t_index_errors::IndexErrorQuery {
+        kind: Some(t_index_errors::KIND_FAILED.to_string()),
+        include_ignored: false,
+        ..query.clone()
+    };
+    let mut result = t_index_errors::IndexErrorRetryResult::default();
+    let mut recount_album_ids = HashSet::new();
+    let mut album_accessibility: HashMap<i64, bool> = HashMap::new();
+
+    for error in t_index_errors::list(&query, 0, 0)? {
+        let album_ok = *album_accessibility
+            .entry(error.album_id)
+            .or_insert_with(|| {
+                Album::get_album_by_id(error.album_id).is_ok_and(|album| album_online(&album))
+            });
+        if !album_ok || album_scan_active(error.album_id) {
+            result.failed += 1;
+            continue;
+        }
+        if !Path::new(&error.file_path).exists() {
+            t_index_errors::delete(error.id)?;
+            result.resolved += 1;
+            continue;
+        }
+        result.retried += 1;
+
+        let file = match error.file_id {
+            Some(file_id) => AFile::get_file_info(file_id)?,
+            None => None,
+        };
+        let resolved = match error.stage.as_str() {
+            t_index_errors::STAGE_METADATA => {
+                let Some(ftype) = get_file_type(&error.file_path) else {
+                    result.failed += 1;
+                    continue;
+                };
+                let album_sync_lock = album_sync_lock(error.album_id);
+                let _album_sync_guard = album_sync_lock
+                    .lock()
+                    .map_err(|_| format!("Album {} sync lock is poisoned", error.album_id))?;
+                let album = Album::get_album_by_id(error.album_id)?;
+                match index_single_file(
+                    &album.path,
+                    error.album_id,
+                    &error.file_path,
+                    ftype,
+                    thumbnail_size,
+                    Utc::now().timestamp_millis(),
+                ) {
+                    Ok(outcome) => {
+                        t_index_errors::resolve(&error.file_path, &error.stage);
+                        recount_album_ids.insert(error.album_id);
+                        // The file is new to the index, so it still needs its thumbnail
+                        // and embedding.
+                        if let Some(task) = outcome.task {
+                            schedule_synced_file_processing(app_handle.clone(), SyncedFileTask {
+                                file_id: task.file_id,
+                                file_path: task.file_path,
+                                file_type: task.file_type,
+                                orientation: task.orientation,
+                                album_id: error.album_id,
+                            });
+                        }
+                        true
+                    }
+                    Err(e) => {
+                        let file_size =
+                            fs::metadata(&error.file_path).map(|m| m.len()).unwrap_or(0);
+                        t_index_errors::record(
+                            error.album_id,
+                            None,
+                            &error.file_path,
+                            &error.stage,
+                            t_index_errors::KIND_FAILED,
+                            &e,
+                            file_size,
+                        );
+                        false
+                    }
+                }
+            }
+            t_index_errors::STAGE_THUMBNAIL => match file {
+                Some(file) => create_thumbnail_recording_errors(
+                    error.file_id.unwrap_or_default(),
+                    &error.file_path,
+                    file.file_type.unwrap_or(0),
+                    file.e_orientation.unwrap_or(1) as i32,
+                    thumbnail_size,
+                    true,
+                    file.duration.map(|d| d.max(0) as u64),
+                )
+                .is_ok(),
+                None => false,
+            },
+            t_index_errors::STAGE_EMBEDDING => match (file, error.file_id) {
+                (Some(_), Some(file_id)) => {
+                    let ai_state: State<crate::t_ai::AiState> = app_handle.state();
+                    match AFile::generate_embedding(&ai_state, file_id) {
+                        Ok(_) => {
+                            t_index_errors::resolve_file(file_id, &error.stage);
+                            true
+                        }
+                        Err(e) => {
+                            t_index_errors::record_for_file(file_id, &error.stage, &e);
+                            false
+                        }
+                    }
+                }
+                _ => false,
+            },
+            _ => {
+                t_index_errors::delete(error.id)?;
+                result.queued += 1;
+                continue;
+            }
+        };
+        if resolved {
+            result.resolved += 1;
+        } else {
+            result.failed += 1;
+        }
+    }
+
+    for album_id in recount_album_ids {
+        let _ = Album::recount_album(album_id);
+    }
+    Ok(result)
+}
+
 pub async fn index_album_worker(
     app_handle: &tauri::AppHandle,
     cancellation_token: Arc<Mutex<HashMap<i64, bool>>>,
@@ -3411,7 +4017,8 @@ pub async fn index_album_worker(
     let album = Album::get_album_by_id(album_id).map_err(|e| e.to_string())?;
     let previous_indexed = album.indexed.unwrap_or(0).max(0) as u64;
     let previous_total = album.total.unwrap_or(0).max(0) as u64;
-    if !directory_accessible(&album.path) {
+    // An offline album keeps its cached files; never scan (and then purge) it.
+    if !album_online(&album) {
         app_handle
             .emit(
                 "index_finished",
@@ -3436,10 +4043,12 @@ pub async fn index_album_worker(
     }
 
     // 2. Count total files
+    let scan_filter = ScanFilter::for_album(album_id, &album.path);
     let (_folders, image_count, _image_size, video_count, _video_size, scan_total, scan_total_size) =
-        count_folder_files(&album.path);
+        count_folder_files(&album.path, &scan_filter);
     let total_files = image_count + video_count;
-    let search_total = image_count;
+    // videos are searchable through their keyframe embeddings
+    let search_total = total_files;
 
     // Resume only when totals match and previous indexed is a valid in-progress value.
     // This avoids breaking normal re-scan behavior after a completed run.
@@ -3479,7 +4088,9 @@ pub async fn index_album_worker(
     let mut thumbnail_join_set: JoinSet<Result<bool, String>> = JoinSet::new();
     for entry in WalkDir::new(&album.path)
         .into_iter()
-        .filter_entry(|e| !is_hidden(e))
+        .filter_entry(|e| {
+            !is_hidden(e) && !(e.file_type().is_dir() && scan_filter.excludes_dir(e.path()))
+        })
     {
         // Check cancellation
         if let Some(&true) = cancellation_token.lock().unwrap().get(&album_id) {
@@ -3538,6 +4149,11 @@ pub async fn index_album_worker(
         }
 
         if entry.file_type().is_file() {
+            // Excluded files are left unseen, so the sweep below drops their rows.
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            if scan_filter.excludes_file(entry.path(), size) {
+                continue;
+            }
             let path_str = entry.path().to_string_lossy().to_string();
             if let Some(ftype) = get_file_type(&path_str) {
                 // Resume mode: skip already-indexed prefix files.
@@ -3555,6 +4171,15 @@ pub async fn index_album_worker(
                         path_str
                     );
                     let file_size = std::fs::metadata(&path_str).map(|m| m.len()).unwrap_or(0);
+                    t_index_errors::record(
+                        album_id,
+                        None,
+                        &path_str,
+                        t_index_errors::STAGE_METADATA,
+                        t_index_errors::KIND_FAILED,
+                        "Skipped after the app stopped while indexing this file",
+                        file_size,
+                    );
                     with_progress_tracker(&tracker, |tracker| {
                         tracker.modify(|snapshot| {
                             snapshot.discovered += 1;
@@ -3568,7 +4193,7 @@ pub async fn index_album_worker(
                     continue;
                 }
 
-                if let Some(outcome) = index_single_file(
+                match index_single_file(
                     &album.path,
                     album_id,
                     &path_str,
@@ -3576,57 +4201,79 @@ pub async fn index_album_worker(
                     thumbnail_size,
                     current_scan_time,
                 ) {
-                    let file_size = outcome
-                        .task
-                        .as_ref()
-                        .map(|task| task.file_size)
-                        .unwrap_or_else(|| {
-                            std::fs::metadata(&path_str).map(|m| m.len()).unwrap_or(0)
+                    Ok(outcome) => {
+                        let file_size = outcome
+                            .task
+                            .as_ref()
+                            .map(|task| task.file_size)
+                            .unwrap_or_else(|| {
+                                std::fs::metadata(&path_str).map(|m| m.len()).unwrap_or(0)
+                            });
+                        if let Some(task) = outcome.task {
+                            thumbnail_join_set.spawn(process_thumbnail_task(
+                                app_handle.clone(),
+                                task,
+                                processing_budget.clone(),
+                                tracker.clone(),
+                            ));
+                        }
+                        with_progress_tracker(&tracker, |tracker| {
+                            tracker.modify(|snapshot| {
+                                snapshot.discovered += 1;
+                                snapshot.current_size += file_size;
+                                if outcome.processed_immediately {
+                                    snapshot.processed += 1;
+                                }
+                                if outcome.search_ready_immediately {
+                                    snapshot.search_ready += 1;
+                                }
+                            });
+                            tracker.maybe_emit();
                         });
-                    if let Some(task) = outcome.task {
-                        thumbnail_join_set.spawn(process_thumbnail_task(
-                            app_handle.clone(),
-                            task,
-                            processing_budget.clone(),
-                            tracker.clone(),
-                        ));
+                        let processed_now =
+                            with_progress_tracker(&tracker, |tracker| tracker.snapshot.processed);
+                        let discovered_now =
+                            with_progress_tracker(&tracker, |tracker| tracker.snapshot.discovered);
+                        if discovered_now % 50 == 0 || processed_now % 50 == 0 {
+                            let _ = Album::update_progress(album_id, processed_now, total_files);
+                        }
                     }
-                    with_progress_tracker(&tracker, |tracker| {
-                        tracker.modify(|snapshot| {
-                            snapshot.discovered += 1;
-                            snapshot.current_size += file_size;
-                            if outcome.processed_immediately {
-                                snapshot.processed += 1;
-                            }
-                            if outcome.search_ready_immediately {
-                                snapshot.search_ready += 1;
-                            }
+                    Err(error) => {
+                        eprintln!("Failed to index file {}: {}", path_str, error);
+                        let file_size = std::fs::metadata(&path_str).map(|m| m.len()).unwrap_or(0);
+                        t_index_errors::record(
+                            album_id,
+                            None,
+                            &path_str,
+                            t_index_errors::STAGE_METADATA,
+                            t_index_errors::KIND_FAILED,
+                            &error,
+                            file_size,
+                        );
+                        with_progress_tracker(&tracker, |tracker| {
+                            tracker.modify(|snapshot| {
+                                snapshot.discovered += 1;
+                                snapshot.failed += 1;
+                                snapshot.failed_size += file_size;
+                                snapshot.current_size += file_size;
+                            });
+                            tracker.maybe_emit();
                         });
-                        tracker.maybe_emit();
-                    });
-                    let processed_now =
-                        with_progress_tracker(&tracker, |tracker| tracker.snapshot.processed);
-                    let discovered_now =
-                        with_progress_tracker(&tracker, |tracker| tracker.snapshot.discovered);
-                    if discovered_now % 50 == 0 || processed_now % 50 == 0 {
-                        let _ = Album::update_progress(album_id, processed_now, total_files);
                     }
-                } else {
-                    let file_size = std::fs::metadata(&path_str).map(|m| m.len()).unwrap_or(0);
-                    with_progress_tracker(&tracker, |tracker| {
-                        tracker.modify(|snapshot| {
-                            snapshot.discovered += 1;
-                            snapshot.failed += 1;
-                            snapshot.failed_size += file_size;
-                            snapshot.current_size += file_size;
-                        });
-                        tracker.maybe_emit();
-                    });
                 }
 
                 traversed_count += 1;
             } else if !is_ignored_scan_sidecar(entry.path()) {
                 let file_size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+                t_index_errors::record(
+                    album_id,
+                    None,
+                    &path_str,
+                    t_index_errors::STAGE_METADATA,
+                    t_index_errors::KIND_SKIPPED,
+                    "Unsupported file type",
+                    file_size,
+                );
                 with_progress_tracker(&tracker, |tracker| {
                     tracker.modify(|snapshot| {
                         snapshot.skipped += 1;
@@ -3655,7 +4302,7 @@ pub async fn index_album_worker(
 
     with_progress_tracker(&tracker, |tracker| tracker.emit_now());
     let mut final_snapshot = with_progress_tracker(&tracker, |tracker| tracker.snapshot());
-    let scan_failed = traversal_failed || !directory_accessible(&album.path);
+    let scan_failed = traversal_failed || !album_online(&album);
     let scan_complete = !is_cancelled && !scan_failed;
     if scan_complete {
         let _ = Album::update_progress(album_id, final_snapshot.processed, total_files);
@@ -3681,6 +4328,13 @@ pub async fn index_album_worker(
             eprintln!("Failed to remove stale folders from album {}: {}", album_id, error);
         }
 
+        // A resumed scan did not revisit the files before its resume point.
+        if resume_from == 0 {
+            if let Err(error) = t_index_errors::sweep_album(album_id, current_scan_time) {
+                eprintln!("Failed to clean up index errors for album {}: {}", album_id, error);
+            }
+        }
+
         println!("Cleaning up removed files from DB for album {}", album_id);
         let deleted_count = AFile::delete_unseen_in_album(album_id, current_scan_time).unwrap_or(0);
         if deleted_count > 0 {
@@ -3792,5 +4446,17 @@ pub async fn index_album_worker(
         )
         .map_err(|e| e.to_string())?;
 
+    // 7. Recognize text in the album's new images when the OCR models are installed
+    // (while a run is already in progress, they wait for the next one).
+    if scan_complete
+        && t_ocr::OcrEngine::is_available(app_handle)
+        && t_ocr::count_unprocessed_files(album_id).unwrap_or(0) > 0
+    {
+        let ocr_state = app_handle.state::<t_ocr::OcrState>();
+        if let Err(e) = t_ocr::start_text_index(app_handle.clone(), &ocr_state, Some(album_id)) {
+            eprintln!("Text recognition not started after album {}: {}", album_id, e);
+        }
+    }
+
     Ok(())
 }
@@ -30,6 +30,7 @@ static SIDE_CAR_DIR: OnceCell<PathBuf> = OnceCell::new();
 const PROCESS_TIMEOUT_SECS: u64 = 30;
 const PROBE_TIMEOUT_SECS: u64 = 5;
 const INDEX_PROBE_TIMEOUT_SECS: u64 = 20;
+const FRAME_SAMPLE_TIMEOUT_SECS: u64 = 180;
 const PROBE_SIZE_BYTES: &str = "5000000";
 const ANALYZE_DURATION_MICROSECONDS: &str = "5000000";
 #[cfg(debug_assertions)]
@@ -585,6 +586,170 @@ pub fn get_video_thumbnail_sync(
     })
 }
 
+/// Extracts evenly spaced keyframes (for AI search).
+/// Returns (offset in seconds, JPEG bytes) pairs; clips without a duration yield the first frame.
+pub async fn get_video_keyframes(
+    file_path: &str,
+    frame_size: u32,
+    known_duration: Option<u64>,
+    max_frames: usize,
+) -> Result<Vec<(u64, Vec<u8>)>, String> {
+    let duration = match known_duration.filter(|d| *d > 0) {
+        Some(d) => d,
+        None => probe_json_async(file_path)
+            .await
+            .ok()
+            .and_then(|json| json["format"]["duration"].as_str().map(str::to_string))
+            .and_then(|s| s.parse::<f64>().ok())
+            .unwrap_or(0.0) as u64,
+    };
+    let ffmpeg_threads = thumbnail_ffmpeg_threads().to_string();
+    let filter = format!(
+        "scale=w={}:h={}:force_original_aspect_ratio=decrease",
+        frame_size, frame_size
+    );
+
+    let mut frames = Vec::new();
+    for offset in keyframe_offsets(duration, max_frames) {
+        let strategy = if offset == 0 {
+            ThumbnailStrategy::FirstFrame
+        } else {
+            ThumbnailStrategy::FastSeek(offset)
+        };
+        match run_thumbnail_command(file_path, &ffmpeg_threads, &filter, strategy, 20).await {
+            Ok(Some(bytes)) => frames.push((offset, bytes)),
+            Ok(None) => {}
+            Err(err) => eprintln!(
+                "Failed to extract keyframe at {}s from {}: {}",
+                offset, file_path, err
+            ),
+        }
+    }
+
+    if frames.is_empty() {
+        return Err(format!(
+            "No keyframes could be extracted from {}",
+            file_path
+        ));
+    }
+    Ok(frames)
+}
+
+/// Sync wrapper for `get_video_keyframes`, usable with or without a runtime context.
+pub fn get_video_keyframes_sync(
+    file_path: &str,
+    frame_size: u32,
+    known_duration: Option<u64>,
+    max_frames: usize,
+) -> Result<Vec<(u64, Vec<u8>)>, String> {
+    let future = get_video_keyframes(file_path, frame_size, known_duration, max_frames);
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => tokio::task::block_in_place(|| handle.block_on(future)),
+        Err(_) => tauri::async_runtime::block_on(future),
+    }
+}
+
+/// Samples one grayscale frame every `interval_secs` from the start of the clip, scaled
+/// to exactly `width`x`height` (for frame fingerprints). Returns raw 8-bit luma frames.
+pub async fn get_video_gray_frames(
+    file_path: &str,
+    interval_secs: u32,
+    max_frames: usize,
+    width: u32,
+    height: u32,
+) -> Result<Vec<Vec<u8>>, String> {
+    let ffmpeg_threads = thumbnail_ffmpeg_threads().to_string();
+    let filter = format!(
+        "fps=1/{},scale={}:{}:flags=area,format=gray",
+        interval_secs.max(1),
+        width,
+        height
+    );
+    let mut cmd = ffmpeg_command();
+    cmd.args(["-v", "error", "-i", file_path]);
+    cmd.args([
+        "-map",
+        "0:v:0",
+        "-an",
+        "-sn",
+        "-vf",
+        &filter,
+        "-frames:v",
+        &max_frames.to_string(),
+        "-f",
+        "rawvideo",
+        "-pix_fmt",
+        "gray",
+        "-threads",
+        &ffmpeg_threads,
+        "pipe:1",
+    ]);
+
+    #[cfg(target_os = "windows")]
+    {
+        cmd.creation_flags(0x08000000);
+    }
+
+    let child = cmd
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    let output = tokio::time::timeout(
+        std::time::Duration::from_secs(FRAME_SAMPLE_TIMEOUT_SECS),
+        child.wait_with_output(),
+    )
+    .await
+    .map_err(|_| {
+        format!(
+            "frame sampling timed out after {}s",
+            FRAME_SAMPLE_TIMEOUT_SECS
+        )
+    })?
+    .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+
+    let frame_len = (width * height) as usize;
+    Ok(output
+        .stdout
+        .chunks_exact(frame_len.max(1))
+        .map(|frame| frame.to_vec())
+        .collect())
+}
+
+/// Sync wrapper for `get_video_gray_frames`, usable with or without a runtime context.
+pub fn get_video_gray_frames_sync(
+    file_path: &str,
+    interval_secs: u32,
+    max_frames: usize,
+    width: u32,
+    height: u32,
+) -> Result<Vec<Vec<u8>>, String> {
+    let future = get_video_gray_frames(file_path, interval_secs, max_frames, width, height);
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => tokio::task::block_in_place(|| handle.block_on(future)),
+        Err(_) => tauri::async_runtime::block_on(future),
+    }
+}
+
+// sample the middle of `max_frames` equal segments, at most one frame per second
+fn keyframe_offsets(duration: u64, max_frames: usize) -> Vec<u64> {
+    if duration == 0 || max_frames <= 1 {
+        return vec![0];
+    }
+    let count = (max_frames as u64).min(duration);
+    let mut offsets: Vec<u64> = (0..count)
+        .map(|i| duration * (2 * i + 1) / (2 * count))
+        .collect();
+    offsets.dedup();
+    offsets
+}
+
 #[derive(Default)]
 pub struct VideoMetadata {
     pub width: u32,
@@ -1414,3 +1579,25 @@ fn hash_id(value: &str) -> u64 {
     }
     h
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spreads_keyframes_over_the_clip() {
+        assert_eq!(keyframe_offsets(100, 4), vec![12, 37, 62, 87]);
+        assert_eq!(keyframe_offsets(60, 1), vec![0]);
+    }
+
+    #[test]
+    fn keeps_one_frame_per_second_of_short_clips() {
+        assert_eq!(keyframe_offsets(3, 8), vec![0, 1, 2]);
+        assert_eq!(keyframe_offsets(1, 8), vec![0]);
+    }
+
+    #[test]
+    fn samples_the_start_of_clips_without_duration() {
+        assert_eq!(keyframe_offsets(0, 8), vec![0]);
+    }
+}
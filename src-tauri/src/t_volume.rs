@@ -0,0 +1,351 @@
+/**
+ * t_volume.rs - Filesystem volume identity
+ *
+ * Albums on external drives are remembered by the volume's UUID plus the album's
+ * path relative to the volume's mount point. That lets an album be recognised as
+ * offline while its drive is unplugged (even if an empty mount directory or
+ * another drive is left at the old path), and found again when the drive is
+ * mounted somewhere else.
+ *
+ * Linux reads /proc/self/mountinfo and /dev/disk/by-uuid, macOS asks `diskutil`
+ * and Windows uses the volume serial number reported by `vol`.
+ */
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const UUID_CACHE_TTL: Duration = Duration::from_secs(10);
+
+// mount point -> (lookup time, volume uuid); lookups may spawn a process
+static UUID_CACHE: Lazy<Mutex<HashMap<PathBuf, (Instant, Option<String>)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// volume uuid -> (lookup time, mount point); same for the reverse lookup
+static MOUNT_CACHE: Lazy<Mutex<HashMap<String, (Instant, Option<PathBuf>)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Where a path lives: the volume's UUID and the path relative to its mount point.
+pub struct VolumeLocation {
+    pub uuid: String,
+    pub relative_path: String,
+}
+
+/// Identify the volume holding `path`.
+pub fn locate(path: &str) -> Option<VolumeLocation> {
+    let path = absolute_path(path)?;
+    let mount = mount_point(&path)?;
+    let uuid = cached_volume_uuid(&mount)?;
+    let relative_path = path
+        .strip_prefix(&mount)
+        .ok()?
+        .to_string_lossy()
+        .to_string();
+    Some(VolumeLocation {
+        uuid,
+        relative_path,
+    })
+}
+
+/// The current location of a path recorded by `locate`, if its volume is mounted.
+pub fn resolve(uuid: &str, relative_path: &str) -> Option<String> {
+    let mount = cached_mount_point(uuid)?;
+    let path = if relative_path.is_empty() {
+        mount
+    } else {
+        mount.join(relative_path)
+    };
+    Some(path.to_string_lossy().to_string())
+}
+
+fn cached_volume_uuid(mount: &Path) -> Option<String> {
+    let mut cache = UUID_CACHE.lock().unwrap();
+    if let Some((at, uuid)) = cache.get(mount) {
+        if at.elapsed() < UUID_CACHE_TTL {
+            return uuid.clone();
+        }
+    }
+    let uuid = volume_uuid(mount);
+    cache.insert(mount.to_path_buf(), (Instant::now(), uuid.clone()));
+    uuid
+}
+
+fn cached_mount_point(uuid: &str) -> Option<PathBuf> {
+    let mut cache = MOUNT_CACHE.lock().unwrap();
+    if let Some((at, mount)) = cache.get(uuid) {
+        if at.elapsed() < UUID_CACHE_TTL {
+            return mount.clone();
+        }
+    }
+    let mount = mount_point_for_uuid(uuid);
+    cache.insert(uuid.to_string(), (Instant::now(), mount.clone()));
+    mount
+}
+
+#[cfg(unix)]
+fn absolute_path(path: &str) -> Option<PathBuf> {
+    std::fs::canonicalize(path).ok()
+}
+
+// canonicalize() returns a `\\?\` path, which no longer starts with the drive root
+#[cfg(target_os = "windows")]
+fn absolute_path(path: &str) -> Option<PathBuf> {
+    std::path::absolute(path).ok()
+}
+
+/// The mount point is the highest ancestor still on the same device.
+#[cfg(unix)]
+fn mount_point(path: &Path) -> Option<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+
+    let dev = std::fs::metadata(path).ok()?.dev();
+    let mut mount = path;
+    while let Some(parent) = mount.parent() {
+        match std::fs::metadata(parent) {
+            Ok(metadata) if metadata.dev() == dev => mount = parent,
+            _ => break,
+        }
+    }
+    Some(mount.to_path_buf())
+}
+
+/// On Windows a volume is mounted at its drive root.
+#[cfg(target_os = "windows")]
+fn mount_point(path: &Path) -> Option<PathBuf> {
+    use std::path::{Component, Prefix};
+
+    match path.components().next()? {
+        Component::Prefix(prefix) => match prefix.kind() {
+            Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => {
+                Some(PathBuf::from(format!("{}:\\", letter as char)))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn volume_uuid(mount: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let dev = std::fs::metadata(mount).ok()?.dev();
+    let source = linux::mounts()
+        .into_iter()
+        .find(|(_, mount_point)| mount_point == mount)
+        .and_then(|(source, _)| std::fs::canonicalize(source).ok());
+    for entry in std::fs::read_dir("/dev/disk/by-uuid").ok()?.flatten() {
+        // Block-device filesystems report the device as st_dev; FUSE and btrfs
+        // do not, so fall back to the mount source.
+        let device = entry.path();
+        let matches = std::fs::metadata(&device).is_ok_and(|m| m.rdev() == dev)
+            || source.is_some() && std::fs::canonicalize(&device).ok() == source;
+        if matches {
+            return entry.file_name().into_string().ok();
+        }
+    }
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn mount_point_for_uuid(uuid: &str) -> Option<PathBuf> {
+    let device = std::fs::canonicalize(Path::new("/dev/disk/by-uuid").join(uuid)).ok()?;
+    linux::mounts()
+        .into_iter()
+        .find(|(source, _)| std::fs::canonicalize(source).ok().as_ref() == Some(&device))
+        .map(|(_, mount_point)| mount_point)
+}
+
+#[cfg(any(target_os = "linux", test))]
+mod linux {
+    use std::path::PathBuf;
+
+    /// (source, mount point) of every mount, from /proc/self/mountinfo.
+    #[cfg(target_os = "linux")]
+    pub(super) fn mounts() -> Vec<(PathBuf, PathBuf)> {
+        let Ok(mountinfo) = std::fs::read_to_string("/proc/self/mountinfo") else {
+            return Vec::new();
+        };
+        parse_mountinfo(&mountinfo)
+    }
+
+    pub(super) fn parse_mountinfo(mountinfo: &str) -> Vec<(PathBuf, PathBuf)> {
+        mountinfo
+            .lines()
+            .filter_map(|line| {
+                // id parent major:minor root mount-point options ... - fstype source options
+                let (fields, tail) = line.split_once(" - ")?;
+                let mount_point = fields.split(' ').nth(4)?;
+                let source = tail.split(' ').nth(1)?;
+                Some((
+                    PathBuf::from(unescape(source)),
+                    PathBuf::from(unescape(mount_point)),
+                ))
+            })
+            .collect()
+    }
+
+    /// mountinfo escapes space, tab, newline and backslash as octal (`\040`).
+    fn unescape(field: &str) -> String {
+        let bytes = field.as_bytes();
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'\\' && i + 4 <= bytes.len() {
+                let digits = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or("");
+                if let Ok(value) = u8::from_str_radix(digits, 8) {
+                    out.push(value);
+                    i += 4;
+                    continue;
+                }
+            }
+            out.push(bytes[i]);
+            i += 1;
+        }
+        String::from_utf8_lossy(&out).to_string()
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn diskutil_field(identifier: &str, field: &str) -> Option<String> {
+    let output = std::process::Command::new("diskutil")
+        .args(["info", identifier])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_diskutil_field(&String::from_utf8_lossy(&output.stdout), field)
+}
+
+/// `diskutil info` prints one `Key:   value` pair per line.
+#[cfg(any(target_os = "macos", test))]
+fn parse_diskutil_field(output: &str, field: &str) -> Option<String> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .find(|(key, _)| key.trim() == field)
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(target_os = "macos")]
+fn volume_uuid(mount: &Path) -> Option<String> {
+    diskutil_field(&mount.to_string_lossy(), "Volume UUID")
+}
+
+#[cfg(target_os = "macos")]
+fn mount_point_for_uuid(uuid: &str) -> Option<PathBuf> {
+    diskutil_field(uuid, "Mount Point").map(PathBuf::from)
+}
+
+#[cfg(target_os = "windows")]
+fn volume_uuid(mount: &Path) -> Option<String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let drive = mount.to_string_lossy();
+    let drive = drive.trim_end_matches('\\');
+    let output = std::process::Command::new("cmd")
+        .args(["/C", "vol", drive])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_vol_serial(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// The last word of `vol` output is the serial number (e.g. `1A2B-3C4D`),
+/// whatever the system language.
+#[cfg(any(target_os = "windows", test))]
+fn parse_vol_serial(output: &str) -> Option<String> {
+    output
+        .split_whitespace()
+        .last()
+        .filter(|serial| serial.len() == 9 && serial.as_bytes()[4] == b'-')
+        .map(str::to_string)
+}
+
+#[cfg(target_os = "windows")]
+fn mount_point_for_uuid(uuid: &str) -> Option<PathBuf> {
+    (b'A'..=b'Z')
+        .map(|letter| PathBuf::from(format!("{}:\\", letter as char)))
+        .filter(|drive| drive.is_dir())
+        .find(|drive| cached_volume_uuid(drive).as_deref() == Some(uuid))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn volume_uuid(_mount: &Path) -> Option<String> {
+    None
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn mount_point_for_uuid(_uuid: &str) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_mountinfo() {
+        let mountinfo = "\
+22 1 8:2 / / rw,relatime shared:1 - ext4 /dev/sda2 rw,errors=remount-ro
+40 22 0:35 / /proc rw,nosuid,nodev,noexec,relatime shared:12 - proc proc rw
+36 22 8:17 / /media/anna/My\\040Photos rw,nosuid,nodev,relatime shared:20 - exfat /dev/sdb1 rw,uid=1000
+51 22 0:48 / /home/anna/cloud rw,nosuid,nodev,relatime shared:31 master:9 - fuse.rclone remote: rw,user_id=1000
+";
+        assert_eq!(
+            linux::parse_mountinfo(mountinfo),
+            vec![
+                (PathBuf::from("/dev/sda2"), PathBuf::from("/")),
+                (PathBuf::from("proc"), PathBuf::from("/proc")),
+                (
+                    PathBuf::from("/dev/sdb1"),
+                    PathBuf::from("/media/anna/My Photos")
+                ),
+                (PathBuf::from("remote:"), PathBuf::from("/home/anna/cloud")),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_diskutil_info() {
+        let output = "   Device Identifier:         disk4s1
+   Device Node:               /dev/disk4s1
+   Whole:                     No
+   Part of Whole:             disk4
+
+   Volume Name:               Photos: 2019
+   Mounted:                   Yes
+   Mount Point:               /Volumes/Photos: 2019
+
+   File System Personality:   ExFAT
+   Volume UUID:               5A3B1C2D-0E4F-3A21-9B8C-7D6E5F4A3B2C
+   Disk / Partition UUID:     8C1D2E3F-4A5B-4C6D-8E7F-9A0B1C2D3E4F
+";
+        assert_eq!(
+            parse_diskutil_field(output, "Volume UUID").as_deref(),
+            Some("5A3B1C2D-0E4F-3A21-9B8C-7D6E5F4A3B2C")
+        );
+        assert_eq!(
+            parse_diskutil_field(output, "Mount Point").as_deref(),
+            Some("/Volumes/Photos: 2019")
+        );
+        assert_eq!(parse_diskutil_field(output, "Media Name"), None);
+    }
+
+    #[test]
+    fn parses_vol_serial() {
+        let english = " Volume in drive D is PHOTOS\r\n Volume Serial Number is 1A2B-3C4D\r\n";
+        let german =
+            " Volume in Laufwerk E: hat keine Bezeichnung.\r\n Volumeseriennummer: 5E6F-7A8B\r\n";
+        assert_eq!(parse_vol_serial(english).as_deref(), Some("1A2B-3C4D"));
+        assert_eq!(parse_vol_serial(german).as_deref(), Some("5E6F-7A8B"));
+        assert_eq!(parse_vol_serial("The device is not ready.\r\n"), None);
+    }
+}
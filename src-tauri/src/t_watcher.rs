@@ -0,0 +1,476 @@
+/**
+ * t_watcher.rs - Real-time folder watching
+ *
+ * On Linux an inotify watch on every album directory queues create/delete/rename/modify
+ * events. They are debounced per directory and fed into `sync_single_folder`, which
+ * schedules thumbnail and embedding generation for new or changed files.
+ *
+ * inotify watches one directory at a time, so a large library can exhaust
+ * `fs.inotify.max_user_watches`; the watcher then falls back to periodic mtime scans.
+ * Other platforms keep relying on `start_folder_mtime_sync`. Every platform checks
+ * periodically whether an offline album's drive has been mounted again.
+ */
+#[cfg(target_os = "linux")]
+use crate::t_sqlite::AFolder;
+use crate::t_utils;
+#[cfg(target_os = "linux")]
+use std::collections::{HashMap, HashSet};
+#[cfg(target_os = "linux")]
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+#[cfg(target_os = "linux")]
+use std::time::Instant;
+#[cfg(target_os = "linux")]
+use tauri::Emitter;
+
+#[cfg(target_os = "linux")]
+const DEBOUNCE: Duration = Duration::from_millis(1500); // quiet time before a directory is synced
+#[cfg(target_os = "linux")]
+const MAX_DEBOUNCE: Duration = Duration::from_secs(10); // sync busy directories at least this often
+const ROOT_REFRESH: Duration = Duration::from_secs(30); // pick up added, removed and reconnected albums
+#[cfg(target_os = "linux")]
+const FALLBACK_POLL: Duration = Duration::from_secs(300);
+#[cfg(target_os = "linux")]
+const MOVE_GRACE: Duration = Duration::from_secs(30); // keep rows of vanished files for a move's other half
+
+static WATCHER_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+// RAW+JPEG grouping is a frontend setting; events stay queued until it is known.
+static GROUP_RAW_JPEG_PAIRS: Mutex<Option<bool>> = Mutex::new(None);
+
+pub fn set_group_raw_jpeg_pairs(value: bool) {
+    *GROUP_RAW_JPEG_PAIRS.lock().unwrap() = Some(value);
+}
+
+/// Start watching the current library's albums, stopping any previous watcher
+/// (e.g. after a library switch).
+pub fn start_folder_watcher(app_handle: tauri::AppHandle) {
+    let generation = WATCHER_GENERATION
+        .fetch_add(1, Ordering::SeqCst)
+        .wrapping_add(1);
+
+    #[cfg(target_os = "linux")]
+    std::thread::spawn(move || {
+        if let Err(e) = linux::run(&app_handle, generation) {
+            eprintln!("folder watcher: {}, falling back to mtime scans", e);
+            poll_mtime_fallback(&app_handle, generation);
+        }
+    });
+
+    // Without a watcher, still notice drives being plugged in.
+    #[cfg(not(target_os = "linux"))]
+    std::thread::spawn(move || {
+        while watcher_active(generation) {
+            std::thread::sleep(ROOT_REFRESH);
+            if watcher_active(generation) {
+                t_utils::reconnect_offline_albums(&app_handle);
+            }
+        }
+    });
+}
+
+fn watcher_active(generation: u64) -> bool {
+    WATCHER_GENERATION.load(Ordering::SeqCst) == generation
+}
+
+/// Directories with pending events: (first event, last event)
+#[cfg(target_os = "linux")]
+type DirtyDirs = HashMap<PathBuf, (Instant, Instant)>;
+
+/// Rows of vanished files, with the time they were found missing
+#[cfg(target_os = "linux")]
+type PendingMissing = Vec<(t_utils::MissingFile, Instant)>;
+
+#[cfg(target_os = "linux")]
+fn mark_dirty(dirty: &mut DirtyDirs, dir: PathBuf) {
+    let now = Instant::now();
+    dirty
+        .entry(dir)
+        .and_modify(|(_, last)| *last = now)
+        .or_insert((now, now));
+}
+
+/// Take the directories whose events have settled.
+#[cfg(target_os = "linux")]
+fn take_settled(dirty: &mut DirtyDirs) -> Vec<PathBuf> {
+    let settled: Vec<PathBuf> = dirty
+        .iter()
+        .filter(|(_, (first, last))| last.elapsed() >= DEBOUNCE || first.elapsed() >= MAX_DEBOUNCE)
+        .map(|(dir, _)| dir.clone())
+        .collect();
+    for dir in &settled {
+        dirty.remove(dir);
+    }
+    settled
+}
+
+/// Sync settled directories, parents first so new folders exist before their files.
+#[cfg(target_os = "linux")]
+fn sync_dirty_dirs(
+    app_handle: &tauri::AppHandle,
+    mut dirs: Vec<PathBuf>,
+    group_raw_jpeg_pairs: bool,
+    pending_missing: &mut PendingMissing,
+) {
+    dirs.sort_by_key(|dir| dir.as_os_str().len());
+    let mut synced = HashSet::new();
+    let mut missing_files = Vec::new();
+    for dir in dirs {
+        let Some(folder) = find_known_folder(&dir) else {
+            continue;
+        };
+        let Some(folder_id) = folder.id else {
+            continue;
+        };
+        if !synced.insert(folder_id) {
+            continue;
+        }
+        // Events also report file modifications, which leave the directory mtime alone,
+        // so always reconcile the folder's files. Vanished subfolders are kept by the
+        // deferred sync; a moved one is re-attached by inode at its new location.
+        match t_utils::sync_single_folder_deferred(
+            app_handle,
+            folder.album_id,
+            folder_id,
+            &folder.path,
+            group_raw_jpeg_pairs,
+            true,
+            &mut missing_files,
+        ) {
+            Ok(result) => {
+                if !result.folder_path_migrations.is_empty() {
+                    let _ = app_handle.emit(
+                        "album-folder-paths-migrated",
+                        &result.folder_path_migrations,
+                    );
+                }
+                if result.dirty_folder_count > 0 || result.new_folder_count > 0 {
+                    let _ = app_handle.emit("library-folder-sync-finished", &result);
+                    let _ = app_handle.emit("library-total-refreshed", ());
+                }
+            }
+            Err(e) => eprintln!("folder watcher: failed to sync {}: {}", folder.path, e),
+        }
+    }
+    let now = Instant::now();
+    pending_missing.extend(missing_files.into_iter().map(|missing| (missing, now)));
+}
+
+/// Delete the rows of files that stayed missing for `MOVE_GRACE`. The two halves of a
+/// move can settle in different batches, so a vanished file's row is kept that long
+/// for its new location to claim it.
+#[cfg(target_os = "linux")]
+fn delete_expired_missing(app_handle: &tauri::AppHandle, pending_missing: &mut PendingMissing) {
+    if pending_missing.is_empty() {
+        return;
+    }
+    let (expired, pending): (PendingMissing, PendingMissing) = std::mem::take(pending_missing)
+        .into_iter()
+        .partition(|(_, found_at)| found_at.elapsed() >= MOVE_GRACE);
+    *pending_missing = pending;
+    let expired: Vec<t_utils::MissingFile> =
+        expired.into_iter().map(|(missing, _)| missing).collect();
+    if !expired.is_empty() && t_utils::delete_missing_files(expired) > 0 {
+        let _ = app_handle.emit("library-total-refreshed", ());
+    }
+}
+
+/// The nearest indexed folder at or above `dir`; a new subfolder is added by syncing its parent.
+#[cfg(target_os = "linux")]
+fn find_known_folder(dir: &Path) -> Option<AFolder> {
+    let mut current = Some(dir);
+    while let Some(path) = current {
+        if let Ok(Some(folder)) = AFolder::fetch(&path.to_string_lossy()) {
+            return Some(folder);
+        }
+        current = path.parent();
+    }
+    None
+}
+
+/// Without inotify, poll with the mtime scan until the watcher is restarted.
+#[cfg(target_os = "linux")]
+fn poll_mtime_fallback(app_handle: &tauri::AppHandle, generation: u64) {
+    let mut last_scan = Instant::now();
+    while watcher_active(generation) {
+        std::thread::sleep(Duration::from_secs(1));
+        if last_scan.elapsed() >= FALLBACK_POLL {
+            t_utils::start_folder_mtime_sync(app_handle.clone());
+            last_scan = Instant::now();
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::*;
+    use crate::t_sqlite::Album;
+    use std::ffi::{CString, OsStr};
+    use std::os::unix::ffi::OsStrExt;
+    use walkdir::WalkDir;
+
+    const WATCH_MASK: u32 = libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_MOVED_FROM
+        | libc::IN_MOVED_TO
+        | libc::IN_CLOSE_WRITE
+        | libc::IN_ATTRIB
+        | libc::IN_DELETE_SELF
+        | libc::IN_MOVE_SELF
+        | libc::IN_ONLYDIR;
+    const EVENT_HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+    const POLL_TIMEOUT_MS: i32 = 250;
+
+    enum Poll {
+        Ready,
+        Overflow,   // the kernel queue overflowed and events were lost
+        WatchLimit, // a new directory could not be watched
+    }
+
+    struct Inotify {
+        fd: i32,
+        watches: HashMap<i32, (PathBuf, String)>, // watch descriptor -> (directory, album root)
+    }
+
+    impl Inotify {
+        fn new() -> Result<Self, String> {
+            let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error().to_string());
+            }
+            Ok(Self {
+                fd,
+                watches: HashMap::new(),
+            })
+        }
+
+        /// Watch `dir` and every visible directory below it.
+        /// Returns false once the watch limit is reached.
+        fn add_tree(&mut self, root: &str, dir: &Path) -> bool {
+            for entry in WalkDir::new(dir)
+                .into_iter()
+                .filter_entry(|e| !t_utils::is_hidden(e))
+                .filter_map(Result::ok)
+                .filter(|e| e.file_type().is_dir())
+            {
+                let Ok(path) = CString::new(entry.path().as_os_str().as_bytes()) else {
+                    continue;
+                };
+                let wd = unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), WATCH_MASK) };
+                if wd < 0 {
+                    let error = std::io::Error::last_os_error();
+                    if error.raw_os_error() == Some(libc::ENOSPC) {
+                        return false;
+                    }
+                    continue; // vanished or unreadable; its parent still reports changes
+                }
+                self.watches
+                    .insert(wd, (entry.path().to_path_buf(), root.to_string()));
+            }
+            true
+        }
+
+        /// Every watched directory, to resync after lost events.
+        fn watched_dirs(&self) -> Vec<PathBuf> {
+            self.watches.values().map(|(dir, _)| dir.clone()).collect()
+        }
+
+        fn remove_root(&mut self, root: &str) {
+            let wds: Vec<i32> = self
+                .watches
+                .iter()
+                .filter(|(_, (_, watch_root))| watch_root == root)
+                .map(|(wd, _)| *wd)
+                .collect();
+            for wd in wds {
+                unsafe { libc::inotify_rm_watch(self.fd, wd) };
+                self.watches.remove(&wd);
+            }
+        }
+
+        /// Wait briefly for events and mark the directories they touch as dirty.
+        fn read_events(&mut self, dirty: &mut DirtyDirs) -> Poll {
+            let mut pfd = libc::pollfd {
+                fd: self.fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            if unsafe { libc::poll(&mut pfd, 1, POLL_TIMEOUT_MS) } <= 0 {
+                return Poll::Ready;
+            }
+
+            let mut result = Poll::Ready;
+            let mut buf = vec![0u8; 64 * 1024];
+            loop {
+                let n = unsafe { libc::read(self.fd, buf.as_mut_ptr().cast(), buf.len()) };
+                if n <= 0 {
+                    break; // EAGAIN: queue drained
+                }
+                let n = n as usize;
+                let mut offset = 0;
+                while offset + EVENT_HEADER <= n {
+                    let event: libc::inotify_event =
+                        unsafe { std::ptr::read_unaligned(buf.as_ptr().add(offset).cast()) };
+                    let name_start = offset + EVENT_HEADER;
+                    let name_end = (name_start + event.len as usize).min(n);
+                    let name = buf[name_start..name_end]
+                        .split(|&b| b == 0)
+                        .next()
+                        .unwrap_or_default()
+                        .to_vec();
+                    offset = name_end;
+
+                    if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                        result = Poll::Overflow;
+                        continue;
+                    }
+                    if event.mask & libc::IN_IGNORED != 0 {
+                        self.watches.remove(&event.wd);
+                        continue;
+                    }
+                    let Some((dir, root)) = self.watches.get(&event.wd).cloned() else {
+                        continue;
+                    };
+                    if name.first() == Some(&b'.') {
+                        continue; // hidden entries are never indexed
+                    }
+                    mark_dirty(dirty, dir.clone());
+                    if event.mask & libc::IN_ISDIR != 0
+                        && event.mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0
+                        && !name.is_empty()
+                    {
+                        let child = dir.join(OsStr::from_bytes(&name));
+                        mark_dirty(dirty, child.clone());
+                        if !self.add_tree(&root, &child) {
+                            return Poll::WatchLimit;
+                        }
+                    }
+                }
+            }
+            result
+        }
+    }
+
+    impl Drop for Inotify {
+        fn drop(&mut self) {
+            unsafe { libc::close(self.fd) };
+        }
+    }
+
+    /// Watch added albums and drop removed ones. Returns false once the watch limit is reached.
+    fn refresh_roots(
+        app_handle: &tauri::AppHandle,
+        inotify: &mut Inotify,
+        roots: &mut HashSet<String>,
+    ) -> Result<bool, String> {
+        t_utils::reconnect_offline_albums(app_handle);
+        let current: HashSet<String> = Album::get_all_albums()?
+            .into_iter()
+            .filter(t_utils::album_online)
+            .map(|album| album.path)
+            .collect();
+        for root in roots.difference(&current) {
+            inotify.remove_root(root);
+        }
+        roots.retain(|root| current.contains(root));
+        for root in current {
+            if !roots.contains(&root) {
+                if !inotify.add_tree(&root, Path::new(&root)) {
+                    return Ok(false);
+                }
+                roots.insert(root);
+            }
+        }
+        Ok(true)
+    }
+
+    pub(super) fn run(app_handle: &tauri::AppHandle, generation: u64) -> Result<(), String> {
+        let mut inotify = Inotify::new()?;
+        let mut roots: HashSet<String> = HashSet::new();
+        let mut dirty: DirtyDirs = HashMap::new();
+        let mut pending_missing: PendingMissing = Vec::new();
+        let mut last_refresh: Option<Instant> = None;
+
+        while watcher_active(generation) {
+            if last_refresh.is_none_or(|at| at.elapsed() >= ROOT_REFRESH) {
+                if !refresh_roots(app_handle, &mut inotify, &mut roots)? {
+                    return Err("inotify watch limit reached".into());
+                }
+                last_refresh = Some(Instant::now());
+            }
+
+            match inotify.read_events(&mut dirty) {
+                Poll::Ready => {}
+                Poll::Overflow => {
+                    // Lost events may include in-place modifications, which leave the
+                    // directory mtime alone, so reconcile every file of every album.
+                    for root in &roots {
+                        if !inotify.add_tree(root, Path::new(root)) {
+                            return Err("inotify watch limit reached".into());
+                        }
+                    }
+                    for dir in inotify.watched_dirs() {
+                        mark_dirty(&mut dirty, dir);
+                    }
+                }
+                Poll::WatchLimit => return Err("inotify watch limit reached".into()),
+            }
+
+            let Some(group_raw_jpeg_pairs) = *GROUP_RAW_JPEG_PAIRS.lock().unwrap() else {
+                continue;
+            };
+            let settled = take_settled(&mut dirty);
+            if !settled.is_empty() && watcher_active(generation) {
+                sync_dirty_dirs(
+                    app_handle,
+                    settled,
+                    group_raw_jpeg_pairs,
+                    &mut pending_missing,
+                );
+            }
+            delete_expired_missing(app_handle, &mut pending_missing);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    fn ago(duration: Duration) -> Instant {
+        Instant::now().checked_sub(duration).unwrap()
+    }
+
+    #[test]
+    fn mark_dirty_keeps_the_first_event() {
+        let mut dirty = DirtyDirs::new();
+        let dir = PathBuf::from("/photos/2024");
+        dirty.insert(dir.clone(), (ago(MAX_DEBOUNCE), ago(MAX_DEBOUNCE)));
+        mark_dirty(&mut dirty, dir.clone());
+        let (first, last) = dirty[&dir];
+        assert!(first.elapsed() >= MAX_DEBOUNCE);
+        assert!(last.elapsed() < DEBOUNCE);
+
+        mark_dirty(&mut dirty, PathBuf::from("/photos/2025"));
+        assert_eq!(dirty.len(), 2);
+    }
+
+    #[test]
+    fn take_settled_waits_for_quiet_or_max_debounce() {
+        let quiet = PathBuf::from("/photos/quiet");
+        let busy = PathBuf::from("/photos/busy");
+        let overdue = PathBuf::from("/photos/overdue");
+        let mut dirty = DirtyDirs::new();
+        dirty.insert(quiet.clone(), (ago(DEBOUNCE), ago(DEBOUNCE)));
+        dirty.insert(busy.clone(), (Instant::now(), Instant::now()));
+        dirty.insert(overdue.clone(), (ago(MAX_DEBOUNCE), Instant::now()));
+
+        let mut settled = take_settled(&mut dirty);
+        settled.sort();
+        assert_eq!(settled, vec![overdue, quiet]);
+        assert_eq!(dirty.keys().collect::<Vec<_>>(), vec![&busy]);
+    }
+}
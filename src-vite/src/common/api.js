@@ -416,6 +416,26 @@ export async function editAlbum(albumId, newName, newDespription) {
   return null;
 }
 
+// get an album's scan filter
+export async function getAlbumScanFilter(albumId) {
+  try {
+    return await invoke('get_album_scan_filter', { albumId });
+  } catch (error) {
+    console.error('getAlbumScanFilter error:', error);
+  }
+  return null;
+}
+
+// save an album's scan filter; returns the number of files removed by it
+export async function setAlbumScanFilter(albumId, settings) {
+  try {
+    return await invoke('set_album_scan_filter', { albumId, settings });
+  } catch (error) {
+    console.error('setAlbumScanFilter error:', error);
+  }
+  return null;
+}
+
 // remove an album
 export async function removeAlbum(albumId) {
   try {
@@ -594,9 +614,10 @@ export async function expandFinalFolder(rootFolder, finalPath) {
 }
 
 // recurse all files under the path(include all sub-folders), and count the number of files
-export async function countFolder(path) {
+// (leaving out files excluded by scanFilter when given, else by the album's saved scan filter)
+export async function countFolder(path, albumId = null, scanFilter = null) {
   try {
-    const result = await invoke('count_folder', { path });
+    const result = await invoke('count_folder', { path, albumId, settings: scanFilter });
     if(result) {
       return result;
     };
@@ -1063,6 +1084,30 @@ export async function renameFile(fileId, filePath, newName) {
   return null;
 }
 
+// preview a template rename; options: { template, seqStart, lowercaseExt }
+export async function batchRenamePreview(fileIds, options) {
+  return await invoke('batch_rename_preview', { fileIds, options });
+}
+
+// rename files by template; throws when the plan has conflicts
+export async function batchRenameApply(fileIds, options) {
+  return await invoke('batch_rename_apply', { fileIds, options });
+}
+
+// restore the names of a rename batch (the latest one when batchId is null); throws on error
+export async function batchRenameUndo(batchId = null) {
+  return await invoke('batch_rename_undo', { batchId });
+}
+
+export async function batchRenameListBatches(limit = 10) {
+  try {
+    return await invoke('batch_rename_list_batches', { limit });
+  } catch (error) {
+    console.error('batchRenameListBatches error:', error);
+  }
+  return [];
+}
+
 // move a file
 export async function moveFile(fileId, filePath, newFolderId, newFolderPath, conflictPolicy = 'keep_both') {
   try {
@@ -1721,6 +1766,56 @@ export async function listenImageSearchModelDownloadProgress(callback) {
   return await listen('image_search_model_download_progress', callback);
 }
 
+// list the bundled and locally installed image embedding models
+export async function listImageSearchModels() {
+  try {
+    return await invoke('list_image_search_models');
+  } catch (error) {
+    console.error('listImageSearchModels error:', error);
+  }
+  return [];
+}
+
+// switch the image embedding model of the current library
+export async function setImageSearchImageModel(modelId) {
+  try {
+    return await invoke('set_image_search_image_model', { modelId });
+  } catch (error) {
+    console.error('setImageSearchImageModel error:', error);
+    throw error;
+  }
+}
+
+export async function startImageSearchReembed() {
+  try {
+    return await invoke('start_image_search_reembed');
+  } catch (error) {
+    console.error('startImageSearchReembed error:', error);
+    throw error;
+  }
+}
+
+export async function cancelImageSearchReembed() {
+  try {
+    return await invoke('cancel_image_search_reembed');
+  } catch (error) {
+    console.error('cancelImageSearchReembed error:', error);
+  }
+}
+
+export async function getImageSearchReembedStatus() {
+  try {
+    return await invoke('get_image_search_reembed_status');
+  } catch (error) {
+    console.error('getImageSearchReembedStatus error:', error);
+  }
+  return null;
+}
+
+export async function listenImageSearchReembedProgress(callback) {
+  return await listen('image-search-reembed-progress', callback);
+}
+
 // generate embedding
 export async function generateEmbedding(fileId) {
   try {
@@ -1790,6 +1885,59 @@ export async function clearIndexRecoveryInfo() {
   return false;
 }
 
+// get files that were skipped or failed during indexing
+// query: { albumId, stage, kind, includeIgnored, ids }
+export async function getIndexErrors(query = {}, offset = 0, limit = 0) {
+  try {
+    return await invoke('get_index_errors', { query, offset, limit });
+  } catch (error) {
+    console.error('getIndexErrors error:', error);
+  }
+  return null;
+}
+
+export async function countIndexErrors(query = {}) {
+  try {
+    return await invoke('count_index_errors', { query });
+  } catch (error) {
+    console.error('countIndexErrors error:', error);
+  }
+  return 0;
+}
+
+// retry the failed files matching the query
+export async function retryIndexErrors(query = {}) {
+  try {
+    return await invoke('retry_index_errors', {
+      query,
+      thumbnailSize: config.settings.thumbnailSize || 512,
+    });
+  } catch (error) {
+    console.error('retryIndexErrors error:', error);
+  }
+  return null;
+}
+
+// hide index errors from the report and from retries (or show them again)
+export async function ignoreIndexErrors(ids, ignored = true) {
+  try {
+    return await invoke('ignore_index_errors', { ids, ignored });
+  } catch (error) {
+    console.error('ignoreIndexErrors error:', error);
+  }
+  return null;
+}
+
+// export index errors to a CSV file
+export async function exportIndexErrors(query, filePath) {
+  try {
+    return await invoke('export_index_errors', { query, filePath });
+  } catch (error) {
+    console.error('exportIndexErrors error:', error);
+  }
+  return null;
+}
+
 // listen index progress
 export async function listenIndexProgress(callback) {
   return await listen('index_progress', callback);
@@ -1798,8 +1946,11 @@ export async function listenIndexProgress(callback) {
 // deduplication
 
 // start deduplication scan
-export async function dedupStartScan(params = null, collectionId = null, fileIds = null) {
-  return await invoke('dedup_start_scan', { params, collectionId, fileIds });
+// matchKind: 'exact' (identical bytes), 'dhash' or 'phash' (similar-looking images within maxDistance bits),
+// or 'video' (re-encoded or trimmed copies of a clip, frames within maxDistance bits, at most 3;
+// only the first 10 minutes of each clip are compared)
+export async function dedupStartScan(params = null, collectionId = null, fileIds = null, matchKind = null, maxDistance = null) {
+  return await invoke('dedup_start_scan', { params, collectionId, fileIds, matchKind, maxDistance });
 }
 
 // get deduplication scan status
@@ -1847,10 +1998,26 @@ export async function dedupSetKeep(groupId, fileId) {
   return await invoke('dedup_set_keep', { groupId, fileId });
 }
 
+// get the keep policy (weighted criteria for picking the file to keep) of the current library
+export async function dedupGetKeepPolicy() {
+  try {
+    return await invoke('dedup_get_keep_policy');
+  } catch (error) {
+    console.error('dedupGetKeepPolicy error:', error);
+  }
+  return null;
+}
+
+// save the keep policy of the current library; existing unreviewed groups are re-ranked
+export async function dedupSetKeepPolicy(policy) {
+  return await invoke('dedup_set_keep_policy', { policy });
+}
+
 // delete selected duplicates
-export async function dedupDeleteSelected(groupIds = null, fileIds = null) {
+// mergeMetadata: union tags/collections, max rating, favorite and comments into the kept file first
+export async function dedupDeleteSelected(groupIds = null, fileIds = null, mergeMetadata = false) {
   try {
-    const result = await invoke('dedup_delete_selected', { groupIds, fileIds });
+    const result = await invoke('dedup_delete_selected', { groupIds, fileIds, mergeMetadata });
     return result;
   } catch (error) {
     console.error('dedupDeleteSelected error:', error);
@@ -1858,13 +2025,259 @@ export async function dedupDeleteSelected(groupIds = null, fileIds = null) {
   }
 }
 
+// replace selected duplicates in place with links to the kept file (mode: 'auto', 'hardlink' or 'reflink')
+export async function dedupLinkSelected(groupIds = null, fileIds = null, mode = null) {
+  try {
+    return await invoke('dedup_link_selected', { groupIds, fileIds, mode });
+  } catch (error) {
+    console.error('dedupLinkSelected error:', error);
+    throw error;
+  }
+}
+
 // listen dedup scan progress
 export async function listenDedupScanProgress(callback) {
   return await listen('dedup-scan-progress', callback);
 }
 
-export async function similarStartScan(scopeKey, sourceVersion, similarityThreshold, params = null, collectionId = null, fileIds = null) {
-  return await invoke('similar_start_scan', { scopeKey, sourceVersion, similarityThreshold, params, collectionId, fileIds });
+// integrity verification
+
+// start an integrity check; options: { albumId, sliceSize (0 = all files), decode }
+export async function integrityStartCheck(options = null) {
+  return await invoke('integrity_start_check', { options });
+}
+
+export async function integrityGetStatus() {
+  try {
+    return await invoke('integrity_get_status');
+  } catch (error) {
+    console.error('integrityGetStatus error:', error);
+  }
+  return null;
+}
+
+export async function integrityCancelCheck() {
+  try {
+    await invoke('integrity_cancel_check');
+  } catch (error) {
+    console.error('integrityCancelCheck error:', error);
+  }
+}
+
+export async function integrityGetOverview() {
+  try {
+    return await invoke('integrity_get_overview');
+  } catch (error) {
+    console.error('integrityGetOverview error:', error);
+  }
+  return null;
+}
+
+// list files with problems; status: 'corrupt', 'decode_failed', 'unreadable' or null for all
+export async function integrityListIssues(status = null, offset = 0, limit = 0) {
+  try {
+    return await invoke('integrity_list_issues', { status, offset, limit });
+  } catch (error) {
+    console.error('integrityListIssues error:', error);
+  }
+  return [];
+}
+
+// other library files that still match a damaged file's last known good hash
+export async function integrityFindIntactCopies(fileId) {
+  try {
+    return await invoke('integrity_find_intact_copies', { fileId });
+  } catch (error) {
+    console.error('integrityFindIntactCopies error:', error);
+  }
+  return null;
+}
+
+// accept the current content of flagged files as their new baseline
+export async function integrityAcceptCurrent(fileIds) {
+  try {
+    return await invoke('integrity_accept_current', { fileIds });
+  } catch (error) {
+    console.error('integrityAcceptCurrent error:', error);
+  }
+  return null;
+}
+
+export async function integrityGetSchedule() {
+  try {
+    return await invoke('integrity_get_schedule');
+  } catch (error) {
+    console.error('integrityGetSchedule error:', error);
+  }
+  return null;
+}
+
+export async function integritySetSchedule(schedule) {
+  try {
+    await invoke('integrity_set_schedule', { schedule });
+  } catch (error) {
+    console.error('integritySetSchedule error:', error);
+  }
+}
+
+// listen integrity check progress
+export async function listenIntegrityCheckProgress(callback) {
+  return await listen('integrity-check-progress', callback);
+}
+
+// import from memory cards and camera folders
+
+// scan a source folder (SD card, DCIM) and open an import session; throws on error
+export async function importScanSource(sourcePath) {
+  return await invoke('import_scan_source', { sourcePath });
+}
+
+export async function importCloseSession(sessionId) {
+  try {
+    await invoke('import_close_session', { sessionId });
+  } catch (error) {
+    console.error('importCloseSession error:', error);
+  }
+}
+
+// base64 jpeg thumbnail of a source file
+export async function importGetPreview(filePath, thumbnailSize) {
+  try {
+    return await invoke('import_get_preview', { filePath, thumbnailSize });
+  } catch (error) {
+    console.error('importGetPreview error:', error);
+  }
+  return null;
+}
+
+// options: { albumId, destTemplate, event, itemIds, skipDuplicates, tagIds, collectionId }
+export async function importStart(sessionId, options) {
+  return await invoke('import_start', { sessionId, options });
+}
+
+export async function importGetStatus() {
+  try {
+    return await invoke('import_get_status');
+  } catch (error) {
+    console.error('importGetStatus error:', error);
+  }
+  return null;
+}
+
+export async function importCancel() {
+  try {
+    await invoke('import_cancel');
+  } catch (error) {
+    console.error('importCancel error:', error);
+  }
+}
+
+// relative destination folder for a template, taken date (seconds) and event name
+export async function importExpandTemplate(template, takenDate, event) {
+  try {
+    return await invoke('import_expand_template', { template, takenDate, event });
+  } catch (error) {
+    console.error('importExpandTemplate error:', error);
+  }
+  return null;
+}
+
+// listen import progress
+export async function listenImportProgress(callback) {
+  return await listen('import-progress', callback);
+}
+
+// import a Google Photos Takeout export
+
+// scan an extracted Takeout folder and open a session; throws on error
+export async function takeoutScanSource(sourcePath) {
+  return await invoke('takeout_scan_source', { sourcePath });
+}
+
+export async function takeoutCloseSession(sessionId) {
+  try {
+    await invoke('takeout_close_session', { sessionId });
+  } catch (error) {
+    console.error('takeoutCloseSession error:', error);
+  }
+}
+
+// options: { albumId, destTemplate, skipDuplicates, writeFiles, createCollections }
+export async function takeoutStart(sessionId, options) {
+  return await invoke('takeout_start', { sessionId, options });
+}
+
+export async function takeoutGetStatus() {
+  try {
+    return await invoke('takeout_get_status');
+  } catch (error) {
+    console.error('takeoutGetStatus error:', error);
+  }
+  return null;
+}
+
+export async function takeoutCancel() {
+  try {
+    await invoke('takeout_cancel');
+  } catch (error) {
+    console.error('takeoutCancel error:', error);
+  }
+}
+
+// listen takeout import progress
+export async function listenTakeoutProgress(callback) {
+  return await listen('takeout-import-progress', callback);
+}
+
+// import ratings, flags, keywords and collections from another application's catalog
+
+// kind: 'lightroom', 'digikam' or 'shotwell'; rootMap: [{ from, to }]; throws on error
+export async function catalogImportPreview(kind, catalogPath, rootMap) {
+  return await invoke('catalog_import_preview', { kind, catalogPath, rootMap });
+}
+
+// options: { rootMap, ratings, flags, tags, collections, faces, overwrite }
+export async function catalogImportApply(kind, catalogPath, options) {
+  return await invoke('catalog_import_apply', { kind, catalogPath, options });
+}
+
+// reorganize album files into template folders
+
+// dry-run plan; scope: { albumId, folderPath, params, fileIds }, options: { template, conflictPolicy }
+export async function organizePreview(scope, options) {
+  return await invoke('organize_preview', { scope, options });
+}
+
+export async function organizeStart(scope, options) {
+  return await invoke('organize_start', { scope, options });
+}
+
+export async function organizeGetStatus() {
+  try {
+    return await invoke('organize_get_status');
+  } catch (error) {
+    console.error('organizeGetStatus error:', error);
+  }
+  return null;
+}
+
+export async function organizeCancel() {
+  try {
+    await invoke('organize_cancel');
+  } catch (error) {
+    console.error('organizeCancel error:', error);
+  }
+}
+
+// listen organize progress
+export async function listenOrganizeProgress(callback) {
+  return await listen('organize-progress', callback);
+}
+
+// scans only new or changed embeddings against the saved index unless fullRescan is set
+// timeWindow: max seconds between members' taken dates; clusterMode: 'complete' or 'community'
+export async function similarStartScan(scopeKey, sourceVersion, similarityThreshold, params = null, collectionId = null, fileIds = null, fullRescan = false, timeWindow = null, clusterMode = null) {
+  return await invoke('similar_start_scan', { scopeKey, sourceVersion, similarityThreshold, params, collectionId, fileIds, fullRescan, timeWindow, clusterMode });
 }
 
 export async function similarGetScanStatus() {
@@ -2019,3 +2432,122 @@ export async function getFacesForFile(fileId) {
   }
   return null;
 }
+
+// add a face box drawn by the user; rect is { x, y, width, height } in original pixels
+export async function addFace(fileId, rect, personId = null) {
+  try {
+    return await invoke('add_face', { fileId, rect, personId });
+  } catch (error) {
+    console.error('Failed to add face:', error);
+    throw error;
+  }
+}
+
+// move or resize a face box
+export async function updateFaceBox(faceId, rect) {
+  try {
+    return await invoke('update_face_box', { faceId, rect });
+  } catch (error) {
+    console.error('Failed to update face box:', error);
+    throw error;
+  }
+}
+
+// assign a face to a person (null to unassign)
+export async function setFacePerson(faceId, personId = null) {
+  try {
+    return await invoke('set_face_person', { faceId, personId });
+  } catch (error) {
+    console.error('Failed to set face person:', error);
+  }
+  return null;
+}
+
+// delete a face; deleted detections are not recreated by reindexing
+export async function deleteFace(faceId) {
+  try {
+    return await invoke('delete_face', { faceId });
+  } catch (error) {
+    console.error('Failed to delete face:', error);
+  }
+  return null;
+}
+
+// search the library for files containing faces similar to a given face
+export async function searchFaces(faceId, threshold = null, limit = null) {
+  try {
+    return await invoke('search_faces', { faceId, threshold, limit });
+  } catch (error) {
+    console.error('Failed to search faces:', error);
+  }
+  return null;
+}
+
+// check if the optional OCR models are installed
+export async function isOcrAvailable() {
+  try {
+    return await invoke('is_ocr_available');
+  } catch (error) {
+    console.error('isOcrAvailable error:', error);
+  }
+  return false;
+}
+
+export async function startOcrIndex() {
+  try {
+    return await invoke('start_ocr_index');
+  } catch (error) {
+    console.error('startOcrIndex error:', error);
+    throw error;
+  }
+}
+
+export async function cancelOcrIndex() {
+  try {
+    return await invoke('cancel_ocr_index');
+  } catch (error) {
+    console.error('cancelOcrIndex error:', error);
+  }
+}
+
+export async function getOcrIndexStatus() {
+  try {
+    return await invoke('get_ocr_index_status');
+  } catch (error) {
+    console.error('getOcrIndexStatus error:', error);
+  }
+  return null;
+}
+
+export async function resetOcrIndex() {
+  try {
+    return await invoke('reset_ocr_index');
+  } catch (error) {
+    console.error('resetOcrIndex error:', error);
+    throw error;
+  }
+}
+
+export async function listenOcrIndexProgress(callback) {
+  return await listen('ocr-index-progress', callback);
+}
+
+// get the recognized text of a file
+export async function getFileText(fileId) {
+  try {
+    return await invoke('get_file_text', { fileId });
+  } catch (error) {
+    console.error('getFileText error:', error);
+  }
+  return null;
+}
+
+// recognize the text of a single file now
+export async function recognizeFileText(fileId) {
+  try {
+    return await invoke('recognize_file_text', { fileId });
+  } catch (error) {
+    console.error('recognizeFileText error:', error);
+    throw error;
+  }
+}
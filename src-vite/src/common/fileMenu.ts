@@ -105,6 +105,13 @@ export const useFileMenuItems = (
         shortcut: shortcut('file.openExternalApp'),
         action: createAction('open-external-app'),
       },
+      { label: "-", action: null },
+      {
+        label: translate('menu.file.batch_rename'),
+        icon: markRaw(IconRename),
+        disabled: selectionCount < 1,
+        action: createAction('batch-rename'),
+      },
     ];
   };
 
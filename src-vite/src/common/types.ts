@@ -41,6 +41,9 @@ export interface Album {
     modified_at?: number;
     children?: Folder[];
     is_accessible?: boolean;
+    is_offline?: boolean;      // the album's drive is not mounted
+    volume_uuid?: string;
+    volume_path?: string;
 }
 
 /**
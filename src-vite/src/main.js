@@ -48,6 +48,10 @@ const i18n = createI18n({
   },
 })
 
+// The folder watcher needs this setting before it applies filesystem changes
+invoke('set_folder_watcher_options', { groupRawJpegPairs: Boolean(config.settings.groupRawJpegPairs) })
+  .catch((error) => console.error('setFolderWatcherOptions error:', error))
+
 // Set up global properties
 app.config.globalProperties.$invoke = invoke
 
@@ -152,6 +156,7 @@ listen('settings-loopVideo-changed', (event) => {
 })
 listen('settings-groupRawJpegPairs-changed', (event) => {
   config.settings.groupRawJpegPairs = event.payload
+  invoke('set_folder_watcher_options', { groupRawJpegPairs: Boolean(event.payload) })
 })
 listen('settings-navigatorViewMode-changed', (event) => {
   config.setNavigatorViewMode(event.payload)
@@ -36,6 +36,7 @@ export const useConfigStore = defineStore('configStore', {
     dedup: {
       activeTab: 'duplicates',   // active dedup tab ('duplicates' | 'similar')
       duplicateSetsHeight: 50,   // duplicate sets section height as a percentage
+      matchKind: 'exact',        // duplicate scan mode ('exact' | 'dhash' | 'phash' | 'video')
     },
 
     infoPanel: {